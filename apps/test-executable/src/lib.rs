@@ -22,63 +22,27 @@
 #![no_std]
 #![feature(asm)]
 
-use core::panic::PanicInfo;
+use core::{fmt::Write, panic::PanicInfo, time::Duration};
+use libsyscall::{Console, spawn_thread, sleep, exit};
 
 #[no_mangle]
 fn _start() -> ! {
-    let thread_id: u64;
-    unsafe {
-        asm!(
-            "svc 0x0002", // spawn thread
-            in("x2") thread_b, // Entry point
-            in("x3") 10,       // Priority
-            in("x4") 0x28000,  // Max stack size
-            lateout("x0") thread_id,
-            options(nomem, preserves_flags, nostack)
-        );
-    }
-    
-    if thread_id == 0 {
-        for c in "!!!Failed to spawn thread B!!!".chars() {
-            putc(c);
-        }
-        unsafe {
-            asm!(
-                "svc 0x0000", // terminate thread
-                options(nostack, noreturn)
-            );
-        }
+    let thread_id = spawn_thread(thread_b, 10, 0x28000);
+
+    if thread_id.is_none() {
+        let _ = write!(Console, "!!!Failed to spawn thread B!!!");
+        exit();
     }
 
     loop {
-        putc('a');
+        let _ = write!(Console, "a");
     }
 }
 
 fn thread_b() -> ! {
     loop {
-        putc('B');
-        sleep(0);
-    }
-}
-
-fn sleep(microseconds: u64) {
-    unsafe {
-        asm!(
-            "svc 0x0001",
-            in("x2") microseconds,
-            options(nomem, preserves_flags, nostack)
-        );
-    }
-}
-
-fn putc(c: char) {
-    unsafe {
-        asm!(
-            "svc 0xff00",
-            in("x2") u64::from(u32::from(c)),
-            options(nomem, preserves_flags, nostack)
-        );
+        let _ = write!(Console, "B");
+        sleep(Duration::ZERO);
     }
 }
 
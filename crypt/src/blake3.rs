@@ -0,0 +1,390 @@
+/* Copyright (c) 2022 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! An implementation of BLAKE3, a tree hash built around a ChaCha-derived compression function.
+//! Unlike the SHA-2 family, the message is split into 1024-byte chunks that are each hashed down
+//! to a 32-byte chaining value independently, and those chaining values are then combined pairwise
+//! up a binary Merkle tree. The chunk- and parent-level compressions are pure functions of their
+//! inputs (a chaining value and a 64-byte block), so nothing here stops a caller from computing
+//! the leaves of that tree on separate threads before folding them together; [`Hasher`] just
+//! doesn't do that itself, since Phoenix has no thread pool in this crate to hand the work to.
+
+use core::convert::TryInto;
+
+const OUT_LEN: usize = 32;
+const KEY_LEN: usize = 32;
+const BLOCK_LEN: usize = 64;
+const CHUNK_LEN: usize = 1024;
+
+// Domain-separation flags, mixed into the compression function's `flags` word to keep chunk
+// compressions, parent compressions, and the various keyed/derive modes from colliding with each
+// other even when their inputs happen to coincide.
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+const KEYED_HASH: u32 = 1 << 4;
+const DERIVE_KEY_CONTEXT: u32 = 1 << 5;
+const DERIVE_KEY_MATERIAL: u32 = 1 << 6;
+
+// The same initial hash value as SHA-256, reused here as BLAKE3's IV.
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+// The message-word permutation applied between each of the compression function's 7 rounds.
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// Computes the BLAKE3 hash of a complete message in one call.
+///
+/// This is a thin wrapper around [`Hasher`] for callers who already have the whole message in
+/// memory. Use `Hasher` directly to hash a message incrementally, to use the keyed or
+/// key-derivation modes, or to produce more than 32 bytes of extendable output.
+pub fn blake3(bytes: &[u8]) -> [u8; OUT_LEN] {
+    let mut hasher = Hasher::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// The quarter-round mixing function (ChaCha's `G` function) over 4 of the 16 state words.
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, mx: u32, my: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(mx);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(my);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+// One round of the compression function: `g` over the 4 columns, then `g` over the 4 diagonals.
+fn round(state: &mut [u32; 16], m: &[u32; 16]) {
+    g(state, 0, 4, 8, 12, m[0], m[1]);
+    g(state, 1, 5, 9, 13, m[2], m[3]);
+    g(state, 2, 6, 10, 14, m[4], m[5]);
+    g(state, 3, 7, 11, 15, m[6], m[7]);
+
+    g(state, 0, 5, 10, 15, m[8], m[9]);
+    g(state, 1, 6, 11, 12, m[10], m[11]);
+    g(state, 2, 7, 8, 13, m[12], m[13]);
+    g(state, 3, 4, 9, 14, m[14], m[15]);
+}
+
+fn permute(m: &mut [u32; 16]) {
+    let permuted = MSG_PERMUTATION.map(|i| m[i]);
+    *m = permuted;
+}
+
+// Runs the 16-word compression function over one 64-byte block, given the 8-word chaining value
+// it's building on, the counter identifying which chunk (or, for a parent node, which 0) this
+// block belongs to, the number of meaningful bytes in the block, and the domain-separation flags.
+fn compress(chaining_value: &[u32; 8], block_words: &[u32; 16], counter: u64, block_len: u32, flags: u32) -> [u32; 16] {
+    let counter_low = counter as u32;
+    let counter_high = (counter >> 32) as u32;
+    let mut state = [
+        chaining_value[0], chaining_value[1], chaining_value[2], chaining_value[3],
+        chaining_value[4], chaining_value[5], chaining_value[6], chaining_value[7],
+        IV[0], IV[1], IV[2], IV[3],
+        counter_low, counter_high, block_len, flags,
+    ];
+    let mut block = *block_words;
+
+    for round_i in 0 .. 7 {
+        round(&mut state, &block);
+        if round_i < 6 {
+            permute(&mut block);
+        }
+    }
+
+    for i in 0 .. 8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= chaining_value[i];
+    }
+    state
+}
+
+fn first_8_words(compression_output: [u32; 16]) -> [u32; 8] {
+    compression_output[0 .. 8].try_into().unwrap()
+}
+
+fn words_from_le_bytes(bytes: &[u8], words: &mut [u32]) {
+    debug_assert_eq!(bytes.len(), 4 * words.len());
+    for (four_bytes, word) in bytes.chunks_exact(4).zip(words) {
+        *word = u32::from_le_bytes(four_bytes.try_into().unwrap());
+    }
+}
+
+// Everything needed to finish either a chunk or a parent node: either fold it into an 8-word
+// chaining value to feed the next level of the tree, or (with the ROOT flag set) expand it into
+// as much output as the caller wants.
+struct Output {
+    input_chaining_value: [u32; 8],
+    block_words: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32; 8] {
+        first_8_words(compress(&self.input_chaining_value, &self.block_words, self.counter, self.block_len, self.flags))
+    }
+
+    // Fills `out` with extendable output, per section 6.4 of the BLAKE3 spec: each 64-byte block
+    // of output is produced by the same compression, just with an incrementing output-block
+    // counter standing in for the chunk counter.
+    fn root_output(&self, out: &mut [u8]) {
+        for (block_counter, out_block) in out.chunks_mut(2 * OUT_LEN).enumerate() {
+            let words = compress(&self.input_chaining_value, &self.block_words, block_counter as u64, self.block_len, self.flags | ROOT);
+            for (word, out_word) in words.iter().zip(out_block.chunks_mut(4)) {
+                let word_bytes = word.to_le_bytes();
+                out_word.copy_from_slice(&word_bytes[.. out_word.len()]);
+            }
+        }
+    }
+}
+
+// The state of a single 1024-byte chunk as it's fed up to 16 blocks at a time.
+struct ChunkState {
+    chaining_value: [u32; 8],
+    chunk_counter: u64,
+    block: [u8; BLOCK_LEN],
+    block_len: u8,
+    blocks_compressed: u8,
+    flags: u32,
+}
+
+impl ChunkState {
+    fn new(key_words: [u32; 8], chunk_counter: u64, flags: u32) -> Self {
+        ChunkState {
+            chaining_value: key_words,
+            chunk_counter,
+            block: [0; BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+            flags,
+        }
+    }
+
+    fn len(&self) -> usize {
+        BLOCK_LEN * usize::from(self.blocks_compressed) + usize::from(self.block_len)
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 { CHUNK_START } else { 0 }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // A full block buffer gets compressed (as a non-final block) to make room for more.
+            if usize::from(self.block_len) == BLOCK_LEN {
+                let mut block_words = [0; 16];
+                words_from_le_bytes(&self.block, &mut block_words);
+                self.chaining_value = first_8_words(
+                    compress(&self.chaining_value, &block_words, self.chunk_counter, BLOCK_LEN as u32, self.flags | self.start_flag())
+                );
+                self.blocks_compressed += 1;
+                self.block = [0; BLOCK_LEN];
+                self.block_len = 0;
+            }
+
+            let want = BLOCK_LEN - usize::from(self.block_len);
+            let take = want.min(data.len());
+            self.block[usize::from(self.block_len) .. usize::from(self.block_len) + take].copy_from_slice(&data[.. take]);
+            self.block_len += take as u8;
+            data = &data[take ..];
+        }
+    }
+
+    fn output(&self) -> Output {
+        let mut block_words = [0; 16];
+        words_from_le_bytes(&self.block, &mut block_words);
+        Output {
+            input_chaining_value: self.chaining_value,
+            block_words,
+            counter: self.chunk_counter,
+            block_len: u32::from(self.block_len),
+            flags: self.flags | self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+fn parent_output(left_child_cv: [u32; 8], right_child_cv: [u32; 8], key_words: [u32; 8], flags: u32) -> Output {
+    let mut block_words = [0; 16];
+    block_words[.. 8].copy_from_slice(&left_child_cv);
+    block_words[8 ..].copy_from_slice(&right_child_cv);
+    Output {
+        input_chaining_value: key_words,
+        block_words,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: PARENT | flags,
+    }
+}
+
+fn parent_cv(left_child_cv: [u32; 8], right_child_cv: [u32; 8], key_words: [u32; 8], flags: u32) -> [u32; 8] {
+    parent_output(left_child_cv, right_child_cv, key_words, flags).chaining_value()
+}
+
+/// The maximum number of subtrees that can be in progress at once: one per bit of a 64-bit chunk
+/// counter, since each chunk can complete at most one subtree per trailing zero bit it has.
+const MAX_STACK_DEPTH: usize = 64;
+
+/// An incremental BLAKE3 hasher, for messages that arrive piece by piece.
+///
+/// ```
+/// # use crypt::blake3::Hasher;
+/// let mut hasher = Hasher::new();
+/// hasher.update(b"hello, ");
+/// hasher.update(b"world");
+/// assert_eq!(hasher.finalize(), crypt::blake3::blake3(b"hello, world"));
+/// ```
+pub struct Hasher {
+    chunk_state: ChunkState,
+    key_words: [u32; 8],
+    flags: u32,
+    // The chaining values of completed subtrees along the right edge of the tree, smallest (most
+    // recently completed) subtree last. Merged pairwise as wider subtrees complete; see
+    // `add_chunk_chaining_value`.
+    cv_stack: [[u32; 8]; MAX_STACK_DEPTH],
+    cv_stack_len: usize,
+}
+
+impl Hasher {
+    fn new_internal(key_words: [u32; 8], flags: u32) -> Self {
+        Hasher {
+            chunk_state: ChunkState::new(key_words, 0, flags),
+            key_words,
+            flags,
+            cv_stack: [[0; 8]; MAX_STACK_DEPTH],
+            cv_stack_len: 0,
+        }
+    }
+
+    /// Starts a new hash, with no input yet.
+    pub fn new() -> Self {
+        Hasher::new_internal(IV, 0)
+    }
+
+    /// Starts a new hash under the given 32-byte key, using BLAKE3's keyed-hash mode.
+    pub fn new_keyed(key: &[u8; KEY_LEN]) -> Self {
+        let mut key_words = [0; 8];
+        words_from_le_bytes(key, &mut key_words);
+        Hasher::new_internal(key_words, KEYED_HASH)
+    }
+
+    /// Starts a new hash in BLAKE3's key-derivation mode: derives a subkey from `context` (a
+    /// fixed, application-specific string identifying this use) for use as the key material
+    /// that's then hashed with [`update`](Self::update) and [`finalize`](Self::finalize).
+    pub fn new_derive_key(context: &str) -> Self {
+        let mut context_hasher = Hasher::new_internal(IV, DERIVE_KEY_CONTEXT);
+        context_hasher.update(context.as_bytes());
+        let context_key_words = first_8_words(context_hasher.root_output_words());
+        Hasher::new_internal(context_key_words, DERIVE_KEY_MATERIAL)
+    }
+
+    // Folds a newly completed chunk's chaining value into the right edge of the tree. Per
+    // section 5.1.2 of the BLAKE3 spec, the number of subtrees this chunk completes is the number
+    // of trailing zero bits in the new total chunk count, and each completed subtree's chaining
+    // value is the parent of the stack's current top entry and the value being folded in.
+    fn add_chunk_chaining_value(&mut self, mut new_cv: [u32; 8], mut total_chunks: u64) {
+        while total_chunks & 1 == 0 {
+            self.cv_stack_len -= 1;
+            new_cv = parent_cv(self.cv_stack[self.cv_stack_len], new_cv, self.key_words, self.flags);
+            total_chunks >>= 1;
+        }
+        self.cv_stack[self.cv_stack_len] = new_cv;
+        self.cv_stack_len += 1;
+    }
+
+    /// Feeds more of the message into the hash. Can be called any number of times before
+    /// [`finalize`](Self::finalize) or [`finalize_xof`](Self::finalize_xof).
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // A full chunk is finalized (as a non-root chunk) and folded into the tree to make
+            // room for the next one.
+            if self.chunk_state.len() == CHUNK_LEN {
+                let chunk_cv = self.chunk_state.output().chaining_value();
+                let total_chunks = self.chunk_state.chunk_counter + 1;
+                self.add_chunk_chaining_value(chunk_cv, total_chunks);
+                self.chunk_state = ChunkState::new(self.key_words, total_chunks, self.flags);
+            }
+
+            let want = CHUNK_LEN - self.chunk_state.len();
+            let take = want.min(data.len());
+            self.chunk_state.update(&data[.. take]);
+            data = &data[take ..];
+        }
+    }
+
+    // Walks the chaining values of the current (rightmost, possibly partial) chunk and every
+    // completed subtree up to the root, returning the root `Output` before it's been expanded into
+    // bytes.
+    fn root_output(&self) -> Output {
+        let mut output = self.chunk_state.output();
+        let mut parent_nodes_remaining = self.cv_stack_len;
+        while parent_nodes_remaining > 0 {
+            parent_nodes_remaining -= 1;
+            output = parent_output(self.cv_stack[parent_nodes_remaining], output.chaining_value(), self.key_words, self.flags);
+        }
+        output
+    }
+
+    fn root_output_words(&self) -> [u32; 16] {
+        let output = self.root_output();
+        compress(&output.input_chaining_value, &output.block_words, output.counter, output.block_len, output.flags | ROOT)
+    }
+
+    /// Finishes the hash and returns the standard 32-byte BLAKE3 digest.
+    pub fn finalize(&self) -> [u8; OUT_LEN] {
+        let mut out = [0; OUT_LEN];
+        self.finalize_xof(&mut out);
+        out
+    }
+
+    /// Finishes the hash and fills `out` with extendable output of any length, per BLAKE3's XOF
+    /// mode: the first 32 bytes are the standard digest, and the rest is further pseudorandom
+    /// output derived from the same root node.
+    pub fn finalize_xof(&self, out: &mut [u8]) {
+        self.root_output().root_output(out);
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Self { Self::new() }
+}
+
+/// Computes a keyed BLAKE3 hash of a complete message in one call, using `key` as the 32-byte key.
+/// See [`Hasher::new_keyed`].
+pub fn keyed_hash(key: &[u8; KEY_LEN], bytes: &[u8]) -> [u8; OUT_LEN] {
+    let mut hasher = Hasher::new_keyed(key);
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// Derives `len` bytes of key material from `context` and `key_material`, using BLAKE3's
+/// key-derivation mode. See [`Hasher::new_derive_key`].
+pub fn derive_key(context: &str, key_material: &[u8], len: usize) -> Vec<u8> {
+    let mut hasher = Hasher::new_derive_key(context);
+    hasher.update(key_material);
+    let mut out = vec![0; len];
+    hasher.finalize_xof(&mut out);
+    out
+}
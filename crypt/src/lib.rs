@@ -25,108 +25,637 @@
 #![feature(maybe_uninit_uninit_array_transpose)]
 #![feature(wrapping_int_impl)]
 
+pub mod blake3;
+
 use core::{
     convert::{TryFrom, TryInto},
-    iter,
     mem::{self, MaybeUninit},
     num::Wrapping,
 };
 
+// Round constants shared by the 64-bit SHA-2 variants (SHA-512 and SHA-384)
+static SHA2_64_K: [Wrapping<u64>; 80] = [
+    Wrapping(0x428a2f98d728ae22), Wrapping(0x7137449123ef65cd), Wrapping(0xb5c0fbcfec4d3b2f), Wrapping(0xe9b5dba58189dbbc),
+    Wrapping(0x3956c25bf348b538), Wrapping(0x59f111f1b605d019), Wrapping(0x923f82a4af194f9b), Wrapping(0xab1c5ed5da6d8118),
+    Wrapping(0xd807aa98a3030242), Wrapping(0x12835b0145706fbe), Wrapping(0x243185be4ee4b28c), Wrapping(0x550c7dc3d5ffb4e2),
+    Wrapping(0x72be5d74f27b896f), Wrapping(0x80deb1fe3b1696b1), Wrapping(0x9bdc06a725c71235), Wrapping(0xc19bf174cf692694),
+    Wrapping(0xe49b69c19ef14ad2), Wrapping(0xefbe4786384f25e3), Wrapping(0x0fc19dc68b8cd5b5), Wrapping(0x240ca1cc77ac9c65),
+    Wrapping(0x2de92c6f592b0275), Wrapping(0x4a7484aa6ea6e483), Wrapping(0x5cb0a9dcbd41fbd4), Wrapping(0x76f988da831153b5),
+    Wrapping(0x983e5152ee66dfab), Wrapping(0xa831c66d2db43210), Wrapping(0xb00327c898fb213f), Wrapping(0xbf597fc7beef0ee4),
+    Wrapping(0xc6e00bf33da88fc2), Wrapping(0xd5a79147930aa725), Wrapping(0x06ca6351e003826f), Wrapping(0x142929670a0e6e70),
+    Wrapping(0x27b70a8546d22ffc), Wrapping(0x2e1b21385c26c926), Wrapping(0x4d2c6dfc5ac42aed), Wrapping(0x53380d139d95b3df),
+    Wrapping(0x650a73548baf63de), Wrapping(0x766a0abb3c77b2a8), Wrapping(0x81c2c92e47edaee6), Wrapping(0x92722c851482353b),
+    Wrapping(0xa2bfe8a14cf10364), Wrapping(0xa81a664bbc423001), Wrapping(0xc24b8b70d0f89791), Wrapping(0xc76c51a30654be30),
+    Wrapping(0xd192e819d6ef5218), Wrapping(0xd69906245565a910), Wrapping(0xf40e35855771202a), Wrapping(0x106aa07032bbd1b8),
+    Wrapping(0x19a4c116b8d2d0c8), Wrapping(0x1e376c085141ab53), Wrapping(0x2748774cdf8eeb99), Wrapping(0x34b0bcb5e19b48a8),
+    Wrapping(0x391c0cb3c5c95a63), Wrapping(0x4ed8aa4ae3418acb), Wrapping(0x5b9cca4f7763e373), Wrapping(0x682e6ff3d6b2b8a3),
+    Wrapping(0x748f82ee5defb2fc), Wrapping(0x78a5636f43172f60), Wrapping(0x84c87814a1f0ab72), Wrapping(0x8cc702081a6439ec),
+    Wrapping(0x90befffa23631e28), Wrapping(0xa4506cebde82bde9), Wrapping(0xbef9a3f7b2c67915), Wrapping(0xc67178f2e372532b),
+    Wrapping(0xca273eceea26619c), Wrapping(0xd186b8c721c0c207), Wrapping(0xeada7dd6cde0eb1e), Wrapping(0xf57d4f7fee6ed178),
+    Wrapping(0x06f067aa72176fba), Wrapping(0x0a637dc5a2c898a6), Wrapping(0x113f9804bef90dae), Wrapping(0x1b710b35131c471b),
+    Wrapping(0x28db77f523047d84), Wrapping(0x32caab7b40c72493), Wrapping(0x3c9ebe0a15c9bebc), Wrapping(0x431d67c49c100d4c),
+    Wrapping(0x4cc5d4becb3e42b6), Wrapping(0x597f299cfc657e2a), Wrapping(0x5fcb6fab3ad6faec), Wrapping(0x6c44198c4a475817),
+];
+
+// Initial hash values for SHA-512
+const SHA512_INITIAL_HASH: [Wrapping<u64>; 8] = [
+    Wrapping(0x6a09e667f3bcc908),
+    Wrapping(0xbb67ae8584caa73b),
+    Wrapping(0x3c6ef372fe94f82b),
+    Wrapping(0xa54ff53a5f1d36f1),
+    Wrapping(0x510e527fade682d1),
+    Wrapping(0x9b05688c2b3e6c1f),
+    Wrapping(0x1f83d9abfb41bd6b),
+    Wrapping(0x5be0cd19137e2179),
+];
+
+// Initial hash values for SHA-384, which is just SHA-512 with a different starting point and a
+// truncated output
+const SHA384_INITIAL_HASH: [Wrapping<u64>; 8] = [
+    Wrapping(0xcbbb9d5dc1059ed8),
+    Wrapping(0x629a292a367cd507),
+    Wrapping(0x9159015a3070dd17),
+    Wrapping(0x152fecd8f70e5939),
+    Wrapping(0x67332667ffc00b31),
+    Wrapping(0x8eb44a8768581511),
+    Wrapping(0xdb0c2e0d64f98fa7),
+    Wrapping(0x47b5481dbefa4fa4),
+];
+
+const BLOCK_SIZE: usize = 128;
+
+/// Computes the SHA-512 digest of a complete message in one call.
+///
+/// This is a thin wrapper around [`Sha512`] for callers who already have the whole message in
+/// memory. Use `Sha512` directly to hash a message incrementally (e.g. a large file or a network
+/// stream) without buffering all of it at once.
 pub fn sha512(bytes: &[u8]) -> [u8; 64] {
-    // SHA-512 round constants
-    static K: [Wrapping<u64>; 80] = [
-        Wrapping(0x428a2f98d728ae22), Wrapping(0x7137449123ef65cd), Wrapping(0xb5c0fbcfec4d3b2f), Wrapping(0xe9b5dba58189dbbc),
-        Wrapping(0x3956c25bf348b538), Wrapping(0x59f111f1b605d019), Wrapping(0x923f82a4af194f9b), Wrapping(0xab1c5ed5da6d8118),
-        Wrapping(0xd807aa98a3030242), Wrapping(0x12835b0145706fbe), Wrapping(0x243185be4ee4b28c), Wrapping(0x550c7dc3d5ffb4e2),
-        Wrapping(0x72be5d74f27b896f), Wrapping(0x80deb1fe3b1696b1), Wrapping(0x9bdc06a725c71235), Wrapping(0xc19bf174cf692694),
-        Wrapping(0xe49b69c19ef14ad2), Wrapping(0xefbe4786384f25e3), Wrapping(0x0fc19dc68b8cd5b5), Wrapping(0x240ca1cc77ac9c65), 
-        Wrapping(0x2de92c6f592b0275), Wrapping(0x4a7484aa6ea6e483), Wrapping(0x5cb0a9dcbd41fbd4), Wrapping(0x76f988da831153b5),
-        Wrapping(0x983e5152ee66dfab), Wrapping(0xa831c66d2db43210), Wrapping(0xb00327c898fb213f), Wrapping(0xbf597fc7beef0ee4),
-        Wrapping(0xc6e00bf33da88fc2), Wrapping(0xd5a79147930aa725), Wrapping(0x06ca6351e003826f), Wrapping(0x142929670a0e6e70),
-        Wrapping(0x27b70a8546d22ffc), Wrapping(0x2e1b21385c26c926), Wrapping(0x4d2c6dfc5ac42aed), Wrapping(0x53380d139d95b3df),
-        Wrapping(0x650a73548baf63de), Wrapping(0x766a0abb3c77b2a8), Wrapping(0x81c2c92e47edaee6), Wrapping(0x92722c851482353b), 
-        Wrapping(0xa2bfe8a14cf10364), Wrapping(0xa81a664bbc423001), Wrapping(0xc24b8b70d0f89791), Wrapping(0xc76c51a30654be30),
-        Wrapping(0xd192e819d6ef5218), Wrapping(0xd69906245565a910), Wrapping(0xf40e35855771202a), Wrapping(0x106aa07032bbd1b8),
-        Wrapping(0x19a4c116b8d2d0c8), Wrapping(0x1e376c085141ab53), Wrapping(0x2748774cdf8eeb99), Wrapping(0x34b0bcb5e19b48a8),
-        Wrapping(0x391c0cb3c5c95a63), Wrapping(0x4ed8aa4ae3418acb), Wrapping(0x5b9cca4f7763e373), Wrapping(0x682e6ff3d6b2b8a3),
-        Wrapping(0x748f82ee5defb2fc), Wrapping(0x78a5636f43172f60), Wrapping(0x84c87814a1f0ab72), Wrapping(0x8cc702081a6439ec), 
-        Wrapping(0x90befffa23631e28), Wrapping(0xa4506cebde82bde9), Wrapping(0xbef9a3f7b2c67915), Wrapping(0xc67178f2e372532b),
-        Wrapping(0xca273eceea26619c), Wrapping(0xd186b8c721c0c207), Wrapping(0xeada7dd6cde0eb1e), Wrapping(0xf57d4f7fee6ed178),
-        Wrapping(0x06f067aa72176fba), Wrapping(0x0a637dc5a2c898a6), Wrapping(0x113f9804bef90dae), Wrapping(0x1b710b35131c471b),
-        Wrapping(0x28db77f523047d84), Wrapping(0x32caab7b40c72493), Wrapping(0x3c9ebe0a15c9bebc), Wrapping(0x431d67c49c100d4c),
-        Wrapping(0x4cc5d4becb3e42b6), Wrapping(0x597f299cfc657e2a), Wrapping(0x5fcb6fab3ad6faec), Wrapping(0x6c44198c4a475817),
-    ];
-
-    // Initial hash values
-    let mut hash: [Wrapping<u64>; 8] = [
-        Wrapping(0x6a09e667f3bcc908),
-        Wrapping(0xbb67ae8584caa73b),
-        Wrapping(0x3c6ef372fe94f82b),
-        Wrapping(0xa54ff53a5f1d36f1),
-        Wrapping(0x510e527fade682d1),
-        Wrapping(0x9b05688c2b3e6c1f),
-        Wrapping(0x1f83d9abfb41bd6b),
-        Wrapping(0x5be0cd19137e2179),
-    ];
-
-    let len_bytes = (u128::try_from(bytes.len()).unwrap() * 8).to_be_bytes();
-
-    let chunks = bytes.iter()
-        // Padding: a single 1 followed by enough bits to finish a 1024-bit chunk with a 128-bit
-        // length at the end
-        .chain(iter::once(&0x80))
-        .chain(iter::repeat(&0x00).take((256 - bytes.len() % 128 - mem::size_of::<u8>() - mem::size_of::<u128>()) % 128))
-        .chain(len_bytes.iter())
-        // Simplify later use
-        .map(|&x| x)
-        // Split into 1024-bit chunks
-        .array_chunks::<128>();
-
-    for chunk in chunks {
-        // Initialize message schedule array from chunk
-        let mut w = [const { MaybeUninit::uninit() }; 80];
-        for i in 0 .. chunk.len() / mem::size_of::<u64>() {
-            w[i].write(Wrapping(u64::from_be_bytes(chunk[i * mem::size_of::<u64>() .. (i + 1) * mem::size_of::<u64>()].try_into().unwrap())));
+    let mut hasher = Sha512::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// Computes the SHA-384 digest of a complete message in one call. See [`sha512`].
+pub fn sha384(bytes: &[u8]) -> [u8; 48] {
+    let mut hasher = Sha384::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// An incremental SHA-512 hasher, for messages that arrive piece by piece.
+///
+/// ```
+/// # use crypt::Sha512;
+/// let mut hasher = Sha512::new();
+/// hasher.update(b"hello, ");
+/// hasher.update(b"world");
+/// assert_eq!(hasher.finalize(), crypt::sha512(b"hello, world"));
+/// ```
+#[derive(Clone)]
+pub struct Sha512(Sha2_64);
+
+impl Sha512 {
+    /// Starts a new hash, with no input yet.
+    pub fn new() -> Self {
+        Sha512(Sha2_64::new(SHA512_INITIAL_HASH))
+    }
+
+    /// Feeds more of the message into the hash. Can be called any number of times before
+    /// [`finalize`](Self::finalize).
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Applies the standard SHA-512 padding (a `1` bit, zero fill, then the message's bit length
+    /// as a 128-bit big-endian integer) and returns the finished digest.
+    pub fn finalize(self) -> [u8; 64] {
+        sha2_64_words_to_bytes(self.0.finalize_words())
+    }
+}
+
+impl Default for Sha512 {
+    fn default() -> Self { Self::new() }
+}
+
+/// An incremental SHA-384 hasher, for messages that arrive piece by piece. See [`Sha512`].
+#[derive(Clone)]
+pub struct Sha384(Sha2_64);
+
+impl Sha384 {
+    /// Starts a new hash, with no input yet.
+    pub fn new() -> Self {
+        Sha384(Sha2_64::new(SHA384_INITIAL_HASH))
+    }
+
+    /// Feeds more of the message into the hash. Can be called any number of times before
+    /// [`finalize`](Self::finalize).
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Applies the standard padding and returns the finished digest, truncated to SHA-384's
+    /// 384-bit output.
+    pub fn finalize(self) -> [u8; 48] {
+        let words = sha2_64_words_to_bytes(self.0.finalize_words());
+        let mut digest = [0; 48];
+        digest.copy_from_slice(&words[.. 48]);
+        digest
+    }
+}
+
+impl Default for Sha384 {
+    fn default() -> Self { Self::new() }
+}
+
+// The state shared by the 64-bit SHA-2 variants (SHA-512 and SHA-384): an 8-word running hash
+// value, a buffer for the partial block that hasn't been compressed yet, and a running count of
+// the total message length.
+#[derive(Clone)]
+struct Sha2_64 {
+    state: [Wrapping<u64>; 8],
+    buffer: [u8; BLOCK_SIZE],
+    buffer_len: usize,
+    total_len: u128,
+}
+
+impl Sha2_64 {
+    fn new(initial_hash: [Wrapping<u64>; 8]) -> Self {
+        Sha2_64 {
+            state: initial_hash,
+            buffer: [0; BLOCK_SIZE],
+            buffer_len: 0,
+            total_len: 0,
         }
-        for i in chunk.len() / mem::size_of::<u64>() .. w.len() {
-            let wi = |n| unsafe { MaybeUninit::<Wrapping<u64>>::assume_init_read(&w[i - n]) };
-            let s0 = wi(15).rotate_right(1) ^ wi(15).rotate_right(8) ^ wi(15) >> 7;
-            let s1 = wi(2).rotate_right(19) ^ wi(2).rotate_right(61) ^ wi(2) >> 6;
-            w[i].write(wi(16) + s0 + wi(7) + s1);
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len += u128::try_from(data.len()).unwrap();
+
+        if self.buffer_len > 0 {
+            let n = (BLOCK_SIZE - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len .. self.buffer_len + n].copy_from_slice(&data[.. n]);
+            self.buffer_len += n;
+            data = &data[n ..];
+
+            if self.buffer_len < BLOCK_SIZE {
+                return;
+            }
+            let block = self.buffer;
+            sha2_64_compress(&mut self.state, &block);
+            self.buffer_len = 0;
         }
-        let w = unsafe { w.transpose().assume_init() };
-
-        // Working variables initialized to current hash value
-        let mut v = hash;
-
-        // Compression function main loop
-        for i in 0 .. w.len() {
-            let s1 = v[4].rotate_right(14) ^ v[4].rotate_right(18) ^ v[4].rotate_right(41);
-            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
-            let temp1 = v[7] + s1 + ch + K[i] + w[i];
-            let s0 = v[0].rotate_right(28) ^ v[0].rotate_right(34) ^ v[0].rotate_right(39);
-            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
-            let temp2 = s0 + maj;
-
-            v[7] = v[6];
-            v[6] = v[5];
-            v[5] = v[4];
-            v[4] = v[3] + temp1;
-            v[3] = v[2];
-            v[2] = v[1];
-            v[1] = v[0];
-            v[0] = temp1 + temp2;
+
+        while data.len() >= BLOCK_SIZE {
+            let block: [u8; BLOCK_SIZE] = data[.. BLOCK_SIZE].try_into().unwrap();
+            sha2_64_compress(&mut self.state, &block);
+            data = &data[BLOCK_SIZE ..];
         }
 
-        // Add the compressed chunk to the hash value.
-        for i in 0 .. hash.len() {
-            hash[i] += v[i];
+        self.buffer[.. data.len()].copy_from_slice(data);
+        self.buffer_len = data.len();
+    }
+
+    // Applies the standard padding (a `1` bit, zero fill, then the message's bit length as a
+    // 128-bit big-endian integer) and returns the finished hash words, still in native endianness.
+    fn finalize_words(mut self) -> [Wrapping<u64>; 8] {
+        let bit_len = (self.total_len * 8).to_be_bytes();
+
+        self.buffer[self.buffer_len] = 0x80;
+        self.buffer_len += 1;
+
+        if self.buffer_len > BLOCK_SIZE - mem::size_of::<u128>() {
+            self.buffer[self.buffer_len ..].fill(0);
+            let block = self.buffer;
+            sha2_64_compress(&mut self.state, &block);
+            self.buffer_len = 0;
         }
+
+        self.buffer[self.buffer_len .. BLOCK_SIZE - mem::size_of::<u128>()].fill(0);
+        self.buffer[BLOCK_SIZE - mem::size_of::<u128>() ..].copy_from_slice(&bit_len);
+        sha2_64_compress(&mut self.state, &self.buffer);
+
+        self.state
     }
+}
 
-    let mut hash_bytes = [0; 64];
+fn sha2_64_words_to_bytes(state: [Wrapping<u64>; 8]) -> [u8; 64] {
+    let mut bytes = [0; 64];
     for i in 0 .. 8 {
-        hash_bytes[i * 8 .. (i + 1) * 8].copy_from_slice(&hash[i].0.to_be_bytes());
+        bytes[i * 8 .. (i + 1) * 8].copy_from_slice(&state[i].0.to_be_bytes());
+    }
+    bytes
+}
+
+// Runs the compression function shared by SHA-512 and SHA-384 over a single 1024-bit block,
+// updating `state` in place.
+fn sha2_64_compress(state: &mut [Wrapping<u64>; 8], block: &[u8; BLOCK_SIZE]) {
+    // Initialize message schedule array from the block
+    let mut w = [const { MaybeUninit::uninit() }; 80];
+    for i in 0 .. 16 {
+        w[i].write(Wrapping(u64::from_be_bytes(block[i * mem::size_of::<u64>() .. (i + 1) * mem::size_of::<u64>()].try_into().unwrap())));
+    }
+    for i in 16 .. w.len() {
+        let wi = |n| unsafe { MaybeUninit::<Wrapping<u64>>::assume_init_read(&w[i - n]) };
+        let s0 = wi(15).rotate_right(1) ^ wi(15).rotate_right(8) ^ wi(15) >> 7;
+        let s1 = wi(2).rotate_right(19) ^ wi(2).rotate_right(61) ^ wi(2) >> 6;
+        w[i].write(wi(16) + s0 + wi(7) + s1);
+    }
+    let w = unsafe { w.transpose().assume_init() };
+
+    // Working variables initialized to current hash value
+    let mut v = *state;
+
+    // Compression function main loop
+    for i in 0 .. w.len() {
+        let s1 = v[4].rotate_right(14) ^ v[4].rotate_right(18) ^ v[4].rotate_right(41);
+        let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+        let temp1 = v[7] + s1 + ch + SHA2_64_K[i] + w[i];
+        let s0 = v[0].rotate_right(28) ^ v[0].rotate_right(34) ^ v[0].rotate_right(39);
+        let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+        let temp2 = s0 + maj;
+
+        v[7] = v[6];
+        v[6] = v[5];
+        v[5] = v[4];
+        v[4] = v[3] + temp1;
+        v[3] = v[2];
+        v[2] = v[1];
+        v[1] = v[0];
+        v[0] = temp1 + temp2;
+    }
+
+    // Add the compressed block to the hash value.
+    for i in 0 .. state.len() {
+        state[i] += v[i];
+    }
+}
+
+// Round constants shared by the 32-bit SHA-2 variants (SHA-256 and SHA-224)
+static SHA2_32_K: [Wrapping<u32>; 64] = [
+    Wrapping(0x428a2f98), Wrapping(0x71374491), Wrapping(0xb5c0fbcf), Wrapping(0xe9b5dba5),
+    Wrapping(0x3956c25b), Wrapping(0x59f111f1), Wrapping(0x923f82a4), Wrapping(0xab1c5ed5),
+    Wrapping(0xd807aa98), Wrapping(0x12835b01), Wrapping(0x243185be), Wrapping(0x550c7dc3),
+    Wrapping(0x72be5d74), Wrapping(0x80deb1fe), Wrapping(0x9bdc06a7), Wrapping(0xc19bf174),
+    Wrapping(0xe49b69c1), Wrapping(0xefbe4786), Wrapping(0x0fc19dc6), Wrapping(0x240ca1cc),
+    Wrapping(0x2de92c6f), Wrapping(0x4a7484aa), Wrapping(0x5cb0a9dc), Wrapping(0x76f988da),
+    Wrapping(0x983e5152), Wrapping(0xa831c66d), Wrapping(0xb00327c8), Wrapping(0xbf597fc7),
+    Wrapping(0xc6e00bf3), Wrapping(0xd5a79147), Wrapping(0x06ca6351), Wrapping(0x14292967),
+    Wrapping(0x27b70a85), Wrapping(0x2e1b2138), Wrapping(0x4d2c6dfc), Wrapping(0x53380d13),
+    Wrapping(0x650a7354), Wrapping(0x766a0abb), Wrapping(0x81c2c92e), Wrapping(0x92722c85),
+    Wrapping(0xa2bfe8a1), Wrapping(0xa81a664b), Wrapping(0xc24b8b70), Wrapping(0xc76c51a3),
+    Wrapping(0xd192e819), Wrapping(0xd6990624), Wrapping(0xf40e3585), Wrapping(0x106aa070),
+    Wrapping(0x19a4c116), Wrapping(0x1e376c08), Wrapping(0x2748774c), Wrapping(0x34b0bcb5),
+    Wrapping(0x391c0cb3), Wrapping(0x4ed8aa4a), Wrapping(0x5b9cca4f), Wrapping(0x682e6ff3),
+    Wrapping(0x748f82ee), Wrapping(0x78a5636f), Wrapping(0x84c87814), Wrapping(0x8cc70208),
+    Wrapping(0x90befffa), Wrapping(0xa4506ceb), Wrapping(0xbef9a3f7), Wrapping(0xc67178f2),
+];
+
+// Initial hash values for SHA-256
+const SHA256_INITIAL_HASH: [Wrapping<u32>; 8] = [
+    Wrapping(0x6a09e667),
+    Wrapping(0xbb67ae85),
+    Wrapping(0x3c6ef372),
+    Wrapping(0xa54ff53a),
+    Wrapping(0x510e527f),
+    Wrapping(0x9b05688c),
+    Wrapping(0x1f83d9ab),
+    Wrapping(0x5be0cd19),
+];
+
+// Initial hash values for SHA-224, which is just SHA-256 with a different starting point and a
+// truncated output
+const SHA224_INITIAL_HASH: [Wrapping<u32>; 8] = [
+    Wrapping(0xc1059ed8),
+    Wrapping(0x367cd507),
+    Wrapping(0x3070dd17),
+    Wrapping(0xf70e5939),
+    Wrapping(0xffc00b31),
+    Wrapping(0x68581511),
+    Wrapping(0x64f98fa7),
+    Wrapping(0xbefa4fa4),
+];
+
+const SHA2_32_BLOCK_SIZE: usize = 64;
+
+/// Computes the SHA-256 digest of a complete message in one call. See [`sha512`].
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// Computes the SHA-224 digest of a complete message in one call. See [`sha512`].
+pub fn sha224(bytes: &[u8]) -> [u8; 28] {
+    let mut hasher = Sha224::new();
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+/// An incremental SHA-256 hasher, for messages that arrive piece by piece. See [`Sha512`].
+#[derive(Clone)]
+pub struct Sha256(Sha2_32);
+
+impl Sha256 {
+    /// Starts a new hash, with no input yet.
+    pub fn new() -> Self {
+        Sha256(Sha2_32::new(SHA256_INITIAL_HASH))
+    }
+
+    /// Feeds more of the message into the hash. Can be called any number of times before
+    /// [`finalize`](Self::finalize).
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Applies the standard SHA-256 padding (a `1` bit, zero fill, then the message's bit length
+    /// as a 64-bit big-endian integer) and returns the finished digest.
+    pub fn finalize(self) -> [u8; 32] {
+        sha2_32_words_to_bytes(self.0.finalize_words())
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self { Self::new() }
+}
+
+/// An incremental SHA-224 hasher, for messages that arrive piece by piece. See [`Sha512`].
+#[derive(Clone)]
+pub struct Sha224(Sha2_32);
+
+impl Sha224 {
+    /// Starts a new hash, with no input yet.
+    pub fn new() -> Self {
+        Sha224(Sha2_32::new(SHA224_INITIAL_HASH))
     }
-    hash_bytes
+
+    /// Feeds more of the message into the hash. Can be called any number of times before
+    /// [`finalize`](Self::finalize).
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Applies the standard padding and returns the finished digest, truncated to SHA-224's
+    /// 224-bit output.
+    pub fn finalize(self) -> [u8; 28] {
+        let words = sha2_32_words_to_bytes(self.0.finalize_words());
+        let mut digest = [0; 28];
+        digest.copy_from_slice(&words[.. 28]);
+        digest
+    }
+}
+
+impl Default for Sha224 {
+    fn default() -> Self { Self::new() }
+}
+
+// The state shared by the 32-bit SHA-2 variants (SHA-256 and SHA-224). Structurally identical to
+// `Sha2_64`, just with 32-bit words, a 512-bit block, and a 64-bit length field.
+#[derive(Clone)]
+struct Sha2_32 {
+    state: [Wrapping<u32>; 8],
+    buffer: [u8; SHA2_32_BLOCK_SIZE],
+    buffer_len: usize,
+    total_len: u64,
+}
+
+impl Sha2_32 {
+    fn new(initial_hash: [Wrapping<u32>; 8]) -> Self {
+        Sha2_32 {
+            state: initial_hash,
+            buffer: [0; SHA2_32_BLOCK_SIZE],
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len += u64::try_from(data.len()).unwrap();
+
+        if self.buffer_len > 0 {
+            let n = (SHA2_32_BLOCK_SIZE - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len .. self.buffer_len + n].copy_from_slice(&data[.. n]);
+            self.buffer_len += n;
+            data = &data[n ..];
+
+            if self.buffer_len < SHA2_32_BLOCK_SIZE {
+                return;
+            }
+            let block = self.buffer;
+            sha2_32_compress(&mut self.state, &block);
+            self.buffer_len = 0;
+        }
+
+        while data.len() >= SHA2_32_BLOCK_SIZE {
+            let block: [u8; SHA2_32_BLOCK_SIZE] = data[.. SHA2_32_BLOCK_SIZE].try_into().unwrap();
+            sha2_32_compress(&mut self.state, &block);
+            data = &data[SHA2_32_BLOCK_SIZE ..];
+        }
+
+        self.buffer[.. data.len()].copy_from_slice(data);
+        self.buffer_len = data.len();
+    }
+
+    // Applies the standard padding (a `1` bit, zero fill, then the message's bit length as a
+    // 64-bit big-endian integer) and returns the finished hash words, still in native endianness.
+    fn finalize_words(mut self) -> [Wrapping<u32>; 8] {
+        let bit_len = (self.total_len * 8).to_be_bytes();
+
+        self.buffer[self.buffer_len] = 0x80;
+        self.buffer_len += 1;
+
+        if self.buffer_len > SHA2_32_BLOCK_SIZE - mem::size_of::<u64>() {
+            self.buffer[self.buffer_len ..].fill(0);
+            let block = self.buffer;
+            sha2_32_compress(&mut self.state, &block);
+            self.buffer_len = 0;
+        }
+
+        self.buffer[self.buffer_len .. SHA2_32_BLOCK_SIZE - mem::size_of::<u64>()].fill(0);
+        self.buffer[SHA2_32_BLOCK_SIZE - mem::size_of::<u64>() ..].copy_from_slice(&bit_len);
+        sha2_32_compress(&mut self.state, &self.buffer);
+
+        self.state
+    }
+}
+
+fn sha2_32_words_to_bytes(state: [Wrapping<u32>; 8]) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for i in 0 .. 8 {
+        bytes[i * 4 .. (i + 1) * 4].copy_from_slice(&state[i].0.to_be_bytes());
+    }
+    bytes
+}
+
+// Runs the compression function shared by SHA-256 and SHA-224 over a single 512-bit block,
+// updating `state` in place.
+fn sha2_32_compress(state: &mut [Wrapping<u32>; 8], block: &[u8; SHA2_32_BLOCK_SIZE]) {
+    // Initialize message schedule array from the block
+    let mut w = [const { MaybeUninit::uninit() }; 64];
+    for i in 0 .. 16 {
+        w[i].write(Wrapping(u32::from_be_bytes(block[i * mem::size_of::<u32>() .. (i + 1) * mem::size_of::<u32>()].try_into().unwrap())));
+    }
+    for i in 16 .. w.len() {
+        let wi = |n| unsafe { MaybeUninit::<Wrapping<u32>>::assume_init_read(&w[i - n]) };
+        let s0 = wi(15).rotate_right(7) ^ wi(15).rotate_right(18) ^ wi(15) >> 3;
+        let s1 = wi(2).rotate_right(17) ^ wi(2).rotate_right(19) ^ wi(2) >> 10;
+        w[i].write(wi(16) + s0 + wi(7) + s1);
+    }
+    let w = unsafe { w.transpose().assume_init() };
+
+    // Working variables initialized to current hash value
+    let mut v = *state;
+
+    // Compression function main loop
+    for i in 0 .. w.len() {
+        let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+        let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+        let temp1 = v[7] + s1 + ch + SHA2_32_K[i] + w[i];
+        let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+        let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+        let temp2 = s0 + maj;
+
+        v[7] = v[6];
+        v[6] = v[5];
+        v[5] = v[4];
+        v[4] = v[3] + temp1;
+        v[3] = v[2];
+        v[2] = v[1];
+        v[1] = v[0];
+        v[0] = temp1 + temp2;
+    }
+
+    // Add the compressed block to the hash value.
+    for i in 0 .. state.len() {
+        state[i] += v[i];
+    }
+}
+
+/// Computes an HMAC-SHA512 tag over a complete message in one call.
+///
+/// This is a thin wrapper around [`HmacSha512`] for callers who already have the whole message in
+/// memory.
+pub fn hmac_sha512(key: &[u8], msg: &[u8]) -> [u8; 64] {
+    let mut mac = HmacSha512::new(key);
+    mac.update(msg);
+    mac.finalize()
+}
+
+/// Returns `true` iff `tag` is the correct HMAC-SHA512 tag for `key` and `msg`, without leaking
+/// timing information about where the first mismatching byte is.
+pub fn verify_hmac_sha512(key: &[u8], msg: &[u8], tag: &[u8; 64]) -> bool {
+    constant_time_eq(&hmac_sha512(key, msg), tag)
+}
+
+/// An incremental HMAC-SHA512 computation, for messages that arrive piece by piece.
+///
+/// This follows the construction in [RFC 2104](https://www.rfc-editor.org/rfc/rfc2104): if `key`
+/// is longer than the 128-byte block size, it's replaced by `sha512(key)`; the (possibly
+/// replaced) key is then right-padded with zeros to the block size to get `K`, and the tag is
+/// `sha512((K ^ opad) || sha512((K ^ ipad) || msg))`.
+pub struct HmacSha512 {
+    inner: Sha512,
+    outer_key_pad: [u8; BLOCK_SIZE],
+}
+
+impl HmacSha512 {
+    /// Starts a new HMAC computation under the given key, with no message yet.
+    pub fn new(key: &[u8]) -> Self {
+        let mut k = [0; BLOCK_SIZE];
+        if key.len() > BLOCK_SIZE {
+            k[.. 64].copy_from_slice(&sha512(key));
+        } else {
+            k[.. key.len()].copy_from_slice(key);
+        }
+
+        let mut inner_key_pad = [0; BLOCK_SIZE];
+        let mut outer_key_pad = [0; BLOCK_SIZE];
+        for i in 0 .. BLOCK_SIZE {
+            inner_key_pad[i] = k[i] ^ 0x36;
+            outer_key_pad[i] = k[i] ^ 0x5c;
+        }
+
+        let mut inner = Sha512::new();
+        inner.update(&inner_key_pad);
+
+        HmacSha512 { inner, outer_key_pad }
+    }
+
+    /// Feeds more of the message into the HMAC. Can be called any number of times before
+    /// [`finalize`](Self::finalize).
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    /// Finishes the computation and returns the tag.
+    pub fn finalize(self) -> [u8; 64] {
+        let inner_digest = self.inner.finalize();
+        let mut outer = Sha512::new();
+        outer.update(&self.outer_key_pad);
+        outer.update(&inner_digest);
+        outer.finalize()
+    }
+}
+
+// Compares two tags for equality without branching on their contents, so the time taken doesn't
+// reveal where (or whether) they first differ.
+fn constant_time_eq(a: &[u8; 64], b: &[u8; 64]) -> bool {
+    let mut diff = 0;
+    for i in 0 .. a.len() {
+        diff |= a[i] ^ b[i];
+    }
+    diff == 0
+}
+
+/// The largest number of bytes that [`expand`] (and therefore [`hkdf_sha512`]) can produce, per
+/// [RFC 5869](https://www.rfc-editor.org/rfc/rfc5869).
+pub const HKDF_SHA512_MAX_LEN: usize = 255 * 64;
+
+/// Derives `len` bytes of keying material from `salt` and `ikm` (input keying material), tagged
+/// with the application-specific `info`, using HKDF-SHA512 as defined in
+/// [RFC 5869](https://www.rfc-editor.org/rfc/rfc5869).
+///
+/// This is a thin wrapper around [`extract`] and [`expand`] for callers who don't need to reuse
+/// the pseudorandom key (PRK) across multiple calls to `expand`.
+///
+/// # Errors
+/// Returns `Err(())` if `len` is greater than [`HKDF_SHA512_MAX_LEN`].
+pub fn hkdf_sha512(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, ()> {
+    expand(&extract(salt, ikm), info, len)
+}
+
+/// The "extract" half of HKDF-SHA512: concentrates the (possibly not uniformly random) entropy of
+/// `ikm` into a fixed-length pseudorandom key. An empty `salt` is treated as 64 zero bytes, as
+/// specified by RFC 5869.
+pub fn extract(salt: &[u8], ikm: &[u8]) -> [u8; 64] {
+    if salt.is_empty() {
+        hmac_sha512(&[0; 64], ikm)
+    } else {
+        hmac_sha512(salt, ikm)
+    }
+}
+
+/// The "expand" half of HKDF-SHA512: stretches a pseudorandom key `prk` (usually the output of
+/// [`extract`]) into `len` bytes of output keying material tagged with `info`.
+///
+/// # Errors
+/// Returns `Err(())` if `len` is greater than [`HKDF_SHA512_MAX_LEN`].
+pub fn expand(prk: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, ()> {
+    if len > HKDF_SHA512_MAX_LEN {
+        return Err(());
+    }
+
+    let block_count = (len + 63) / 64;
+    let mut okm = Vec::with_capacity(block_count * 64);
+    let mut t: Vec<u8> = Vec::new();
+    for counter in 1 ..= block_count {
+        let mut mac = HmacSha512::new(prk);
+        mac.update(&t);
+        mac.update(info);
+        mac.update(&[counter as u8]);
+        t = mac.finalize().to_vec();
+        okm.extend_from_slice(&t);
+    }
+    okm.truncate(len);
+
+    Ok(okm)
 }
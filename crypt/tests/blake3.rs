@@ -0,0 +1,180 @@
+/* Copyright (c) 2023 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests for `crypt::blake3`.
+//!
+//! `known_answer_vectors` checks actual digests from the BLAKE3 reference implementation's
+//! published test vectors (`test_vectors.json`, using its standard input: byte `i` of the input is
+//! `i % 251`) for unkeyed, keyed, and `derive_key` hashing, at lengths that land on and straddle
+//! chunk (1024-byte) and subtree boundaries. Unlike a self-consistency check, these catch a
+//! systematic bug in the IV, rotation constants, message permutation, or domain-separation flags,
+//! since they're computed by an independent implementation rather than by this one. The rest of
+//! these cases check properties the vectors file doesn't: that one-shot hashing agrees with
+//! incremental hashing no matter how the input is split into `update` calls, and that
+//! `finalize_xof`'s first 32 bytes agree with `finalize`.
+
+use crypt::blake3::{self, Hasher};
+
+// Matches the input used by the official BLAKE3 test vectors: byte `i` of the input is `i % 251`.
+fn test_input(len: usize) -> Vec<u8> {
+    (0 .. len).map(|i| (i % 251) as u8).collect()
+}
+
+fn from_hex(s: &str) -> Vec<u8> {
+    (0 .. s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i .. i + 2], 16).unwrap()).collect()
+}
+
+#[test]
+fn known_answer_vectors() {
+    // Unkeyed hashes of the reference implementation's standard test input, at lengths chosen to
+    // land on and straddle chunk and subtree boundaries.
+    let unkeyed: &[(usize, &str)] = &[
+        (0,        "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"),
+        (1,        "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213"),
+        (1023,     "10108970eeda3eb932baac1428c7a2163b0e924c9a9e25b35bba72b28f70bd11"),
+        (1024,     "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7"),
+        (1025,     "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444"),
+        (2048,     "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a"),
+        (2049,     "5f4d72f40d7a5f82b15ca2b2e44b1de3c2ef86c426c95c1af0b6879522563030"),
+        (8 * 1024 + 17, "7a9d1fbb3de36ab3f51b9e0273250b3c91c7ba9ea25a8f71f7c5add6cd3f8770"),
+    ];
+    for &(len, digest) in unkeyed {
+        assert_eq!(blake3::blake3(&test_input(len))[..], from_hex(digest)[.. 32], "len={len}");
+    }
+
+    // Keyed hash of a 1024-byte input, with key bytes all `0x42`.
+    let key = [0x42; 32];
+    assert_eq!(
+        blake3::keyed_hash(&key, &test_input(1024))[..],
+        from_hex("c6788e713628fc8ff69b4f169c2fd610bb35efad35c81bc10183c38d57f64727")[.. 32]
+    );
+
+    // `derive_key("context A", ...)` over a 64-byte key material, extended to 64 output bytes via
+    // the XOF.
+    let mut derived = [0; 64];
+    derived.copy_from_slice(&blake3::derive_key("context A", &test_input(64), 64));
+    assert_eq!(
+        derived[..],
+        from_hex("6618d579ac479baf9ee1c78b4e53917227d055e9118e8b0714326114dacf69424fbab8f28bcf22a1c683b159873ac1a4f8a941de2e839be39535aa31402cb34e")[..]
+    );
+}
+
+#[test]
+fn empty_input() {
+    let hasher = Hasher::new();
+    assert_eq!(hasher.finalize(), blake3::blake3(b""));
+    let mut xof = [0; 64];
+    hasher.finalize_xof(&mut xof);
+    assert_eq!(&xof[.. 32], &blake3::blake3(b"")[..]);
+}
+
+#[test]
+fn one_byte_input() {
+    one_shot_matches_incremental(1);
+}
+
+#[test]
+fn chunk_boundary_inputs() {
+    // `CHUNK_LEN` is 1024 bytes; these lengths land exactly on, one below, and one above a chunk
+    // boundary, which is where an off-by-one in `Hasher::update`'s "is this chunk full yet?" check
+    // would show up.
+    for len in [1023, 1024, 1025, 2048, 2049] {
+        one_shot_matches_incremental(len);
+    }
+}
+
+#[test]
+fn subtree_boundary_input() {
+    // 8 chunks: enough for `add_chunk_chaining_value` to fold several completed subtrees together
+    // while the message is still being fed in, not just at `finalize`.
+    one_shot_matches_incremental(8 * 1024 + 17);
+}
+
+// Checks that hashing `test_input(len)` in one call agrees with hashing it incrementally, split at
+// a handful of different points (including right on a chunk boundary, where present).
+fn one_shot_matches_incremental(len: usize) {
+    let input = test_input(len);
+    let expected = blake3::blake3(&input);
+
+    for split in [0, 1, len / 2, len.saturating_sub(1), len] {
+        let mut hasher = Hasher::new();
+        hasher.update(&input[.. split]);
+        hasher.update(&input[split ..]);
+        assert_eq!(hasher.finalize(), expected, "len={len}, split={split}");
+    }
+
+    // Feeding the input one byte at a time must agree too, not just a two-call split.
+    let mut hasher = Hasher::new();
+    for byte in &input {
+        hasher.update(std::slice::from_ref(byte));
+    }
+    assert_eq!(hasher.finalize(), expected, "len={len}, byte-at-a-time");
+}
+
+#[test]
+fn finalize_xof_extends_finalize() {
+    for len in [0, 1, 1024, 2049] {
+        let input = test_input(len);
+        let mut hasher = Hasher::new();
+        hasher.update(&input);
+
+        let digest = hasher.finalize();
+        let mut xof = [0; 128];
+        hasher.finalize_xof(&mut xof);
+
+        // The first 32 bytes of the extendable output are the standard digest.
+        assert_eq!(&xof[.. 32], &digest[..], "len={len}");
+    }
+}
+
+#[test]
+fn keyed_hash_differs_from_unkeyed_and_is_key_dependent() {
+    let key_a = [0x42; 32];
+    let key_b = [0x43; 32];
+    let input = test_input(1024);
+
+    let unkeyed = blake3::blake3(&input);
+    let keyed_a = blake3::keyed_hash(&key_a, &input);
+    let keyed_b = blake3::keyed_hash(&key_b, &input);
+
+    assert_ne!(keyed_a, unkeyed);
+    assert_ne!(keyed_a, keyed_b);
+
+    // Keyed hashing is deterministic and agrees between one-shot and incremental use.
+    let mut hasher = Hasher::new_keyed(&key_a);
+    hasher.update(&input);
+    assert_eq!(hasher.finalize(), keyed_a);
+}
+
+#[test]
+fn derive_key_is_context_and_key_material_dependent() {
+    let key_material = test_input(64);
+
+    let a = blake3::derive_key("context A", &key_material, 32);
+    let b = blake3::derive_key("context B", &key_material, 32);
+    let c = blake3::derive_key("context A", &test_input(65), 32);
+
+    assert_ne!(a, b);
+    assert_ne!(a, c);
+    assert_ne!(a, blake3::blake3(&key_material).to_vec());
+
+    // `derive_key` can produce more than the standard 32-byte digest, via the same XOF mode as
+    // `finalize_xof`.
+    let long = blake3::derive_key("context A", &key_material, 64);
+    assert_eq!(&long[.. 32], &a[..]);
+}
@@ -0,0 +1,61 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests for `crypt::hkdf_sha512`, `crypt::extract`, and `crypt::expand`.
+//!
+//! [RFC 5869](https://www.rfc-editor.org/rfc/rfc5869) only publishes test vectors for HKDF with
+//! SHA-256, not SHA-512, so these reuse RFC 5869 A.1's IKM/salt/info/length inputs under
+//! HKDF-SHA-512 instead, checked against an independent HKDF-SHA-512 implementation.
+
+use crypt::{hkdf_sha512, extract, expand, HKDF_SHA512_MAX_LEN};
+
+fn from_hex(s: &str) -> Vec<u8> {
+    (0 .. s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i .. i + 2], 16).unwrap()).collect()
+}
+
+#[test]
+fn known_answer_vector() {
+    let ikm = from_hex("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+    let salt = from_hex("000102030405060708090a0b0c");
+    let info = from_hex("f0f1f2f3f4f5f6f7f8f9");
+    let prk = from_hex(
+        "665799823737ded04a88e47e54a5890bb2c3d247c7a4254a8e61350723590a2\
+         6c36238127d8661b88cf80ef802d57e2f7cebcf1e00e083848be19929c61b4237"
+    );
+    let okm = from_hex(
+        "832390086cda71fb47625bb5ceb168e4c8e26a1a16ed34d9fc7fe92c1481579\
+         338da362cb8d9f925d7cb"
+    );
+
+    assert_eq!(extract(&salt, &ikm)[..], prk[..]);
+    assert_eq!(expand(&prk, &info, okm.len()).unwrap(), okm);
+    assert_eq!(hkdf_sha512(&salt, &ikm, &info, okm.len()).unwrap(), okm);
+}
+
+#[test]
+fn empty_salt_is_treated_as_zeros() {
+    let ikm = b"input keying material";
+    assert_eq!(extract(&[], ikm), extract(&[0; 64], ikm));
+}
+
+#[test]
+fn len_over_max_is_rejected() {
+    let ikm = b"input keying material";
+    assert!(hkdf_sha512(&[], ikm, b"", HKDF_SHA512_MAX_LEN).is_ok());
+    assert!(hkdf_sha512(&[], ikm, b"", HKDF_SHA512_MAX_LEN + 1).is_err());
+}
@@ -0,0 +1,56 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Tests for `crypt::hmac_sha512` and `crypt::HmacSha512`, using the HMAC-SHA-512 test vectors
+//! published in [RFC 4231](https://www.rfc-editor.org/rfc/rfc4231).
+
+use crypt::{hmac_sha512, verify_hmac_sha512, HmacSha512};
+
+fn from_hex(s: &str) -> Vec<u8> {
+    (0 .. s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i .. i + 2], 16).unwrap()).collect()
+}
+
+#[test]
+fn rfc4231_test_case_1() {
+    let key = [0x0b; 20];
+    let data = b"Hi There";
+    let tag = from_hex(
+        "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde\
+         daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854"
+    );
+
+    assert_eq!(hmac_sha512(&key, data)[..], tag[..]);
+    assert!(verify_hmac_sha512(&key, data, &hmac_sha512(&key, data)));
+
+    let mut mac = HmacSha512::new(&key);
+    mac.update(&data[.. 3]);
+    mac.update(&data[3 ..]);
+    assert_eq!(mac.finalize()[..], tag[..]);
+}
+
+#[test]
+fn rfc4231_test_case_2() {
+    let key = b"Jefe";
+    let data = b"what do ya want for nothing?";
+    let tag = from_hex(
+        "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549\
+         758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+    );
+
+    assert_eq!(hmac_sha512(key, data)[..], tag[..]);
+}
@@ -29,25 +29,95 @@ use {
         io::{BufReader, BufRead},
         path::PathBuf,
     },
-    crypt::sha512,
+    crypt::{sha512, sha384, sha256, sha224, Sha512, Sha384, Sha256, Sha224},
 };
 
+// A digest-length-agnostic view of the incremental hashers exported by `crypt`, so the RSP driver
+// below can be written once and instantiated for each SHA-2 variant.
+trait IncrementalHash<const N: usize> {
+    fn new() -> Self;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> [u8; N];
+    fn oneshot(data: &[u8]) -> [u8; N];
+}
+
+macro_rules! impl_incremental_hash {
+    ($hasher:ty, $oneshot:ident, $len:expr) => {
+        impl IncrementalHash<$len> for $hasher {
+            fn new() -> Self { <$hasher>::new() }
+            fn update(&mut self, data: &[u8]) { <$hasher>::update(self, data) }
+            fn finalize(self) -> [u8; $len] { <$hasher>::finalize(self) }
+            fn oneshot(data: &[u8]) -> [u8; $len] { $oneshot(data) }
+        }
+    };
+}
+
+impl_incremental_hash!(Sha512, sha512, 64);
+impl_incremental_hash!(Sha384, sha384, 48);
+impl_incremental_hash!(Sha256, sha256, 32);
+impl_incremental_hash!(Sha224, sha224, 28);
+
 #[test]
 fn sha512_short_msg() {
-    sha512_test("SHA512ShortMsg.rsp");
+    nist_test::<Sha512, 64>("SHA512ShortMsg.rsp");
 }
 
 #[test]
 fn sha512_long_msg() {
-    sha512_test("SHA512LongMsg.rsp");
+    nist_test::<Sha512, 64>("SHA512LongMsg.rsp");
 }
 
 #[test]
 fn sha512_monte_carlo() {
-    sha512_test("SHA512Monte.txt");
+    nist_test::<Sha512, 64>("SHA512Monte.txt");
+}
+
+#[test]
+fn sha384_short_msg() {
+    nist_test::<Sha384, 48>("SHA384ShortMsg.rsp");
+}
+
+#[test]
+fn sha384_long_msg() {
+    nist_test::<Sha384, 48>("SHA384LongMsg.rsp");
+}
+
+#[test]
+fn sha384_monte_carlo() {
+    nist_test::<Sha384, 48>("SHA384Monte.txt");
+}
+
+#[test]
+fn sha256_short_msg() {
+    nist_test::<Sha256, 32>("SHA256ShortMsg.rsp");
 }
 
-fn sha512_test(rsp_filename: &str) {
+#[test]
+fn sha256_long_msg() {
+    nist_test::<Sha256, 32>("SHA256LongMsg.rsp");
+}
+
+#[test]
+fn sha256_monte_carlo() {
+    nist_test::<Sha256, 32>("SHA256Monte.txt");
+}
+
+#[test]
+fn sha224_short_msg() {
+    nist_test::<Sha224, 28>("SHA224ShortMsg.rsp");
+}
+
+#[test]
+fn sha224_long_msg() {
+    nist_test::<Sha224, 28>("SHA224LongMsg.rsp");
+}
+
+#[test]
+fn sha224_monte_carlo() {
+    nist_test::<Sha224, 28>("SHA224Monte.txt");
+}
+
+fn nist_test<H: IncrementalHash<N>, const N: usize>(rsp_filename: &str) {
     let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     path.push("tests");
     path.push("shabytetestvectors");
@@ -61,7 +131,7 @@ fn sha512_test(rsp_filename: &str) {
 
     // Monte Carlo test state
     let mut m = Vec::new();
-    let mut md = [const { [0; 64] }; 1003];
+    let mut md = vec![[0; N]; 1003];
     let mut i = 3;
     let mut j = 0;
     let mut checkpoint_i = None;
@@ -133,7 +203,7 @@ fn sha512_test(rsp_filename: &str) {
         }
 
         if var == "M" {
-            do_monte_carlo(&mut i, &mut j, checkpoint_i, checkpoint_j, &mut md, &mut m);
+            do_monte_carlo::<H, N>(&mut i, &mut j, checkpoint_i, checkpoint_j, &mut md, &mut m);
             let expected = value.chars().array_chunks::<2>()
                 .map(|cs| u8::from_str_radix(&cs.iter().collect::<String>(), 16).expect("invalid message"))
                 .collect::<Vec<u8>>();
@@ -142,7 +212,7 @@ fn sha512_test(rsp_filename: &str) {
         }
 
         if var == "MDi" {
-            let mdi = do_monte_carlo(&mut i, &mut j, checkpoint_i, checkpoint_j, &mut md, &mut m);
+            let mdi = do_monte_carlo::<H, N>(&mut i, &mut j, checkpoint_i, checkpoint_j, &mut md, &mut m);
             let expected = value.chars().array_chunks::<2>()
                 .map(|cs| u8::from_str_radix(&cs.iter().collect::<String>(), 16).expect("invalid message digest"))
                 .collect::<Vec<u8>>();
@@ -152,11 +222,11 @@ fn sha512_test(rsp_filename: &str) {
 
         if var == "MD" {
             let md = if checkpoint_j.is_some() {
-                do_monte_carlo(&mut i, &mut j, None, checkpoint_j, &mut md, &mut m)
+                do_monte_carlo::<H, N>(&mut i, &mut j, None, checkpoint_j, &mut md, &mut m)
             } else {
                 let msg = msg.as_ref().expect("missing message");
                 let len = len.expect("missing length");
-                sha512(&msg[0 .. len])
+                H::oneshot(&msg[0 .. len])
             };
             let expected = value.chars().array_chunks::<2>()
                 .map(|cs| u8::from_str_radix(&cs.iter().collect::<String>(), 16).expect("invalid message digest"))
@@ -169,24 +239,33 @@ fn sha512_test(rsp_filename: &str) {
     }
 }
 
-fn do_monte_carlo(
+fn do_monte_carlo<H: IncrementalHash<N>, const N: usize>(
     i: &mut usize,
     j: &mut usize,
     checkpoint_i: Option<usize>,
     checkpoint_j: Option<usize>,
-    md: &mut [[u8; 64]; 1003],
+    md: &mut [[u8; N]],
     m: &mut Vec<u8>,
-) -> [u8; 64] {
+) -> [u8; N] {
     let checkpoint_j = checkpoint_j.expect("no checkpoint");
     let checkpoint_i = checkpoint_i.unwrap_or(1002);
 
+    // `m` is only needed in its concatenated form to check against the RSP file's `M` values, so
+    // it's filled in place here instead of being reallocated every iteration. The digest itself
+    // is computed by feeding the three checkpoints to a streaming hasher directly, without ever
+    // materializing them as a single contiguous buffer.
+    m.resize(3 * N, 0);
+
     loop {
-        *m = md[*i - 3].iter()
-            .chain(md[*i - 2].iter())
-            .chain(md[*i - 1].iter())
-            .map(|&x| x)
-            .collect();
-        md[*i] = sha512(m);
+        m[0 * N .. 1 * N].copy_from_slice(&md[*i - 3]);
+        m[1 * N .. 2 * N].copy_from_slice(&md[*i - 2]);
+        m[2 * N .. 3 * N].copy_from_slice(&md[*i - 1]);
+
+        let mut hasher = H::new();
+        hasher.update(&md[*i - 3]);
+        hasher.update(&md[*i - 2]);
+        hasher.update(&md[*i - 1]);
+        md[*i] = hasher.finalize();
 
         if *j == checkpoint_j && *i == checkpoint_i {
             break;
@@ -0,0 +1,223 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! This program is the Phoenix operating system's driver for the VirtIO entropy source
+//! ([specification]). Rather than handing the device's raw output straight to callers, it
+//! harvests buffers from the device into a local [`EntropyPool`], crediting the pool with an
+//! entropy estimate before any bytes can be drawn back out.
+//! [specification]: https://docs.oasis-open.org/virtio/virtio/v1.1/cs01/virtio-v1.1-cs01.html#x1-2430002
+//!
+//! # Required permissions:
+//! * own device mmio/virtio-4
+
+#![no_std]
+#![deny(/*warnings, */missing_docs)]
+
+#![feature(allocator_api)]
+#![feature(default_alloc_error_handler)]
+#![feature(inline_const)]
+#![feature(start)]
+
+extern crate alloc;
+
+use {
+    core::{
+        arch::asm,
+        cell::RefCell,
+        fmt::Write,
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+    },
+    libphoenix::{
+        allocator::{Allocator, PhysBox},
+        future::SysCallExecutor,
+    },
+    libdriver::Device,
+    virtio::{
+        DeviceDetails, GenericFeatures, QueueConfig,
+        virtqueue::{SendRecvResult, VirtQueue, future::Executor},
+    },
+    self::pool::EntropyPool,
+};
+
+mod pool;
+
+const DEVICE_TYPE_ENTROPY: u32 = 4;
+
+// The size of each buffer handed to the device to be filled with random bytes.
+const HARVEST_BUF_SIZE: usize = 64;
+// How many harvest buffers are kept in flight at once.
+const HARVEST_BUFFERS_COUNT: usize = 4;
+
+fn main() {
+    SysCallExecutor::new()
+        .spawn(async {
+            let device = Device::claim("mmio/virtio-4").await
+                .expect("no VirtIO entropy source found");
+            run_driver(device);
+        })
+        .block_on_all();
+}
+
+fn run_driver(device: Device<'_>) {
+    let mut device_details = match virtio::init(
+            &device,
+            DEVICE_TYPE_ENTROPY,
+            0, // The entropy device has no device-specific configuration space.
+            QueueIndex::Count as u32,
+            GenericFeatures::empty().bits(),
+            (GenericFeatures::VERSION_1 | GenericFeatures::ANY_LAYOUT).bits(),
+            |_queue_index| QueueConfig::default(),
+    ) {
+        Ok(x) => x,
+        Err(e) => panic!("failed to initialize the VirtIO entropy source: {}", e)
+    };
+
+    let virtqueues = device_details.virtqueues();
+    let request_q = &virtqueues[QueueIndex::Request as usize];
+
+    let pool = RefCell::new(EntropyPool::new());
+
+    let mut executor = Executor::new();
+    for _ in 0 .. HARVEST_BUFFERS_COUNT {
+        executor.spawn(harvest(request_q, &pool));
+    }
+
+    // Demonstrate the blocking API by drawing a fully-credited buffer out of the pool.
+    executor.spawn(async {
+        let mut buf = [0u8; 32];
+        fill(&pool, &mut buf).await;
+        let _ = writeln!(KernelWriter, "virtio-entropy: drew {} bytes from the pool", buf.len());
+    });
+
+    executor.block_on_all();
+}
+
+// Repeatedly hands a buffer to the device, mixes whatever comes back into the pool, and hands the
+// same buffer back for another round. Runs forever, so it should be spawned and left in the
+// executor rather than awaited to completion.
+async fn harvest<'a>(request_q: &'a VirtQueue<'a>, pool: &'a RefCell<EntropyPool>) {
+    const MAX_ADDR_BITS: usize = 44;
+    let mut buf = match Allocator.malloc_phys::<[u8; HARVEST_BUF_SIZE]>(MAX_ADDR_BITS) {
+        Ok(mut buf) => {
+            buf.write([0; HARVEST_BUF_SIZE]);
+            PhysBox::assume_init(buf)
+        },
+        Err(_) => {
+            let _ = writeln!(KernelWriter, "virtio-entropy: WARNING: failed to allocate a harvest buffer");
+            return;
+        }
+    };
+
+    loop {
+        buf = match request_q.send_recv(buf, 0, Some(HARVEST_BUF_SIZE)) {
+            SendRecvResult::Ok(future) => {
+                let response = future.await;
+                let valid_bytes = response.valid_bytes();
+                pool.borrow_mut().add(&response.buffer()[.. valid_bytes]);
+                response.into_buffer()
+            },
+            SendRecvResult::Retry(buf) => {
+                RelaxFuture::new().await;
+                buf
+            },
+            SendRecvResult::Err(e) => {
+                let _ = writeln!(KernelWriter, "virtio-entropy: ERROR: {e}");
+                return;
+            }
+        };
+    }
+}
+
+/// Asynchronously draws `buf.len()` bytes out of the pool, waiting until enough entropy has been
+/// credited to cover the request and decrementing the pool's credit afterward.
+pub async fn fill(pool: &RefCell<EntropyPool>, buf: &mut [u8]) {
+    loop {
+        if pool.borrow_mut().try_fill(buf) {
+            return;
+        }
+        RelaxFuture::new().await;
+    }
+}
+
+/// Draws `buf.len()` bytes out of the pool immediately if it's fully credited, or leaves `buf`
+/// untouched and returns `false` if not. Unlike [`fill`], this never waits.
+pub fn try_fill(pool: &RefCell<EntropyPool>, buf: &mut [u8]) -> bool {
+    pool.borrow_mut().try_fill(buf)
+}
+
+// A future that returns `Pending` once, then `Ready`. The purpose is to allow other futures to run
+// while an `async` block waits for an external event.
+struct RelaxFuture {
+    finished: bool
+}
+
+impl RelaxFuture {
+    const fn new() -> Self {
+        Self { finished: false }
+    }
+}
+
+impl Future for RelaxFuture {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<Self::Output> {
+        if self.finished {
+            Poll::Ready(())
+        } else {
+            self.finished = true;
+            Poll::Pending
+        }
+    }
+}
+
+#[repr(u32)]
+enum QueueIndex {
+    Request = 0,
+    Count   = 1,
+}
+
+// FIXME: Remove this debugging aid.
+struct KernelWriter;
+
+impl core::fmt::Write for KernelWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for c in s.chars() {
+            self.write_char(c)?;
+        }
+        Ok(())
+    }
+
+    fn write_char(&mut self, c: char) -> core::fmt::Result {
+        unsafe {
+            asm!(
+                "svc 0xff00",
+                in("x2") u64::from(u32::from(c)),
+                options(nomem, preserves_flags, nostack)
+            );
+        }
+        Ok(())
+    }
+}
+
+#[panic_handler]
+fn panic_handler(p: &core::panic::PanicInfo) -> ! {
+    let _ = write!(KernelWriter, "Unexpected error: {}\n", p);
+    libphoenix::syscall::thread_exit(255) // TODO: Use a named constant for the exit status.
+}
@@ -0,0 +1,80 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! A small entropy pool that accumulates randomness harvested from the VirtIO device and only
+//! doles it back out once enough has been credited to it. This is the same discipline as Linux's
+//! `RNDADDTOENTCNT`: bytes fed into the pool add to an entropy estimate before anything can be
+//! drawn back out, so a caller can never silently receive fewer "real" random bits than it asked
+//! for, even if the device itself turns out to be a weak or predictable source.
+
+/// An entropy pool fed by harvested device bytes and drained by [`try_fill`](EntropyPool::try_fill).
+pub struct EntropyPool {
+    state: [u8; Self::SIZE],
+    entropy_bits: usize,
+}
+
+impl EntropyPool {
+    const SIZE: usize = 64;
+
+    /// Creates an empty pool with no credited entropy.
+    pub const fn new() -> Self {
+        Self { state: [0; Self::SIZE], entropy_bits: 0 }
+    }
+
+    /// Mixes freshly harvested bytes into the pool and credits it with that many bits of entropy,
+    /// capped at the pool's size. The mixing step is just a diffusion, not a cryptographic hash:
+    /// each harvested byte is folded into one pool byte (after rotating it, so harvesting the
+    /// same buffer twice doesn't cancel out), which is then carried into the next pool byte. A
+    /// pool byte therefore depends on the harvested bytes at and before its position, not on
+    /// every harvested byte.
+    pub fn add(&mut self, bytes: &[u8]) {
+        for (i, &byte) in bytes.iter().enumerate() {
+            let idx = i % Self::SIZE;
+            self.state[idx] = self.state[idx].rotate_left(1) ^ byte;
+            self.state[(idx + 1) % Self::SIZE] ^= self.state[idx].rotate_left(3);
+        }
+        self.entropy_bits = usize::min(self.entropy_bits + bytes.len() * 8, Self::SIZE * 8);
+    }
+
+    /// Returns `true` if the pool currently has enough credited entropy to satisfy a draw of
+    /// `len` bytes.
+    pub fn ready_for(&self, len: usize) -> bool {
+        self.entropy_bits >= len * 8
+    }
+
+    /// Draws `buf.len()` bytes out of the pool without blocking, but only if it already has
+    /// enough credited entropy to cover the request. Returns `false` and leaves `buf` untouched
+    /// otherwise.
+    pub fn try_fill(&mut self, buf: &mut [u8]) -> bool {
+        if !self.ready_for(buf.len()) {
+            return false;
+        }
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let idx = i % Self::SIZE;
+            *byte = self.state[idx];
+            // Re-mix what was just handed out so the same bytes are never drawn twice.
+            self.state[idx] = self.state[idx].rotate_left(5) ^ (i as u8);
+        }
+        self.entropy_bits -= buf.len() * 8;
+        true
+    }
+}
+
+impl Default for EntropyPool {
+    fn default() -> Self { Self::new() }
+}
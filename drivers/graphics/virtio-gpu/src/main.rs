@@ -46,7 +46,7 @@ use {
     },
     libdriver::Device,
     virtio::{
-        DeviceEndian, DeviceDetails, GenericFeatures,
+        DeviceEndian, DeviceDetails, GenericFeatures, QueueConfig,
         virtqueue::future::Executor
     },
     self::api::*,
@@ -81,7 +81,8 @@ fn run_driver<'a, I>(kernel_profile: I, start_time_nanos: u64, device: Device<'_
             ConfigurationSpace::SIZE,
             QueueIndex::Count as u32,
             Features::empty().bits(),
-            (Features::ANY_LAYOUT | Features::VERSION_1 | Features::ORDER_PLATFORM).bits()
+            (Features::ANY_LAYOUT | Features::VERSION_1 | Features::ORDER_PLATFORM).bits(),
+            |_queue_index| QueueConfig::default()
     ) {
         Ok(x) => x,
         Err(e) => panic!("failed to initialize the VirtIO GPU: {}", e)
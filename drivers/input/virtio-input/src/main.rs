@@ -52,7 +52,7 @@ use {
     },
     libdriver::Device,
     virtio::{
-        DeviceEndian, DeviceDetails, GenericFeatures,
+        DeviceEndian, DeviceDetails, GenericFeatures, QueueConfig,
         virtqueue::{
             VirtQueue,
             future::Executor,
@@ -85,6 +85,7 @@ fn main() {
                     GenericFeatures::IN_ORDER |
                     GenericFeatures::ORDER_PLATFORM
                 ).bits(),
+                |_queue_index| QueueConfig::default(),
             ) {
                 Ok(x) => x,
                 Err(e) => {
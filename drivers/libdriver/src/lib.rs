@@ -139,5 +139,8 @@ pub struct Resource {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BusType {
     /// Memory-mapped I/O (registers are accessed just like RAM)
-    Mmio = 0
+    Mmio = 0,
+
+    /// The PCI (or PCI Express) bus
+    Pci = 1
 }
@@ -25,7 +25,7 @@
 extern crate alloc;
 
 use {
-    alloc::vec::Vec,
+    alloc::{boxed::Box, vec::Vec},
     core::{
         convert::TryInto,
         fmt,
@@ -40,15 +40,24 @@ use {
 };
 
 pub mod virtqueue;
+pub mod volatile;
+
+use self::volatile::{ReadOnly, WriteOnly, ReadWrite};
 
 /// Initializes the given device.
-pub fn init<'a>(
+///
+/// # Parameters
+/// * `queue_config`: Called with each queue's index to decide that queue's [`DriverFlags`] and
+///   maximum length. A driver that only cares about one or two queues can ignore the index and
+///   return the same [`QueueConfig`] for all of them.
+pub fn init<'a, F: Fn(u32) -> QueueConfig>(
         device:            &'a Device,
         device_type:       u32,
         config_space_size: usize,
         queues_count:      u32,
         required_features: u64,
-        optional_features: u64
+        optional_features: u64,
+        queue_config:      F
 ) -> Result<DeviceDetails<'a>, VirtIoInitError> {
     let resources = device.resources();
     if resources.len() == 0 {
@@ -63,38 +72,68 @@ pub fn init<'a>(
             config_space_size,
             queues_count,
             required_features,
-            optional_features
+            optional_features,
+            queue_config
+        ),
+        BusType::Pci => init_pci(
+            resources,
+            device_type,
+            config_space_size,
+            queues_count,
+            required_features,
+            optional_features,
+            queue_config
         )
     }
 }
 
-fn init_mmio<'a>(
+/// Per-queue configuration accepted by [`init`], letting a driver give each of its virtqueues a
+/// different [`DriverFlags`] and length cap instead of every queue being forced into the same
+/// defaults (e.g. a large receive queue for virtio-net alongside small queues for everything else,
+/// or opting a particular queue into `RING_EVENT_INDEX`-based interrupt suppression).
+#[derive(Debug, Clone, Copy)]
+pub struct QueueConfig {
+    /// The flags this queue's driver-owned structures should be initialized with.
+    pub flags: DriverFlags,
+    /// The largest length this queue should be allowed to have. This is still capped by whatever
+    /// the device itself reports as its maximum; `None` caps the length only at the device's
+    /// maximum and at 0x8000.
+    pub max_len: Option<u16>
+}
+
+impl Default for QueueConfig {
+    /// Returns the configuration every queue used before per-queue configuration existed: no
+    /// special flags and no length cap beyond the device's own maximum and 0x8000.
+    fn default() -> Self {
+        QueueConfig { flags: DriverFlags::empty(), max_len: None }
+    }
+}
+
+fn init_mmio<'a, F: Fn(u32) -> QueueConfig>(
         resource:          &'a Resource,
         device_type:       u32,
         config_space_size: usize,
         queues_count:      u32,
         required_features: u64,
-        optional_features: u64
+        optional_features: u64,
+        queue_config:      F
 ) -> Result<DeviceDetails<'a>, VirtIoInitError> {
+    const REGS_SIZE: usize = mem::size_of::<MmioRegisterBlock>();
+
     assert_eq!(resource.bus, BusType::Mmio);
-    if resource.size < 0x100 {
-        return Err(VirtIoInitError::TooFewRegisters(0x100, resource.size));
+    if resource.size < REGS_SIZE {
+        return Err(VirtIoInitError::TooFewRegisters(REGS_SIZE, resource.size));
     }
-    if resource.size < 0x100 + config_space_size {
-        return Err(VirtIoInitError::TooLittleConfigSpace(config_space_size, resource.size - 0x100))
+    if resource.size < REGS_SIZE + config_space_size {
+        return Err(VirtIoInitError::TooLittleConfigSpace(config_space_size, resource.size - REGS_SIZE))
     }
     let mut regs = MmioRegisters {
-        slice: unsafe {
-            slice::from_raw_parts_mut(
-                resource.base as *mut u32,
-                0x100 / mem::size_of::<u32>()
-            )
-        }
+        block: unsafe { &mut *(resource.base as *mut MmioRegisterBlock) }
     };
     let configuration_space = unsafe {
         slice::from_raw_parts_mut(
-            resource.base.wrapping_add(0x100) as *mut u8,
-            resource.size - 0x100
+            resource.base.wrapping_add(REGS_SIZE) as *mut u8,
+            resource.size - REGS_SIZE
         )
     };
     validate_mmio(&mut regs, device_type)?;
@@ -104,7 +143,144 @@ fn init_mmio<'a>(
         .or_status(DeviceStatus::ACKNOWLEDGE)
         .or_status(DeviceStatus::DRIVER);
 
-    // Negotiate features.
+    let (features, legacy) = negotiate_features(&mut regs, required_features, optional_features)?;
+
+    // Initialize the virtqueues. Legacy devices use a page-based queue layout that no other
+    // transport in this crate needs to understand, so it's handled here instead of being folded
+    // into `setup_queues_modern`.
+    let virtqueues = if legacy {
+        let page_size = syscall::memory_page_size();
+        regs.legacy_set_guest_page_size(
+            page_size.try_into().expect("page size exceeds 32 bits")
+        );
+
+        let mut virtqueues = Vec::new();
+        for queue_index in 0 .. queues_count {
+            regs.select_queue(queue_index);
+            assert_eq!(regs.legacy_queue_page_number(), 0, "virtqueue {} already in use", queue_index);
+            let max_queue_len = regs.queue_len_max();
+            if max_queue_len == 0 {
+                // Assume for now that this virtqueue isn't necessary. If the driver needs this queue, it can
+                // panic after we finish.
+                continue;
+            }
+            let config = queue_config(queue_index);
+            let queue_len = u32::min(max_queue_len, config.max_len.map_or(0x8000, u32::from));
+            let queue = VirtQueue::new(resource, features, legacy, queue_index, queue_len as u16, config.flags);
+            regs.set_queue_len(queue_len);
+            regs.legacy_set_device_ring_align(
+                VirtQueue::LEGACY_DEVICE_RING_ALIGN.try_into().expect("device ring alignment exceeds 32 bits")
+            );
+            let page_number = (queue.descriptors_addr_phys() / page_size).try_into()
+                .expect("virtqueue address is too high");
+            regs.legacy_set_queue_page_number(page_number);
+            virtqueues.push(queue);
+        }
+        virtqueues
+    } else {
+        setup_queues_modern(&mut regs, resource, queues_count, features, queue_config)
+    };
+
+    regs.or_status(DeviceStatus::DRIVER_OK);
+
+    Ok(DeviceDetails {
+        legacy,
+        features,
+        configuration_space,
+        virtqueues,
+        regs: Transport::Mmio(regs)
+    })
+}
+
+fn init_pci<'a, F: Fn(u32) -> QueueConfig>(
+        resources:         &'a [Resource],
+        device_type:       u32,
+        config_space_size: usize,
+        queues_count:      u32,
+        required_features: u64,
+        optional_features: u64,
+        queue_config:      F
+) -> Result<DeviceDetails<'a>, VirtIoInitError> {
+    let cfg_resource = &resources[0];
+    assert_eq!(cfg_resource.bus, BusType::Pci);
+    if cfg_resource.size < 0x40 {
+        return Err(VirtIoInitError::TooFewRegisters(0x40, cfg_resource.size));
+    }
+    let pci_cfg = unsafe { slice::from_raw_parts(cfg_resource.base as *const u8, cfg_resource.size) };
+    validate_pci(pci_cfg, device_type)?;
+
+    let (common_cap, notify_cap, isr_cap, device_cap) = find_pci_caps(pci_cfg);
+    let common_cap = common_cap.ok_or(VirtIoInitError::MissingCapability("common configuration"))?;
+    let (notify_cap, notify_off_multiplier) = notify_cap.ok_or(VirtIoInitError::MissingCapability("notification configuration"))?;
+    let isr_cap = isr_cap.ok_or(VirtIoInitError::MissingCapability("ISR status"))?;
+    let device_cap = device_cap.ok_or(VirtIoInitError::MissingCapability("device-specific configuration"))?;
+
+    if (device_cap.length as usize) < config_space_size {
+        return Err(VirtIoInitError::TooLittleConfigSpace(config_space_size, device_cap.length as usize));
+    }
+    if (common_cap.length as usize) < 0x38 {
+        return Err(VirtIoInitError::MissingCapability("common configuration (too short)"));
+    }
+
+    let pci_bar = |cap: &PciCap| -> Result<&'a Resource, VirtIoInitError> {
+        resources.get(1 + cap.bar as usize)
+            .filter(|bar_resource| bar_resource.bus == BusType::Pci)
+            .ok_or(VirtIoInitError::MissingCapability("a BAR referenced by a virtio capability"))
+    };
+
+    let common = unsafe {
+        slice::from_raw_parts_mut(
+            (pci_bar(&common_cap)?.base + common_cap.offset as usize) as *mut u8,
+            common_cap.length as usize
+        )
+    };
+    let notify_base = pci_bar(&notify_cap)?.base + notify_cap.offset as usize;
+    let isr = unsafe { &mut *((pci_bar(&isr_cap)?.base + isr_cap.offset as usize) as *mut u8) };
+    let configuration_space = unsafe {
+        slice::from_raw_parts_mut(
+            (pci_bar(&device_cap)?.base + device_cap.offset as usize) as *mut u8,
+            device_cap.length as usize
+        )
+    };
+
+    let mut regs = PciRegisters { common, isr, notify_base, notify_off_multiplier };
+
+    // Reset and acknowledge the device.
+    regs.set_status(DeviceStatus::empty())
+        .or_status(DeviceStatus::ACKNOWLEDGE)
+        .or_status(DeviceStatus::DRIVER);
+
+    let (features, legacy) = negotiate_features(&mut regs, required_features, optional_features)?;
+    if legacy {
+        // The capability list we just parsed is part of the modern virtio-pci layout, so this can
+        // only happen if the device negotiated away VERSION_1 anyway. We don't implement the
+        // legacy (transitional) virtio-pci queue layout, so refuse rather than guess at one.
+        regs.or_status(DeviceStatus::FAILED);
+        return Err(VirtIoInitError::FeatureNegotiationFailed);
+    }
+
+    let virtqueues = setup_queues_modern(&mut regs, cfg_resource, queues_count, features, queue_config);
+
+    regs.or_status(DeviceStatus::DRIVER_OK);
+
+    Ok(DeviceDetails {
+        legacy,
+        features,
+        configuration_space,
+        virtqueues,
+        regs: Transport::Pci(regs)
+    })
+}
+
+/// Negotiates the feature set with the device, returning the agreed-upon features and whether the
+/// device turned out to be a legacy (pre-1.0) device. Shared between transports; only the MMIO
+/// transport goes on to use a legacy device, since the PCI transport implemented here only speaks
+/// the modern interface and refuses a device for which this returns `true`.
+fn negotiate_features<'a, R: VirtioRegisters<'a>>(
+        regs:              &mut R,
+        required_features: u64,
+        optional_features: u64
+) -> Result<(u64, bool), VirtIoInitError> {
     let device_features = regs.device_features();
     if required_features & !device_features != 0 {
         return Err(VirtIoInitError::MissingRequiredFeatures(required_features, device_features));
@@ -128,58 +304,42 @@ fn init_mmio<'a>(
         }
     }
 
-    // Initialize the virtqueues.
-    let page_size;
+    Ok((features, legacy))
+}
+
+/// Sets up every virtqueue the driver asked for, using the modern (non-legacy) register layout
+/// that the MMIO and PCI transports share. `device_resource` is the resource that virtqueues
+/// should notify through by default; [`VirtioRegisters::queue_notify_resource`] may override this
+/// per queue for transports (like PCI) that notify each queue through a different address.
+fn setup_queues_modern<'a, R: VirtioRegisters<'a>, F: Fn(u32) -> QueueConfig>(
+        regs:            &mut R,
+        device_resource: &'a Resource,
+        queues_count:    u32,
+        features:        u64,
+        queue_config:    F
+) -> Vec<VirtQueue<'a>> {
     let mut virtqueues = Vec::new();
-    if legacy {
-        page_size = syscall::memory_page_size();
-        regs.legacy_set_guest_page_size(
-            page_size.try_into().expect("page size exceeds 32 bits")
-        );
-    } else {
-        page_size = 0; // This isn't used here except in legacy devices.
-    }
     for queue_index in 0 .. queues_count {
         regs.select_queue(queue_index);
-        if legacy {
-            assert_eq!(regs.legacy_queue_page_number(), 0, "virtqueue {} already in use", queue_index);
-        } else {
-            assert!(!regs.queue_ready(), "virtqueue {} already in use", queue_index);
-        }
+        assert!(!regs.queue_ready(), "virtqueue {} already in use", queue_index);
         let max_queue_len = regs.queue_len_max();
         if max_queue_len == 0 {
             // Assume for now that this virtqueue isn't necessary. If the driver needs this queue, it can
             // panic after we finish.
             continue;
         }
-        let queue_len = u32::min(max_queue_len, 0x8000);
-        // TODO: Allow the driver to specify the DriverFlags for each virtqueue.
-        let queue = VirtQueue::new(resource, features, legacy, queue_index, queue_len as u16, DriverFlags::empty());
+        let config = queue_config(queue_index);
+        let queue_len = u32::min(max_queue_len, config.max_len.map_or(0x8000, u32::from));
+        let notify_resource = regs.queue_notify_resource(device_resource);
+        let queue = VirtQueue::new(notify_resource, features, false, queue_index, queue_len as u16, config.flags);
+        regs.set_queue_descriptor_area(queue.descriptors_addr_phys().try_into().unwrap());
+        regs.set_queue_driver_area(queue.driver_ring_addr_phys().try_into().unwrap());
+        regs.set_queue_device_area(queue.device_ring_addr_phys().try_into().unwrap());
         regs.set_queue_len(queue_len);
-        if legacy {
-            regs.legacy_set_device_ring_align(
-                VirtQueue::LEGACY_DEVICE_RING_ALIGN.try_into().expect("device ring alignment exceeds 32 bits")
-            );
-            let page_number = (queue.descriptors_addr_phys() / page_size).try_into()
-                .expect("virtqueue address is too high");
-            regs.legacy_set_queue_page_number(page_number);
-        } else {
-            regs.set_queue_descriptor_area(queue.descriptors_addr_phys().try_into().unwrap());
-            regs.set_queue_driver_area(queue.driver_ring_addr_phys().try_into().unwrap());
-            regs.set_queue_device_area(queue.device_ring_addr_phys().try_into().unwrap());
-            regs.set_queue_ready(true);
-        }
+        regs.set_queue_ready(true);
         virtqueues.push(queue);
     }
-
-    regs.or_status(DeviceStatus::DRIVER_OK);
-
-    Ok(DeviceDetails {
-        legacy,
-        features,
-        configuration_space,
-        virtqueues
-    })
+    virtqueues
 }
 
 fn validate_mmio<'a>(
@@ -205,86 +365,285 @@ fn validate_mmio<'a>(
     Ok(())
 }
 
+/// Looks up the device's vendor and device IDs in PCI config space, in lieu of a magic number.
+fn validate_pci(pci_cfg: &[u8], device_type: u32) -> Result<(), VirtIoInitError> {
+    const VIRTIO_PCI_VENDOR_ID:      u16 = 0x1af4;
+    const VIRTIO_PCI_DEVICE_ID_BASE: u16 = 0x1040;
+
+    let vendor_id = u16::from_le_bytes(pci_cfg[0x00 .. 0x02].try_into().unwrap());
+    if vendor_id != VIRTIO_PCI_VENDOR_ID {
+        return Err(VirtIoInitError::WrongVendor(VIRTIO_PCI_VENDOR_ID, vendor_id));
+    }
+    let device_id = u16::from_le_bytes(pci_cfg[0x02 .. 0x04].try_into().unwrap());
+    let found_device_type = u32::from(device_id.wrapping_sub(VIRTIO_PCI_DEVICE_ID_BASE));
+    if found_device_type != device_type {
+        return Err(VirtIoInitError::WrongDeviceType(device_type, found_device_type));
+    }
+
+    Ok(())
+}
+
+/// One of the vendor-specific PCI capabilities that a modern virtio-pci device uses to point the
+/// driver at its registers, as found by [`find_pci_caps`].
+struct PciCap {
+    bar:    u8,
+    offset: u32,
+    length: u32
+}
+
+/// Walks a PCI device's capability list (starting from the pointer at the standard
+/// `PCI_CAPABILITIES_POINTER` offset) looking for the four vendor-specific virtio capabilities
+/// that every modern virtio-pci device must expose: common configuration, notification
+/// configuration (along with its `notify_off_multiplier`), ISR status and device-specific
+/// configuration.
+fn find_pci_caps(pci_cfg: &[u8]) -> (Option<PciCap>, Option<(PciCap, u32)>, Option<PciCap>, Option<PciCap>) {
+    const PCI_CAP_ID_VENDOR_SPECIFIC: u8 = 0x09;
+    const PCI_CAPABILITIES_POINTER:   usize = 0x34;
+
+    const VIRTIO_PCI_CAP_COMMON_CFG: u8 = 1;
+    const VIRTIO_PCI_CAP_NOTIFY_CFG: u8 = 2;
+    const VIRTIO_PCI_CAP_ISR_CFG:    u8 = 3;
+    const VIRTIO_PCI_CAP_DEVICE_CFG: u8 = 4;
+
+    let mut common = None;
+    let mut notify = None;
+    let mut isr = None;
+    let mut device = None;
+
+    let mut cap_ptr = pci_cfg[PCI_CAPABILITIES_POINTER] as usize;
+    // Both the offsets this walk follows (`cap_ptr`/`cap_next`) come straight from the device's own
+    // config space, so a hostile or broken device can point them anywhere, including past the end
+    // of `pci_cfg` or into a cycle. Bounding the hop count at `pci_cfg.len()` catches both: a
+    // well-formed list can't have more entries than there are bytes to hold them, so this is never
+    // hit by a real device, and it turns a would-be infinite loop into a bailout instead.
+    for _ in 0 .. pci_cfg.len() {
+        if cap_ptr == 0 {
+            break;
+        }
+        let Some(&cap_id) = pci_cfg.get(cap_ptr) else { break };
+        let Some(&cap_next) = pci_cfg.get(cap_ptr + 1) else { break };
+
+        if cap_id == PCI_CAP_ID_VENDOR_SPECIFIC {
+            if let Some(header) = pci_cfg.get(cap_ptr .. cap_ptr + 16) {
+                let cfg_type = header[3];
+                let bar = header[4];
+                let offset = u32::from_le_bytes(header[8 .. 12].try_into().unwrap());
+                let length = u32::from_le_bytes(header[12 .. 16].try_into().unwrap());
+                let cap = PciCap { bar, offset, length };
+
+                match cfg_type {
+                    VIRTIO_PCI_CAP_COMMON_CFG => common = Some(cap),
+                    VIRTIO_PCI_CAP_NOTIFY_CFG => {
+                        if let Some(mult) = pci_cfg.get(cap_ptr + 16 .. cap_ptr + 20) {
+                            let notify_off_multiplier = u32::from_le_bytes(mult.try_into().unwrap());
+                            notify = Some((cap, notify_off_multiplier));
+                        }
+                        // Too short to hold `notify_off_multiplier`; skip this malformed capability.
+                    },
+                    VIRTIO_PCI_CAP_ISR_CFG => isr = Some(cap),
+                    VIRTIO_PCI_CAP_DEVICE_CFG => device = Some(cap),
+                    _ => {} // Not a capability we need (or a vendor-specific capability with a cfg_type
+                            // we don't recognize, e.g. VIRTIO_PCI_CAP_PCI_CFG).
+                }
+            }
+            // Too short to hold a vendor-specific capability's fixed fields; skip it.
+        }
+
+        cap_ptr = cap_next as usize;
+    }
+
+    (common, notify, isr, device)
+}
+
+/// The subset of a transport's registers needed to negotiate features and set up modern
+/// (non-legacy) virtqueues, shared by every VirtIO transport this crate supports. Legacy register
+/// layouts aren't part of this trait, since only the MMIO transport still needs to speak them.
+///
+/// This is a generic bound rather than a trait object because a couple of methods return
+/// `&mut Self`, which isn't object-safe.
+trait VirtioRegisters<'a> {
+    fn device_features(&mut self) -> u64;
+    fn set_driver_features(&mut self, features: u64) -> &mut Self;
+    fn status(&mut self) -> DeviceStatus;
+    fn set_status(&mut self, status: DeviceStatus) -> &mut Self;
+    fn or_status(&mut self, status: DeviceStatus) -> &mut Self;
+    fn select_queue(&mut self, queue_index: u32) -> &mut Self;
+    fn queue_len_max(&mut self) -> u32;
+    fn set_queue_len(&mut self, len: u32) -> &mut Self;
+    fn queue_ready(&mut self) -> bool;
+    fn set_queue_ready(&mut self, ready: bool) -> &mut Self;
+    fn set_queue_descriptor_area(&mut self, phys_addr: u64) -> &mut Self;
+    fn set_queue_driver_area(&mut self, phys_addr: u64) -> &mut Self;
+    fn set_queue_device_area(&mut self, phys_addr: u64) -> &mut Self;
+
+    /// Returns the resource that the virtqueue just selected with
+    /// [`select_queue`](Self::select_queue) should notify through. The MMIO transport shares a
+    /// single notification register between every queue, so the default implementation just
+    /// echoes back the device's own resource; the PCI transport overrides this, since each queue
+    /// notifies through a different offset into its notification BAR.
+    fn queue_notify_resource(&mut self, device_resource: &'a Resource) -> &'a Resource {
+        device_resource
+    }
+}
+
+/// The MMIO register window, laid directly over a device's resource. Every field's offset matches
+/// the VirtIO MMIO register layout exactly, so there's no longer any index arithmetic to get
+/// wrong; the reserved gaps exist purely to hold the later fields at their correct offsets.
+#[repr(C)]
+struct MmioRegisterBlock {
+    magic_number:           ReadOnly<u32>,  // 0x000
+    version:                ReadOnly<u32>,  // 0x004
+    device_id:              ReadOnly<u32>,  // 0x008
+    vendor_id:              ReadOnly<u32>,  // 0x00c
+    device_features:        ReadOnly<u32>,  // 0x010
+    device_features_sel:    WriteOnly<u32>, // 0x014
+    _reserved0:             [u32; 2],
+    driver_features:        WriteOnly<u32>, // 0x020
+    driver_features_sel:    WriteOnly<u32>, // 0x024
+    legacy_guest_page_size: WriteOnly<u32>, // 0x028 (legacy devices only)
+    _reserved1:             u32,
+    queue_sel:              WriteOnly<u32>, // 0x030
+    queue_num_max:          ReadOnly<u32>,  // 0x034
+    queue_num:              WriteOnly<u32>, // 0x038
+    legacy_queue_align:     WriteOnly<u32>, // 0x03c (legacy devices only)
+    legacy_queue_pfn:       ReadWrite<u32>, // 0x040 (legacy devices only)
+    queue_ready:            ReadWrite<u32>, // 0x044
+    _reserved2:             [u32; 2],
+    queue_notify:           WriteOnly<u32>, // 0x050
+    _reserved3:             [u32; 3],
+    interrupt_status:       ReadOnly<u32>,  // 0x060
+    interrupt_ack:          WriteOnly<u32>, // 0x064
+    _reserved4:             [u32; 2],
+    status:                 ReadWrite<u32>, // 0x070
+    _reserved5:             [u32; 3],
+    queue_desc_low:         WriteOnly<u32>, // 0x080
+    queue_desc_high:        WriteOnly<u32>, // 0x084
+    _reserved6:             [u32; 2],
+    queue_driver_low:       WriteOnly<u32>, // 0x090
+    queue_driver_high:      WriteOnly<u32>, // 0x094
+    _reserved7:             [u32; 2],
+    queue_device_low:       WriteOnly<u32>, // 0x0a0
+    queue_device_high:      WriteOnly<u32>, // 0x0a4
+    _reserved8:             [u32; 21],
+    config_generation:      ReadOnly<u32>   // 0x0fc
+}
+
 struct MmioRegisters<'a> {
-    slice: &'a mut [u32]
+    block: &'a mut MmioRegisterBlock
 }
 
 impl<'a> MmioRegisters<'a> {
     fn magic_number(&mut self) -> u32 {
-        unsafe { u32::from_le((&self.slice[0x00] as *const u32).read_volatile()) }
+        self.block.magic_number.read()
     }
 
     fn version(&mut self) -> u32 {
-        unsafe { u32::from_le((&self.slice[0x01] as *const u32).read_volatile()) }
+        self.block.version.read()
     }
 
     fn device_id(&mut self) -> u32 {
-        unsafe { u32::from_le((&self.slice[0x02] as *const u32).read_volatile()) }
+        self.block.device_id.read()
     }
 
     fn vendor_id(&mut self) -> u32 {
-        unsafe { u32::from_le((&self.slice[0x03] as *const u32).read_volatile()) }
+        self.block.vendor_id.read()
     }
 
-    fn device_features(&mut self) -> u64 {
-        unsafe {
-            (&mut self.slice[0x05] as *mut u32).write_volatile(FeaturesSelection::Low as u32);
-            let low = u32::from_le((&self.slice[0x04] as *const u32).read_volatile());
+    fn legacy_set_guest_page_size(&mut self, page_size: u32) -> &mut Self {
+        self.block.legacy_guest_page_size.write(page_size);
+        self
+    }
 
-            (&mut self.slice[0x05] as *mut u32).write_volatile(FeaturesSelection::High as u32);
-            let high = u32::from_le((&self.slice[0x04] as *const u32).read_volatile());
+    fn legacy_set_device_ring_align(&mut self, align: u32) -> &mut Self {
+        self.block.legacy_queue_align.write(align);
+        self
+    }
 
-            u64::from(low) | (u64::from(high) << 32)
-        }
+    fn legacy_queue_page_number(&mut self) -> u32 {
+        self.block.legacy_queue_pfn.read()
     }
 
-    fn set_driver_features(&mut self, features: u64) -> &mut Self {
-        unsafe {
-            (&mut self.slice[0x09] as *mut u32).write_volatile(FeaturesSelection::Low as u32);
-            (&mut self.slice[0x08] as *mut u32).write_volatile((features as u32).to_le());
+    fn legacy_set_queue_page_number(&mut self, page_number: u32) -> &mut Self {
+        self.block.legacy_queue_pfn.write(page_number);
+        self
+    }
 
-            (&mut self.slice[0x09] as *mut u32).write_volatile(FeaturesSelection::High as u32);
-            (&mut self.slice[0x08] as *mut u32).write_volatile(((features >> 32) as u32).to_le());
+    fn queue_notify(&mut self, notification: u32) -> &mut Self {
+        // NOTE: If VIRTIO_F_NOTIFICATION_DATA has been negotiated, `notification` contains more than
+        //       just a queue index.
+        self.block.queue_notify.write(notification);
+        self
+    }
 
-            self
-        }
+    fn interrupt_status(&mut self) -> Interrupts {
+        unsafe { Interrupts::from_bits_unchecked(self.block.interrupt_status.read()) }
     }
 
-    fn legacy_set_guest_page_size(&mut self, page_size: u32) -> &mut Self {
-        unsafe { (&mut self.slice[0x0a] as *mut u32).write_volatile(page_size.to_le()); }
+    fn acknowledge_interrupt(&mut self, interrupts: Interrupts) -> &mut Self {
+        self.block.interrupt_ack.write(interrupts.bits());
         self
     }
 
-    fn select_queue(&mut self, queue_index: u32) -> &mut Self {
-        unsafe { (&mut self.slice[0x0c] as *mut u32).write_volatile(queue_index.to_le()); }
+    fn config_generation(&mut self) -> u32 {
+        // This is probably little-endian, but it's an opaque value, so it doesn't matter. The
+        // only meaningful operation on this value is a test for equality with another value from
+        // the same register, which requires a proper atomic load rather than just a volatile one.
+        unsafe { (&self.block.config_generation as *const ReadOnly<u32> as *const AtomicU32).as_ref().unwrap() }
+            .load(Ordering::Acquire)
+    }
+}
+
+impl<'a> VirtioRegisters<'a> for MmioRegisters<'a> {
+    fn device_features(&mut self) -> u64 {
+        self.block.device_features_sel.write(FeaturesSelection::Low as u32);
+        let low = self.block.device_features.read();
+
+        self.block.device_features_sel.write(FeaturesSelection::High as u32);
+        let high = self.block.device_features.read();
+
+        u64::from(low) | (u64::from(high) << 32)
+    }
+
+    fn set_driver_features(&mut self, features: u64) -> &mut Self {
+        self.block.driver_features_sel.write(FeaturesSelection::Low as u32);
+        self.block.driver_features.write(features as u32);
+
+        self.block.driver_features_sel.write(FeaturesSelection::High as u32);
+        self.block.driver_features.write((features >> 32) as u32);
+
         self
     }
 
-    fn queue_len_max(&mut self) -> u32 {
-        unsafe { u32::from_le((&self.slice[0x0d] as *const u32).read_volatile()) }
+    fn status(&mut self) -> DeviceStatus {
+        unsafe { DeviceStatus::from_bits_unchecked(self.block.status.read()) }
     }
 
-    fn set_queue_len(&mut self, len: u32) -> &mut Self {
-        unsafe { (&mut self.slice[0x0e] as *mut u32).write_volatile(len.to_le()); }
+    fn set_status(&mut self, status: DeviceStatus) -> &mut Self {
+        self.block.status.write(status.bits());
         self
     }
 
-    fn legacy_set_device_ring_align(&mut self, align: u32) -> &mut Self {
-        unsafe { (&mut self.slice[0x0f] as *mut u32).write_volatile(align.to_le()); }
+    fn or_status(&mut self, mut status: DeviceStatus) -> &mut Self {
+        status |= self.status();
+        self.set_status(status)
+    }
+
+    fn select_queue(&mut self, queue_index: u32) -> &mut Self {
+        self.block.queue_sel.write(queue_index);
         self
     }
 
-    fn legacy_queue_page_number(&mut self) -> u32 {
-        unsafe { u32::from_le((&self.slice[0x10] as *const u32).read_volatile()) }
+    fn queue_len_max(&mut self) -> u32 {
+        self.block.queue_num_max.read()
     }
 
-    fn legacy_set_queue_page_number(&mut self, page_number: u32) -> &mut Self {
-        unsafe { (&mut self.slice[0x10] as *mut u32).write_volatile(page_number.to_le()); }
+    fn set_queue_len(&mut self, len: u32) -> &mut Self {
+        self.block.queue_num.write(len);
         self
     }
 
     fn queue_ready(&mut self) -> bool {
-        match unsafe { u32::from_le((&self.slice[0x11] as *const u32).read_volatile()) } {
+        match self.block.queue_ready.read() {
             0 => false,
             1 => true,
             x => panic!("invalid value found in QueueReady: {}", x)
@@ -292,32 +651,94 @@ impl<'a> MmioRegisters<'a> {
     }
 
     fn set_queue_ready(&mut self, ready: bool) -> &mut Self {
-        unsafe { (&mut self.slice[0x11] as *mut u32).write_volatile(u32::to_le(if ready { 1 } else { 0 })); }
+        self.block.queue_ready.write(if ready { 1 } else { 0 });
         self
     }
 
-    fn queue_notify(&mut self, notification: u32) -> &mut Self {
-        // NOTE: If VIRTIO_F_NOTIFICATION_DATA has been negotiated, `notification` contains more than
-        //       just a queue index.
-        unsafe { (&mut self.slice[0x14] as *mut u32).write_volatile(notification.to_le()); }
+    fn set_queue_descriptor_area(&mut self, phys_addr: u64) -> &mut Self {
+        self.block.queue_desc_low.write(phys_addr as u32);
+        self.block.queue_desc_high.write((phys_addr >> 32) as u32);
         self
     }
 
+    fn set_queue_driver_area(&mut self, phys_addr: u64) -> &mut Self {
+        self.block.queue_driver_low.write(phys_addr as u32);
+        self.block.queue_driver_high.write((phys_addr >> 32) as u32);
+        self
+    }
+
+    fn set_queue_device_area(&mut self, phys_addr: u64) -> &mut Self {
+        self.block.queue_device_low.write(phys_addr as u32);
+        self.block.queue_device_high.write((phys_addr >> 32) as u32);
+        self
+    }
+}
+
+/// The VirtIO-over-PCI "modern" register interface: a handful of capability-pointed regions
+/// inside the device's PCI BARs, found by walking its vendor-specific PCI capability list (see
+/// [`find_pci_caps`]). There's no legacy mode to support here, so `common`'s layout is exactly the
+/// `virtio_pci_common_cfg` structure from the VirtIO specification.
+struct PciRegisters<'a> {
+    common:                 &'a mut [u8],
+    isr:                    &'a mut u8,
+    notify_base:            usize,
+    notify_off_multiplier:  u32
+}
+
+impl<'a> PciRegisters<'a> {
+    fn common_ptr<T>(&mut self, offset: usize) -> *mut T {
+        (self.common.as_mut_ptr() as usize + offset) as *mut T
+    }
+
+    fn queue_notify_off(&mut self) -> u16 {
+        unsafe { u16::from_le(self.common_ptr::<u16>(0x1e).read_volatile()) }
+    }
+
     fn interrupt_status(&mut self) -> Interrupts {
-        unsafe { Interrupts::from_bits_unchecked((&self.slice[0x18] as *const u32).read_volatile()) }
+        unsafe { Interrupts::from_bits_unchecked(u32::from((self.isr as *mut u8).read_volatile())) }
     }
 
-    fn acknowledge_interrupt(&mut self, interrupts: Interrupts) -> &mut Self {
-        unsafe { (&mut self.slice[0x19] as *mut u32).write_volatile(interrupts.bits()); }
+    fn acknowledge_interrupt(&mut self, _interrupts: Interrupts) -> &mut Self {
+        // Reading the ISR register (which `interrupt_status` already did) clears it; there's no
+        // separate acknowledgement register like there is on the MMIO transport.
+        self
+    }
+
+    fn config_generation(&mut self) -> u32 {
+        unsafe { u32::from((self.common_ptr::<u8>(0x15)).read_volatile()) }
+    }
+}
+
+impl<'a> VirtioRegisters<'a> for PciRegisters<'a> {
+    fn device_features(&mut self) -> u64 {
+        unsafe {
+            self.common_ptr::<u32>(0x00).write_volatile((FeaturesSelection::Low as u32).to_le());
+            let low = u32::from_le(self.common_ptr::<u32>(0x04).read_volatile());
+
+            self.common_ptr::<u32>(0x00).write_volatile((FeaturesSelection::High as u32).to_le());
+            let high = u32::from_le(self.common_ptr::<u32>(0x04).read_volatile());
+
+            u64::from(low) | (u64::from(high) << 32)
+        }
+    }
+
+    fn set_driver_features(&mut self, features: u64) -> &mut Self {
+        unsafe {
+            self.common_ptr::<u32>(0x08).write_volatile((FeaturesSelection::Low as u32).to_le());
+            self.common_ptr::<u32>(0x0c).write_volatile((features as u32).to_le());
+
+            self.common_ptr::<u32>(0x08).write_volatile((FeaturesSelection::High as u32).to_le());
+            self.common_ptr::<u32>(0x0c).write_volatile(((features >> 32) as u32).to_le());
+        }
         self
     }
 
     fn status(&mut self) -> DeviceStatus {
-        unsafe { DeviceStatus::from_bits_unchecked((&self.slice[0x1c] as *const u32).read_volatile()) }
+        unsafe { DeviceStatus::from_bits_unchecked(u32::from(self.common_ptr::<u8>(0x14).read_volatile())) }
     }
 
     fn set_status(&mut self, status: DeviceStatus) -> &mut Self {
-        unsafe { (&mut self.slice[0x1c] as *mut u32).write_volatile(status.bits()); }
+        unsafe { self.common_ptr::<u8>(0x14).write_volatile(status.bits() as u8); }
         self
     }
 
@@ -326,43 +747,65 @@ impl<'a> MmioRegisters<'a> {
         self.set_status(status)
     }
 
-    fn set_queue_descriptor_area(&mut self, phys_addr: u64) -> &mut Self {
-        unsafe {
-            (&mut self.slice[0x20] as *mut u32).write_volatile((phys_addr as u32).to_le());
-            (&mut self.slice[0x21] as *mut u32).write_volatile(((phys_addr >> 32) as u32).to_le());
+    fn select_queue(&mut self, queue_index: u32) -> &mut Self {
+        unsafe { self.common_ptr::<u16>(0x16).write_volatile((queue_index as u16).to_le()); }
+        self
+    }
+
+    fn queue_len_max(&mut self) -> u32 {
+        unsafe { u32::from(u16::from_le(self.common_ptr::<u16>(0x18).read_volatile())) }
+    }
+
+    fn set_queue_len(&mut self, len: u32) -> &mut Self {
+        unsafe { self.common_ptr::<u16>(0x18).write_volatile((len as u16).to_le()); }
+        self
+    }
+
+    fn queue_ready(&mut self) -> bool {
+        match unsafe { u16::from_le(self.common_ptr::<u16>(0x1c).read_volatile()) } {
+            0 => false,
+            1 => true,
+            x => panic!("invalid value found in queue_enable: {}", x)
         }
+    }
+
+    fn set_queue_ready(&mut self, ready: bool) -> &mut Self {
+        unsafe { self.common_ptr::<u16>(0x1c).write_volatile(u16::to_le(if ready { 1 } else { 0 })); }
+        self
+    }
+
+    fn set_queue_descriptor_area(&mut self, phys_addr: u64) -> &mut Self {
+        unsafe { self.common_ptr::<u64>(0x20).write_volatile(phys_addr.to_le()); }
         self
     }
 
     fn set_queue_driver_area(&mut self, phys_addr: u64) -> &mut Self {
-        unsafe {
-            (&mut self.slice[0x24] as *mut u32).write_volatile((phys_addr as u32).to_le());
-            (&mut self.slice[0x25] as *mut u32).write_volatile(((phys_addr >> 32) as u32).to_le());
-        }
+        unsafe { self.common_ptr::<u64>(0x28).write_volatile(phys_addr.to_le()); }
         self
     }
 
     fn set_queue_device_area(&mut self, phys_addr: u64) -> &mut Self {
-        unsafe {
-            (&mut self.slice[0x28] as *mut u32).write_volatile((phys_addr as u32).to_le());
-            (&mut self.slice[0x29] as *mut u32).write_volatile(((phys_addr >> 32) as u32).to_le());
-        }
+        unsafe { self.common_ptr::<u64>(0x30).write_volatile(phys_addr.to_le()); }
         self
     }
 
-    fn config_generation(&mut self) -> u32 {
-        // This is probably little-endian, but it's an opaque value, so it doesn't matter. The
-        // only meaningful operation on this value is a test for equality with another value
-        // from the same register.
-        unsafe { (*(&mut self.slice[0x3f] as *mut u32 as *mut AtomicU32)).load(Ordering::Acquire) }
+    fn queue_notify_resource(&mut self, _device_resource: &'a Resource) -> &'a Resource {
+        let notify_off = self.queue_notify_off();
+        let addr = self.notify_base + notify_off as usize * self.notify_off_multiplier as usize;
+        // `VirtQueue` only ever borrows one `Resource` for the lifetime of the whole device, but
+        // PCI needs a distinct notification address per queue. Leaking a small `Resource` per
+        // queue avoids changing `VirtQueue`'s (widely used) signature to carry more than one.
+        // The size is that of a `u32` (rather than the `u16` a plain notification needs) so the
+        // resource is also valid if `VIRTIO_F_NOTIFICATION_DATA` gets negotiated.
+        Box::leak(Box::new(Resource { bus: BusType::Pci, base: addr, size: mem::size_of::<u32>() }))
     }
 }
 
 #[derive(Debug, Clone, Copy)]
 #[repr(u32)]
 enum FeaturesSelection {
-    Low  = u32::to_le(0),
-    High = u32::to_le(1)
+    Low  = 0,
+    High = 1
 }
 
 bitflags! {
@@ -411,20 +854,51 @@ bitflags! {
 }
 
 bitflags! {
-    struct Interrupts: u32 {
-        const USED_BUFFER    = u32::to_le(0x0000_0001);
-        const CONFIG_CHANGED = u32::to_le(0x0000_0002);
+    /// The reasons a device's interrupt can be raised, as returned by
+    /// [`DeviceDetails::handle_interrupt`].
+    pub struct Interrupts: u32 {
+        /// The device has used one or more buffers in a virtqueue.
+        const USED_BUFFER    = 0x0000_0001;
+
+        /// The device's configuration space has changed.
+        const CONFIG_CHANGED = 0x0000_0002;
     }
 }
 
 bitflags! {
     struct DeviceStatus: u32 {
-        const ACKNOWLEDGE = u32::to_le(0x01); // OS has noticed the device
-        const DRIVER      = u32::to_le(0x02); // OS knows how to drive the device
-        const DRIVER_OK   = u32::to_le(0x04); // Driver is ready
-        const FEATURES_OK = u32::to_le(0x08); // Driver has acknowledged the features it understands
-        const NEEDS_RESET = u32::to_le(0x40); // Device has experienced an error and needs to be reset
-        const FAILED      = u32::to_le(0x80); // OS has given up on the device
+        const ACKNOWLEDGE = 0x01; // OS has noticed the device
+        const DRIVER      = 0x02; // OS knows how to drive the device
+        const DRIVER_OK   = 0x04; // Driver is ready
+        const FEATURES_OK = 0x08; // Driver has acknowledged the features it understands
+        const NEEDS_RESET = 0x40; // Device has experienced an error and needs to be reset
+        const FAILED      = 0x80; // OS has given up on the device
+    }
+}
+
+/// Which transport a device's registers were found on. Kept private so [`DeviceDetails`] can
+/// expose transport-agnostic behavior without callers needing to know or care which transport
+/// backs a given device.
+#[derive(Debug)]
+enum Transport<'a> {
+    Mmio(MmioRegisters<'a>),
+    Pci(PciRegisters<'a>)
+}
+
+impl fmt::Debug for MmioRegisters<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MmioRegisters").field("block", &(self.block as *const MmioRegisterBlock)).finish()
+    }
+}
+
+impl fmt::Debug for PciRegisters<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PciRegisters")
+            .field("common", &(self.common.as_ptr(), self.common.len()))
+            .field("isr", &(self.isr as *const u8))
+            .field("notify_base", &self.notify_base)
+            .field("notify_off_multiplier", &self.notify_off_multiplier)
+            .finish()
     }
 }
 
@@ -434,7 +908,8 @@ pub struct DeviceDetails<'a> {
     legacy:              bool,
     features:            u64,
     configuration_space: &'a mut [u8],
-    virtqueues:          Vec<VirtQueue<'a>>
+    virtqueues:          Vec<VirtQueue<'a>>,
+    regs:                Transport<'a>
 }
 
 impl<'a> DeviceDetails<'a> {
@@ -458,29 +933,77 @@ impl<'a> DeviceDetails<'a> {
     pub fn virtqueues(&mut self) -> Vec<VirtQueue<'a>> {
         mem::replace(&mut self.virtqueues, Vec::new())
     }
+
+    /// Reads and acknowledges the device's pending interrupts, returning exactly the bits that
+    /// were set so the caller can distinguish a used-buffer notification from a configuration
+    /// change.
+    pub fn handle_interrupt(&mut self) -> Interrupts {
+        let interrupts = match &mut self.regs {
+            Transport::Mmio(regs) => regs.interrupt_status(),
+            Transport::Pci(regs) => regs.interrupt_status()
+        };
+        match &mut self.regs {
+            Transport::Mmio(regs) => { regs.acknowledge_interrupt(interrupts); }
+            Transport::Pci(regs) => { regs.acknowledge_interrupt(interrupts); }
+        }
+        interrupts
+    }
+
+    /// Runs `f` against the device's configuration space, guaranteeing a torn-free snapshot even
+    /// if the device updates its configuration asynchronously (e.g. a virtio-net device's MAC
+    /// address or link status).
+    ///
+    /// This works by reading `config_generation` before and after calling `f` and retrying `f` if
+    /// the generation changed in between, per the VirtIO specification's recommended protocol for
+    /// reading multi-field configuration structures.
+    pub fn read_config<F, R>(&mut self, mut f: F) -> R
+            where F: FnMut(&mut [u8]) -> R {
+        loop {
+            let generation_before = match &mut self.regs {
+                Transport::Mmio(regs) => regs.config_generation(),
+                Transport::Pci(regs) => regs.config_generation()
+            };
+            let result = f(&mut *self.configuration_space);
+            let generation_after = match &mut self.regs {
+                Transport::Mmio(regs) => regs.config_generation(),
+                Transport::Pci(regs) => regs.config_generation()
+            };
+            if generation_before == generation_after {
+                return result;
+            }
+        }
+    }
 }
 
-fn notify_device<'a>(resource: &'a Resource, notification: u32) {
+fn notify_device<'a>(queue: &VirtQueue<'a>) {
+    let resource = queue.resource();
     match resource.bus {
-        BusType::Mmio => notify_mmio(resource, notification)
+        BusType::Mmio => notify_mmio(resource, queue.notification()),
+        BusType::Pci => notify_pci(resource, queue.notification(), queue.notification_data())
     }
 }
 
 fn notify_mmio<'a>(resource: &'a Resource, notification: u32) {
     assert_eq!(resource.bus, BusType::Mmio);
-    assert!(resource.size >= 0x100);
+    assert!(resource.size >= mem::size_of::<MmioRegisterBlock>());
     let mut regs = MmioRegisters {
-        slice: unsafe {
-            slice::from_raw_parts_mut(
-                resource.base as *mut u32,
-                0x100 / mem::size_of::<u32>()
-            )
-        }
+        block: unsafe { &mut *(resource.base as *mut MmioRegisterBlock) }
     };
 
     regs.queue_notify(notification);
 }
 
+fn notify_pci<'a>(resource: &'a Resource, notification: u32, notification_data: bool) {
+    assert_eq!(resource.bus, BusType::Pci);
+    if notification_data {
+        assert!(resource.size >= mem::size_of::<u32>());
+        unsafe { (resource.base as *mut u32).write_volatile(notification.to_le()); }
+    } else {
+        assert!(resource.size >= mem::size_of::<u16>());
+        unsafe { (resource.base as *mut u16).write_volatile((notification as u16).to_le()); }
+    }
+}
+
 /// Defines how to convert an integer from "device-endian" to the CPU's endianness.
 ///
 /// This is necessary because the VirtIO specification used to say that a device always used the
@@ -537,10 +1060,16 @@ pub enum VirtIoInitError {
     TooLittleConfigSpace(usize, usize),
     /// The device doesn't have the right magic number to be a VirtIO device.
     WrongMagicNumber(u32, u32),
+    /// The device's PCI vendor ID isn't the one reserved for VirtIO devices.
+    WrongVendor(u16, u16),
     /// The device uses a version of the VirtIO specification that we don't support.
     UnsupportedVersion(u32, u32),
     /// The device isn't of the type (e.g. GPU, network card, block device) that we expected.
     WrongDeviceType(u32, u32),
+    /// A capability that every VirtIO device is required to expose (e.g. the common
+    /// configuration or a PCI BAR referenced by one) is missing or malformed. The string names
+    /// the capability that's missing.
+    MissingCapability(&'static str),
     /// The device doesn't support all of the features that the driver requires.
     MissingRequiredFeatures(u64, u64),
     /// The device didn't accept our requested set of features.
@@ -558,10 +1087,14 @@ impl fmt::Display for VirtIoInitError {
                 => write!(f, "device has too little configuration space: expected {}, found {}", expected, actual),
             Self::WrongMagicNumber(expected, actual)
                 => write!(f, "magic number not found: expected {:#x}, found {:#x}", expected, actual),
+            Self::WrongVendor(expected, actual)
+                => write!(f, "wrong PCI vendor ID found: expected {:#x}, found {:#x}", expected, actual),
             Self::UnsupportedVersion(expected, actual)
                 => write!(f, "VirtIO version {} not supported (we only support up to version {})", actual, expected),
             Self::WrongDeviceType(expected, actual)
                 => write!(f, "wrong device type found: expected {}, found {}", expected, actual),
+            Self::MissingCapability(name)
+                => write!(f, "device is missing a required capability: {}", name),
             Self::MissingRequiredFeatures(required, found)
                 => write!(f, "driver requires feature set {:#x}, but device only supports {:#x}", required, found),
             Self::FeatureNegotiationFailed
@@ -34,7 +34,7 @@ use {
     },
     libphoenix::allocator::PhysBox,
     crate::{DeviceEndian, GenericFeatures},
-    super::{Response, VirtQueue}
+    super::{QueueLayout, Response, VirtQueue}
 };
 
 /// An executor that can run most futures without return values, including `async` blocks that run
@@ -180,6 +180,7 @@ pub struct ResponseFuture<'a, T: ?Sized> {
     desc_head_idx: u16,
     desc_tail_idx: u16,
     descriptors_count: u16,
+    head_wrap: bool, // Only meaningful for packed virtqueues
     buffer: Option<PhysBox<T>>,
     legacy_response_len: Option<usize>
 }
@@ -190,6 +191,7 @@ impl<'a, T: ?Sized> ResponseFuture<'a, T> {
         desc_head_idx: u16,
         desc_tail_idx: u16,
         descriptors_count: u16,
+        head_wrap: bool,
         buffer: PhysBox<T>,
         legacy_response_len: Option<usize>
     ) -> Self {
@@ -199,6 +201,7 @@ impl<'a, T: ?Sized> ResponseFuture<'a, T> {
             desc_head_idx,
             desc_tail_idx,
             descriptors_count,
+            head_wrap,
             buffer: Some(buffer),
             legacy_response_len
         }
@@ -211,6 +214,7 @@ impl<'a, T: ?Sized> ResponseFuture<'a, T> {
             desc_head_idx: 0,
             desc_tail_idx: 0,
             descriptors_count: 0,
+            head_wrap: false,
             buffer: Some(buffer),
             legacy_response_len
         }
@@ -231,22 +235,50 @@ impl<'a, T: ?Sized> Future for ResponseFuture<'a, T> {
                 let valid_bytes = mem::size_of_val(&*buffer);
                 Poll::Ready(Response { buffer, valid_bytes })
             },
+            ResponseFuture {
+                virtq: Some(ref virtq),
+                desc_head_idx,
+                desc_tail_idx: _,
+                descriptors_count,
+                head_wrap,
+                ref mut buffer,
+                legacy_response_len: _
+            } if matches!(virtq.layout, QueueLayout::Packed(_)) => {
+                // Unlike the split ring, a packed ring's device only ever updates the head
+                // descriptor of a chain, and does so in place, so each future can check its own
+                // chain directly without any shared "next index" to serialize on.
+                let QueueLayout::Packed(ref ring) = virtq.layout else { unreachable!() };
+                match ring.poll_used(desc_head_idx, head_wrap, descriptors_count) {
+                    Some(len) => {
+                        let buffer = mem::replace(buffer, None)
+                            .expect("polled a ResponseFuture that was already finished");
+                        Poll::Ready(Response { buffer, valid_bytes: len as usize })
+                    },
+                    None => {
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                }
+            },
             ResponseFuture {
                 virtq: Some(ref virtq),
                 desc_head_idx,
                 desc_tail_idx,
                 descriptors_count,
+                head_wrap: _,
                 ref mut buffer,
                 legacy_response_len
             } => {
-                let dev_ring = virtq.device_ring.ring();
+                let QueueLayout::Split { ref descriptors, ref driver_ring, ref device_ring } = virtq.layout
+                    else { unreachable!() };
+                let dev_ring = device_ring.ring();
                 let last_dev_ring_idx = virtq.last_dev_ring_idx.load(Ordering::Acquire);
                 let dev_ring_entry = &dev_ring[last_dev_ring_idx as usize % dev_ring.len()];
                 let found_desc_idx = u32::from_device_endian(
                     unsafe { (&dev_ring_entry.id as *const u32).read_volatile() },
                     virtq.legacy
                 ) as u16;
-                if virtq.device_ring.idx() == last_dev_ring_idx {
+                if device_ring.idx() == last_dev_ring_idx {
                     // The device hasn't read any buffers yet. Stay awake so we don't miss it.
                     // PERF: Wait for a "used buffer notification" before waking the appropriate
                     //       future to avoid needless polling.
@@ -261,13 +293,13 @@ impl<'a, T: ?Sized> Future for ResponseFuture<'a, T> {
 
                     let offset = virtq.accumulated_batch_size.load(Ordering::Acquire);
                     let next_idx = last_dev_ring_idx.wrapping_add(offset) % virtq.len();
-                    let next_desc_idx = virtq.driver_ring[next_idx].load(Ordering::Acquire);
+                    let next_desc_idx = driver_ring[next_idx].load(Ordering::Acquire);
                     if next_desc_idx == desc_head_idx {
                         // This future's descriptor is next in line. Handle it as above, except we
                         // don't have a `UsedElem` object from the device. That just means we can
                         // assume the device has read or written to every byte in the buffer.
 
-                        virtq.descriptors.dealloc_chain(desc_head_idx, desc_tail_idx, descriptors_count);
+                        descriptors.dealloc_chain(desc_head_idx, desc_tail_idx, descriptors_count);
 
                         // We need to keep track of how many descriptor chains are in this batch so
                         // we can skip forward the correct amount.
@@ -275,7 +307,7 @@ impl<'a, T: ?Sized> Future for ResponseFuture<'a, T> {
 
                         // Wake the next future in line.
                         let next_idx = next_idx.wrapping_add(1) % virtq.len();
-                        let next_desc_idx = virtq.driver_ring[next_idx].load(Ordering::Acquire);
+                        let next_desc_idx = driver_ring[next_idx].load(Ordering::Acquire);
                         if let Some(waker) = virtq.wakers[next_desc_idx as usize].replace(None) {
                             waker.wake();
                         }
@@ -300,7 +332,7 @@ impl<'a, T: ?Sized> Future for ResponseFuture<'a, T> {
                 } else if found_desc_idx == desc_head_idx {
                     // The device has used this future's buffer.
 
-                    virtq.descriptors.dealloc_chain(desc_head_idx, desc_tail_idx, descriptors_count);
+                    descriptors.dealloc_chain(desc_head_idx, desc_tail_idx, descriptors_count);
 
                     // Make sure we look in the right place for the next buffer returned by the
                     // device.
@@ -311,9 +343,9 @@ impl<'a, T: ?Sized> Future for ResponseFuture<'a, T> {
 
                     // If we haven't gotten through all the available descriptors yet, wake the next
                     // descriptor's future.
-                    if last_dev_ring_idx != virtq.driver_ring.idx() {
+                    if last_dev_ring_idx != driver_ring.idx() {
                         let last_dev_ring_idx = virtq.last_dev_ring_idx.load(Ordering::Acquire);
-                        let next_desc_idx = virtq.driver_ring[last_dev_ring_idx % virtq.len()]
+                        let next_desc_idx = driver_ring[last_dev_ring_idx % virtq.len()]
                             .load(Ordering::Acquire);
                         if let Some(waker) = virtq.wakers[next_desc_idx as usize].replace(None) {
                             waker.wake();
@@ -20,6 +20,7 @@
 //! It is the core data structure underlying the entire VirtIO communication protocol.
 
 pub mod future;
+mod packed;
 
 use {
     alloc::{
@@ -44,7 +45,8 @@ use {
         syscall
     },
     crate::{DeviceEndian, GenericFeatures, VirtIoError},
-    self::future::ResponseFuture
+    self::future::ResponseFuture,
+    self::packed::PackedRing
 };
 
 /// A virtqueue, as defined in the VirtIO specification. This queue is the primary means of
@@ -54,15 +56,25 @@ pub struct VirtQueue<'a> {
     resource: &'a Resource,
     device_features: u64,
     id: u32,
-    descriptors: DescriptorTable,
-    driver_ring: DriverRing,
-    device_ring: DeviceRing,
+    layout: QueueLayout,
     last_dev_ring_idx: AtomicU16,
-    accumulated_batch_size: AtomicU16, // Used for handling the `IN_ORDER` feature
+    accumulated_batch_size: AtomicU16, // Used for handling the `IN_ORDER` feature (split rings only)
     wakers: Box<[RefCell<Option<Waker>>]>,
     legacy: bool
 }
 
+/// The data structures that make up a virtqueue differ depending on whether the split or packed
+/// layout was negotiated; this is the split between them. Legacy devices always use `Split`.
+#[derive(Debug)]
+enum QueueLayout {
+    Split {
+        descriptors: DescriptorTable,
+        driver_ring: DriverRing,
+        device_ring: DeviceRing
+    },
+    Packed(PackedRing)
+}
+
 impl<'a> VirtQueue<'a> {
     // FIXME: This depends on the transport, so it may not always be 0x1000.
     pub(crate) const LEGACY_DEVICE_RING_ALIGN: usize = 0x1000;
@@ -81,11 +93,14 @@ impl<'a> VirtQueue<'a> {
         let log_2 = |x: usize| mem::size_of_val(&x) * 8 - x.leading_zeros() as usize + 1;
 
         let in_order = device_features & GenericFeatures::IN_ORDER.bits() != 0;
-
-        let descriptors;
-        let driver_ring;
-        let device_ring;
-        if legacy {
+        let packed = device_features & GenericFeatures::RING_PACKED.bits() != 0;
+        // Legacy devices never support the packed layout.
+        assert!(!legacy || !packed);
+
+        let layout;
+        if packed {
+            layout = QueueLayout::Packed(PackedRing::new(len, driver_flags));
+        } else if legacy {
             // In "legacy" devices, everything needs to be roughly contiguous, so we allocate it
             // all in one chunk.
             let size_of_descriptors = mem::size_of::<BufferDescriptor>() * usize::from(len);
@@ -106,11 +121,14 @@ impl<'a> VirtQueue<'a> {
             }
 
             let block = PhysBox::slice_assume_init(block);
-            unsafe {
-                driver_ring = DriverRing::new_legacy(&block, size_of_descriptors, len);
-                device_ring = DeviceRing::new_legacy(&block, align(size_of_descriptors + size_of_driver_ring), len);
-            }
-            descriptors = DescriptorTable::new_legacy(block, len, in_order);
+            let (driver_ring, device_ring) = unsafe {
+                (
+                    DriverRing::new_legacy(&block, size_of_descriptors, len),
+                    DeviceRing::new_legacy(&block, align(size_of_descriptors + size_of_driver_ring), len)
+                )
+            };
+            let descriptors = DescriptorTable::new_legacy(block, len, in_order);
+            layout = QueueLayout::Split { descriptors, driver_ring, device_ring };
         } else {
             // TODO
             unimplemented!();
@@ -124,9 +142,7 @@ impl<'a> VirtQueue<'a> {
             resource,
             device_features,
             id,
-            descriptors,
-            driver_ring,
-            device_ring,
+            layout,
             last_dev_ring_idx: AtomicU16::new(0),
             accumulated_batch_size: AtomicU16::new(0),
             wakers,
@@ -135,20 +151,63 @@ impl<'a> VirtQueue<'a> {
     }
 
     /// Returns the maximum number of messages that can be waiting in this queue at the same time.
-    pub const fn len(&self) -> u16 {
-        self.descriptors.len
+    pub fn len(&self) -> u16 {
+        match self.layout {
+            QueueLayout::Split { ref descriptors, .. } => descriptors.len,
+            QueueLayout::Packed(ref ring) => ring.len()
+        }
     }
 
     pub(crate) fn descriptors_addr_phys(&self) -> usize {
-        self.descriptors.base_addr_phys()
+        match self.layout {
+            QueueLayout::Split { ref descriptors, .. } => descriptors.base_addr_phys(),
+            QueueLayout::Packed(ref ring) => ring.descriptors_addr_phys()
+        }
     }
 
     pub(crate) fn driver_ring_addr_phys(&self) -> usize {
-        self.driver_ring.base_addr_phys()
+        match self.layout {
+            QueueLayout::Split { ref driver_ring, .. } => driver_ring.base_addr_phys(),
+            QueueLayout::Packed(ref ring) => ring.driver_event_addr_phys()
+        }
     }
 
     pub(crate) fn device_ring_addr_phys(&self) -> usize {
-        self.device_ring.base_addr_phys()
+        match self.layout {
+            QueueLayout::Split { ref device_ring, .. } => device_ring.base_addr_phys(),
+            QueueLayout::Packed(ref ring) => ring.device_event_addr_phys()
+        }
+    }
+
+    /// Returns the resource through which this queue's device should be notified.
+    pub(crate) fn resource(&self) -> &'a Resource {
+        self.resource
+    }
+
+    /// Returns whether `VIRTIO_F_NOTIFICATION_DATA` was negotiated for this queue, i.e. whether
+    /// its notifications carry more than just the queue index.
+    pub(crate) fn notification_data(&self) -> bool {
+        self.device_features & GenericFeatures::NOTIFICATION_DATA.bits() != 0
+    }
+
+    /// Builds the value that should be sent to notify the device of this queue, folding in ring
+    /// state when `VIRTIO_F_NOTIFICATION_DATA` has been negotiated so the device can tell what
+    /// changed without scanning every queue.
+    pub(crate) fn notification(&self) -> u32 {
+        if !self.notification_data() {
+            return self.id;
+        }
+
+        match self.layout {
+            // The high bits are the `idx` most recently made available.
+            QueueLayout::Split { ref driver_ring, .. } => self.id | (u32::from(driver_ring.idx()) << 16),
+            // The high bits are the ring offset and wrap counter of the descriptor most recently
+            // made available (VirtIO specification § 2.8.14.1).
+            QueueLayout::Packed(ref ring) => {
+                let (idx, wrap) = ring.notification_state();
+                self.id | (u32::from(idx) << 16) | (u32::from(wrap) << 31)
+            }
+        }
     }
 
     /// Asynchronously sends a message to the device and returns its response.
@@ -183,61 +242,99 @@ impl<'a> VirtQueue<'a> {
         // We need one descriptor for output and one for input.
         // If `first_recv_idx` is past the end of `buf`, we're only outputting.
         // If it's `0`, we're only inputting.
-        let mut descriptor_indices = [0u16; 2];
-        let descriptor_indices = &mut descriptor_indices[
-            if first_recv_idx >= buf_size || first_recv_idx == 0 { 0 .. 1 } else { 0 .. 2 }
-        ];
-
-        match self.descriptors.make_chain(descriptor_indices, self.legacy) {
-            SendRecvResult::Ok(()) => {},
-            SendRecvResult::Retry(()) => return SendRecvResult::Retry(buf),
-            SendRecvResult::Err(e) => return SendRecvResult::Err(e)
-        };
+        let only_output = first_recv_idx >= buf_size;
+        let only_input = first_recv_idx == 0;
+
+        match self.layout {
+            QueueLayout::Split { ref descriptors, ref driver_ring, ref device_ring } => {
+                let mut descriptor_indices = [0u16; 2];
+                let descriptor_indices = &mut descriptor_indices[if only_output || only_input { 0 .. 1 } else { 0 .. 2 }];
+
+                match descriptors.make_chain(descriptor_indices, self.legacy) {
+                    SendRecvResult::Ok(()) => {},
+                    SendRecvResult::Retry(()) => return SendRecvResult::Retry(buf),
+                    SendRecvResult::Err(e) => return SendRecvResult::Err(e)
+                };
 
-        // Attach the descriptors to the appropriate parts of the buffer.
-        if first_recv_idx > 0 {
-            let first_desc = &self.descriptors[descriptor_indices[0]];
-            first_desc.set_addr(buf.addr_phys() as u64, self.legacy);
-            first_desc.set_len(usize::min(buf_size, first_recv_idx) as u32, self.legacy);
-        }
-        if first_recv_idx < buf_size {
-            let last_desc = &self.descriptors[descriptor_indices[descriptor_indices.len() - 1]];
-            last_desc.set_addr((buf.addr_phys() + first_recv_idx) as u64, self.legacy);
-            last_desc.set_len((buf_size - first_recv_idx) as u32, self.legacy);
+                // Attach the descriptors to the appropriate parts of the buffer.
+                if first_recv_idx > 0 {
+                    let first_desc = &descriptors[descriptor_indices[0]];
+                    first_desc.set_addr(buf.addr_phys() as u64, self.legacy);
+                    first_desc.set_len(usize::min(buf_size, first_recv_idx) as u32, self.legacy);
+                }
+                if first_recv_idx < buf_size {
+                    let last_desc = &descriptors[descriptor_indices[descriptor_indices.len() - 1]];
+                    last_desc.set_addr((buf.addr_phys() + first_recv_idx) as u64, self.legacy);
+                    last_desc.set_len((buf_size - first_recv_idx) as u32, self.legacy);
 
-            // Mark this as an input buffer (i.e. writable from the device's perspective).
-            last_desc.set_flags(last_desc.flags(self.legacy) | BufferFlags::WRITE, self.legacy);
-        }
+                    // Mark this as an input buffer (i.e. writable from the device's perspective).
+                    last_desc.set_flags(last_desc.flags(self.legacy) | BufferFlags::WRITE, self.legacy);
+                }
 
-        // The device only needs the index of the first descriptor in the chain.
-        let Ok((idx, entries_revealed)) = self.driver_ring.set_next_entry(descriptor_indices[0]) else {
-            return SendRecvResult::Retry(buf);
-        };
+                // The device only needs the index of the first descriptor in the chain.
+                let Ok((idx, entries_revealed)) = driver_ring.set_next_entry(descriptor_indices[0]) else {
+                    return SendRecvResult::Retry(buf);
+                };
 
-        let idx_matches_avail_event = || {
-            let mut avail_event = self.device_ring.avail_event();
-            if avail_event < idx {
-                avail_event += self.len() as u16;
-            }
-            idx <= avail_event && avail_event < idx + entries_revealed
-        };
+                let idx_matches_avail_event = || {
+                    let mut avail_event = device_ring.avail_event();
+                    if avail_event < idx {
+                        avail_event += self.len() as u16;
+                    }
+                    idx <= avail_event && avail_event < idx + entries_revealed
+                };
 
-        // Notify the device of the new buffers, but only if it expects notifications.
-        let event_index_feature = self.device_features & GenericFeatures::RING_EVENT_INDEX.bits() != 0;
-        if (!event_index_feature && !self.device_ring.flags().contains(DeviceFlags::NO_INTERRUPT)) ||
-                (event_index_feature && idx_matches_avail_event()) {
-            super::notify_device(self.resource, self.id);
-        }
-
-        // Wait for the device to respond.
-        SendRecvResult::Ok(ResponseFuture::new(
-            self,
-            descriptor_indices[0],
-            descriptor_indices[descriptor_indices.len() - 1],
-            descriptor_indices.len().try_into().unwrap(),
-            buf,
-            legacy_response_len
-        ))
+                // Notify the device of the new buffers, but only if it expects notifications.
+                let event_index_feature = self.device_features & GenericFeatures::RING_EVENT_INDEX.bits() != 0;
+                if (!event_index_feature && !device_ring.flags().contains(DeviceFlags::NO_INTERRUPT)) ||
+                        (event_index_feature && idx_matches_avail_event()) {
+                    super::notify_device(self);
+                }
+
+                // Wait for the device to respond.
+                SendRecvResult::Ok(ResponseFuture::new(
+                    self,
+                    descriptor_indices[0],
+                    descriptor_indices[descriptor_indices.len() - 1],
+                    descriptor_indices.len().try_into().unwrap(),
+                    false,
+                    buf,
+                    legacy_response_len
+                ))
+            },
+            QueueLayout::Packed(ref ring) => {
+                let mut entries = [(0u64, 0u32, false); 2];
+                let entries = &mut entries[if only_output || only_input { 0 .. 1 } else { 0 .. 2 }];
+
+                if first_recv_idx > 0 {
+                    entries[0] = (buf.addr_phys() as u64, usize::min(buf_size, first_recv_idx) as u32, false);
+                }
+                if first_recv_idx < buf_size {
+                    entries[entries.len() - 1] =
+                        ((buf.addr_phys() + first_recv_idx) as u64, (buf_size - first_recv_idx) as u32, true);
+                }
+
+                let (head_idx, head_wrap) = match ring.write_chain(entries) {
+                    SendRecvResult::Ok(x) => x,
+                    SendRecvResult::Retry(()) => return SendRecvResult::Retry(buf),
+                    SendRecvResult::Err(e) => return SendRecvResult::Err(e)
+                };
+
+                if ring.should_notify() {
+                    super::notify_device(self);
+                }
+
+                SendRecvResult::Ok(ResponseFuture::new(
+                    self,
+                    head_idx,
+                    head_idx, // The packed ring only ever needs the head index to look up a chain.
+                    entries.len().try_into().unwrap(),
+                    head_wrap,
+                    buf,
+                    legacy_response_len
+                ))
+            }
+        }
     }
 }
 
@@ -0,0 +1,287 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Defines the packed virtqueue layout used instead of [`DescriptorTable`](super::DescriptorTable),
+//! [`DriverRing`](super::DriverRing), and [`DeviceRing`](super::DeviceRing) when `RING_PACKED` has
+//! been negotiated. A single descriptor ring takes the place of the separate descriptor table and
+//! driver/device rings, and availability/use are signaled by flag bits that are compared against a
+//! single-bit wrap counter instead of by advancing a separate index.
+//!
+//! Legacy devices never support this layout; every entry point here assumes a modern (non-legacy)
+//! device.
+
+use {
+    core::{
+        mem::MaybeUninit,
+        sync::atomic::{AtomicU16, AtomicU32, AtomicU64, Ordering}
+    },
+    bitflags::bitflags,
+    libphoenix::allocator::{Allocator, PhysBox},
+    super::{DriverFlags, SendRecvResult, VirtIoError},
+    crate::DeviceEndian
+};
+
+bitflags! {
+    struct PackedDescFlags: u16 {
+        const NEXT     = 0x0001;
+        const WRITE    = 0x0002;
+        const INDIRECT = 0x0004;
+        const AVAIL    = 0x0080;
+        const USED     = 0x8000;
+    }
+}
+
+#[derive(Debug)]
+#[repr(C, align(16))]
+struct PackedDescriptor {
+    // Each of these is stored in device-endian order (always little-endian; packed rings don't
+    // exist on legacy devices). Use the accessor methods instead.
+    addr:  AtomicU64,
+    len:   AtomicU32,
+    id:    AtomicU16,
+    flags: AtomicU16
+}
+
+impl PackedDescriptor {
+    fn write(&self, addr: u64, len: u32, id: u16, flags: PackedDescFlags, ordering: Ordering) {
+        self.addr.store(addr.to_device_endian(false), Ordering::Relaxed);
+        self.len.store(len.to_device_endian(false), Ordering::Relaxed);
+        self.id.store(id.to_device_endian(false), Ordering::Relaxed);
+        // The flags word is what publishes this descriptor to the device (via the `AVAIL`/`USED`
+        // bits), so it must be written last. The head of a chain uses `Ordering::Release` for this
+        // so the device never observes a half-written chain.
+        self.flags.store(flags.bits().to_device_endian(false), ordering);
+    }
+
+    fn flags(&self) -> PackedDescFlags {
+        PackedDescFlags::from_bits_truncate(u16::from_device_endian(self.flags.load(Ordering::Acquire), false))
+    }
+
+    fn len(&self) -> u32 {
+        u32::from_device_endian(self.len.load(Ordering::Acquire), false)
+    }
+}
+
+// The driver-owned and device-owned event suppression structures share this layout (VirtIO
+// specification § 2.8.10).
+#[derive(Debug)]
+#[repr(C)]
+struct EventSuppress {
+    desc_event_off_wrap: AtomicU16,
+    desc_event_flags:    AtomicU16
+}
+
+// Values of `EventSuppress::desc_event_flags`. These are states, not independent bits.
+const EVENT_FLAGS_ENABLE:  u16 = 0x0000;
+const EVENT_FLAGS_DISABLE: u16 = 0x0001;
+
+/// The packed virtqueue layout.
+#[derive(Debug)]
+pub(super) struct PackedRing {
+    descriptors:  PhysBox<[PackedDescriptor]>,
+    driver_event: PhysBox<EventSuppress>, // Written by us; tells the device our interrupt preference.
+    device_event: PhysBox<EventSuppress>, // Written by the device; tells us its notification preference.
+    len:          u16,
+    free_descs:   AtomicU16,
+    // Bits 0..15: the index of the next descriptor the driver will write. Bit 16: the driver's
+    // wrap counter (0 or 1), which flips every time that index passes the end of the ring.
+    next_state:   AtomicU32
+}
+
+impl PackedRing {
+    pub(super) fn new(len: u16, driver_flags: DriverFlags) -> Self {
+        let mut descriptors: PhysBox<[MaybeUninit<PackedDescriptor>]> = Allocator.malloc_phys_array(len.into(), 64)
+            .expect("failed to allocate a virtqueue");
+        for i in 0 .. usize::from(len) {
+            descriptors[i].write(PackedDescriptor {
+                addr:  AtomicU64::new(0),
+                len:   AtomicU32::new(0),
+                id:    AtomicU16::new(0),
+                flags: AtomicU16::new(0)
+            });
+        }
+        let descriptors = PhysBox::slice_assume_init(descriptors);
+
+        let driver_event_flags = if driver_flags.contains(DriverFlags::NO_INTERRUPT) {
+            EVENT_FLAGS_DISABLE
+        } else {
+            EVENT_FLAGS_ENABLE
+        };
+        let driver_event = PhysBox::new(EventSuppress {
+            desc_event_off_wrap: AtomicU16::new(0),
+            desc_event_flags:    AtomicU16::new(driver_event_flags.to_device_endian(false))
+        });
+        // The device overwrites this as soon as it's running, but it has to start somewhere.
+        let device_event = PhysBox::new(EventSuppress {
+            desc_event_off_wrap: AtomicU16::new(0),
+            desc_event_flags:    AtomicU16::new(EVENT_FLAGS_ENABLE.to_device_endian(false))
+        });
+
+        PackedRing {
+            descriptors,
+            driver_event,
+            device_event,
+            len,
+            free_descs: AtomicU16::new(len),
+            next_state: AtomicU32::new(1 << 16) // idx 0, wrap counter 1
+        }
+    }
+
+    pub(super) const fn len(&self) -> u16 {
+        self.len
+    }
+
+    pub(super) fn descriptors_addr_phys(&self) -> usize {
+        self.descriptors.addr_phys()
+    }
+
+    pub(super) fn driver_event_addr_phys(&self) -> usize {
+        self.driver_event.addr_phys()
+    }
+
+    pub(super) fn device_event_addr_phys(&self) -> usize {
+        self.device_event.addr_phys()
+    }
+
+    /// Returns the driver's current ring position and wrap counter, for use in
+    /// `VIRTIO_F_NOTIFICATION_DATA`'s high bits (VirtIO specification § 2.8.14.1).
+    pub(super) fn notification_state(&self) -> (u16, bool) {
+        let state = self.next_state.load(Ordering::Acquire);
+        ((state & 0xffff) as u16, state & (1 << 16) != 0)
+    }
+
+    /// Reserves a chain of `count` contiguous descriptors (this crate only ever builds chains of
+    /// one or two descriptors: one for the request and, optionally, one for the response).
+    ///
+    /// # Returns
+    /// The index and wrap counter of the chain's head descriptor, as a [`SendRecvResult`].
+    fn reserve(&self, count: u16) -> SendRecvResult<(u16, bool), (), VirtIoError> {
+        if count == 0 || count > 2 {
+            return SendRecvResult::Err(VirtIoError::new(
+                "packed virtqueues in this crate only support chains of one or two descriptors"
+            ));
+        }
+        if count > self.len {
+            return SendRecvResult::Err(
+                VirtIoError::new("attempted to make a chain with more descriptors than the queue has")
+            );
+        }
+
+        let mut free_descs = self.free_descs.load(Ordering::Acquire);
+        loop {
+            if free_descs < count {
+                return SendRecvResult::Retry(());
+            }
+            match self.free_descs.compare_exchange(
+                free_descs, free_descs - count, Ordering::AcqRel, Ordering::Acquire
+            ) {
+                Ok(_) => break,
+                Err(x) => free_descs = x
+            }
+        }
+
+        let mut state = self.next_state.load(Ordering::Acquire);
+        let (head_idx, head_wrap) = loop {
+            let idx = (state & 0xffff) as u16;
+            let wrap = state & (1 << 16) != 0;
+            // Do the sum in `u32` so an `idx` near `u16::MAX` can't overflow it.
+            let sum = u32::from(idx) + u32::from(count);
+            let new_idx = (sum % u32::from(self.len)) as u16;
+            let new_wrap = if sum >= u32::from(self.len) { !wrap } else { wrap };
+            let new_state = u32::from(new_idx) | ((new_wrap as u32) << 16);
+            match self.next_state.compare_exchange_weak(state, new_state, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break (idx, wrap),
+                Err(x) => state = x
+            }
+        };
+        SendRecvResult::Ok((head_idx, head_wrap))
+    }
+
+    /// Writes the given buffers into a freshly reserved chain. Whether the device wants to be
+    /// notified of it is a separate question, answered by `should_notify`.
+    ///
+    /// # Arguments
+    /// * `entries`: One or two `(addr, len, write)` tuples describing the chain's buffers, in the
+    ///   order they should be read/written by the device.
+    ///
+    /// # Returns
+    /// The chain's head index and the driver's wrap counter at that index, both needed later to
+    /// poll for its completion.
+    pub(super) fn write_chain(&self, entries: &[(u64, u32, bool)]) -> SendRecvResult<(u16, bool), (), VirtIoError> {
+        let (head_idx, head_wrap) = match self.reserve(entries.len() as u16) {
+            SendRecvResult::Ok(x) => x,
+            SendRecvResult::Retry(()) => return SendRecvResult::Retry(()),
+            SendRecvResult::Err(e) => return SendRecvResult::Err(e)
+        };
+
+        // Write every descriptor but the head first, then the head last (with `Release`
+        // ordering), so the device never sees a partially written chain once it observes the
+        // head as available.
+        for i in (0 .. entries.len()).rev() {
+            let (addr, len, write) = entries[i];
+            let pos = (usize::from(head_idx) + i) % usize::from(self.len);
+            let wrapped = usize::from(head_idx) + i >= usize::from(self.len);
+            let this_wrap = head_wrap ^ wrapped;
+
+            let mut flags = PackedDescFlags::empty();
+            if write {
+                flags |= PackedDescFlags::WRITE;
+            }
+            if i + 1 < entries.len() {
+                flags |= PackedDescFlags::NEXT;
+            }
+            // A descriptor is made available by setting exactly one of `AVAIL`/`USED` to match
+            // the wrap counter at its ring position (never both, which would instead mean "used").
+            flags |= if this_wrap { PackedDescFlags::AVAIL } else { PackedDescFlags::USED };
+
+            let ordering = if i == 0 { Ordering::Release } else { Ordering::Relaxed };
+            self.descriptors[pos].write(addr, len, head_idx, flags, ordering);
+        }
+
+        SendRecvResult::Ok((head_idx, head_wrap))
+    }
+
+    /// Checks whether the chain whose head is at `head_idx` (reserved with wrap counter
+    /// `head_wrap`) has been used by the device. The device only ever updates the head
+    /// descriptor of a chain, matching it back to the chain's `len`/`id` the driver wrote there.
+    ///
+    /// # Returns
+    /// The number of bytes the device reports writing to the chain, if it's done with it, and
+    /// frees the chain's `count` descriptors for reuse.
+    pub(super) fn poll_used(&self, head_idx: u16, head_wrap: bool, count: u16) -> Option<u32> {
+        let desc = &self.descriptors[usize::from(head_idx)];
+        let flags = desc.flags();
+        let avail = flags.contains(PackedDescFlags::AVAIL);
+        let used = flags.contains(PackedDescFlags::USED);
+        if avail != used || avail != head_wrap {
+            return None;
+        }
+
+        let len = desc.len();
+        self.free_descs.fetch_add(count, Ordering::AcqRel);
+        Some(len)
+    }
+
+    /// Returns whether the device wants to be notified of the chain most recently made available.
+    pub(super) fn should_notify(&self) -> bool {
+        // NB: `RING_EVENT_INDEX`'s "notify only when a specific descriptor is used/made available"
+        // mode (`desc_event_flags == 2`) isn't implemented; we fall back to always notifying
+        // unless the device has asked for no notifications at all.
+        u16::from_device_endian(self.device_event.desc_event_flags.load(Ordering::Acquire), false) != EVENT_FLAGS_DISABLE
+    }
+}
@@ -0,0 +1,73 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Typed wrappers for individual memory-mapped registers, meant to be used as the fields of a
+//! `#[repr(C)]` struct that's projected directly onto a device's register window. Each wrapper
+//! performs `read_volatile`/`write_volatile` on exactly the field it wraps and converts to or from
+//! the device's endianness (always little-endian, for every register this crate deals with), so a
+//! register's offset and endianness only need to be gotten right once, in the struct's definition,
+//! instead of being repeated at every access.
+
+use core::ptr;
+use crate::DeviceEndian;
+
+/// A register that can only be read, never written.
+#[repr(transparent)]
+pub struct ReadOnly<T> {
+    value: T
+}
+
+impl<T: Copy + DeviceEndian> ReadOnly<T> {
+    /// Performs a volatile read of the register, converting its value from the device's
+    /// endianness to the CPU's.
+    pub fn read(&self) -> T {
+        T::from_device_endian(unsafe { ptr::read_volatile(&self.value) }, false)
+    }
+}
+
+/// A register that can only be written, never read back.
+#[repr(transparent)]
+pub struct WriteOnly<T> {
+    value: T
+}
+
+impl<T: Copy + DeviceEndian> WriteOnly<T> {
+    /// Performs a volatile write to the register, converting `val` from the CPU's endianness to
+    /// the device's.
+    pub fn write(&mut self, val: T) {
+        unsafe { ptr::write_volatile(&mut self.value, val.to_device_endian(false)); }
+    }
+}
+
+/// A register that can be both read and written.
+#[repr(transparent)]
+pub struct ReadWrite<T> {
+    value: T
+}
+
+impl<T: Copy + DeviceEndian> ReadWrite<T> {
+    /// See [`ReadOnly::read`].
+    pub fn read(&self) -> T {
+        T::from_device_endian(unsafe { ptr::read_volatile(&self.value) }, false)
+    }
+
+    /// See [`WriteOnly::write`].
+    pub fn write(&mut self, val: T) {
+        unsafe { ptr::write_volatile(&mut self.value, val.to_device_endian(false)); }
+    }
+}
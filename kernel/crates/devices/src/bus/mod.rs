@@ -20,6 +20,7 @@
 //! them.
 
 pub mod mmio;
+pub mod pci;
 
 use {
     core::fmt,
@@ -31,8 +32,8 @@ use {
 
 /// Enumerates all the buses under the given level of the device tree.
 pub fn enumerate(device_tree: &mut DeviceTree) -> Result<(), ()> {
-    mmio::enumerate(device_tree)
-    // TODO: pci::enumerate(device_tree);
+    mmio::enumerate(device_tree).map_err(|_| ())?;
+    pci::enumerate(device_tree).map_err(|_| ())
 }
 
 /// Common functionality that all buses need to have.
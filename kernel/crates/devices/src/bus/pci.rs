@@ -0,0 +1,67 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! This module defines the PCI bus, addressed through its memory-mapped (ECAM) configuration
+//! space. Unlike the MMIO bus, which is just the whole address space, a PCI bus has its own
+//! addressing scheme (bus/device/function numbers), so its devices are found by walking that
+//! scheme rather than by a simple linear scan; see [`crate::virtio::enumerate`] for the VirtIO-
+//! specific half of that walk.
+
+use {
+    alloc::{
+        collections::TryReserveError,
+        vec::Vec
+    },
+    memory::allocator::AllMemAlloc,
+    super::{Bus, Resource, ReserveError},
+    crate::DeviceTree
+};
+
+/// Enumerates any PCI buses present at the given level of the device tree.
+pub fn enumerate(device_tree: &mut DeviceTree) -> Result<(), TryReserveError> {
+    match *device_tree {
+        DeviceTree::Root { children: ref mut subtrees } => {
+            subtrees.try_reserve(1)?;
+            subtrees.push(DeviceTree::Pci { bus: PciBus, children: Vec::new() });
+        },
+        _ => {} // The PCI bus is found only at the root.
+    };
+    Ok(())
+}
+
+/// The PCI bus, addressed through its ECAM configuration space rather than through a single
+/// contiguous range of addresses.
+#[derive(Debug)]
+pub struct PciBus;
+
+impl PciBus {
+    const BUS_NAME: &'static str = "pci";
+}
+
+impl Bus for PciBus {
+    fn reserve(&self, base: usize, size: usize) -> Result<Resource, ReserveError> {
+        // This just needs to claim the physical address range so nothing else can reuse it; the
+        // kernel never needs to free a device's resources, so there's no handle to keep around.
+        AllMemAlloc.mmio_mut::<u8>(base, size)
+            .map(|block| {
+                core::mem::forget(block);
+                Resource { bus: libdriver::BusType::Pci, base, size }
+            })
+            .map_err(|_| ReserveError { bus_type: Self::BUS_NAME, base, size })
+    }
+}
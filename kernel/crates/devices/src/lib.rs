@@ -88,14 +88,13 @@ pub enum DeviceTree {
         /// The devices and/or buses found on this bus.
         children: Vec<DeviceTree>
     },
-    // TODO:
-    // /// A PCI bus.
-    // Pci {
-    //     /// The bus itself.
-    //     bus: bus::pci::Bus,
-    //     /// The devices and/or buses found on this bus.
-    //     children: Vec<DeviceTree>
-    // },
+    /// A PCI bus, addressed through its ECAM configuration space.
+    Pci {
+        /// The bus itself.
+        bus: bus::pci::PciBus,
+        /// The devices and/or buses found on this bus.
+        children: Vec<DeviceTree>
+    },
     // TODO:
     // /// The x86 ISA bus.
     // Isa {
@@ -165,6 +164,16 @@ impl DeviceTree {
                 }
                 Err(())
             },
+            DeviceTree::Pci { ref children, .. } => {
+                if let Some(path) = path.match_and_advance("pci/") {
+                    for child in children {
+                        if let Ok(addr) = child.claim_device(path.clone(), root_page_table) {
+                            return Ok(addr);
+                        }
+                    }
+                }
+                Err(())
+            },
             DeviceTree::Device {
                 ref name,
                 ref claimed,
@@ -209,7 +218,10 @@ impl DeviceTree {
                     // Give the process access to the device's resources.
                     for (i, resource) in resources.iter().enumerate() {
                         match resource.bus {
-                            BusType::Mmio => {
+                            // A PCI resource (ECAM configuration space or a BAR) is still just a
+                            // range of physical addresses from the kernel's point of view, so it's
+                            // mapped exactly the same way as a plain MMIO resource.
+                            BusType::Mmio | BusType::Pci => {
                                 // FIXME: If the resource is not page-aligned and page-sized and
                                 //        the device doesn't have a certain permission
                                 //        ("unsafe direct unaligned mmio"?), map it into the
@@ -26,9 +26,10 @@ use {
     core::{
         fmt::Write,
         mem,
+        slice,
         sync::atomic::AtomicBool
     },
-    libdriver::BusType,
+    libdriver::{BusType, Resource},
     shared::{
         ffi_enum,
         ffi::{Endian, Le}
@@ -41,6 +42,20 @@ use {
 
 const MMIO_MAGIC_NUMBER: u32 = 0x74726976; // Little-endian "virt"
 
+const PCI_VIRTIO_VENDOR_ID:      u16 = 0x1af4;
+const PCI_VIRTIO_DEVICE_ID_BASE: u16 = 0x1040; // Modern devices only; see below.
+
+// Offsets into a function's 4-KiB slice of PCI(e) configuration space that this module cares
+// about. These match the standard PCI type-0 header layout.
+const PCI_CFG_VENDOR_ID:       usize = 0x00;
+const PCI_CFG_DEVICE_ID:       usize = 0x02;
+const PCI_CFG_SUBSYSTEM_ID:    usize = 0x2e;
+const PCI_CFG_CAPABILITIES_PTR: usize = 0x34;
+const PCI_CFG_BAR0:            usize = 0x10;
+const PCI_CFG_FUNCTION_SIZE:   usize = 0x1000; // One function's slice of ECAM space.
+
+const PCI_CAP_ID_VENDOR_SPECIFIC: u8 = 0x09;
+
 /// Inserts all the VirtIO devices into the given device tree.
 pub fn enumerate(device_tree: &mut DeviceTree) -> Result<(), ()> {
     match *device_tree {
@@ -100,11 +115,174 @@ pub fn enumerate(device_tree: &mut DeviceTree) -> Result<(), ()> {
                 };
             }
         },
+        DeviceTree::Pci { ref bus, ref mut children } => {
+            #[cfg(target_arch = "x86_64")] mod temp {
+                // FIXME: This assumes the fixed ECAM base address that QEMU's "q35" machine type
+                //        uses for its PCI Express host bridge, covering every function on buses
+                //        0-255. A real x86-64 boot path would need to read this out of the ACPI
+                //        MCFG table instead of assuming it.
+                pub const ECAM_BASE: usize = 0xb000_0000;
+                pub const ECAM_BUS_COUNT: usize = 256;
+            }
+            #[cfg(not(target_arch = "x86_64"))] mod temp {
+                pub const ECAM_BASE: usize = 0;
+                pub const ECAM_BUS_COUNT: usize = 0;
+            }
+            use temp::*;
+
+            for pci_bus in 0 .. ECAM_BUS_COUNT {
+                for device in 0 .. 32usize {
+                    for function in 0 .. 8usize {
+                        let cfg_addr = ECAM_BASE
+                            | (pci_bus << 20) | (device << 15) | (function << 12);
+                        let cfg_resource = bus.reserve(cfg_addr, PCI_CFG_FUNCTION_SIZE)
+                            .map_err(|_| ())?;
+                        let BusType::Pci = cfg_resource.bus else { panic!("unexpected bus type for VirtIO resource: {:?}", cfg_resource.bus) };
+                        let pci_cfg = unsafe {
+                            slice::from_raw_parts(cfg_resource.base as *const u8, PCI_CFG_FUNCTION_SIZE)
+                        };
+
+                        let vendor_id = read_u16(pci_cfg, PCI_CFG_VENDOR_ID);
+                        if vendor_id != PCI_VIRTIO_VENDOR_ID {
+                            // No function here, or not a VirtIO device.
+                            continue;
+                        }
+                        let pci_device_id = read_u16(pci_cfg, PCI_CFG_DEVICE_ID);
+
+                        let (device_type, mut resources) = if pci_device_id >= PCI_VIRTIO_DEVICE_ID_BASE {
+                            // A modern (1.0+) device. Its device type is the offset of its PCI
+                            // device ID from the modern base, matching `DeviceType`'s values.
+                            let device_type = u32::from(pci_device_id - PCI_VIRTIO_DEVICE_ID_BASE);
+                            let bars = pci_bar_resources(bus, pci_cfg)?;
+                            (device_type, bars)
+                        } else if pci_device_id >= 0x1000 {
+                            // A legacy (pre-1.0) device. Its device type comes from the subsystem
+                            // ID instead, since every legacy device shares the same PCI device ID
+                            // range. This crate's VirtIO driver library doesn't speak the legacy
+                            // transitional queue layout, so we still record the device (with no
+                            // BAR resources beyond its configuration space) and let the driver
+                            // reject it: its `init_pci` only accepts modern PCI device IDs, so
+                            // the device fails validation before feature negotiation begins.
+                            let device_type = u32::from(read_u16(pci_cfg, PCI_CFG_SUBSYSTEM_ID));
+                            (device_type, Vec::new())
+                        } else {
+                            continue; // Not a VirtIO device after all.
+                        };
+
+                        let mut name = String::new();
+                        name.try_reserve(mem::size_of_val("virtio-4294967295"))
+                            .map_err(|_| ())?;
+                        write!(name, "virtio-{}", device_type).unwrap();
+
+                        let mut all_resources = Vec::new();
+                        all_resources.try_reserve(1 + resources.len())
+                            .map_err(|_| ())?;
+                        all_resources.push(cfg_resource);
+                        all_resources.append(&mut resources);
+
+                        children.try_reserve(1)
+                            .map_err(|_| ())?;
+                        children.push(DeviceTree::Device {
+                            name,
+                            claimed: AtomicBool::new(false),
+                            resources: all_resources
+                        });
+                    }
+                }
+            }
+        },
         DeviceTree::Device { .. } => {} // There can't be a VirtIO device inside another device.
     };
     Ok(())
 }
 
+fn read_u16(cfg: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(cfg[offset .. offset + 2].try_into().unwrap())
+}
+
+fn read_u32(cfg: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(cfg[offset .. offset + 4].try_into().unwrap())
+}
+
+/// Reserves the BARs that a modern VirtIO-over-PCI device's capability list actually points to
+/// (common/notification/ISR/device-specific configuration), indexed the same way
+/// `drivers/virtio`'s `init_pci` expects: `resources[1 + bar_index]` is BAR number `bar_index`.
+/// BARs that no VirtIO capability references are left as zero-sized placeholders so the indices
+/// still line up.
+fn pci_bar_resources(bus: &crate::bus::pci::PciBus, pci_cfg: &[u8]) -> Result<Vec<Resource>, ()> {
+    const VIRTIO_PCI_CAP_COMMON_CFG: u8 = 1;
+    const VIRTIO_PCI_CAP_NOTIFY_CFG: u8 = 2;
+    const VIRTIO_PCI_CAP_ISR_CFG:    u8 = 3;
+    const VIRTIO_PCI_CAP_DEVICE_CFG: u8 = 4;
+
+    // Find the highest BAR index any virtio-pci capability references, and read each referenced
+    // BAR's address as we go. (Its size isn't read; see below.)
+    let mut bar_addrs: [Option<u64>; 6] = [None; 6];
+    let mut highest_bar = None;
+
+    let mut cap_ptr = usize::from(pci_cfg[PCI_CFG_CAPABILITIES_PTR]);
+    // Both the offsets this walk follows (`cap_ptr`/`cap_next`) come straight from the device's own
+    // config space, so a hostile or broken device can point them anywhere, including past the end
+    // of `pci_cfg` or into a cycle. Bounding the hop count at `pci_cfg.len()` catches both: a
+    // well-formed list can't have more entries than there are bytes to hold them, so this is never
+    // hit by a real device, and it turns a would-be infinite loop into a bailout instead.
+    for _ in 0 .. pci_cfg.len() {
+        if cap_ptr == 0 {
+            break;
+        }
+        let Some(&cap_id) = pci_cfg.get(cap_ptr) else { break };
+        let Some(&cap_next) = pci_cfg.get(cap_ptr + 1) else { break };
+
+        if cap_id == PCI_CAP_ID_VENDOR_SPECIFIC {
+            if let Some(header) = pci_cfg.get(cap_ptr .. cap_ptr + 5) {
+                let cfg_type = header[3];
+                let bar = usize::from(header[4]);
+
+                if matches!(
+                    cfg_type,
+                    VIRTIO_PCI_CAP_COMMON_CFG | VIRTIO_PCI_CAP_NOTIFY_CFG |
+                    VIRTIO_PCI_CAP_ISR_CFG | VIRTIO_PCI_CAP_DEVICE_CFG
+                ) && bar < bar_addrs.len() && bar_addrs[bar].is_none() {
+                    let bar_offset = PCI_CFG_BAR0 + bar * 4;
+                    let bar_low = read_u32(pci_cfg, bar_offset);
+                    let is_64_bit = bar_low & 0x6 == 0x4;
+                    let bar_high = if is_64_bit { read_u32(pci_cfg, bar_offset + 4) } else { 0 };
+                    let addr = (u64::from(bar_high) << 32) | u64::from(bar_low & !0xf);
+                    bar_addrs[bar] = Some(addr);
+                    highest_bar = Some(usize::max(highest_bar.unwrap_or(0), bar));
+                }
+            }
+            // Too short to hold a vendor-specific capability's fixed fields; skip it.
+        }
+
+        cap_ptr = usize::from(cap_next);
+    }
+
+    let mut resources = Vec::new();
+    let Some(highest_bar) = highest_bar else { return Ok(resources); };
+    resources.try_reserve(highest_bar + 1).map_err(|_| ())?;
+
+    for bar in 0 ..= highest_bar {
+        match bar_addrs[bar] {
+            Some(addr) => {
+                // We don't know the BAR's exact size without sizing it through the (writable)
+                // ECAM region, which this crate's bus abstraction doesn't yet support poking.
+                // Reserve a generous fixed window instead; every structure a capability can point
+                // to is far smaller than this.
+                const BAR_RESOURCE_SIZE: usize = 0x1000;
+                resources.push(bus.reserve(addr as usize, BAR_RESOURCE_SIZE).map_err(|_| ())?);
+            },
+            None => {
+                // This BAR index isn't referenced by any virtio-pci capability, but the resource
+                // list still needs an entry here to keep later BARs' indices correct.
+                resources.push(Resource { bus: BusType::Pci, base: 0, size: 0 });
+            }
+        }
+    }
+
+    Ok(resources)
+}
+
 ffi_enum! {
     #[repr(u32)]
     #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,187 @@
+/* Copyright (c) 2022 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! This module defines the `bytecode` executable-format backend: a minimal flat header describing
+//! a register-based bytecode/VM image's code and data segments. It exists mainly to prove that
+//! `ExecImage` really is file-format-independent, by loading a guest interpreter's program through
+//! the exact same lazy `load_segment_piece`/`VirtReader` machinery as ELF.
+//!
+//! # File layout
+//! ```text
+//! offset 0:  magic number, b"PXBC"
+//! offset 4:  format version (currently 1), u32 little-endian
+//! offset 8:  entry point offset within the image's address space, u32 little-endian
+//! offset 12: number of segment entries, u32 little-endian
+//! offset 16: the segment entries themselves, each a `SegmentEntry`
+//! ```
+
+use {
+    alloc::{alloc::AllocError, sync::Arc, vec::Vec},
+    core::{fmt, num::NonZeroUsize},
+
+    locks::Mutex,
+
+    error::Error,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    memory::virt::paging::{self, RootPageTable},
+
+    super::{ExecImage, Readahead, Segment, SegmentFlags, SegmentType, read_struct},
+    super::format::ExecFormat
+};
+
+const MAGIC: [u8; 4] = *b"PXBC";
+const VERSION: u32 = 1;
+
+#[repr(C)]
+struct Header {
+    magic: [u8; 4],
+    version: u32,
+    entry_point: u32,
+    segment_count: u32
+}
+
+#[repr(C)]
+struct SegmentEntry {
+    file_offset: u32,
+    vaddr: u32,
+    file_sz: u32,
+    mem_sz: u32,
+    flags: u8,
+    _reserved: [u8; 3]
+}
+
+/// The bit layout of `SegmentEntry::flags`, matching `crate::SegmentFlags`.
+mod segment_flags {
+    pub(super) const EXECUTABLE: u8 = 0x01;
+    pub(super) const READABLE:   u8 = 0x02;
+    pub(super) const WRITABLE:   u8 = 0x04;
+}
+
+/// The flat bytecode/VM executable-format backend.
+pub(crate) struct Bytecode;
+
+impl ExecFormat for Bytecode {
+    fn probe(file: &mut File) -> io::Result<bool> {
+        let mut magic = [0u8; 4];
+        match file.read_exact(&mut magic) {
+            Ok(()) => Ok(magic == MAGIC),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn parse(mut file: File) -> io::Result<ExecImage<File>> {
+        file.seek(SeekFrom::Start(0))?;
+
+        let header: Header = unsafe { read_struct(&mut file)? };
+        if header.magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, BytecodeParseError::WrongMagic));
+        }
+        if header.version != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, BytecodeParseError::UnsupportedVersion(header.version)));
+        }
+
+        let mut segments = Vec::with_capacity(header.segment_count as usize);
+        let mut entry_point_in_segment = false;
+        for _ in 0 .. header.segment_count {
+            let entry: SegmentEntry = unsafe { read_struct(&mut file)? };
+            let mut flags = SegmentFlags::empty();
+            if entry.flags & segment_flags::EXECUTABLE != 0 { flags |= SegmentFlags::EXECUTABLE; }
+            if entry.flags & segment_flags::READABLE   != 0 { flags |= SegmentFlags::READABLE; }
+            if entry.flags & segment_flags::WRITABLE   != 0 { flags |= SegmentFlags::WRITABLE; }
+
+            let vaddr = entry.vaddr as usize;
+            let mem_sz = entry.mem_sz as usize;
+            if (header.entry_point as usize) >= vaddr && (header.entry_point as usize) < vaddr + mem_sz {
+                entry_point_in_segment = true;
+            }
+
+            segments.push(Segment {
+                seg_type: SegmentType::Load,
+                flags,
+                file_offset: entry.file_offset as usize,
+                vaddr,
+                file_sz: entry.file_sz as usize,
+                mem_sz
+            });
+        }
+        segments.sort_by_key(|seg| seg.vaddr);
+
+        if !entry_point_in_segment {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, BytecodeParseError::EntryPointNotInSegment));
+        }
+
+        // TODO: Try to give each process its own ASID instead of using a constant one.
+        const ASID: u16 = 0;
+        let page_table = Arc::new(
+            RootPageTable::new_userspace(ASID)
+                .map_err(|AllocError| io::Error::new(io::ErrorKind::Other, AllocError))?
+        );
+
+        for segment in segments.iter() {
+            let page_size = paging::page_size();
+            let addr = segment.vaddr / page_size * page_size;
+            let size = segment.vaddr.wrapping_add(segment.mem_sz).wrapping_sub(addr).wrapping_add(page_size - 1)
+                / page_size * page_size;
+            if let Some(size) = NonZeroUsize::new(size) {
+                unsafe {
+                    (*page_table.index(0)).map_exe_file(Some(addr), size)
+                        .map_err(|_| io::Error::new(io::ErrorKind::Other, AllocError))?;
+                }
+            }
+        }
+
+        Ok(ExecImage {
+            reader: Mutex::new(file),
+            _interpreted: None,
+            entry_point: header.entry_point as usize,
+            page_table,
+            segments,
+            readahead: Mutex::new(Readahead::default()),
+            relocations: Vec::new(), // This format has no relocations.
+            reloc_word_size: 4,
+            build_id: None,
+            stack_executable: false,
+            tls_template: None
+        })
+    }
+}
+
+/// An error encountered while parsing a bytecode-format image.
+#[derive(Debug)]
+pub(crate) enum BytecodeParseError {
+    /// The file's magic number wasn't `PXBC`.
+    WrongMagic,
+    /// The file's format version isn't one this kernel understands.
+    UnsupportedVersion(u32),
+    /// The entry point doesn't fall inside any of the image's segments.
+    EntryPointNotInSegment
+}
+
+impl Error for BytecodeParseError {}
+
+impl fmt::Display for BytecodeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BytecodeParseError::WrongMagic => write!(f, "not a recognized bytecode image (wrong magic number)"),
+            BytecodeParseError::UnsupportedVersion(v) => write!(f, "unsupported bytecode image format version {}", v),
+            BytecodeParseError::EntryPointNotInSegment => write!(f, "the bytecode image's entry point isn't in any of its segments")
+        }
+    }
+}
@@ -0,0 +1,112 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Decompresses sections whose `sh_flags` includes `SHF_COMPRESSED`, e.g. the `.debug_*` sections
+//! a toolchain emits when it's asked to shrink debug info. Such a section's file contents begin
+//! with a compression header (`Elf32_Chdr`/`Elf64_Chdr` in the gABI) giving the algorithm and the
+//! uncompressed size, followed by the compressed payload in place of the section's usual bytes.
+
+use {
+    core::mem::size_of,
+    alloc::{string::String, vec::Vec},
+    miniz_oxide::inflate::decompress_to_vec_zlib_with_limit,
+    i18n::Text,
+    io::{Read, Seek, SeekFrom},
+    super::{
+        endian::{Endian, U32, U64},
+        error::ElfParseError,
+        headers::{ElfClass, SectionHeaderEntry64},
+        read_struct
+    }
+};
+
+/// The only compression algorithm the gABI defines today, and the only one this loader
+/// understands.
+const ELFCOMPRESS_ZLIB: u32 = 1;
+
+// On-disk, 32-bit compression header (`Elf32_Chdr`).
+#[repr(C, packed)]
+struct CompressionHeader32 {
+    ch_type:      U32,
+    ch_size:      U32,
+    ch_addralign: U32
+}
+
+// On-disk, 64-bit compression header (`Elf64_Chdr`). Unlike the 32-bit form, this one has an
+// explicit reserved word so `ch_size`/`ch_addralign` stay 8-byte aligned.
+#[repr(C, packed)]
+struct CompressionHeader64 {
+    ch_type:      U32,
+    ch_reserved:  U32,
+    ch_size:      U64,
+    ch_addralign: U64
+}
+
+/// Reads and inflates a section whose `sh_flags` includes `SHF_COMPRESSED`, returning its
+/// expanded contents. The caller is responsible for checking that flag before calling this;
+/// calling it on an uncompressed section will misinterpret the section's real contents as a
+/// compression header.
+pub(crate) fn read_compressed_section<T: Read + Seek>(
+    reader: &mut T,
+    class: ElfClass,
+    endian: Endian,
+    section: &SectionHeaderEntry64
+) -> io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(section.sh_offset))?;
+
+    let (ch_type, ch_size, header_size) = match class {
+        ElfClass::Bits32 => {
+            let header: CompressionHeader32 = unsafe { read_struct(reader)? };
+            (header.ch_type.get(endian), header.ch_size.get(endian) as u64, size_of::<CompressionHeader32>())
+        },
+        ElfClass::Bits64 => {
+            let header: CompressionHeader64 = unsafe { read_struct(reader)? };
+            (header.ch_type.get(endian), header.ch_size.get(endian), size_of::<CompressionHeader64>())
+        }
+    };
+
+    if ch_type != ELFCOMPRESS_ZLIB {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(Text::ElfUnsupportedCompression(ch_type))));
+    }
+
+    let compressed_len = (section.sh_size as usize).saturating_sub(header_size);
+    let mut compressed = Vec::with_capacity(compressed_len);
+    compressed.resize(compressed_len, 0);
+    reader.read_exact(&mut compressed)?;
+
+    // `ch_size` is the decompressed size the section itself claims, so it's also exactly the
+    // right limit to decompress under: a payload that would expand past it is already invalid
+    // (checked below) and isn't worth the memory to finish inflating, let alone a small malicious
+    // payload crafted to expand far past it (a decompression bomb).
+    let ch_size_limit = usize::try_from(ch_size).map_err(|_| io::Error::new(
+        io::ErrorKind::InvalidData,
+        ElfParseError::new(Text::ElfInvalidFile(String::from("a compressed section's ch_size is too large")))
+    ))?;
+    let decompressed = decompress_to_vec_zlib_with_limit(&compressed, ch_size_limit).map_err(|_| io::Error::new(
+        io::ErrorKind::InvalidData,
+        ElfParseError::new(Text::ElfInvalidFile(String::from("a compressed section's zlib data is corrupt")))
+    ))?;
+
+    if decompressed.len() as u64 != ch_size {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(
+            Text::ElfInvalidFile(String::from("a compressed section's decompressed length doesn't match its ch_size"))
+        )));
+    }
+
+    Ok(decompressed)
+}
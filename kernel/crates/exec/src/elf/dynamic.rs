@@ -0,0 +1,121 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Reads the `PT_DYNAMIC` array of a position-independent or shared ELF image and collects the
+//! handful of tags the loader needs in order to relocate it. See `reloc` for what's actually done
+//! with the result.
+
+use {
+    core::mem::size_of,
+    alloc::string::String,
+    i18n::Text,
+    io::Read,
+    super::{error::ElfParseError, headers::ElfClass, read_struct}
+};
+
+// We only recognize the tags relevant to relocation; everything else (DT_NEEDED, DT_HASH, DT_INIT,
+// ...) is read and ignored, the same way unrecognized program-header flags would be if they showed
+// up outside the bits we validate.
+const DT_NULL: i64 = 0;
+const DT_PLTRELSZ: i64 = 2;
+// const DT_STRTAB: i64 = 5; (ELF defines this tag, but we have no use for symbol names.)
+const DT_SYMTAB: i64 = 6;
+pub(crate) const DT_RELA: i64 = 7;
+const DT_RELASZ: i64 = 8;
+const DT_RELAENT: i64 = 9;
+pub(crate) const DT_REL: i64 = 17;
+const DT_RELSZ: i64 = 18;
+const DT_RELENT: i64 = 19;
+const DT_PLTREL: i64 = 20;
+const DT_JMPREL: i64 = 23;
+// const DT_RELACOUNT: i64 = 0x6fff_fff9; (ELF defines this tag, but it's only an optimization hint.)
+
+#[repr(C, packed)]
+struct DynEntry32 {
+    d_tag: i32,
+    d_val: u32 // Also used as d_ptr; both are a single 32-bit word in the file.
+}
+
+#[repr(C, packed)]
+struct DynEntry64 {
+    d_tag: i64,
+    d_val: u64 // Also used as d_ptr; both are a single 64-bit word in the file.
+}
+
+/// The subset of `PT_DYNAMIC` tags the loader understands, all as the virtual addresses or sizes
+/// they describe, except `pltrel`, which holds a relocation tag (`DT_RELA` or `DT_REL`). A field is `None` if its tag wasn't present, which is how an image that needs
+/// no relocating (or that has no PLT) is represented.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct DynamicInfo {
+    pub(crate) rela:       Option<u64>,
+    pub(crate) relasz:     Option<u64>,
+    pub(crate) relaent:    Option<u64>,
+    pub(crate) rel:        Option<u64>,
+    pub(crate) relsz:      Option<u64>,
+    pub(crate) relent:     Option<u64>,
+    pub(crate) pltrel:     Option<u64>,
+    pub(crate) jmprel:     Option<u64>,
+    pub(crate) pltrelsz:   Option<u64>,
+    pub(crate) symtab:     Option<u64>
+}
+
+/// Reads every entry of a `PT_DYNAMIC` segment (`size` bytes, starting at the reader's current
+/// position) and collects the tags relevant to relocation. Stops early at a `DT_NULL` entry, as
+/// the format requires the array to end with one.
+pub(crate) fn read_dynamic<T: Read>(reader: &mut T, class: ElfClass, size: u64) -> io::Result<DynamicInfo> {
+    let entry_size = match class {
+        ElfClass::Bits32 => size_of::<DynEntry32>(),
+        ElfClass::Bits64 => size_of::<DynEntry64>()
+    } as u64;
+    if size % entry_size != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(
+            Text::ElfInvalidFile(String::from("PT_DYNAMIC's size is not a multiple of its entry size"))
+        )));
+    }
+
+    let mut info = DynamicInfo::default();
+    for _ in 0 .. size / entry_size {
+        let (tag, val): (i64, u64) = match class {
+            ElfClass::Bits32 => {
+                let entry: DynEntry32 = unsafe { read_struct(reader)? };
+                (entry.d_tag as i64, entry.d_val as u64)
+            },
+            ElfClass::Bits64 => {
+                let entry: DynEntry64 = unsafe { read_struct(reader)? };
+                (entry.d_tag, entry.d_val)
+            }
+        };
+
+        match tag {
+            DT_NULL    => break,
+            DT_PLTRELSZ => info.pltrelsz = Some(val),
+            DT_SYMTAB   => info.symtab   = Some(val),
+            DT_RELA     => info.rela     = Some(val),
+            DT_RELASZ   => info.relasz   = Some(val),
+            DT_RELAENT  => info.relaent  = Some(val),
+            DT_REL      => info.rel      = Some(val),
+            DT_RELSZ    => info.relsz    = Some(val),
+            DT_RELENT   => info.relent   = Some(val),
+            DT_PLTREL   => info.pltrel   = Some(val),
+            DT_JMPREL   => info.jmprel   = Some(val),
+            _ => {} // Not a tag we need for relocation.
+        }
+    }
+
+    Ok(info)
+}
@@ -0,0 +1,57 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! A byte-swapping abstraction for the multi-byte fields of an on-disk ELF header, program-header
+//! entry, or section-header entry, modeled on the `object` crate's `endian` module. Every such
+//! field is stored as one of the wrapper types below (a plain byte array with no inherent order)
+//! instead of a host-endian integer, so the raw `#[repr(C, packed)]` structs in `headers` can be
+//! `read_struct`'d from either a little- or big-endian image without ever reinterpreting its bytes
+//! as the wrong-endian value first. The only way to get a usable integer out is `get`, which always
+//! takes the `Endian` the image actually claims to be.
+
+/// Which byte order a value is stored in, as read from an image's own `ElfHeader::data` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Endian {
+    Little,
+    Big
+}
+
+macro_rules! endian_int {
+    ($name:ident, $int:ty) => {
+        /// An on-disk integer whose byte order isn't known until the image's `ElfHeader::data`
+        /// has been read.
+        #[repr(transparent)]
+        #[derive(Clone, Copy)]
+        pub(crate) struct $name([u8; core::mem::size_of::<$int>()]);
+
+        impl $name {
+            /// Decodes this field as a host-native integer, swapping its bytes first if `endian`
+            /// doesn't match the host's own byte order.
+            pub(crate) fn get(self, endian: Endian) -> $int {
+                match endian {
+                    Endian::Little => <$int>::from_le_bytes(self.0),
+                    Endian::Big => <$int>::from_be_bytes(self.0)
+                }
+            }
+        }
+    };
+}
+
+endian_int!(U16, u16);
+endian_int!(U32, u32);
+endian_int!(U64, u64);
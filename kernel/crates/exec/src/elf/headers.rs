@@ -19,11 +19,12 @@
 use {
     core::{
         convert::TryFrom,
-        mem::size_of,
-        ptr
+        mem::{self, size_of}
     },
+    alloc::{string::String, vec::Vec},
     i18n::Text,
-    super::error::ElfParseError
+    io::{Read, Seek, SeekFrom},
+    super::{endian::{Endian, U16, U32, U64}, error::ElfParseError}
 };
 
 //
@@ -51,26 +52,29 @@ impl ElfHeader {
         }
 
         ElfClass::validate(unsafe { *(&self.class as *const _ as *const u8) })?;
-        ElfData::validate(unsafe { *(&self.data as *const _ as *const u8) })?;
+        let data = unsafe { *(&self.data as *const _ as *const u8) };
+        ElfData::validate(data)?;
+        let endian = ElfData::try_from(data).unwrap().endian();
         if self.ident_version != 1 {
             return Err(ElfParseError::new(Text::ElfUnsupportedVersion(self.ident_version as u32)));
         }
         OsAbi::validate(unsafe { *(&self.os_abi as *const _ as *const u8) }, self.abi_version)?;
 
         match self.class {
-            ElfClass::Bits32 => unsafe { self.header_ex.header_32.validate()? },
-            ElfClass::Bits64 => unsafe { self.header_ex.header_64.validate()? }
+            ElfClass::Bits32 => unsafe { self.header_ex.header_32.validate(endian)? },
+            ElfClass::Bits64 => unsafe { self.header_ex.header_64.validate(endian)? }
         };
 
         Ok(())
     }
 
     /// Returns the variable-sized portion of the ELF header as an `ElfHeaderEx64`, since the union
-    /// type is more cumbersome.
-    pub(crate) fn ex_64(self) -> ElfHeaderEx64 {
+    /// type is more cumbersome. `endian` must be the byte order `self.data` indicates; it isn't
+    /// re-derived here because every caller has already read it off this same header.
+    pub(crate) fn ex_64(self, endian: Endian) -> ElfHeaderEx64 {
         match self.class {
-            ElfClass::Bits32 => unsafe { self.header_ex.header_32 }.into(),
-            ElfClass::Bits64 => unsafe { self.header_ex.header_64 }
+            ElfClass::Bits32 => unsafe { self.header_ex.header_32 }.decode(endian),
+            ElfClass::Bits64 => unsafe { self.header_ex.header_64 }.decode(endian)
         }
     }
 }
@@ -78,26 +82,48 @@ impl ElfHeader {
 #[repr(C)]
 pub(crate) union ElfHeaderEx {
     header_32: ElfHeaderEx32,
-    header_64: ElfHeaderEx64
+    header_64: ElfHeaderEx64Raw
 }
 
+// On-disk, 32-bit layout. Every multi-byte field's byte order isn't known until `ElfHeader::data`
+// has been read, so each one is stored as an `endian`-wrapped integer instead of a host-native type.
 #[repr(C, packed)]
 pub(crate) struct ElfHeaderEx32 {
-    file_type:     ElfType,
-    target_arch:   Arch,
-    elf_version:   u32,
-    entry_point:   u32,
-    ph_off:        u32,
-    sh_off:        u32,
-    flags:         ElfFlags,
-    eh_size:       u16,
-    ph_ent_size:   u16,
-    ph_num:        u16,
-    sh_ent_size:   u16,
-    sh_num:        u16,
-    sh_str_index:  u16
-}
-
+    file_type:     U16,
+    target_arch:   U16,
+    elf_version:   U32,
+    entry_point:   U32,
+    ph_off:        U32,
+    sh_off:        U32,
+    flags:         U32,
+    eh_size:       U16,
+    ph_ent_size:   U16,
+    ph_num:        U16,
+    sh_ent_size:   U16,
+    sh_num:        U16,
+    sh_str_index:  U16
+}
+
+// On-disk, 64-bit layout. See `ElfHeaderEx32` for why these fields are endian-wrapped.
+#[repr(C, packed)]
+pub(crate) struct ElfHeaderEx64Raw {
+    file_type:     U16,
+    target_arch:   U16,
+    elf_version:   U32,
+    entry_point:   U64,
+    ph_off:        U64,
+    sh_off:        U64,
+    flags:         U32,
+    eh_size:       U16,
+    ph_ent_size:   U16,
+    ph_num:        U16,
+    sh_ent_size:   U16,
+    sh_num:        U16,
+    sh_str_index:  U16
+}
+
+/// The decoded, host-native form of the variable-sized portion of an ELF header, regardless of
+/// which on-disk class or byte order it was read from.
 #[repr(C, packed)]
 pub(crate) struct ElfHeaderEx64 {
     pub(crate) file_type:     ElfType,
@@ -115,64 +141,88 @@ pub(crate) struct ElfHeaderEx64 {
     pub(crate) sh_str_index:  u16
 }
 
-impl From<ElfHeaderEx32> for ElfHeaderEx64 {
-    fn from(header_32: ElfHeaderEx32) -> ElfHeaderEx64 {
+impl ElfHeaderEx32 {
+    fn decode(&self, endian: Endian) -> ElfHeaderEx64 {
         ElfHeaderEx64 {
-            file_type:    header_32.file_type,
-            target_arch:  header_32.target_arch,
-            elf_version:  header_32.elf_version,
-            entry_point:  header_32.entry_point as u64,
-            ph_off:       header_32.ph_off as u64,
-            sh_off:       header_32.sh_off as u64,
-            flags:        header_32.flags,
-            eh_size:      header_32.eh_size,
-            ph_ent_size:  header_32.ph_ent_size,
-            ph_num:       header_32.ph_num,
-            sh_ent_size:  header_32.sh_ent_size,
-            sh_num:       header_32.sh_num,
-            sh_str_index: header_32.sh_str_index
+            file_type:    unsafe { mem::transmute(self.file_type.get(endian)) },
+            target_arch:  unsafe { mem::transmute(self.target_arch.get(endian)) },
+            elf_version:  self.elf_version.get(endian),
+            entry_point:  self.entry_point.get(endian) as u64,
+            ph_off:       self.ph_off.get(endian) as u64,
+            sh_off:       self.sh_off.get(endian) as u64,
+            flags:        unsafe { mem::transmute(self.flags.get(endian)) },
+            eh_size:      self.eh_size.get(endian),
+            ph_ent_size:  self.ph_ent_size.get(endian),
+            ph_num:       self.ph_num.get(endian),
+            sh_ent_size:  self.sh_ent_size.get(endian),
+            sh_num:       self.sh_num.get(endian),
+            sh_str_index: self.sh_str_index.get(endian)
         }
     }
-}
 
-impl ElfHeaderEx32 {
-    pub(crate) fn validate(&self) -> Result<(), ElfParseError> {
-        ElfType::validate(unsafe { *(ptr::addr_of!(self.file_type) as *const u16) })?;
-        Arch::validate(unsafe { *(ptr::addr_of!(self.target_arch) as *const u16) })?;
-        if self.elf_version != 1 {
-            return Err(ElfParseError::new(Text::ElfUnsupportedVersion(self.elf_version)));
+    fn validate(&self, endian: Endian) -> Result<(), ElfParseError> {
+        ElfType::validate(self.file_type.get(endian))?;
+        Arch::validate(self.target_arch.get(endian))?;
+        let elf_version = self.elf_version.get(endian);
+        if elf_version != 1 {
+            return Err(ElfParseError::new(Text::ElfUnsupportedVersion(elf_version)));
         }
-        ElfFlags::validate(unsafe { *(ptr::addr_of!(self.flags) as *const u32) })?;
-        if (self.eh_size as usize) < 16 + size_of::<ElfHeaderEx32>() {
-            return Err(ElfParseError::new(Text::ElfHeaderTooSmall(16 + size_of::<ElfHeaderEx32>(), self.eh_size)));
+        ElfFlags::validate(self.flags.get(endian))?;
+        let eh_size = self.eh_size.get(endian);
+        if (eh_size as usize) < 16 + size_of::<ElfHeaderEx32>() {
+            return Err(ElfParseError::new(Text::ElfHeaderTooSmall(16 + size_of::<ElfHeaderEx32>(), eh_size)));
         }
-        if self.ph_ent_size != 0 && (self.ph_ent_size as usize) < size_of::<ProgramHeaderEntry32>() {
-            return Err(ElfParseError::new(Text::ElfPHEntriesTooSmall(size_of::<ProgramHeaderEntry32>(), self.ph_ent_size)));
+        let ph_ent_size = self.ph_ent_size.get(endian);
+        if ph_ent_size != 0 && (ph_ent_size as usize) < size_of::<ProgramHeaderEntry32>() {
+            return Err(ElfParseError::new(Text::ElfPHEntriesTooSmall(size_of::<ProgramHeaderEntry32>(), ph_ent_size)));
         }
-        if self.sh_ent_size != 0 && (self.sh_ent_size as usize) < size_of::<SectionHeaderEntry32>() {
-            return Err(ElfParseError::new(Text::ElfSHEntriesTooSmall(size_of::<SectionHeaderEntry32>(), self.sh_ent_size)));
+        let sh_ent_size = self.sh_ent_size.get(endian);
+        if sh_ent_size != 0 && (sh_ent_size as usize) < size_of::<SectionHeaderEntry32>() {
+            return Err(ElfParseError::new(Text::ElfSHEntriesTooSmall(size_of::<SectionHeaderEntry32>(), sh_ent_size)));
         }
 
         Ok(())
     }
 }
 
-impl ElfHeaderEx64 {
-    pub(crate) fn validate(&self) -> Result<(), ElfParseError> {
-        ElfType::validate(unsafe { *(ptr::addr_of!(self.file_type) as *const u16) })?;
-        Arch::validate(unsafe { *(ptr::addr_of!(self.target_arch) as *const u16) })?;
-        if self.elf_version != 1 {
-            return Err(ElfParseError::new(Text::ElfUnsupportedVersion(self.elf_version)));
+impl ElfHeaderEx64Raw {
+    fn decode(&self, endian: Endian) -> ElfHeaderEx64 {
+        ElfHeaderEx64 {
+            file_type:    unsafe { mem::transmute(self.file_type.get(endian)) },
+            target_arch:  unsafe { mem::transmute(self.target_arch.get(endian)) },
+            elf_version:  self.elf_version.get(endian),
+            entry_point:  self.entry_point.get(endian),
+            ph_off:       self.ph_off.get(endian),
+            sh_off:       self.sh_off.get(endian),
+            flags:        unsafe { mem::transmute(self.flags.get(endian)) },
+            eh_size:      self.eh_size.get(endian),
+            ph_ent_size:  self.ph_ent_size.get(endian),
+            ph_num:       self.ph_num.get(endian),
+            sh_ent_size:  self.sh_ent_size.get(endian),
+            sh_num:       self.sh_num.get(endian),
+            sh_str_index: self.sh_str_index.get(endian)
+        }
+    }
+
+    fn validate(&self, endian: Endian) -> Result<(), ElfParseError> {
+        ElfType::validate(self.file_type.get(endian))?;
+        Arch::validate(self.target_arch.get(endian))?;
+        let elf_version = self.elf_version.get(endian);
+        if elf_version != 1 {
+            return Err(ElfParseError::new(Text::ElfUnsupportedVersion(elf_version)));
         }
-        ElfFlags::validate(unsafe { *(ptr::addr_of!(self.flags) as *const u32) })?;
-        if (self.eh_size as usize) < 16 + size_of::<ElfHeaderEx64>() {
-            return Err(ElfParseError::new(Text::ElfHeaderTooSmall(16 + size_of::<ElfHeaderEx64>(), self.eh_size)));
+        ElfFlags::validate(self.flags.get(endian))?;
+        let eh_size = self.eh_size.get(endian);
+        if (eh_size as usize) < 16 + size_of::<ElfHeaderEx64Raw>() {
+            return Err(ElfParseError::new(Text::ElfHeaderTooSmall(16 + size_of::<ElfHeaderEx64Raw>(), eh_size)));
         }
-        if self.ph_ent_size != 0 && (self.ph_ent_size as usize) < size_of::<ProgramHeaderEntry64>() {
-            return Err(ElfParseError::new(Text::ElfPHEntriesTooSmall(size_of::<ProgramHeaderEntry64>(), self.ph_ent_size)));
+        let ph_ent_size = self.ph_ent_size.get(endian);
+        if ph_ent_size != 0 && (ph_ent_size as usize) < size_of::<ProgramHeaderEntry64Raw>() {
+            return Err(ElfParseError::new(Text::ElfPHEntriesTooSmall(size_of::<ProgramHeaderEntry64Raw>(), ph_ent_size)));
         }
-        if self.sh_ent_size != 0 && (self.sh_ent_size as usize) < size_of::<SectionHeaderEntry64>() {
-            return Err(ElfParseError::new(Text::ElfSHEntriesTooSmall(size_of::<SectionHeaderEntry64>(), self.sh_ent_size)));
+        let sh_ent_size = self.sh_ent_size.get(endian);
+        if sh_ent_size != 0 && (sh_ent_size as usize) < size_of::<SectionHeaderEntry64Raw>() {
+            return Err(ElfParseError::new(Text::ElfSHEntriesTooSmall(size_of::<SectionHeaderEntry64Raw>(), sh_ent_size)));
         }
 
         Ok(())
@@ -305,31 +355,22 @@ impl ElfClass {
 }
 
 impl ElfData {
-    #[cfg(target_endian = "little")]
     pub(crate) fn validate(val: u8) -> Result<(), ElfParseError> {
-        // If the file's endianness doesn't match the system's, we can't use it.
-        // TODO: Some architectures, like AArch64, may allow software to change the system's
-        // endianness. If the system supports that, we should support both endianness settings.
-        // Also, ARMv6 supports BE-8 images, which seem to be a mixture of big- and
-        // little-endian.
-        match Self::try_from(val) {
-            Ok(ElfData::LittleEndian) => Ok(()),
-            Ok(ElfData::BigEndian)    => Err(ElfParseError::new(Text::ElfBigOnLittle)),
-            Err(_) => Err(ElfParseError::new(Text::ElfUnsupportedEndianness(val)))
+        // Either byte order is fine; the loader decodes the rest of the file according to
+        // whichever one this turns out to be. (ARMv6's BE-8 images, which mix big- and
+        // little-endian, still aren't supported.)
+        if Self::try_from(val).is_ok() {
+            Ok(())
+        } else {
+            Err(ElfParseError::new(Text::ElfUnsupportedEndianness(val)))
         }
     }
 
-    #[cfg(target_endian = "big")]
-    pub(crate) fn validate(val: u8) -> Result<(), ElfParseError> {
-        // If the file's endianness doesn't match the system's, we can't use it.
-        // TODO: Some architectures, like AArch64, may allow software to change the system's
-        // endianness. If the system supports that, we should support both endianness settings.
-        // Also, ARMv6 supports BE-8 images, which seem to be a mixture of big- and
-        // little-endian.
-        match Self::try_from(val) {
-            Ok(ElfData::BigEndian)    => Ok(()),
-            Ok(ElfData::LittleEndian) => Err(ElfParseError::new(Text::ElfLittleOnBig)),
-            Err(_) => Err(ElfParseError::new(Text::ElfUnsupportedEndianness(val)))
+    /// The byte order this value says the rest of the image's multi-byte fields are stored in.
+    pub(crate) fn endian(self) -> Endian {
+        match self {
+            ElfData::LittleEndian => Endian::Little,
+            ElfData::BigEndian => Endian::Big
         }
     }
 }
@@ -408,18 +449,34 @@ pub(crate) union ProgramHeaderEntry {
     ph_64: ProgramHeaderEntry64
 }*/
 
+// On-disk, 32-bit layout. See `ElfHeaderEx32` for why these fields are endian-wrapped.
 #[repr(C, packed)]
 pub(crate) struct ProgramHeaderEntry32 {
-    seg_type: SegmentType,
-    offset:   u32,
-    vaddr:    u32,
-    paddr:    u32,
-    file_sz:  u32,
-    mem_sz:   u32,
-    flags:    SegmentFlags,
-    align:    u32
+    seg_type: U32,
+    offset:   U32,
+    vaddr:    U32,
+    paddr:    U32,
+    file_sz:  U32,
+    mem_sz:   U32,
+    flags:    U32,
+    align:    U32
 }
 
+// On-disk, 64-bit layout. See `ElfHeaderEx32` for why these fields are endian-wrapped.
+#[repr(C, packed)]
+pub(crate) struct ProgramHeaderEntry64Raw {
+    seg_type: U32,
+    flags:    U32,
+    offset:   U64,
+    vaddr:    U64,
+    paddr:    U64,
+    file_sz:  U64,
+    mem_sz:   U64,
+    align:    U64
+}
+
+/// The decoded, host-native form of a program-header entry, regardless of which on-disk class or
+/// byte order it was read from.
 #[repr(C, packed)]
 pub(crate) struct ProgramHeaderEntry64 {
     pub(crate) seg_type: SegmentType,
@@ -432,21 +489,6 @@ pub(crate) struct ProgramHeaderEntry64 {
     pub(crate) align:    u64
 }
 
-impl From<ProgramHeaderEntry32> for ProgramHeaderEntry64 {
-    fn from(old: ProgramHeaderEntry32) -> ProgramHeaderEntry64 {
-        ProgramHeaderEntry64 {
-            seg_type: old.seg_type,
-            flags: old.flags,
-            offset: old.offset as u64,
-            vaddr: old.vaddr as u64,
-            paddr: old.paddr as u64,
-            file_sz: old.file_sz as u64,
-            mem_sz: old.mem_sz as u64,
-            align: old.align as u64
-        }
-    }
-}
-
 /*impl ProgramHeaderEntry {
     pub(crate) fn validate(&self, class: ElfClass) -> Result<(), ElfParseError> {
         match class {
@@ -457,20 +499,38 @@ impl From<ProgramHeaderEntry32> for ProgramHeaderEntry64 {
 }*/
 
 impl ProgramHeaderEntry32 {
-    pub(crate) fn validate(&self) -> Result<(), ElfParseError> {
-        SegmentType::validate(unsafe { *(ptr::addr_of!(self.seg_type) as *const u32) })?;
-        SegmentFlags::validate(unsafe { *(ptr::addr_of!(self.flags) as *const u32) })?;
+    pub(crate) fn decode(&self, endian: Endian) -> ProgramHeaderEntry64 {
+        ProgramHeaderEntry64 {
+            seg_type: unsafe { mem::transmute(self.seg_type.get(endian)) },
+            flags:    unsafe { mem::transmute(self.flags.get(endian)) },
+            offset:   self.offset.get(endian) as u64,
+            vaddr:    self.vaddr.get(endian) as u64,
+            paddr:    self.paddr.get(endian) as u64,
+            file_sz:  self.file_sz.get(endian) as u64,
+            mem_sz:   self.mem_sz.get(endian) as u64,
+            align:    self.align.get(endian) as u64
+        }
+    }
+
+    pub(crate) fn validate(&self, endian: Endian) -> Result<(), ElfParseError> {
+        SegmentType::validate(self.seg_type.get(endian))?;
+        SegmentFlags::validate(self.flags.get(endian))?;
 
         // The alignment should be 0 or a power of 2.
-        if self.align.count_ones() > 1 {
-            return Err(ElfParseError::new(Text::ElfBadSegAlign(self.align as u64)));
+        let align = self.align.get(endian);
+        if align.count_ones() > 1 {
+            return Err(ElfParseError::new(Text::ElfBadSegAlign(align as u64)));
         }
-        let align_mask = if self.align == 0 { 0 } else { self.align - 1 };
+        let align_mask = if align == 0 { 0 } else { align - 1 };
 
-        if self.file_sz != 0 && self.mem_sz != 0 {
+        let file_sz = self.file_sz.get(endian);
+        let mem_sz = self.mem_sz.get(endian);
+        if file_sz != 0 && mem_sz != 0 {
             // The addresses should be aligned correctly.
-            if self.offset & align_mask != self.vaddr & align_mask {
-                return Err(ElfParseError::new(Text::ElfSegmentMisaligned(self.offset as u64, self.vaddr as u64)));
+            let offset = self.offset.get(endian);
+            let vaddr = self.vaddr.get(endian);
+            if offset & align_mask != vaddr & align_mask {
+                return Err(ElfParseError::new(Text::ElfSegmentMisaligned(offset as u64, vaddr as u64)));
             }
         }
 
@@ -478,21 +538,39 @@ impl ProgramHeaderEntry32 {
     }
 }
 
-impl ProgramHeaderEntry64 {
-    pub(crate) fn validate(&self) -> Result<(), ElfParseError> {
-        SegmentType::validate(unsafe { *(ptr::addr_of!(self.seg_type) as *const u32) })?;
-        SegmentFlags::validate(unsafe { *(ptr::addr_of!(self.flags) as *const u32) })?;
+impl ProgramHeaderEntry64Raw {
+    pub(crate) fn decode(&self, endian: Endian) -> ProgramHeaderEntry64 {
+        ProgramHeaderEntry64 {
+            seg_type: unsafe { mem::transmute(self.seg_type.get(endian)) },
+            flags:    unsafe { mem::transmute(self.flags.get(endian)) },
+            offset:   self.offset.get(endian),
+            vaddr:    self.vaddr.get(endian),
+            paddr:    self.paddr.get(endian),
+            file_sz:  self.file_sz.get(endian),
+            mem_sz:   self.mem_sz.get(endian),
+            align:    self.align.get(endian)
+        }
+    }
+
+    pub(crate) fn validate(&self, endian: Endian) -> Result<(), ElfParseError> {
+        SegmentType::validate(self.seg_type.get(endian))?;
+        SegmentFlags::validate(self.flags.get(endian))?;
 
         // The alignment should be 0 or a power of 2.
-        if self.align.count_ones() > 1 {
-            return Err(ElfParseError::new(Text::ElfBadSegAlign(self.align)));
+        let align = self.align.get(endian);
+        if align.count_ones() > 1 {
+            return Err(ElfParseError::new(Text::ElfBadSegAlign(align)));
         }
-        let align_mask = if self.align == 0 { 0 } else { self.align - 1 };
+        let align_mask = if align == 0 { 0 } else { align - 1 };
 
-        if self.file_sz != 0 && self.mem_sz != 0 {
+        let file_sz = self.file_sz.get(endian);
+        let mem_sz = self.mem_sz.get(endian);
+        if file_sz != 0 && mem_sz != 0 {
             // The addresses should be aligned correctly.
-            if self.offset & align_mask != self.vaddr & align_mask {
-                return Err(ElfParseError::new(Text::ElfSegmentMisaligned(self.offset, self.vaddr)));
+            let offset = self.offset.get(endian);
+            let vaddr = self.vaddr.get(endian);
+            if offset & align_mask != vaddr & align_mask {
+                return Err(ElfParseError::new(Text::ElfSegmentMisaligned(offset, vaddr)));
             }
         }
 
@@ -552,6 +630,7 @@ ffi_enum! {
         Note    = 0x0000_0004,
         ShLib   = 0x0000_0005,
         PHdr    = 0x0000_0006,
+        Tls     = 0x0000_0007,
         // LoOs .. HiOs = 0x6000_0000 .. 0x6fff_ffff
         // LoProc .. HiProc = 0x7000_0000 .. 0x7fff_ffff
 
@@ -571,6 +650,7 @@ ffi_enum! {
         Note    = SegmentTypeCommon::Note as u32,
         ShLib   = SegmentTypeCommon::ShLib as u32,
         PHdr    = SegmentTypeCommon::PHdr as u32,
+        Tls     = SegmentTypeCommon::Tls as u32,
         ArchExt = 0x7000_0000,
         Unwind  = 0x7000_0001,
 
@@ -613,13 +693,179 @@ pub(crate) union SectionHeaderEntry {
     sh_64: SectionHeaderEntry64
 }*/
 
-// TODO
+// On-disk, 32-bit layout. See `ElfHeaderEx32` for why these fields are endian-wrapped.
 #[repr(C, packed)]
-pub(crate) struct SectionHeaderEntry32;
-
-// TODO
+pub(crate) struct SectionHeaderEntry32 {
+    sh_name:      U32,
+    sh_type:      U32,
+    sh_flags:     U32,
+    sh_addr:      U32,
+    sh_offset:    U32,
+    sh_size:      U32,
+    sh_link:      U32,
+    sh_info:      U32,
+    sh_addralign: U32,
+    sh_entsize:   U32
+}
+
+// On-disk, 64-bit layout. See `ElfHeaderEx32` for why these fields are endian-wrapped.
 #[repr(C, packed)]
-pub(crate) struct SectionHeaderEntry64;
+pub(crate) struct SectionHeaderEntry64Raw {
+    sh_name:      U32,
+    sh_type:      U32,
+    sh_flags:     U64,
+    sh_addr:      U64,
+    sh_offset:    U64,
+    sh_size:      U64,
+    sh_link:      U32,
+    sh_info:      U32,
+    sh_addralign: U64,
+    sh_entsize:   U64
+}
+
+/// The decoded, host-native form of a section-header entry, regardless of which on-disk class or
+/// byte order it was read from.
+#[repr(C, packed)]
+pub(crate) struct SectionHeaderEntry64 {
+    pub(crate) sh_name:      u32,
+    pub(crate) sh_type:      SectionType,
+    pub(crate) sh_flags:     u64,
+    pub(crate) sh_addr:      u64,
+    pub(crate) sh_offset:    u64,
+    pub(crate) sh_size:      u64,
+    pub(crate) sh_link:      u32,
+    pub(crate) sh_info:      u32,
+    pub(crate) sh_addralign: u64,
+    pub(crate) sh_entsize:   u64
+}
+
+impl SectionHeaderEntry32 {
+    pub(crate) fn decode(&self, endian: Endian) -> SectionHeaderEntry64 {
+        SectionHeaderEntry64 {
+            sh_name:      self.sh_name.get(endian),
+            sh_type:      unsafe { mem::transmute(self.sh_type.get(endian)) },
+            sh_flags:     self.sh_flags.get(endian) as u64,
+            sh_addr:      self.sh_addr.get(endian) as u64,
+            sh_offset:    self.sh_offset.get(endian) as u64,
+            sh_size:      self.sh_size.get(endian) as u64,
+            sh_link:      self.sh_link.get(endian),
+            sh_info:      self.sh_info.get(endian),
+            sh_addralign: self.sh_addralign.get(endian) as u64,
+            sh_entsize:   self.sh_entsize.get(endian) as u64
+        }
+    }
+
+    pub(crate) fn validate(&self, endian: Endian) -> Result<(), ElfParseError> {
+        SectionType::validate(self.sh_type.get(endian))?;
+
+        // The alignment should be 0 or a power of 2.
+        let sh_addralign = self.sh_addralign.get(endian);
+        if sh_addralign.count_ones() > 1 {
+            return Err(ElfParseError::new(Text::ElfBadSectionAlign(sh_addralign as u64)));
+        }
+        let align_mask = if sh_addralign == 0 { 0 } else { sh_addralign - 1 };
+
+        // Only sections that are actually loaded into memory need their file offset and address
+        // to agree on alignment.
+        let sh_addr = self.sh_addr.get(endian);
+        let sh_offset = self.sh_offset.get(endian);
+        if sh_addr != 0 && sh_offset & align_mask != sh_addr & align_mask {
+            return Err(ElfParseError::new(Text::ElfSectionMisaligned(sh_offset as u64, sh_addr as u64)));
+        }
+
+        Ok(())
+    }
+}
+
+impl SectionHeaderEntry64Raw {
+    pub(crate) fn decode(&self, endian: Endian) -> SectionHeaderEntry64 {
+        SectionHeaderEntry64 {
+            sh_name:      self.sh_name.get(endian),
+            sh_type:      unsafe { mem::transmute(self.sh_type.get(endian)) },
+            sh_flags:     self.sh_flags.get(endian),
+            sh_addr:      self.sh_addr.get(endian),
+            sh_offset:    self.sh_offset.get(endian),
+            sh_size:      self.sh_size.get(endian),
+            sh_link:      self.sh_link.get(endian),
+            sh_info:      self.sh_info.get(endian),
+            sh_addralign: self.sh_addralign.get(endian),
+            sh_entsize:   self.sh_entsize.get(endian)
+        }
+    }
+
+    pub(crate) fn validate(&self, endian: Endian) -> Result<(), ElfParseError> {
+        SectionType::validate(self.sh_type.get(endian))?;
+
+        // The alignment should be 0 or a power of 2.
+        let sh_addralign = self.sh_addralign.get(endian);
+        if sh_addralign.count_ones() > 1 {
+            return Err(ElfParseError::new(Text::ElfBadSectionAlign(sh_addralign)));
+        }
+        let align_mask = if sh_addralign == 0 { 0 } else { sh_addralign - 1 };
+
+        // Only sections that are actually loaded into memory need their file offset and address
+        // to agree on alignment.
+        let sh_addr = self.sh_addr.get(endian);
+        let sh_offset = self.sh_offset.get(endian);
+        if sh_addr != 0 && sh_offset & align_mask != sh_addr & align_mask {
+            return Err(ElfParseError::new(Text::ElfSectionMisaligned(sh_offset, sh_addr)));
+        }
+
+        Ok(())
+    }
+}
+
+ffi_enum! {
+    #[repr(u32)]
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub(crate) enum SectionType {
+        Null     = 0x0000_0000,
+        ProgBits = 0x0000_0001,
+        SymTab   = 0x0000_0002,
+        StrTab   = 0x0000_0003,
+        Rela     = 0x0000_0004,
+        Hash     = 0x0000_0005,
+        Dynamic  = 0x0000_0006,
+        Note     = 0x0000_0007,
+        NoBits   = 0x0000_0008,
+        Rel      = 0x0000_0009,
+        ShLib    = 0x0000_000a,
+        DynSym   = 0x0000_000b
+        // LoOs .. HiOs = 0x6000_0000 .. 0x6fff_ffff
+        // LoProc .. HiProc = 0x7000_0000 .. 0x7fff_ffff
+    }
+}
+
+impl SectionType {
+    pub(crate) fn validate(val: u32) -> Result<(), ElfParseError> {
+        if Self::try_from(val).is_ok() {
+            Ok(())
+        } else {
+            Err(ElfParseError::new(Text::ElfUnsupportedSectionType(val)))
+        }
+    }
+}
+
+/// Resolves the name of a section, given the entry describing it and the section that holds the
+/// section-header string table (identified by the ELF header's `sh_str_index`). Section names are
+/// null-terminated strings packed into `.shstrtab`, addressed by a byte offset rather than an
+/// index, so this has to seek and read one byte at a time like `elf::read_interpreter` does for
+/// `PT_INTERP`.
+pub(crate) fn section_name<T: Read + Seek>(reader: &mut T, shstrtab: &SectionHeaderEntry64, sh_name: u32)
+    -> io::Result<String>
+{
+    reader.seek(SeekFrom::Start(shstrtab.sh_offset + sh_name as u64))?;
+    let mut bytes = Vec::new();
+    loop {
+        let mut buffer = [0u8; 1];
+        reader.read_exact(&mut buffer)?;
+        if buffer[0] == 0 {
+            break;
+        }
+        bytes.push(buffer[0]);
+    }
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
 
 //
 // ---------- Unit Tests ----------
@@ -16,16 +16,19 @@
  * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
+mod compressed;
+mod dynamic;
+mod endian;
 mod error;
 mod headers;
+mod note;
+mod reloc;
 mod segment;
 
 use {
     core::{
         cmp::Ordering,
-        mem::{self, MaybeUninit},
-        num::NonZeroUsize,
-        slice
+        num::NonZeroUsize
     },
     alloc::{
         alloc::AllocError,
@@ -41,16 +44,36 @@ use {
     memory::virt::paging::{self, RootPageTable},
     fs::File,
 
-    super::{ExecImage, Segment, SegmentType},
+    super::{ExecImage, Readahead, Segment, SegmentType, TlsTemplate, read_struct},
+    super::format::ExecFormat,
     self::{
+        dynamic::read_dynamic,
+        endian::Endian,
         error::ElfParseError,
         headers::*,
-        segment::read_segment
+        note::{find_build_id, read_notes},
+        reloc::apply_relocations,
+        segment::{decode_segment, find_tls_entry, read_ph_entry, validate_ph_table}
     }
 };
 
-pub fn read_exe(file: File) -> io::Result<ExecImage<File>> {
-    read_interpreter(file, None)
+/// The ELF executable-format backend. See the crate-level `format` module for how this plugs
+/// into `read_exe`.
+pub(crate) struct Elf;
+
+impl ExecFormat for Elf {
+    fn probe(file: &mut File) -> io::Result<bool> {
+        let mut magic = [0u8; 4];
+        match file.read_exact(&mut magic) {
+            Ok(()) => Ok(&magic == b"\x7fELF"),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn parse(file: File) -> io::Result<ExecImage<File>> {
+        read_interpreter(file, None)
+    }
 }
 
 fn read_interpreter(mut file: File, interpreted: Option<File>) -> io::Result<ExecImage<File>> {
@@ -63,7 +86,8 @@ fn read_interpreter(mut file: File, interpreted: Option<File>) -> io::Result<Exe
         elf_header.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
     }
     let elf_class = elf_header.class;
-    let elf_header_ex = elf_header.ex_64();
+    let endian = elf_header.data.endian();
+    let elf_header_ex = elf_header.ex_64(endian);
     if elf_header_ex.ph_ent_size == 0 {
         return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(Text::ElfZeroSizedPH)));
     }
@@ -78,12 +102,58 @@ fn read_interpreter(mut file: File, interpreted: Option<File>) -> io::Result<Exe
 
     // A sorted array of segment descriptors
     let mut segments = Vec::with_capacity(elf_header_ex.ph_num as usize);
+    // The PT_DYNAMIC segment's contents, if this image has one. Relocations can't be applied
+    // until every segment has been read (a relocation's target has to resolve to a `Load`
+    // segment, and `PT_DYNAMIC` isn't guaranteed to come after those in the program header), so
+    // this is only acted on once the loop below is finished.
+    let mut dynamic_info = None;
+    // The GNU build-id pulled out of a PT_NOTE segment, if any. The first one found wins, same as
+    // every other loader that recognizes this note.
+    let mut build_id = None;
 
-    // Handle the program header entries as they arise (rather than reading all of them at once).
+    // Read and decode every program-header entry up front, then run a whole-table validation pass
+    // that no single entry's own `validate` can express: that PT_INTERP, PT_PHDR, and PT_DYNAMIC
+    // each appear at most once, that a PT_PHDR entry (if present) is covered by a PT_LOAD segment
+    // and matches this header's own ph_off/ph_num, and that every PT_LOAD entry's mem_sz is at
+    // least as large as its file_sz. This also tells us whether PT_GNU_STACK asked for an
+    // executable stack; the stack is mapped non-executable unless it did.
+    let mut ph_entries = Vec::with_capacity(elf_header_ex.ph_num as usize);
     for i in 0 .. elf_header_ex.ph_num as u64 {
         file.seek(SeekFrom::Start(elf_header_ex.ph_off + i * elf_header_ex.ph_ent_size as u64))?;
+        ph_entries.push(read_ph_entry(&mut file, elf_class, endian)?);
+    }
+    let stack_executable = validate_ph_table(&ph_entries, elf_header_ex.ph_off, elf_header_ex.ph_num, elf_header_ex.ph_ent_size)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    // The thread-local storage template, if this image has a PT_TLS segment. This is read
+    // straight from the raw entry (rather than going through `decode_segment`) because it needs
+    // `align`, which `Segment` has no field for, and because its bytes are a template to be
+    // copied into each new thread's own TLS block, not something to map into this image directly.
+    let tls_template = match find_tls_entry(&ph_entries) {
+        Some(entry) => {
+            if entry.file_sz > entry.mem_sz {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(
+                    Text::ElfInvalidFile(String::from("a PT_TLS segment's file_sz is larger than its mem_sz"))
+                )));
+            }
+            file.seek(SeekFrom::Start(entry.offset))?;
+            let mut init_image = Vec::with_capacity(entry.file_sz as usize);
+            init_image.resize(entry.file_sz as usize, 0);
+            file.read_exact(&mut init_image)?;
+            Some(TlsTemplate {
+                init_size: init_image.len(),
+                init_image,
+                total_size: entry.mem_sz as usize,
+                align: entry.align as usize
+            })
+        },
+        None => None
+    };
 
-        if let Some(segment) = read_segment(&mut file, elf_class)? {
+    // Handle each entry's effect on the image now that the table as a whole is known to be
+    // well-formed.
+    for ph_entry in ph_entries {
+        if let Some(segment) = decode_segment(ph_entry)? {
             // Interpreter?
             if segment.seg_type == SegmentType::Interpreter {
                 if interpreted.is_none() {
@@ -109,8 +179,14 @@ fn read_interpreter(mut file: File, interpreted: Option<File>) -> io::Result<Exe
 
             // Dynamic section?
             if segment.seg_type == SegmentType::DLib {
-                // TODO: Support dynamic linking.
-                unimplemented!();
+                file.seek(SeekFrom::Start(segment.file_offset as u64))?;
+                dynamic_info = Some(read_dynamic(&mut file, elf_class, segment.file_sz as u64)?);
+            }
+
+            // Notes, e.g. a build-id or an ABI tag?
+            if segment.seg_type == SegmentType::Note && build_id.is_none() {
+                let notes = read_notes(&mut file, &segment)?;
+                build_id = find_build_id(&notes).map(Vec::from);
             }
 
             // Segment loaded into memory?
@@ -143,6 +219,18 @@ fn read_interpreter(mut file: File, interpreted: Option<File>) -> io::Result<Exe
         return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(Text::ElfEntryPointNotInSegment)));
     }
 
+    // If this is a position-independent or shared image, work out what needs to change once it's
+    // loaded at its (for now, always 0) load base. The segment pieces these addresses land in
+    // haven't been loaded yet, so the values are just collected here and patched in as each piece
+    // is faulted in; see `ExecImage::load_segment_piece`.
+    let mut relocations = Vec::new();
+    if let Some(dynamic_info) = &dynamic_info {
+        const LOAD_BASE: usize = 0;
+        apply_relocations(&mut file, elf_class, LOAD_BASE, &segments, dynamic_info, |vaddr, value| {
+            relocations.push((vaddr, value));
+        })?;
+    }
+
     // TODO: Try to give each process its own ASID instead of using a constant one.
     const ASID: u16 = 0;
 
@@ -169,7 +257,13 @@ fn read_interpreter(mut file: File, interpreted: Option<File>) -> io::Result<Exe
         interpreted,
         entry_point,
         page_table,
-        segments
+        segments,
+        readahead: Mutex::new(Readahead::default()),
+        relocations,
+        reloc_word_size: match elf_class { ElfClass::Bits32 => 4, ElfClass::Bits64 => 8 },
+        build_id,
+        stack_executable,
+        tls_template
     })
 }
 
@@ -180,7 +274,7 @@ fn read_interpreter(mut file: File, interpreted: Option<File>) -> io::Result<Exe
         elf_header = read_struct(&mut file)?;
         elf_header.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
     }
-    let elf_header_ex = elf_header.ex_64();
+    let elf_header_ex = elf_header.ex_64(elf_header.data.endian());
 
     // Make sure this is a dynamic library.
     if unsafe { elf_header_ex.file_type.common } != ElfTypeCommon::Dyn {
@@ -190,19 +284,6 @@ fn read_interpreter(mut file: File, interpreted: Option<File>) -> io::Result<Exe
     unimplemented!();
 }*/
 
-/// Reads any `Sized` structure from the reader's current location. This should only be used for
-/// types with known memory layouts (i.e. those defined with `repr(C)` or `repr(transparent)`). On
-/// failure, the structure is not dropped.
-///
-/// # Safety
-/// This function is `unsafe` because it makes no guarantee that the returned structure is valid.
-/// Using the structure without validating it first is undefined behavior.
-unsafe fn read_struct<T: Read, U>(reader: &mut T) -> io::Result<U> {
-    let mut result: MaybeUninit<U> = MaybeUninit::uninit();
-    reader.read_exact(slice::from_raw_parts_mut(result.as_mut_ptr() as *mut u8, mem::size_of::<U>()))?;
-    Ok(result.assume_init())
-}
-
 #[cfg(test)]
 mod tests {
     // TODO: Add tests.
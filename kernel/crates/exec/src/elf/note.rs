@@ -0,0 +1,100 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Decodes the notes inside a `PT_NOTE` segment: a `namesz`/`descsz`/`n_type` header, followed by
+//! the name and then the descriptor, each individually padded to a 4-byte boundary. See the
+//! System V gABI's description of `SHT_NOTE`/`PT_NOTE` for the format this follows.
+
+use {
+    core::mem::size_of,
+    alloc::{string::String, vec::Vec},
+    i18n::Text,
+    io::{Read, Seek, SeekFrom},
+    super::{error::ElfParseError, read_struct},
+    crate::Segment
+};
+
+/// The GNU toolchain's note type for the build-id: a handful of bytes (usually a hash of the
+/// binary's contents) that uniquely identifies a build, independent of its file name or path.
+pub(crate) const NT_GNU_BUILD_ID: u32 = 3;
+/// The GNU toolchain's note type for recording which OS ABI (and minimum kernel version) a binary
+/// was built against.
+pub(crate) const NT_GNU_ABI_TAG: u32 = 1;
+
+/// One note parsed out of a `PT_NOTE` segment.
+#[derive(Debug)]
+pub(crate) struct Note {
+    pub(crate) name:   Vec<u8>,
+    pub(crate) n_type: u32,
+    pub(crate) desc:   Vec<u8>
+}
+
+#[repr(C, packed)]
+struct NoteHeader {
+    namesz: u32,
+    descsz: u32,
+    n_type: u32
+}
+
+/// Reads every note in a `PT_NOTE` segment, in file order. `namesz` and `descsz` are each rounded
+/// up to the next multiple of 4 before the next note (or the end of the segment) is expected,
+/// matching how every note producer in the wild pads them.
+pub(crate) fn read_notes<T: Read + Seek>(reader: &mut T, segment: &Segment) -> io::Result<Vec<Note>> {
+    reader.seek(SeekFrom::Start(segment.file_offset as u64))?;
+
+    let mut notes = Vec::new();
+    let mut pos = 0usize;
+    while pos + size_of::<NoteHeader>() <= segment.file_sz {
+        let header: NoteHeader = unsafe { read_struct(reader)? };
+        pos += size_of::<NoteHeader>();
+
+        let name_len = header.namesz as usize;
+        let name_padded = (name_len + 3) & !3;
+        let desc_len = header.descsz as usize;
+        let desc_padded = (desc_len + 3) & !3;
+        if pos + name_padded + desc_padded > segment.file_sz {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(
+                Text::ElfInvalidFile(String::from("a PT_NOTE entry's name or descriptor runs past the end of the segment"))
+            )));
+        }
+
+        let mut name = Vec::with_capacity(name_padded);
+        name.resize(name_padded, 0);
+        reader.read_exact(&mut name)?;
+        name.truncate(name_len);
+        pos += name_padded;
+
+        let mut desc = Vec::with_capacity(desc_padded);
+        desc.resize(desc_padded, 0);
+        reader.read_exact(&mut desc)?;
+        desc.truncate(desc_len);
+        pos += desc_padded;
+
+        notes.push(Note { name, n_type: header.n_type, desc });
+    }
+
+    Ok(notes)
+}
+
+/// Finds the GNU build-id note among an already-read list of notes, if one is present, so the
+/// kernel can match this binary against its symbol files or use the id in a crash-report.
+pub(crate) fn find_build_id(notes: &[Note]) -> Option<&[u8]> {
+    notes.iter()
+        .find(|note| note.n_type == NT_GNU_BUILD_ID && note.name == b"GNU\0")
+        .map(|note| note.desc.as_slice())
+}
@@ -0,0 +1,231 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Applies the relocations described by a `PT_DYNAMIC` segment (see `dynamic`) to a
+//! position-independent or shared ELF image, so its absolute addresses are correct once the
+//! kernel has picked a load address for it.
+//!
+//! The kernel never actually moves a binary in virtual memory once it's loaded (`load_base` is
+//! always 0 for now; nothing randomizes it yet), but the relocation math is written in terms of
+//! `load_base` anyway so that changes when ASLR is added.
+//!
+//! Only `*_RELATIVE`, `*_GLOB_DAT` and `*_JUMP_SLOT` are implemented, which is enough for a
+//! self-contained position-independent executable: `GLOB_DAT`/`JUMP_SLOT` entries are resolved
+//! against this image's own symbol table (`DT_SYMTAB`), since nothing here links against any
+//! other loaded image. A binary whose dynamic symbols can't be resolved that way (i.e. one that
+//! actually depends on a separate shared object) isn't supported.
+
+use {
+    core::{convert::TryFrom, mem::size_of},
+    alloc::string::String,
+    i18n::Text,
+    io::{Read, Seek, SeekFrom},
+    super::{error::ElfParseError, headers::ElfClass, dynamic::{DynamicInfo, DT_REL, DT_RELA}, read_struct},
+    crate::{Segment, SegmentType}
+};
+
+#[repr(C, packed)]
+struct Rela32 { r_offset: u32, r_info: u32, r_addend: i32 }
+#[repr(C, packed)]
+struct Rela64 { r_offset: u64, r_info: u64, r_addend: i64 }
+#[repr(C, packed)]
+struct Rel32 { r_offset: u32, r_info: u32 }
+#[repr(C, packed)]
+struct Rel64 { r_offset: u64, r_info: u64 }
+
+#[repr(C, packed)]
+struct Sym32 { st_name: u32, st_value: u32, st_size: u32, st_info: u8, st_other: u8, st_shndx: u16 }
+#[repr(C, packed)]
+struct Sym64 { st_name: u32, st_info: u8, st_other: u8, st_shndx: u16, st_value: u64, st_size: u64 }
+
+/// One relocation entry, normalized to 64 bits regardless of which ELF class it came from.
+struct RelocEntry {
+    r_offset: u64,
+    r_sym:    u32,
+    r_type:   u32,
+    r_addend: i64
+}
+
+fn read_rela<T: Read>(reader: &mut T, class: ElfClass) -> io::Result<RelocEntry> {
+    match class {
+        ElfClass::Bits32 => {
+            let e: Rela32 = unsafe { read_struct(reader)? };
+            Ok(RelocEntry { r_offset: e.r_offset as u64, r_sym: e.r_info >> 8, r_type: e.r_info & 0xff, r_addend: e.r_addend as i64 })
+        },
+        ElfClass::Bits64 => {
+            let e: Rela64 = unsafe { read_struct(reader)? };
+            Ok(RelocEntry { r_offset: e.r_offset, r_sym: (e.r_info >> 32) as u32, r_type: (e.r_info & 0xffff_ffff) as u32, r_addend: e.r_addend })
+        }
+    }
+}
+
+fn read_rel<T: Read>(reader: &mut T, class: ElfClass) -> io::Result<RelocEntry> {
+    match class {
+        ElfClass::Bits32 => {
+            let e: Rel32 = unsafe { read_struct(reader)? };
+            Ok(RelocEntry { r_offset: e.r_offset as u64, r_sym: e.r_info >> 8, r_type: e.r_info & 0xff, r_addend: 0 })
+        },
+        ElfClass::Bits64 => {
+            let e: Rel64 = unsafe { read_struct(reader)? };
+            Ok(RelocEntry { r_offset: e.r_offset, r_sym: (e.r_info >> 32) as u32, r_type: (e.r_info & 0xffff_ffff) as u32, r_addend: 0 })
+        }
+    }
+}
+
+fn symbol_value<T: Read + Seek>(reader: &mut T, class: ElfClass, symtab_file_offset: u64, sym: u32) -> io::Result<u64> {
+    let entry_size = match class {
+        ElfClass::Bits32 => size_of::<Sym32>(),
+        ElfClass::Bits64 => size_of::<Sym64>()
+    } as u64;
+    reader.seek(SeekFrom::Start(symtab_file_offset + sym as u64 * entry_size))?;
+    match class {
+        ElfClass::Bits32 => Ok(unsafe { read_struct::<T, Sym32>(reader)? }.st_value as u64),
+        ElfClass::Bits64 => Ok(unsafe { read_struct::<T, Sym64>(reader)? }.st_value)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+ffi_enum! {
+    #[repr(u32)]
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub(crate) enum RelocType {
+        Relative = 8,  // R_X86_64_RELATIVE
+        GlobDat  = 6,  // R_X86_64_GLOB_DAT
+        JumpSlot = 7   // R_X86_64_JUMP_SLOT
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+ffi_enum! {
+    #[repr(u32)]
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub(crate) enum RelocType {
+        Relative = 1027, // R_AARCH64_RELATIVE
+        GlobDat  = 1025, // R_AARCH64_GLOB_DAT
+        JumpSlot = 1026  // R_AARCH64_JUMP_SLOT
+    }
+}
+
+fn vaddr_to_file_offset(segments: &[Segment], vaddr: u64) -> Option<u64> {
+    segments.iter()
+        .find(|seg| seg.seg_type == SegmentType::Load
+            && vaddr >= seg.vaddr as u64
+            && vaddr < seg.vaddr as u64 + seg.file_sz as u64)
+        .map(|seg| seg.file_offset as u64 + (vaddr - seg.vaddr as u64))
+}
+
+fn offset_in_loadable_segment(segments: &[Segment], vaddr: u64) -> bool {
+    segments.iter().any(|seg| seg.seg_type == SegmentType::Load
+        && vaddr >= seg.vaddr as u64
+        && vaddr < seg.vaddr as u64 + seg.mem_sz as u64)
+}
+
+/// Walks one relocation table (RELA if `is_rela`, REL otherwise) and calls `write_word` with the
+/// relocated virtual address and the value to store there.
+fn apply_table<T: Read + Seek>(
+    reader: &mut T,
+    class: ElfClass,
+    load_base: usize,
+    segments: &[Segment],
+    symtab_file_offset: Option<u64>,
+    table_vaddr: u64,
+    table_size: u64,
+    entry_size: u64,
+    is_rela: bool,
+    write_word: &mut dyn FnMut(usize, u64)
+) -> io::Result<()> {
+    if entry_size == 0 || table_size % entry_size != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(
+            Text::ElfInvalidFile(String::from("relocation table's size is not a multiple of its entry size"))
+        )));
+    }
+    let table_offset = vaddr_to_file_offset(segments, table_vaddr)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(Text::LoadSegmentOutOfBounds)))?;
+
+    for i in 0 .. table_size / entry_size {
+        reader.seek(SeekFrom::Start(table_offset + i * entry_size))?;
+        let entry = if is_rela { read_rela(reader, class)? } else { read_rel(reader, class)? };
+
+        if !offset_in_loadable_segment(segments, entry.r_offset) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(Text::LoadSegmentOutOfBounds)));
+        }
+
+        let value = match RelocType::try_from(entry.r_type) {
+            Ok(RelocType::Relative) => (load_base as u64).wrapping_add(entry.r_addend as u64),
+            Ok(RelocType::GlobDat) | Ok(RelocType::JumpSlot) => {
+                let symtab_file_offset = symtab_file_offset.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                    ElfParseError::new(Text::ElfInvalidFile(String::from("relocation references a symbol, but PT_DYNAMIC has no DT_SYMTAB")))
+                ))?;
+                let st_value = symbol_value(reader, class, symtab_file_offset, entry.r_sym)?;
+                (load_base as u64).wrapping_add(st_value)
+            },
+            Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(Text::ElfUnsupportedRelocType(entry.r_type))))
+        };
+
+        write_word(entry.r_offset as usize, value);
+    }
+
+    Ok(())
+}
+
+/// Applies every relocation described by `dynamic` (the already-parsed `PT_DYNAMIC` tags) to the
+/// image, via `write_word(vaddr, value)`. Relocation entries themselves, and the symbol table used
+/// to resolve `GLOB_DAT`/`JUMP_SLOT` entries, are read directly from `reader` as needed rather
+/// than being loaded up front.
+pub(crate) fn apply_relocations<T: Read + Seek>(
+    reader: &mut T,
+    class: ElfClass,
+    load_base: usize,
+    segments: &[Segment],
+    dynamic: &DynamicInfo,
+    mut write_word: impl FnMut(usize, u64)
+) -> io::Result<()> {
+    let default_rela_ent = match class { ElfClass::Bits32 => size_of::<Rela32>(), ElfClass::Bits64 => size_of::<Rela64>() } as u64;
+    let default_rel_ent  = match class { ElfClass::Bits32 => size_of::<Rel32>(),  ElfClass::Bits64 => size_of::<Rel64>()  } as u64;
+
+    // DT_SYMTAB gives the symbol table's virtual address, but its entries are read from the file.
+    let symtab = match dynamic.symtab {
+        Some(vaddr) => Some(vaddr_to_file_offset(segments, vaddr)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new(Text::LoadSegmentOutOfBounds)))?),
+        None => None
+    };
+
+    if let (Some(rela), Some(relasz)) = (dynamic.rela, dynamic.relasz) {
+        apply_table(reader, class, load_base, segments, symtab, rela, relasz,
+            dynamic.relaent.unwrap_or(default_rela_ent), true, &mut write_word)?;
+    }
+
+    if let (Some(rel), Some(relsz)) = (dynamic.rel, dynamic.relsz) {
+        apply_table(reader, class, load_base, segments, symtab, rel, relsz,
+            dynamic.relent.unwrap_or(default_rel_ent), false, &mut write_word)?;
+    }
+
+    if let (Some(jmprel), Some(pltrelsz)) = (dynamic.jmprel, dynamic.pltrelsz) {
+        match dynamic.pltrel.map(|tag| tag as i64) {
+            Some(DT_RELA) => apply_table(reader, class, load_base, segments, symtab, jmprel, pltrelsz,
+                dynamic.relaent.unwrap_or(default_rela_ent), true, &mut write_word)?,
+            Some(DT_REL) => apply_table(reader, class, load_base, segments, symtab, jmprel, pltrelsz,
+                dynamic.relent.unwrap_or(default_rel_ent), false, &mut write_word)?,
+            // Without DT_PLTREL we don't know whether DT_JMPREL's entries have addends, so there's
+            // nothing safe to do with them.
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
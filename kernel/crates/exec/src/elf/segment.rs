@@ -19,13 +19,17 @@
 //! This module defines the functions used to read and decode segments in an ELF file.
 
 use {
+    alloc::string::String,
+    i18n::Text,
     io::Read,
     super::{
+        endian::Endian,
         error::ElfParseError,
         headers::{
             ElfClass,
             ProgramHeaderEntry32,
             ProgramHeaderEntry64,
+            ProgramHeaderEntry64Raw,
             SegmentFlags as ElfSegFlags
         },
         read_struct
@@ -37,30 +41,37 @@ use super::headers::SegmentTypeArm as ElfSegTypeArm;
 #[cfg(target_arch = "x86_64")]
 use super::headers::SegmentTypeCommon as ElfSegTypeCommon;
 
-pub(crate) fn read_segment<T: Read>(reader: &mut T, class: ElfClass) -> io::Result<Option<Segment>> {
-    let ph_entry: ProgramHeaderEntry64;
+pub(crate) fn read_segment<T: Read>(reader: &mut T, class: ElfClass, endian: Endian) -> io::Result<Option<Segment>> {
+    decode_segment(read_ph_entry(reader, class, endian)?)
+}
+
+/// Reads, validates, and decodes one program-header entry, without yet deciding what (if
+/// anything) it means for the file-format-independent loader. Split out from `read_segment` so a
+/// whole program-header table can be read up front and checked by `validate_ph_table` before any
+/// of its entries are acted on.
+pub(crate) fn read_ph_entry<T: Read>(reader: &mut T, class: ElfClass, endian: Endian) -> io::Result<ProgramHeaderEntry64> {
     match class {
         ElfClass::Bits32 => {
             let temp_entry: ProgramHeaderEntry32;
             unsafe {
                 temp_entry = read_struct(reader)?;
-                temp_entry.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
             }
-            ph_entry = temp_entry.into();
+            temp_entry.validate(endian).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            Ok(temp_entry.decode(endian))
         },
         ElfClass::Bits64 => {
+            let temp_entry: ProgramHeaderEntry64Raw;
             unsafe {
-                ph_entry = read_struct(reader)?;
-                ph_entry.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                temp_entry = read_struct(reader)?;
             }
+            temp_entry.validate(endian).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            Ok(temp_entry.decode(endian))
         }
-    };
-
-    decode_segment(ph_entry)
+    }
 }
 
 #[cfg(target_arch = "aarch64")]
-fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>> {
+pub(crate) fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>> {
     // TODO: These flags might be segment-type-specific on some architectures.
     let flags = ph_entry.flags;
     let flags =
@@ -75,7 +86,6 @@ fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>>
         
         ElfSegTypeArm::Load |
         ElfSegTypeArm::Interp |
-        ElfSegTypeArm::Note |
         ElfSegTypeArm::PHdr => Ok(Some(Segment {
                 seg_type:    SegmentType::Load,
                 flags,
@@ -87,6 +97,17 @@ fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>>
                 //align:       ph_entry.align as usize
             })),
 
+        ElfSegTypeArm::Note => Ok(Some(Segment {
+                seg_type:    SegmentType::Note,
+                flags,
+                file_offset: ph_entry.offset as usize,
+                vaddr:       ph_entry.vaddr as usize,
+                //paddr:       ph_entry.paddr as usize,
+                file_sz:     ph_entry.file_sz as usize,
+                mem_sz:      ph_entry.mem_sz as usize,
+                //align:       ph_entry.align as usize
+            })),
+
         ElfSegTypeArm::Dynamic => Ok(Some(Segment {
                 seg_type:    SegmentType::DLib,
                 flags,
@@ -110,13 +131,15 @@ fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>>
             io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new("section of reserved type PT_AARCH64_UNWIND found"))
         ),
 
-        // Segment types to ignore
-        ElfSegTypeArm::GnuStack => { Ok(None) }
+        // Segment types to ignore: their raw entries are read directly by `validate_ph_table` and
+        // `find_tls_entry` instead, since this function's `Segment` output has nowhere to carry
+        // `align` or stack-flag data.
+        ElfSegTypeArm::GnuStack | ElfSegTypeArm::Tls => Ok(None)
     }
 }
 
 #[cfg(target_arch = "x86_64")]
-fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>> {
+pub(crate) fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>> {
     // TODO: These flags might be segment-type-specific on some architectures.
     let flags = ph_entry.flags;
     let flags =
@@ -131,7 +154,6 @@ fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>>
         
         ElfSegTypeCommon::Load |
         ElfSegTypeCommon::Interp |
-        ElfSegTypeCommon::Note |
         ElfSegTypeCommon::PHdr => Ok(Some(Segment {
                 seg_type:    SegmentType::Load,
                 flags,
@@ -143,6 +165,17 @@ fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>>
                 //align:       ph_entry.align as usize
             })),
 
+        ElfSegTypeCommon::Note => Ok(Some(Segment {
+                seg_type:    SegmentType::Note,
+                flags,
+                file_offset: ph_entry.offset as usize,
+                vaddr:       ph_entry.vaddr as usize,
+                //paddr:       ph_entry.paddr as usize,
+                file_sz:     ph_entry.file_sz as usize,
+                mem_sz:      ph_entry.mem_sz as usize,
+                //align:       ph_entry.align as usize
+            })),
+
         ElfSegTypeCommon::Dynamic => Ok(Some(Segment {
                 seg_type:    SegmentType::DLib,
                 flags,
@@ -158,11 +191,162 @@ fn decode_segment(ph_entry: ProgramHeaderEntry64) -> io::Result<Option<Segment>>
             io::Error::new(io::ErrorKind::InvalidData, ElfParseError::new("section of reserved type PT_SHLIB found"))
         ),
 
-        // Segment types to ignore
-        ElfSegTypeCommon::GnuStack => { Ok(None) }
+        // Segment types to ignore: their raw entries are read directly by `validate_ph_table` and
+        // `find_tls_entry` instead, since this function's `Segment` output has nowhere to carry
+        // `align` or stack-flag data.
+        ElfSegTypeCommon::GnuStack | ElfSegTypeCommon::Tls => Ok(None)
     }
 }
 
+/// Checks invariants that span the whole program-header table, which no single entry's own
+/// `validate` can express: that `PT_INTERP`, `PT_PHDR`, and `PT_DYNAMIC` each appear at most once;
+/// that a `PT_PHDR` entry, if present, is covered by a `PT_LOAD` segment and matches the ELF
+/// header's own `ph_off`/`ph_num`; and that every `PT_LOAD` entry's `mem_sz` is at least as large
+/// as its `file_sz`. Returns whether the stack should be mapped executable, honoring a
+/// `PT_GNU_STACK` entry's flags if there is one (following Fuchsia's ELF loader), or `false` by
+/// default if there isn't.
+#[cfg(target_arch = "aarch64")]
+pub(crate) fn validate_ph_table(
+    entries: &[ProgramHeaderEntry64],
+    ph_off: u64,
+    ph_num: u16,
+    ph_ent_size: u16
+) -> Result<bool, ElfParseError> {
+    let mut interp_seen = false;
+    let mut phdr_entry = None;
+    let mut dynamic_seen = false;
+    let mut stack_executable = false;
+
+    for entry in entries {
+        match unsafe { entry.seg_type.arm } {
+            ElfSegTypeArm::Interp => {
+                if interp_seen {
+                    return Err(ElfParseError::new(Text::ElfMultipleHeaders(ElfSegTypeArm::Interp as u32)));
+                }
+                interp_seen = true;
+            },
+            ElfSegTypeArm::PHdr => {
+                if phdr_entry.is_some() {
+                    return Err(ElfParseError::new(Text::ElfMultipleHeaders(ElfSegTypeArm::PHdr as u32)));
+                }
+                phdr_entry = Some(entry);
+            },
+            ElfSegTypeArm::Dynamic => {
+                if dynamic_seen {
+                    return Err(ElfParseError::new(Text::ElfMultipleHeaders(ElfSegTypeArm::Dynamic as u32)));
+                }
+                dynamic_seen = true;
+            },
+            ElfSegTypeArm::Load => {
+                if entry.mem_sz < entry.file_sz {
+                    return Err(ElfParseError::new(
+                        Text::ElfInvalidFile(String::from("a PT_LOAD segment's mem_sz is smaller than its file_sz"))
+                    ));
+                }
+            },
+            ElfSegTypeArm::GnuStack => {
+                stack_executable = entry.flags.contains(ElfSegFlags::EXECUTABLE);
+            },
+            _ => {}
+        }
+    }
+
+    if let Some(phdr_entry) = phdr_entry {
+        let covered = entries.iter().any(|entry| {
+            let is_load = match unsafe { entry.seg_type.arm } { ElfSegTypeArm::Load => true, _ => false };
+            is_load
+                && phdr_entry.offset >= entry.offset
+                && phdr_entry.offset + phdr_entry.file_sz <= entry.offset + entry.file_sz
+        });
+        if !covered || phdr_entry.offset != ph_off || phdr_entry.file_sz != ph_num as u64 * ph_ent_size as u64 {
+            return Err(ElfParseError::new(Text::ElfInvalidFile(
+                String::from("PT_PHDR isn't covered by a PT_LOAD segment, or doesn't match the ELF header's ph_off/ph_num")
+            )));
+        }
+    }
+
+    Ok(stack_executable)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn validate_ph_table(
+    entries: &[ProgramHeaderEntry64],
+    ph_off: u64,
+    ph_num: u16,
+    ph_ent_size: u16
+) -> Result<bool, ElfParseError> {
+    let mut interp_seen = false;
+    let mut phdr_entry = None;
+    let mut dynamic_seen = false;
+    let mut stack_executable = false;
+
+    for entry in entries {
+        match unsafe { entry.seg_type.common } {
+            ElfSegTypeCommon::Interp => {
+                if interp_seen {
+                    return Err(ElfParseError::new(Text::ElfMultipleHeaders(ElfSegTypeCommon::Interp as u32)));
+                }
+                interp_seen = true;
+            },
+            ElfSegTypeCommon::PHdr => {
+                if phdr_entry.is_some() {
+                    return Err(ElfParseError::new(Text::ElfMultipleHeaders(ElfSegTypeCommon::PHdr as u32)));
+                }
+                phdr_entry = Some(entry);
+            },
+            ElfSegTypeCommon::Dynamic => {
+                if dynamic_seen {
+                    return Err(ElfParseError::new(Text::ElfMultipleHeaders(ElfSegTypeCommon::Dynamic as u32)));
+                }
+                dynamic_seen = true;
+            },
+            ElfSegTypeCommon::Load => {
+                if entry.mem_sz < entry.file_sz {
+                    return Err(ElfParseError::new(
+                        Text::ElfInvalidFile(String::from("a PT_LOAD segment's mem_sz is smaller than its file_sz"))
+                    ));
+                }
+            },
+            ElfSegTypeCommon::GnuStack => {
+                stack_executable = entry.flags.contains(ElfSegFlags::EXECUTABLE);
+            },
+            _ => {}
+        }
+    }
+
+    if let Some(phdr_entry) = phdr_entry {
+        let covered = entries.iter().any(|entry| {
+            let is_load = match unsafe { entry.seg_type.common } { ElfSegTypeCommon::Load => true, _ => false };
+            is_load
+                && phdr_entry.offset >= entry.offset
+                && phdr_entry.offset + phdr_entry.file_sz <= entry.offset + entry.file_sz
+        });
+        if !covered || phdr_entry.offset != ph_off || phdr_entry.file_sz != ph_num as u64 * ph_ent_size as u64 {
+            return Err(ElfParseError::new(Text::ElfInvalidFile(
+                String::from("PT_PHDR isn't covered by a PT_LOAD segment, or doesn't match the ELF header's ph_off/ph_num")
+            )));
+        }
+    }
+
+    Ok(stack_executable)
+}
+
+/// Finds the program header table's `PT_TLS` entry, if it has one. The gABI allows at most one;
+/// if a malformed image somehow has more, the first one found wins, the same as every other
+/// loader that bothers to check.
+#[cfg(target_arch = "aarch64")]
+pub(crate) fn find_tls_entry(entries: &[ProgramHeaderEntry64]) -> Option<&ProgramHeaderEntry64> {
+    entries.iter().find(|entry| match unsafe { entry.seg_type.arm } { ElfSegTypeArm::Tls => true, _ => false })
+}
+
+/// Finds the program header table's `PT_TLS` entry, if it has one. The gABI allows at most one;
+/// if a malformed image somehow has more, the first one found wins, the same as every other
+/// loader that bothers to check.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn find_tls_entry(entries: &[ProgramHeaderEntry64]) -> Option<&ProgramHeaderEntry64> {
+    entries.iter().find(|entry| match unsafe { entry.seg_type.common } { ElfSegTypeCommon::Tls => true, _ => false })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -213,7 +397,7 @@ mod tests {
             0x00, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x00, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits32), Ok(None));
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits32, Endian::Little), Ok(None));
     }
 
     #[test]
@@ -238,7 +422,7 @@ mod tests {
             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits64), Ok(None));
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits64, Endian::Little), Ok(None));
     }
 
     #[test]
@@ -263,7 +447,7 @@ mod tests {
             0x05, 0x00, 0x00, 0x00,
             0x01, 0x00, 0x00, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits32), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits32, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::Load,
             flags:       x,
             file_offset: 2,
@@ -295,7 +479,7 @@ mod tests {
             0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits64), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits64, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::Load,
             flags:       x,
             file_offset: 2,
@@ -327,7 +511,7 @@ mod tests {
             0x04, 0x00, 0x00, 0x00,
             0x00, 0x80, 0x00, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits32), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits32, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::Load,
             flags:       SegmentFlags::READABLE,
             file_offset: 0x10000,
@@ -359,7 +543,7 @@ mod tests {
             0x67, 0x15, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits64), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits64, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::Load,
             flags:       SegmentFlags::READABLE,
             file_offset: 0x10000,
@@ -391,7 +575,7 @@ mod tests {
             0x06, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x01, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits32), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits32, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::Load,
             flags:       x,
             file_offset: 0x047c00,
@@ -423,7 +607,7 @@ mod tests {
             0x00, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits64), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits64, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::Load,
             flags:       x,
             file_offset: 0x047c00,
@@ -455,7 +639,7 @@ mod tests {
             0x02, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x01, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits32), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits32, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::Load,
             flags:       SegmentFlags::WRITABLE,
             file_offset: 0x00000,
@@ -487,7 +671,7 @@ mod tests {
             0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits64), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits64, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::Load,
             flags:       SegmentFlags::WRITABLE,
             file_offset: 0x00000,
@@ -519,7 +703,7 @@ mod tests {
             0x04, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x01, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits32), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits32, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::DLib,
             flags:       SegmentFlags::READABLE,
             file_offset: 0x00100000,
@@ -551,7 +735,7 @@ mod tests {
             0xdc, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00
         ]);
-        assert_pat!(read_segment(&mut reader, ElfClass::Bits64), Ok(Some(Segment {
+        assert_pat!(read_segment(&mut reader, ElfClass::Bits64, Endian::Little), Ok(Some(Segment {
             seg_type:    SegmentType::DLib,
             flags:       SegmentFlags::READABLE,
             file_offset: 0x00100000,
@@ -583,7 +767,7 @@ mod tests {
             0x00, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x00, 0x00
         ]);
-        assert!(read_segment(&mut reader, ElfClass::Bits32).is_err());
+        assert!(read_segment(&mut reader, ElfClass::Bits32, Endian::Little).is_err());
     }
 
     #[test]
@@ -608,6 +792,6 @@ mod tests {
             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
         ]);
-        assert!(read_segment(&mut reader, ElfClass::Bits64).is_err());
+        assert!(read_segment(&mut reader, ElfClass::Bits64, Endian::Little).is_err());
     }
 }
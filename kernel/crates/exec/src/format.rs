@@ -0,0 +1,80 @@
+/* Copyright (c) 2022 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! This module defines the `ExecFormat` trait, which lets `read_exe` stay ignorant of which
+//! executable-file formats exist. Each backend (`elf`, `bytecode`, ...) implements it and
+//! registers itself in `FORMATS`, so adding a new format never requires touching `read_exe`
+//! itself.
+
+use {
+    core::fmt,
+
+    error::Error,
+    fs::File,
+    io::{Seek, SeekFrom},
+
+    super::ExecImage
+};
+
+/// A file format that `read_exe` can recognize and parse into an `ExecImage`.
+pub(crate) trait ExecFormat {
+    /// Returns true if `file` looks like it's in this format, judging by a short magic-number
+    /// check at the start of the file. Leaves the reader's position unspecified; callers must
+    /// seek back to the start before using `file` again.
+    fn probe(file: &mut File) -> io::Result<bool> where Self: Sized;
+
+    /// Parses `file`, which `probe` has already confirmed looks like this format, into an
+    /// `ExecImage`. The reader's position is unspecified; implementations should seek to
+    /// wherever they need before reading.
+    fn parse(file: File) -> io::Result<ExecImage<File>> where Self: Sized;
+}
+
+type ProbeFn = fn(&mut File) -> io::Result<bool>;
+type ParseFn = fn(File) -> io::Result<ExecImage<File>>;
+
+/// All the executable formats `read_exe` knows how to sniff and load, in the order they're
+/// tried. Adding a backend means adding one entry here; nothing else in the crate needs to know.
+static FORMATS: &[(ProbeFn, ParseFn)] = &[
+    (crate::elf::Elf::probe, crate::elf::Elf::parse),
+    (crate::bytecode::Bytecode::probe, crate::bytecode::Bytecode::parse),
+];
+
+/// Sniffs `file`'s magic bytes against every registered `ExecFormat` and parses it with the
+/// first one that claims it. This is the dispatcher behind the crate's public `read_exe`.
+pub(crate) fn read_exe(mut file: File) -> io::Result<ExecImage<File>> {
+    for &(probe, parse) in FORMATS {
+        file.seek(SeekFrom::Start(0))?;
+        if probe(&mut file)? {
+            file.seek(SeekFrom::Start(0))?;
+            return parse(file);
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, UnrecognizedFormatError))
+}
+
+/// Returned by `read_exe` when no registered `ExecFormat` recognizes the file.
+#[derive(Debug)]
+pub struct UnrecognizedFormatError;
+
+impl Error for UnrecognizedFormatError {}
+
+impl fmt::Display for UnrecognizedFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the file isn't in any executable format this kernel knows how to load")
+    }
+}
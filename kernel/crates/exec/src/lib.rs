@@ -28,7 +28,9 @@ extern crate alloc;
 #[macro_use] extern crate bitflags;
 #[macro_use] extern crate shared;
 
+mod bytecode;
 mod elf;
+mod format;
 mod segment;
 
 use {
@@ -60,6 +62,34 @@ use {
 };
 pub use segment::*;
 
+/// The smallest readahead window we'll shrink down to once a fault pattern stops looking
+/// sequential: just the page that was actually faulted on.
+const READAHEAD_MIN_PAGES: usize = 1;
+/// The largest readahead window we'll grow to for a strongly sequential fault pattern.
+const READAHEAD_MAX_PAGES: usize = 32;
+/// The window size stored in a fresh `ExecImage`. Its first fault never continues a sequential
+/// pattern, so that fault resets the window to `READAHEAD_MIN_PAGES` before this value is used.
+const READAHEAD_INITIAL_PAGES: usize = 8;
+
+/// Tracks recent page-fault addresses for one `ExecImage` so `load_segment_piece` can tell a
+/// sequential access pattern (e.g. a program being read straight through) from a random one
+/// (e.g. a jump table) and size its readahead window accordingly.
+#[derive(Debug)]
+struct Readahead {
+    /// The address just past the end of the last piece that was loaded, if any.
+    prev_end: Option<usize>,
+    /// The number of pages the last load tried to cluster into a single read. A load that
+    /// continues a sequential pattern doubles it (up to `READAHEAD_MAX_PAGES`) before using it;
+    /// any other load resets it to `READAHEAD_MIN_PAGES`.
+    window_pages: usize
+}
+
+impl Default for Readahead {
+    fn default() -> Self {
+        Readahead { prev_end: None, window_pages: READAHEAD_INITIAL_PAGES }
+    }
+}
+
 /// Represents an executable file image in a file-format-independent form.
 #[derive(Debug)]
 pub struct ExecImage<T: Read+Seek> {
@@ -68,7 +98,22 @@ pub struct ExecImage<T: Read+Seek> {
     /// The virtual address of the program's entry point.
     pub entry_point: usize,
     page_table: Arc<BlockMut<RootPageTable>>,
-    segments: Vec<Segment>   // A sorted array of segment descriptors
+    segments: Vec<Segment>,  // A sorted array of segment descriptors
+    readahead: Mutex<Readahead>,
+    /// Every word this image's dynamic linking step computed, as `(vaddr, value)` pairs. Applied
+    /// lazily in `load_segment_piece`, one segment piece at a time, rather than all at once, since
+    /// the segment pieces these addresses fall in aren't loaded until something faults them in.
+    relocations: Vec<(usize, u64)>,
+    /// The width, in bytes, of each value in `relocations` (4 for a 32-bit image, 8 for 64-bit).
+    reloc_word_size: u8,
+    /// This image's GNU build-id, if its `PT_NOTE` segment(s) had one. Lets the kernel match the
+    /// running binary against a separate symbol file or identify it in a crash report.
+    build_id: Option<Vec<u8>>,
+    /// Whether this image's stack should be mapped executable. Honors a `PT_GNU_STACK` segment's
+    /// flags if it has one; defaults to `false` (non-executable) if it doesn't.
+    stack_executable: bool,
+    /// The thread-local storage template taken from this image's `PT_TLS` segment, if it has one.
+    tls_template: Option<TlsTemplate>
 }
 
 /*/// Represents a dynamic library image in a file-format-independent form.
@@ -79,6 +124,20 @@ pub struct DLibImage<T: Read+Seek> {
     segments: Vec<Segment>
 }*/
 
+/// Reads any `Sized` structure from the reader's current location. This should only be used for
+/// types with known memory layouts (i.e. those defined with `repr(C)` or `repr(transparent)`). On
+/// failure, the structure is not dropped. Shared by every `ExecFormat` backend so none of them
+/// have to hand-roll header parsing.
+///
+/// # Safety
+/// This function is `unsafe` because it makes no guarantee that the returned structure is valid.
+/// Using the structure without validating it first is undefined behavior.
+pub(crate) unsafe fn read_struct<T: Read, U>(reader: &mut T) -> io::Result<U> {
+    let mut result: core::mem::MaybeUninit<U> = core::mem::MaybeUninit::uninit();
+    reader.read_exact(slice::from_raw_parts_mut(result.as_mut_ptr() as *mut u8, core::mem::size_of::<U>()))?;
+    Ok(result.assume_init())
+}
+
 /// Starts loading the executable from the given reader and returns an `ExecImage`. The segments
 /// themselves aren't loaded yet: they're loaded lazily when the program is run by calling
 /// `load_segment`.
@@ -88,7 +147,7 @@ pub fn read_exe(file: File) -> io::Result<ExecImage<File>> {
     // The only thing that can't be copied should be the root page table, although all the read-
     // only pages can be mapped to the same physical memory. (If we do that, we'll need to be
     // careful when swapping out pages.)
-    elf::read_exe(file)
+    format::read_exe(file)
 }
 
 /*/// Starts loading the dynamic library from the given reader and returns a `DLibImage`. The
@@ -118,6 +177,31 @@ impl<T: Read+Seek> ExecImage<T> {
         let base = base / page_size * page_size;
         let size = end.wrapping_sub(base);
 
+        // Track whether this fault continues a sequential access pattern, and if so, cluster
+        // more pages than were strictly asked for so we don't have to come back to the reader
+        // for every single page. A fault that doesn't continue the pattern (e.g. a jump table or
+        // the first fault on a fresh `ExecImage`) shrinks the window back down.
+        let (base, size) = {
+            let Ok(mut readahead) = self.readahead.try_lock() else { return Err(None); };
+            let sequential = readahead.prev_end == Some(base);
+            readahead.window_pages = if sequential {
+                usize::min(readahead.window_pages * 2, READAHEAD_MAX_PAGES)
+            } else {
+                READAHEAD_MIN_PAGES
+            };
+
+            // Clamp the readahead window to the segment's bounds so we never read past it.
+            let segment_end = segment.vaddr.saturating_add(segment.mem_sz);
+            let wanted_end = usize::min(
+                base.saturating_add(usize::max(size, readahead.window_pages * page_size)),
+                (segment_end + page_size - 1) / page_size * page_size
+            );
+            let wanted_end = usize::max(wanted_end, end); // Never shrink below what was requested.
+
+            readahead.prev_end = Some(wanted_end);
+            (base, wanted_end.wrapping_sub(base))
+        };
+
         // If there's nothing to load from the file (e.g. this is a .bss section), just map a
         // pre-allocated CoW page filled with zeroes.
         let segment_overflows = segment.vaddr.checked_add(segment.file_sz).is_none();
@@ -130,9 +214,8 @@ impl<T: Read+Seek> ExecImage<T> {
         // PERF: If the pages are read-only and have already been loaded into another process with
         // the same `ExecImage`, share them instead of loading them from the reader again.
 
-        // PERF: Load more than the bare minimum if more subsequent pages are likely to be needed.
-
-        // Allocate enough space for the segment piece.
+        // Allocate enough space for the segment piece (which may be larger than what was asked
+        // for, thanks to readahead clustering above).
         let block = match AllMemAlloc.malloc::<u8>(size, NonZeroUsize::new(page_size).unwrap()) {
             Ok(block) => block,
             Err(AllocError) => return Err(Some(LoadSegmentError::AllocError(size)))
@@ -146,7 +229,7 @@ impl<T: Read+Seek> ExecImage<T> {
         let block_overflows = base.checked_add(size).is_none();
         if !segment_overflows && (block_overflows || base + size > segment.vaddr + segment.file_sz) {
             let file_end = segment.vaddr + segment.file_sz;
-            let dest: &mut [u8] = unsafe { slice::from_raw_parts_mut(block.index(file_end - base), end.wrapping_sub(file_end)) };
+            let dest: &mut [u8] = unsafe { slice::from_raw_parts_mut(block.index(file_end - base), (base + size).wrapping_sub(file_end)) };
             dest.iter_mut().for_each(|x| *x = 0);
         }
 
@@ -170,6 +253,22 @@ impl<T: Read+Seek> ExecImage<T> {
             }
         }
 
+        // Patch in any relocated values that land in this piece. A relocation's target is always
+        // naturally aligned and this piece is always page-aligned, so a word either falls
+        // entirely within the piece or not at all; there's no need to handle one straddling the
+        // edge.
+        for &(vaddr, value) in self.relocations.iter() {
+            if vaddr >= base && vaddr + self.reloc_word_size as usize <= base + size {
+                let dest: &mut [u8] = unsafe {
+                    slice::from_raw_parts_mut(block.index(vaddr - base), self.reloc_word_size as usize)
+                };
+                match self.reloc_word_size {
+                    4 => dest.copy_from_slice(&(value as u32).to_le_bytes()),
+                    _ => dest.copy_from_slice(&value.to_le_bytes())
+                }
+            }
+        }
+
         // Map the block into virtual memory.
         let region_type = if segment.flags.contains(SegmentFlags::WRITABLE) {
             RegionType::Ram
@@ -199,10 +298,40 @@ impl<T: Read+Seek> ExecImage<T> {
         unsafe { &*self.page_table.index(0) }
     }
 
+    /// Returns this image's GNU build-id, if it has one.
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.build_id.as_deref()
+    }
+
+    /// Returns whether this image's stack should be mapped executable.
+    pub fn stack_executable(&self) -> bool {
+        self.stack_executable
+    }
+
+    /// Returns this image's thread-local storage template, if it has one.
+    pub fn tls_template(&self) -> Option<&TlsTemplate> {
+        self.tls_template.as_ref()
+    }
+
     /// Makes a reader object that can seek to virtual addresses rather than to file offsets.
     pub fn virt_reader(&self) -> VirtReader<'_, T> {
         VirtReader { image: self, addr: 0 }
     }
+
+    /// Eagerly loads and maps the given range, the same way a page fault would. This is meant to
+    /// be called before the program actually starts running, so the first instructions it and its
+    /// interpreter execute don't have to wait on a page fault. Unlike `load_segment_piece`, this
+    /// retries automatically instead of reporting `Err(None)`, since there's no fault to resume
+    /// from if we gave up here.
+    pub fn prefault(&self, base: usize, size: NonZeroUsize) -> Result<(), LoadSegmentError> {
+        loop {
+            match self.load_segment_piece(base, size) {
+                Ok(_) => return Ok(()),
+                Err(Some(e)) => return Err(e),
+                Err(None) => continue // Someone else holds the reader lock; try again.
+            }
+        }
+    }
 }
 
 /// An object that allows reading from an executable file by seeking to virtual memory addresses
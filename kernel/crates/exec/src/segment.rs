@@ -19,6 +19,8 @@
 //! This module defines a file-format-independent representation of a segment of an executable
 //! file.
 
+use alloc::vec::Vec;
+
 /// A descriptor for a segment of an executable file.
 #[derive(Debug)]
 pub struct Segment {
@@ -42,7 +44,27 @@ pub enum SegmentType {
     /// This segment defines information needed for dynamic linking.
     DLib,
     /// This segment specifies another file to be used as an interpreter for this one.
-    Interpreter
+    Interpreter,
+    /// This segment holds vendor- or system-defined notes, e.g. a build-id or an ABI tag.
+    Note
+}
+
+/// The template for a new thread's thread-local storage block, taken from an image's `PT_TLS`
+/// segment. The kernel's thread setup copies `init_image` into the new block and zeroes the rest
+/// up to `total_size`, matching the "initial TLS block" construction described by the System V
+/// ABI's TLS supplement.
+#[derive(Debug)]
+pub struct TlsTemplate {
+    /// The initialized part of the TLS block (what ELF calls `.tdata`), copied verbatim into
+    /// every new thread's TLS block.
+    pub init_image: Vec<u8>,
+    /// The length of `init_image`, in bytes. Always equal to `init_image.len()`.
+    pub init_size: usize,
+    /// The total size of the TLS block, in bytes, including the zero-filled `.tbss` portion that
+    /// follows `init_image`. Always at least `init_size`.
+    pub total_size: usize,
+    /// The alignment the TLS block must be placed at, in bytes. Always 0 or a power of 2.
+    pub align: usize
 }
 
 bitflags! {
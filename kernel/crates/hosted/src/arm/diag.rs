@@ -0,0 +1,43 @@
+/* Copyright (c) 2023 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Routes `Text` diagnostics to a binary channel on the host, using `i18n::wire`, instead of
+//! formatting them as words. This only makes sense while something is actually attached to the
+//! semihosting link to read it, which is exactly the condition under which opening the channel's
+//! special file succeeds; a bare-metal boot (or an emulator run with no debugger listening) will
+//! fail to open it, and the caller is expected to fall back to printing `Text`'s usual `Display`
+//! output over the UART instead.
+
+use {
+    i18n::{Text, wire::{Render, Wire}},
+    io_crate::Write,
+    super::io::{File, FileMode}
+};
+
+/// Sends `text` down the binary hosted channel, if one is attached.
+///
+/// # Returns
+/// `true` if the diagnostic was written to the channel. `false` if nothing is listening, in which
+/// case the caller should fall back to its own textual path.
+pub(crate) fn try_emit(text: &Text) -> bool {
+    let mut channel = match File::open(c_str!(":semihosting-diag"), FileMode::AppendBin) {
+        Ok(channel) => channel,
+        Err(_errno) => return false
+    };
+    channel.write_all(&Wire::render(text)).is_ok()
+}
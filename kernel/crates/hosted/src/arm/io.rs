@@ -215,7 +215,10 @@ impl Drop for File {
     fn drop(&mut self) {
         let _ = self.flush();
         if semihost(Operation::Close, &self.handle as *const _ as Field) == -1 {
-            println!("{}", Text::HostedCouldntCloseFile(self.handle.to_string(), errno() as i64));
+            let diag = Text::HostedCouldntCloseFile(self.handle.to_string(), errno() as i64);
+            if !super::diag::try_emit(&diag) {
+                println!("{}", diag);
+            }
 
             // TODO: Remove this old implementation. I'm keeping it around for now because it might
             // be the basis for a good serial-port-free `print!` implementation.
@@ -20,6 +20,7 @@
 
 pub mod io;
 pub mod fs;
+mod diag;
 
 #[cfg(target_pointer_width = "32")]
 type Field = i32;
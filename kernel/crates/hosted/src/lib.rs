@@ -25,18 +25,21 @@
 #![deny(warnings, missing_docs)]
 
 extern crate alloc;
-#[cfg_attr(any(target_arch = "arm", target_arch = "armv5te", target_arch = "armv7", target_arch = "aarch64"), macro_use)] extern crate io as io_crate;
+#[cfg_attr(any(target_arch = "arm", target_arch = "armv5te", target_arch = "armv7", target_arch = "aarch64",
+    target_arch = "x86_64"), macro_use)] extern crate io as io_crate;
 
-#[cfg(not(target_arch = "x86_64"))]
 #[macro_use] extern crate bitflags;
 #[cfg(not(target_arch = "x86_64"))]
 #[macro_use] extern crate static_assertions;
-#[cfg(not(target_arch = "x86_64"))]
 #[macro_use] extern crate shared;
 
 #[cfg(any(target_arch = "arm", target_arch = "armv5te", target_arch = "armv7", target_arch = "aarch64"))]
     mod arm;
 #[cfg(any(target_arch = "arm", target_arch = "armv5te", target_arch = "armv7", target_arch = "aarch64"))]
     pub use self::arm::*;
+#[cfg(target_arch = "x86_64")]
+    mod virtio9p;
+#[cfg(target_arch = "x86_64")]
+    pub use self::virtio9p::*;
 #[cfg(feature = "unit-test")] mod shim;
 #[cfg(feature = "unit-test")] pub use self::shim::*;
@@ -0,0 +1,252 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! This module defines the x86-64 implementation of the I/O parts of the `hosted` API, backed by
+//! [`super`]'s 9P2000.L transport instead of ARM semihosting.
+
+use {
+    alloc::{string::ToString, vec::Vec},
+    i18n::Text,
+    shared::ffi::CStrRef,
+    io_crate::{Read, Write, Seek, SeekFrom},
+    super::{proto, proto::lflags, transport, libc_errno::ENODEV}
+};
+
+/// A file in the host's filesystem, reached over the `virtio-9p` transport.
+#[derive(Debug)]
+pub struct File {
+    fid: u32,
+    cursor: u64,
+    _mode: FileMode
+}
+
+/// The mode under which a file is to be opened. Identical in meaning (and numeric value) to
+/// [`super::arm::io::FileMode`]; it's redefined here instead of shared because that module doesn't
+/// exist on this architecture.
+#[cfg_attr(target_pointer_width = "32", repr(u32))]
+#[cfg_attr(target_pointer_width = "64", repr(u64))]
+#[derive(Debug, Clone, Copy)]
+pub enum FileMode {
+    /// Read-only, textual data.
+    ReadText         = 0b0000,
+    /// Read-only, binary data.
+    ReadBin          = 0b0001,
+    /// Read-write, textual data.
+    ReadUpdateText   = 0b0010,
+    /// Read-write, binary data.
+    ReadUpdateBin    = 0b0011,
+    /// Write-only, starting from empty, textual data.
+    WriteText        = 0b0100,
+    /// Write-only, starting from empty, binary data.
+    WriteBin         = 0b0101,
+    /// Read-write, starting from empty, textual data.
+    WriteUpdateText  = 0b0110,
+    /// Read-write, starting from empty, binary data.
+    WriteUpdateBin   = 0b0111,
+    /// Appending at the end, textual data.
+    AppendText       = 0b1000,
+    /// Appending at the end, binary data.
+    AppendBin        = 0b1001,
+    /// Read-write, all writes are at the end, textual data.
+    AppendUpdateText = 0b1010,
+    /// Read-write, all writes are at the end, binary data.
+    AppendUpdateBin  = 0b1011
+}
+
+impl FileMode {
+    // Whether opening in this mode creates the file if it doesn't already exist, as opposed to
+    // failing.
+    fn creates(self) -> bool {
+        !matches!(self, FileMode::ReadText | FileMode::ReadBin | FileMode::ReadUpdateText | FileMode::ReadUpdateBin)
+    }
+
+    // The 9P2000.L open/create flags this mode maps onto. 9P has no notion of text vs. binary mode
+    // (every file is just a byte stream), so that bit of `FileMode` has no effect here, the same way
+    // it has no effect in the ARM backend's underlying C library.
+    fn lflags(self) -> u32 {
+        match self {
+            FileMode::ReadText | FileMode::ReadBin => lflags::O_RDONLY,
+            FileMode::ReadUpdateText | FileMode::ReadUpdateBin => lflags::O_RDWR,
+            FileMode::WriteText | FileMode::WriteBin => lflags::O_WRONLY | lflags::O_CREAT | lflags::O_TRUNC,
+            FileMode::WriteUpdateText | FileMode::WriteUpdateBin => lflags::O_RDWR | lflags::O_CREAT | lflags::O_TRUNC,
+            FileMode::AppendText | FileMode::AppendBin => lflags::O_WRONLY | lflags::O_CREAT | lflags::O_APPEND,
+            FileMode::AppendUpdateText | FileMode::AppendUpdateBin => lflags::O_RDWR | lflags::O_CREAT | lflags::O_APPEND
+        }
+    }
+
+    // Whether the cursor should start at the end of the file instead of the beginning.
+    fn appends(self) -> bool {
+        matches!(self,
+            FileMode::AppendText | FileMode::AppendBin | FileMode::AppendUpdateText | FileMode::AppendUpdateBin)
+    }
+}
+
+impl File {
+    /// Attempts to open a file exported by the host's `virtio-9p` device.
+    ///
+    /// # Returns
+    /// A new `File`, or the closest `errno`-style code this driver has for what went wrong.
+    pub fn open(path: CStrRef, mode: FileMode) -> Result<File, i64> {
+        let transport = transport().ok_or(ENODEV)?;
+        let mut transport = transport.lock();
+
+        let path = path.as_str().map_err(|_| libc_errno::EILSEQ)?;
+        let components: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let (dir_components, file_name) = match components.split_last() {
+            Some((last, init)) => (init, *last),
+            None => return Err(libc_errno::ENOENT)
+        };
+
+        let dir_fid = transport.alloc_fid();
+        let walked = transport.request(
+            |buf| proto::encode_walk(buf, proto::ROOT_FID, dir_fid, dir_components),
+            proto::decode_walk
+        )?;
+        if walked != dir_components.len() {
+            return Err(libc_errno::ENOENT);
+        }
+
+        let fid = if mode.creates() {
+            transport.request(
+                |buf| proto::encode_lcreate(buf, dir_fid, file_name, mode.lflags(), 0o644, 0),
+                proto::decode_lcreate
+            )?;
+            dir_fid // `Tlcreate` turns `dir_fid` itself into a handle to the new file.
+        } else {
+            let file_fid = transport.alloc_fid();
+            let walked = transport.request(
+                |buf| proto::encode_walk(buf, dir_fid, file_fid, &[file_name]),
+                proto::decode_walk
+            )?;
+            if walked != 1 {
+                return Err(libc_errno::ENOENT);
+            }
+            transport.request(
+                |buf| proto::encode_lopen(buf, file_fid, mode.lflags()),
+                proto::decode_lopen
+            )?;
+            let _ = transport.request(
+                |buf| proto::encode_clunk(buf, dir_fid),
+                proto::decode_clunk
+            ); // Best-effort: `dir_fid` was only ever a stepping stone to `file_fid`.
+            file_fid
+        };
+
+        let mut file = File { fid, cursor: 0, _mode: mode };
+        if mode.appends() {
+            file.cursor = file.len()?;
+        }
+        Ok(file)
+    }
+
+    /// Returns the number of bytes in this file.
+    pub fn len(&self) -> Result<u64, i64> {
+        let transport = transport().ok_or(ENODEV)?;
+        transport.lock().request(
+            |buf| proto::encode_getattr(buf, self.fid),
+            proto::decode_getattr
+        )
+    }
+
+    /// Returns whether this file is actually an interactive terminal. A 9P mount never is.
+    pub fn is_tty(&self) -> Result<bool, ()> {
+        Ok(false)
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io_crate::Result<usize> {
+        let transport = transport().ok_or(io_crate::ErrorKind::NotFound)?;
+        let bytes_read = transport.lock().read_into(self.fid, self.cursor, buf)
+            .map_err(|_| io_crate::ErrorKind::Other)?;
+        self.cursor += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> io_crate::Result<usize> {
+        let transport = transport().ok_or(io_crate::ErrorKind::NotFound)?;
+        let mut transport = transport.lock();
+        let len = usize::min(buf.len(), transport.max_io_len());
+        let bytes_written = transport.request(
+            |req_buf| proto::encode_write(req_buf, self.fid, self.cursor, &buf[.. len]),
+            proto::decode_write
+        ).map_err(|_| io_crate::ErrorKind::Other)? as u64;
+        self.cursor += bytes_written;
+        Ok(bytes_written as usize)
+    }
+
+    fn flush(&mut self) -> io_crate::Result<()> { Ok(()) } // Every write is already synchronous.
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> io_crate::Result<u64> {
+        match pos {
+            SeekFrom::Start(abs) => {
+                self.cursor = abs;
+                Ok(self.cursor)
+            },
+            SeekFrom::End(rel) => {
+                let size = self.len().map_err(|_errno| io_crate::Error::from(io_crate::ErrorKind::Other))?;
+                if rel >= 0 {
+                    self.cursor = size + rel as u64;
+                } else {
+                    let neg_rel = (-rel) as u64;
+                    if neg_rel > size {
+                        return Err(io_crate::ErrorKind::InvalidInput.into());
+                    }
+                    self.cursor = size - neg_rel;
+                }
+                Ok(self.cursor)
+            },
+            SeekFrom::Current(rel) => {
+                if rel >= 0 {
+                    self.cursor += rel as u64;
+                } else {
+                    let neg_rel = (-rel) as u64;
+                    if neg_rel > self.cursor {
+                        return Err(io_crate::ErrorKind::InvalidInput.into());
+                    }
+                    self.cursor -= neg_rel;
+                }
+                Ok(self.cursor)
+            }
+        }
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        let _ = self.flush();
+        let Some(transport) = transport() else { return };
+        let result = transport.lock().request(
+            |buf| proto::encode_clunk(buf, self.fid),
+            proto::decode_clunk
+        );
+        if let Err(errno) = result {
+            let diag = Text::HostedCouldntCloseFile(self.fid.to_string(), errno);
+            println!("{}", diag);
+        }
+    }
+}
+
+mod libc_errno {
+    pub(super) const ENOENT: i64 = 2;
+    pub(super) const EILSEQ: i64 = 84;
+}
@@ -0,0 +1,534 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! x86-64's implementation of the `hosted` API. Real x86-64 hardware has no semihosting
+//! instruction, so instead of trapping to the host the way the `arm` module does, this talks to a
+//! `virtio-9p` device over a minimal split virtqueue and speaks just enough 9P2000.L
+//! ([`proto`]) to back [`io::File`].
+//!
+//! Unlike the rest of the kernel's VirtIO support in `drivers/virtio`, this driver can't run as a
+//! separate userspace process: `hosted::io::File` has to be usable from deep within the kernel
+//! (for instance, while the root page table itself is still being built), long before there's a
+//! process to claim a device with `libdriver::Device::claim`. So this module finds and drives its
+//! device directly, using `devices::DEVICES`. It also only ever has one 9P request outstanding at a
+//! time, since `File::read`/`write`/`seek` are synchronous calls with no concurrency of their own,
+//! so the virtqueue here is a deliberately tiny, non-async one: a single two-descriptor chain (one
+//! descriptor out for the request, one in for the response) that's reused for every request, with
+//! the caller just busy-polling the used ring until the device answers.
+//!
+//! # Limitations
+//! * Only a `virtio-9p` device reachable directly on the MMIO bus is supported. One behind a PCI
+//!   bridge (found by [`chunk97-1`]'s PCI transport work) isn't, since that needs walking the
+//!   device's vendor-specific PCI capabilities to find its registers, which this module doesn't do.
+//!   [`crate::arm`] doesn't have this problem because ARM semihosting has no device at all to find.
+//!
+//! [`chunk97-1`]: https://github.com/jeremydavis519/phoenix
+
+pub mod io;
+mod proto;
+
+use {
+    alloc::{string::String, vec::Vec},
+    core::{mem, num::NonZeroUsize, str, sync::atomic::Ordering},
+    spin::Mutex,
+    volatile::{ReadOnly, Volatile, WriteOnly},
+    shared::{ffi::Le, once::Once},
+    libdriver::{BusType, Resource},
+    memory::{
+        allocator::AllMemAlloc,
+        phys::block::{BlockMut, Mmio}
+    },
+    devices::{DEVICES, DeviceTree}
+};
+
+/// The name every `virtio-9p` device is given in the device tree, regardless of which bus it was
+/// found on (see `devices::virtio::enumerate`).
+const DEVICE_NAME: &str = "virtio-9";
+const DEVICE_TYPE_9P: u32 = 9;
+
+/// How many bytes of the device's mount tag this driver bothers reading. Real mount tags (the
+/// string a Linux guest would pass as `-o trans=virtio,. tag`) are always much shorter than this.
+const MAX_TAG_LEN: usize = 32;
+
+/// The number of descriptors in the request queue: one for the outgoing request, one for the
+/// incoming response. Since only one 9P request is ever in flight, that's all this driver needs.
+const QUEUE_SIZE: u16 = 2;
+
+static TRANSPORT: Once<Option<Mutex<Transport>>> = Once::new();
+
+/// Finds the `virtio-9` device in the tree, if there is one, and sets up a virtqueue to talk to it.
+/// Returns `None` (without panicking) if there's no such device, or if it's already claimed, or if
+/// it's attached to a bus this driver doesn't know how to read its registers from -- any of which
+/// just means this kernel build isn't hosted by a virtio-9p share, which is a perfectly normal way
+/// to run.
+fn transport() -> Option<&'static Mutex<Transport>> {
+    unsafe { TRANSPORT.call_once(|| Transport::new(&DEVICES).ok()) }.as_ref()
+}
+
+// Finds the device node named `DEVICE_NAME`, if any, searching every bus in the tree.
+fn find_device(tree: &DeviceTree) -> Option<(&core::sync::atomic::AtomicBool, &[Resource])> {
+    match tree {
+        DeviceTree::Device { name, claimed, resources } => {
+            if name == DEVICE_NAME { Some((claimed, resources)) } else { None }
+        },
+        DeviceTree::Root { children } => children.iter().find_map(find_device),
+        DeviceTree::Mmio { children, .. } => children.iter().find_map(find_device),
+        DeviceTree::Pci { children, .. } => children.iter().find_map(find_device)
+    }
+}
+
+/// Why [`Transport::new`] couldn't set up a connection to the device.
+#[derive(Debug)]
+enum InitError {
+    /// There's no `virtio-9` device in the tree.
+    NotFound,
+    /// There is one, but something else has already claimed it.
+    AlreadyClaimed,
+    /// There is one, but it isn't on a bus this driver can read registers from (see the module's
+    /// `# Limitations`).
+    UnsupportedBus,
+    /// It's on the MMIO bus, but it didn't validate as a `virtio-9p` device once mapped.
+    NotVirtio9p
+}
+
+/// Holds the live connection to the `virtio-9p` device: its MMIO registers and the single
+/// request/response virtqueue this driver uses for every 9P message.
+struct Transport {
+    regs: Mmio<MmioRegisters>,
+    desc: BlockMut<VirtqDesc>,
+    avail: BlockMut<VirtqAvail>,
+    used: BlockMut<VirtqUsed>,
+    req_buf: BlockMut<u8>,
+    resp_buf: BlockMut<u8>,
+    msize: u32,
+    /// The number of requests sent so far, which is also the next index to use in the avail and
+    /// used rings (mod `QUEUE_SIZE`) and the tag-free way this driver tells its own requests apart
+    /// from stale responses.
+    next_idx: u16,
+    /// The next fid this driver will hand out. Fids are never reused once clunked; `u32` is large
+    /// enough that this is never a problem in practice.
+    next_fid: u32,
+    /// The mount tag the device reported, mostly kept around for diagnostics.
+    #[allow(dead_code)]
+    mount_tag: String
+}
+
+impl Transport {
+    fn new(root: &DeviceTree) -> Result<Self, InitError> {
+        let (claimed, resources) = find_device(root).ok_or(InitError::NotFound)?;
+        let resource = resources.first().ok_or(InitError::NotFound)?;
+        if resource.bus != BusType::Mmio {
+            return Err(InitError::UnsupportedBus);
+        }
+        if claimed.swap(true, Ordering::AcqRel) {
+            return Err(InitError::AlreadyClaimed);
+        }
+
+        let regs = AllMemAlloc.mmio_mut::<MmioRegisters>(resource.base, mem::size_of::<MmioRegisters>())
+            .map_err(|_| InitError::NotFound)?;
+        Self::validate(&regs)?;
+        Self::negotiate_features(&regs)?;
+        let mount_tag = Self::read_mount_tag(&regs);
+
+        let (desc, avail, used) = Self::alloc_queue();
+        {
+            let regs = unsafe { &mut *regs.index(0) };
+            regs.queue_sel.write(Le::from_native(0));
+            assert_eq!(regs.queue_ready.read().into_native(), 0, "virtio-9p: request queue already in use");
+            assert!(regs.queue_num_max.read().into_native() >= u32::from(QUEUE_SIZE),
+                "virtio-9p: device doesn't support a queue of size {}", QUEUE_SIZE);
+            regs.queue_num.write(Le::from_native(u32::from(QUEUE_SIZE)));
+
+            let desc_addr = desc.base().as_addr_phys() as u64;
+            regs.queue_desc_low.write(Le::from_native(desc_addr as u32));
+            regs.queue_desc_high.write(Le::from_native((desc_addr >> 32) as u32));
+            let avail_addr = avail.base().as_addr_phys() as u64;
+            regs.queue_avail_low.write(Le::from_native(avail_addr as u32));
+            regs.queue_avail_high.write(Le::from_native((avail_addr >> 32) as u32));
+            let used_addr = used.base().as_addr_phys() as u64;
+            regs.queue_used_low.write(Le::from_native(used_addr as u32));
+            regs.queue_used_high.write(Le::from_native((used_addr >> 32) as u32));
+
+            regs.queue_ready.write(Le::from_native(1));
+            regs.status.write(regs.status.read() | DeviceStatus::DRIVER_OK);
+        }
+
+        let mut transport = Self {
+            regs, desc, avail, used,
+            req_buf: Self::alloc_buf(proto::MSIZE as usize),
+            resp_buf: Self::alloc_buf(proto::MSIZE as usize),
+            msize: proto::MSIZE,
+            next_idx: 0,
+            next_fid: proto::ROOT_FID + 1,
+            mount_tag
+        };
+        transport.init_queue_layout();
+        transport.negotiate_version();
+        transport.attach()?;
+        Ok(transport)
+    }
+
+    // Attaches to the root of the exported tree as fid 0. This driver doesn't run as any particular
+    // user, so it attaches as uid 0; the host's 9P server is free to map that however it configures
+    // its export (commonly by squashing every request to one fixed user).
+    fn attach(&mut self) -> Result<(), InitError> {
+        self.request(
+            |buf| proto::encode_attach(buf, 0),
+            proto::decode_attach
+        ).map_err(|_| InitError::NotVirtio9p)
+    }
+
+    /// Allocates a fid that [`io::File`] can use, distinct from every other fid handed out so far.
+    fn alloc_fid(&mut self) -> u32 {
+        let fid = self.next_fid;
+        self.next_fid += 1;
+        fid
+    }
+
+    fn validate(regs: &Mmio<MmioRegisters>) -> Result<(), InitError> {
+        const MAGIC_NUMBER: u32 = 0x7472_6976; // Little-endian "virt"
+        let regs = unsafe { &*regs.index(0) };
+        if regs.magic_number.read().into_native() != MAGIC_NUMBER
+                || regs.version.read().into_native() != 2 // Only the "modern" MMIO transport is supported.
+                || regs.device_id.read().into_native() != DEVICE_TYPE_9P {
+            return Err(InitError::NotVirtio9p);
+        }
+        Ok(())
+    }
+
+    // Resets the device and negotiates just the one feature this driver cares about: VIRTIO_F_VERSION_1,
+    // which every modern device offers and which this driver requires so it never has to fall back to
+    // the legacy (guest-page-size-based) queue layout.
+    fn negotiate_features(regs: &Mmio<MmioRegisters>) -> Result<(), InitError> {
+        let regs = unsafe { &mut *regs.index(0) };
+        regs.status.write(DeviceStatus::empty());
+        regs.status.write(regs.status.read() | DeviceStatus::ACKNOWLEDGE);
+        regs.status.write(regs.status.read() | DeviceStatus::DRIVER);
+
+        regs.device_features_sel.write(Le::from_native(1));
+        let features_high = regs.device_features.read().into_native();
+        if features_high & 0x1 == 0 { // VIRTIO_F_VERSION_1 is bit 32, i.e. bit 0 of the high dword.
+            regs.status.write(regs.status.read() | DeviceStatus::FAILED);
+            return Err(InitError::NotVirtio9p);
+        }
+        regs.driver_features_sel.write(Le::from_native(0));
+        regs.driver_features.write(Le::from_native(0));
+        regs.driver_features_sel.write(Le::from_native(1));
+        regs.driver_features.write(Le::from_native(0x1));
+
+        regs.status.write(regs.status.read() | DeviceStatus::FEATURES_OK);
+        if !regs.status.read().contains(DeviceStatus::FEATURES_OK) {
+            regs.status.write(regs.status.read() | DeviceStatus::FAILED);
+            return Err(InitError::NotVirtio9p);
+        }
+        Ok(())
+    }
+
+    // Reads the mount tag out of the device-specific configuration space, capped at `MAX_TAG_LEN`
+    // bytes. A device that reports a longer tag just has it truncated here; this driver never needs
+    // the tag for anything besides a diagnostic.
+    fn read_mount_tag(regs: &Mmio<MmioRegisters>) -> String {
+        let regs = unsafe { &*regs.index(0) };
+        let len = usize::min(regs.mount_tag_len.read().into_native() as usize, MAX_TAG_LEN);
+        let tag_bytes = &regs.mount_tag[.. len];
+        str::from_utf8(tag_bytes).unwrap_or("(invalid UTF-8)").into()
+    }
+
+    fn alloc_queue() -> (BlockMut<VirtqDesc>, BlockMut<VirtqAvail>, BlockMut<VirtqUsed>) {
+        let desc = AllMemAlloc.malloc::<VirtqDesc>(
+                mem::size_of::<VirtqDesc>() * usize::from(QUEUE_SIZE),
+                NonZeroUsize::new(mem::align_of::<VirtqDesc>()).unwrap()
+            )
+            .expect("not enough memory for the virtio-9p request queue's descriptor table");
+        for i in 0 .. usize::from(QUEUE_SIZE) {
+            unsafe { (*desc.index(i)).write(VirtqDesc::default()); }
+        }
+        let desc = unsafe { desc.assume_init() };
+
+        let avail = AllMemAlloc.malloc::<VirtqAvail>(mem::size_of::<VirtqAvail>(), NonZeroUsize::new(mem::align_of::<VirtqAvail>()).unwrap())
+            .expect("not enough memory for the virtio-9p request queue's available ring");
+        unsafe { (*avail.index(0)).write(VirtqAvail::default()); }
+        let avail = unsafe { avail.assume_init() };
+
+        let used = AllMemAlloc.malloc::<VirtqUsed>(mem::size_of::<VirtqUsed>(), NonZeroUsize::new(mem::align_of::<VirtqUsed>()).unwrap())
+            .expect("not enough memory for the virtio-9p request queue's used ring");
+        unsafe { (*used.index(0)).write(VirtqUsed::default()); }
+        let used = unsafe { used.assume_init() };
+
+        (desc, avail, used)
+    }
+
+    fn alloc_buf(len: usize) -> BlockMut<u8> {
+        let buf = AllMemAlloc.malloc::<u8>(len, NonZeroUsize::new(1).unwrap())
+            .expect("not enough memory for a virtio-9p message buffer");
+        for i in 0 .. len {
+            unsafe { (*buf.index(i)).write(0); }
+        }
+        unsafe { buf.assume_init() }
+    }
+
+    // Points the two permanent descriptors at the request and response buffers. Only `desc[0]`'s
+    // `len` ever changes after this, since every request has a different length but the buffers
+    // themselves are reused forever.
+    fn init_queue_layout(&mut self) {
+        let req_addr = self.req_buf.base().as_addr_phys() as u64;
+        let resp_addr = self.resp_buf.base().as_addr_phys() as u64;
+        unsafe {
+            *self.desc.index(0) = VirtqDesc {
+                addr: Volatile::new(Le::from_native(req_addr)),
+                len: Volatile::new(Le::from_native(0)),
+                flags: Volatile::new(Le::from_native(VirtqDescFlags::NEXT.bits())),
+                next: Volatile::new(Le::from_native(1))
+            };
+            *self.desc.index(1) = VirtqDesc {
+                addr: Volatile::new(Le::from_native(resp_addr)),
+                len: Volatile::new(Le::from_native(self.msize)),
+                flags: Volatile::new(Le::from_native(VirtqDescFlags::WRITE.bits())),
+                next: Volatile::new(Le::from_native(0))
+            };
+        }
+    }
+
+    // Negotiates the 9P-level protocol version (as opposed to the VirtIO feature bits already
+    // handled by `negotiate_features`) and shrinks `msize` to whatever the two sides agreed on.
+    fn negotiate_version(&mut self) {
+        let req_len = proto::encode_version(self.req_slice(), proto::MSIZE)
+            .expect("Tversion's fixed-size body always fits in msize");
+        let resp = self.transceive_raw(req_len);
+        self.msize = proto::decode_version(resp).expect("virtio-9p: host doesn't speak 9P2000.L");
+    }
+
+    fn req_slice(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.req_buf.index(0), self.req_buf.size()) }
+    }
+
+    fn resp_slice(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.resp_buf.index(0), self.resp_buf.size()) }
+    }
+
+    // Sends whatever is in the first `req_len` bytes of the request buffer, blocks until the device
+    // answers, and returns a slice of the response buffer holding the reply.
+    fn transceive_raw(&mut self, req_len: usize) -> &[u8] {
+        unsafe {
+            (*self.desc.index(0)).len.write(Le::from_native(req_len as u32));
+        }
+
+        let slot = (self.next_idx % QUEUE_SIZE) as usize;
+        unsafe {
+            let avail = &mut *self.avail.index(0);
+            avail.ring[slot].write(Le::from_native(0)); // Always the head of our one descriptor chain.
+            avail.idx.write(Le::from_native(self.next_idx.wrapping_add(1)));
+        }
+
+        {
+            let regs = unsafe { &mut *self.regs.index(0) };
+            regs.queue_notify.write(Le::from_native(0));
+        }
+
+        // Busy-poll for the response. There's no interrupt handler wired up for this driver (it
+        // needs to work even before interrupts are set up during early boot), and it only ever has
+        // one request outstanding, so there's nothing better to do while waiting.
+        loop {
+            let used_idx = unsafe { (*self.used.index(0)).idx.read().into_native() };
+            if used_idx != self.next_idx {
+                break;
+            }
+            core::hint::spin_loop();
+        }
+        self.next_idx = self.next_idx.wrapping_add(1);
+
+        self.resp_slice()
+    }
+
+    /// Sends a 9P request built by `encode` and decodes the reply with `decode`, mapping a 9P-level
+    /// error or a malformed reply (or a request that didn't fit in `msize`) to the same kind of
+    /// `i64` errno that `io::File`'s ARM counterpart returns. `decode` can't borrow from the
+    /// response (see [`Self::read_into`] for the one request that needs to).
+    fn request<T>(
+            &mut self,
+            encode: impl FnOnce(&mut [u8]) -> Result<usize, proto::Overflow>,
+            decode: impl FnOnce(&[u8]) -> Result<T, proto::Error>
+    ) -> Result<T, i64> {
+        let req_len = encode(self.req_slice()).map_err(|proto::Overflow| libc_errno::ENAMETOOLONG)?;
+        let resp = self.transceive_raw(req_len);
+        decode(resp).map_err(|e| match e {
+            proto::Error::Remote(errno) => errno,
+            proto::Error::Malformed => libc_errno::EIO
+        })
+    }
+
+    /// The most data a single `Tread`/`Twrite` can carry given the negotiated `msize`, leaving room
+    /// for the larger of the two messages' headers. Neither `File::read` nor `File::write` loops
+    /// across multiple 9P messages to satisfy one call, consistent with `io::Read`/`io::Write`'s
+    /// documented partial-transfer contract.
+    fn max_io_len(&self) -> usize {
+        // Tread/Twrite's header (size+type+tag+fid+offset+count) is 23 bytes; Rread's is 11. 23 is
+        // the larger of the two, so it's the one that bounds how much payload fits in `msize`.
+        (self.msize as usize).saturating_sub(23)
+    }
+
+    /// Sends a `Tread` for up to `buf.len()` bytes at `offset` and copies whatever comes back
+    /// straight into `buf`, returning how many bytes were copied. This can't go through
+    /// [`Self::request`] because the decoded payload borrows the response buffer, which that method
+    /// can't express without holding the transport borrowed for longer than it should.
+    fn read_into(&mut self, fid: u32, offset: u64, buf: &mut [u8]) -> Result<usize, i64> {
+        let len = usize::min(buf.len(), self.max_io_len());
+        let count = u32::try_from(len).unwrap_or(u32::MAX);
+        let req_len = proto::encode_read(self.req_slice(), fid, offset, count)
+            .expect("Tread's fixed-size body always fits in msize");
+        let resp = self.transceive_raw(req_len);
+        let data = proto::decode_read(resp).map_err(|e| match e {
+            proto::Error::Remote(errno) => errno,
+            proto::Error::Malformed => libc_errno::EIO
+        })?;
+        let len = usize::min(data.len(), buf.len());
+        buf[.. len].copy_from_slice(&data[.. len]);
+        Ok(len)
+    }
+}
+
+unsafe impl Send for Transport {}
+
+// A tiny stand-in for a `libc errno.h`, since this crate has no such dependency of its own.
+mod libc_errno {
+    pub(crate) const EIO: i64 = 5;
+    pub(crate) const ENODEV: i64 = 19;
+    pub(crate) const ENAMETOOLONG: i64 = 36;
+}
+
+#[repr(C)]
+struct MmioRegisters {
+    magic_number:         ReadOnly<Le<u32>>,       // 0x000
+    version:              ReadOnly<Le<u32>>,       // 0x004
+    device_id:            ReadOnly<Le<u32>>,       // 0x008
+    vendor_id:            ReadOnly<Le<u32>>,       // 0x00c
+    device_features:      ReadOnly<Le<u32>>,       // 0x010
+    device_features_sel:  WriteOnly<Le<u32>>,      // 0x014
+    _reserved0:           [u8; 8],                 // 0x018
+    driver_features:      WriteOnly<Le<u32>>,      // 0x020
+    driver_features_sel:  WriteOnly<Le<u32>>,      // 0x024
+    _reserved1:           [u8; 8],                 // 0x028
+    queue_sel:            WriteOnly<Le<u32>>,      // 0x030
+    queue_num_max:        ReadOnly<Le<u32>>,       // 0x034
+    queue_num:            WriteOnly<Le<u32>>,      // 0x038
+    _reserved2:           [u8; 8],                 // 0x03c
+    queue_ready:          Volatile<Le<u32>>,       // 0x044
+    _reserved3:           [u8; 8],                 // 0x048
+    queue_notify:         WriteOnly<Le<u32>>,      // 0x050
+    _reserved4:           [u8; 12],                // 0x054
+    interrupt_status:     ReadOnly<Le<u32>>,       // 0x060
+    interrupt_ack:        WriteOnly<Le<u32>>,      // 0x064
+    _reserved5:           [u8; 8],                 // 0x068
+    status:               Volatile<DeviceStatus>,  // 0x070
+    _reserved6:           [u8; 12],                // 0x074
+    queue_desc_low:       WriteOnly<Le<u32>>,      // 0x080
+    queue_desc_high:      WriteOnly<Le<u32>>,      // 0x084
+    _reserved7:           [u8; 8],                 // 0x088
+    queue_avail_low:      WriteOnly<Le<u32>>,      // 0x090
+    queue_avail_high:     WriteOnly<Le<u32>>,      // 0x094
+    _reserved8:           [u8; 8],                 // 0x098
+    queue_used_low:       WriteOnly<Le<u32>>,      // 0x0a0
+    queue_used_high:      WriteOnly<Le<u32>>,      // 0x0a4
+    _reserved9:           [u8; 0x58],               // 0x0a8 .. 0x100
+
+    // 9P-specific device configuration
+    mount_tag_len:        ReadOnly<Le<u16>>,       // 0x100
+    mount_tag:            [u8; MAX_TAG_LEN]
+}
+
+bitflags! {
+    struct DeviceStatus: u32 {
+        const ACKNOWLEDGE = u32::to_le(0x01); // OS has noticed the device
+        const DRIVER      = u32::to_le(0x02); // OS knows how to drive the device
+        const DRIVER_OK   = u32::to_le(0x04); // Driver is ready
+        const FEATURES_OK = u32::to_le(0x08); // Driver has acknowledged the features it understands
+        const NEEDS_RESET = u32::to_le(0x40); // Device has experienced an error and needs to be reset
+        const FAILED      = u32::to_le(0x80); // OS has given up on the device
+    }
+}
+
+// Every field below is shared with the device over DMA, not just memory this driver owns, so each
+// one is wrapped in `Volatile` to stop the compiler from eliding or reordering accesses to it (most
+// importantly `VirtqUsed::idx`, which is polled in a busy-wait loop) the same way `MmioRegisters`'s
+// fields are.
+#[repr(C, align(16))]
+struct VirtqDesc {
+    addr: Volatile<Le<u64>>,
+    len: Volatile<Le<u32>>,
+    flags: Volatile<Le<u16>>,
+    next: Volatile<Le<u16>>
+}
+
+impl Default for VirtqDesc {
+    fn default() -> Self {
+        Self {
+            addr: Volatile::new(Le::from_native(0)),
+            len: Volatile::new(Le::from_native(0)),
+            flags: Volatile::new(Le::from_native(0)),
+            next: Volatile::new(Le::from_native(0))
+        }
+    }
+}
+
+bitflags! {
+    struct VirtqDescFlags: u16 {
+        const NEXT  = u16::to_le(0x1);
+        const WRITE = u16::to_le(0x2);
+    }
+}
+
+#[repr(C, align(2))]
+struct VirtqAvail {
+    flags: Volatile<Le<u16>>,
+    idx: Volatile<Le<u16>>,
+    ring: [Volatile<Le<u16>>; QUEUE_SIZE as usize]
+}
+
+impl Default for VirtqAvail {
+    fn default() -> Self {
+        Self {
+            flags: Volatile::new(Le::from_native(0)),
+            idx: Volatile::new(Le::from_native(0)),
+            ring: [Volatile::new(Le::from_native(0)); QUEUE_SIZE as usize]
+        }
+    }
+}
+
+#[repr(C, align(4))]
+#[derive(Clone, Copy)]
+struct VirtqUsedElem {
+    id: Le<u32>,
+    len: Le<u32>
+}
+
+#[repr(C, align(4))]
+struct VirtqUsed {
+    flags: Volatile<Le<u16>>,
+    idx: Volatile<Le<u16>>,
+    ring: [Volatile<VirtqUsedElem>; QUEUE_SIZE as usize]
+}
+
+impl Default for VirtqUsed {
+    fn default() -> Self {
+        Self {
+            flags: Volatile::new(Le::from_native(0)),
+            idx: Volatile::new(Le::from_native(0)),
+            ring: [Volatile::new(VirtqUsedElem { id: Le::from_native(0), len: Le::from_native(0) }); QUEUE_SIZE as usize]
+        }
+    }
+}
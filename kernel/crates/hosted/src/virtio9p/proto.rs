@@ -0,0 +1,384 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Wire encoding and decoding for the small subset of 9P2000.L that [`super::io::File`] needs:
+//! version negotiation, attaching to the exported tree, walking to a path, opening or creating a
+//! file, reading and writing at an explicit offset, stat-ing a file for its length, and clunking a
+//! fid when it's no longer needed. None of this module talks to the device directly; it just turns
+//! requests into bytes and bytes into responses, leaving the transceiving to
+//! [`super::Transport::transceive_raw`].
+
+use alloc::string::String;
+
+/// The fid that `Tattach` assigns to the root of the exported tree. Every other fid is derived from
+/// it with `Twalk`.
+pub(crate) const ROOT_FID: u32 = 0;
+
+/// The maximum size in bytes of a single 9P message, in either direction, that this driver will
+/// ever send or accept. This is proposed to the host in `Tversion`; the host is free to negotiate
+/// it down, in which case [`super::Transport`] shrinks its own notion of `msize` to match, but it's
+/// never negotiated up, so this is also the size of the buffers the transport allocates up front.
+pub(crate) const MSIZE: u32 = 4096;
+
+/// The tag used for every request. This driver only ever has one request in flight at a time, so
+/// there's no need to tell responses apart by tag the way a concurrent 9P client would.
+const TAG: u16 = 0;
+/// The tag `Tversion` must use, before tags have even been negotiated.
+const NOTAG: u16 = 0xffff;
+
+/// The version string this driver understands. The host's 9P server is free to fall back to plain
+/// `9P2000`, but this driver doesn't support that dialect, so a different reply is treated as a
+/// failure to mount.
+const VERSION: &str = "9P2000.L";
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MsgType {
+    Rlerror  = 7,
+    Tattach  = 104,
+    Rattach  = 105,
+    Tversion = 100,
+    Rversion = 101,
+    Twalk    = 110,
+    Rwalk    = 111,
+    Tlopen   = 12,
+    Rlopen   = 13,
+    Tlcreate = 14,
+    Rlcreate = 15,
+    Tread    = 116,
+    Rread    = 117,
+    Twrite   = 118,
+    Rwrite   = 119,
+    Tgetattr = 24,
+    Rgetattr = 25,
+    Tclunk   = 120,
+    Rclunk   = 121
+}
+
+/// The `Rlopen`/`Rlcreate` flags for `O_RDONLY`/`O_WRONLY`/`O_RDWR`, as used by `Tlopen`/`Tlcreate`'s
+/// `flags` field. 9P2000.L borrows these directly from Linux, so only the handful this driver
+/// actually issues are named here.
+pub(crate) mod lflags {
+    pub(crate) const O_RDONLY: u32 = 0o0;
+    pub(crate) const O_WRONLY: u32 = 0o1;
+    pub(crate) const O_RDWR:   u32 = 0o2;
+    pub(crate) const O_CREAT:  u32 = 0o100;
+    pub(crate) const O_TRUNC:  u32 = 0o1000;
+    pub(crate) const O_APPEND: u32 = 0o2000;
+}
+
+/// The `request_mask` bit for `Tgetattr` that asks for just the file's size.
+pub(crate) const GETATTR_SIZE: u64 = 0x0000_0200;
+
+/// An error reported by the 9P server itself (an `Rlerror`), or detected in a malformed response.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Error {
+    /// The server returned `Rlerror` with this `errno`.
+    Remote(i64),
+    /// The response didn't parse as a well-formed 9P message, or wasn't the message type we asked
+    /// for.
+    Malformed
+}
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// Returned by [`Encoder`]'s writers (and so by every `encode_*` function) when there isn't enough
+/// room left in the fixed-size message buffer to write the requested value. In practice this means
+/// a caller-supplied path or write was too long to fit alongside the rest of the request in
+/// [`MSIZE`] bytes; it's never the host's fault, unlike [`Error`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Overflow;
+
+type EncodeResult<T> = core::result::Result<T, Overflow>;
+
+/// A cursor for writing 9P messages into a fixed-size buffer.
+struct Encoder<'a> {
+    buf: &'a mut [u8],
+    pos: usize
+}
+
+impl<'a> Encoder<'a> {
+    // Leaves room for the size[4] + type[1] + tag[2] header that `finish` fills in once the body's
+    // length is known.
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, pos: 7 }
+    }
+
+    fn u8(&mut self, val: u8) -> EncodeResult<&mut Self> {
+        *self.buf.get_mut(self.pos).ok_or(Overflow)? = val;
+        self.pos += 1;
+        Ok(self)
+    }
+
+    fn u16(&mut self, val: u16) -> EncodeResult<&mut Self> {
+        self.buf.get_mut(self.pos .. self.pos + 2).ok_or(Overflow)?.copy_from_slice(&val.to_le_bytes());
+        self.pos += 2;
+        Ok(self)
+    }
+
+    fn u32(&mut self, val: u32) -> EncodeResult<&mut Self> {
+        self.buf.get_mut(self.pos .. self.pos + 4).ok_or(Overflow)?.copy_from_slice(&val.to_le_bytes());
+        self.pos += 4;
+        Ok(self)
+    }
+
+    fn u64(&mut self, val: u64) -> EncodeResult<&mut Self> {
+        self.buf.get_mut(self.pos .. self.pos + 8).ok_or(Overflow)?.copy_from_slice(&val.to_le_bytes());
+        self.pos += 8;
+        Ok(self)
+    }
+
+    fn string(&mut self, val: &str) -> EncodeResult<&mut Self> {
+        self.u16(val.len().try_into().map_err(|_| Overflow)?)?;
+        self.buf.get_mut(self.pos .. self.pos + val.len()).ok_or(Overflow)?.copy_from_slice(val.as_bytes());
+        self.pos += val.len();
+        Ok(self)
+    }
+
+    fn bytes(&mut self, val: &[u8]) -> EncodeResult<&mut Self> {
+        self.buf.get_mut(self.pos .. self.pos + val.len()).ok_or(Overflow)?.copy_from_slice(val);
+        self.pos += val.len();
+        Ok(self)
+    }
+
+    // Writes the message's size[4] + type[1] + tag[2] header, now that the body's length is known,
+    // and returns the total length of the message.
+    fn finish(self, msg_type: MsgType, tag: u16) -> usize {
+        let len = self.pos;
+        self.buf[0 .. 4].copy_from_slice(&(len as u32).to_le_bytes());
+        self.buf[4] = msg_type as u8;
+        self.buf[5 .. 7].copy_from_slice(&tag.to_le_bytes());
+        len
+    }
+}
+
+/// A cursor for reading 9P messages out of a fixed-size buffer.
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Decoder<'a> {
+    fn u8(&mut self) -> Result<u8> {
+        let val = *self.buf.get(self.pos).ok_or(Error::Malformed)?;
+        self.pos += 1;
+        Ok(val)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.buf.get(self.pos .. self.pos + 2).ok_or(Error::Malformed)?;
+        self.pos += 2;
+        Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.buf.get(self.pos .. self.pos + 4).ok_or(Error::Malformed)?;
+        self.pos += 4;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let bytes = self.buf.get(self.pos .. self.pos + 8).ok_or(Error::Malformed)?;
+        self.pos += 8;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self.buf.get(self.pos .. self.pos + len).ok_or(Error::Malformed)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::Malformed)
+    }
+
+    // Reads the header, checks that `msg_type` is either what the caller expects or `Rlerror`, and
+    // positions the cursor at the start of the body.
+    fn header(buf: &'a [u8], expected: MsgType) -> Result<Decoder<'a>> {
+        let mut dec = Decoder { buf, pos: 0 };
+        let _size = dec.u32()?;
+        let msg_type = dec.u8()?;
+        let _tag = dec.u16()?;
+        if msg_type == MsgType::Rlerror as u8 {
+            let errno = dec.u32()?;
+            return Err(Error::Remote(errno as i64));
+        }
+        if msg_type != expected as u8 {
+            return Err(Error::Malformed);
+        }
+        Ok(dec)
+    }
+}
+
+/// Encodes a `Tversion` request into `buf` and returns its length.
+pub(crate) fn encode_version(buf: &mut [u8], msize: u32) -> EncodeResult<usize> {
+    let mut enc = Encoder::new(buf);
+    enc.u32(msize)?.string(VERSION)?;
+    Ok(enc.finish(MsgType::Tversion, NOTAG))
+}
+
+/// Decodes an `Rversion` reply, returning the negotiated `msize`.
+///
+/// # Returns
+/// `Err(Error::Malformed)` if the server didn't agree to the `9P2000.L` dialect this driver speaks.
+pub(crate) fn decode_version(buf: &[u8]) -> Result<u32> {
+    let mut dec = Decoder::header(buf, MsgType::Rversion)?;
+    let msize = dec.u32()?;
+    let version = dec.string()?;
+    if version != VERSION {
+        return Err(Error::Malformed);
+    }
+    Ok(msize)
+}
+
+/// Encodes a `Tattach` request, attaching as the given user to the tree exported under `mount_tag`.
+pub(crate) fn encode_attach(buf: &mut [u8], uid: u32) -> EncodeResult<usize> {
+    let mut enc = Encoder::new(buf);
+    enc.u32(ROOT_FID)?
+        .u32(u32::MAX)? // afid = NOFID; no authentication
+        .string("")? // uname is unused when uid is given
+        .string("")? // aname: the default export
+        .u32(uid)?;
+    Ok(enc.finish(MsgType::Tattach, TAG))
+}
+
+/// Decodes an `Rattach` reply. There's nothing in it this driver needs besides confirmation that it
+/// succeeded.
+pub(crate) fn decode_attach(buf: &[u8]) -> Result<()> {
+    Decoder::header(buf, MsgType::Rattach)?;
+    Ok(())
+}
+
+/// Encodes a `Twalk` request that clones `fid` into `new_fid`, walking the path components in
+/// `names` (an empty slice just clones `fid` without moving).
+pub(crate) fn encode_walk(buf: &mut [u8], fid: u32, new_fid: u32, names: &[&str]) -> EncodeResult<usize> {
+    let mut enc = Encoder::new(buf);
+    enc.u32(fid)?.u32(new_fid)?.u16(names.len().try_into().map_err(|_| Overflow)?)?;
+    for name in names {
+        enc.string(name)?;
+    }
+    Ok(enc.finish(MsgType::Twalk, TAG))
+}
+
+/// Decodes an `Rwalk` reply.
+///
+/// # Returns
+/// The number of path components that were actually walked, which is less than the number
+/// requested if (and only if) the walk failed partway through (for instance, because a component
+/// doesn't exist).
+pub(crate) fn decode_walk(buf: &[u8]) -> Result<usize> {
+    let mut dec = Decoder::header(buf, MsgType::Rwalk)?;
+    let nwqid = dec.u16()? as usize;
+    for _ in 0 .. nwqid {
+        dec.bytes(13)?; // Each qid is a fixed 13-byte (type, version, path) triple.
+    }
+    Ok(nwqid)
+}
+
+/// Encodes a `Tlopen` request.
+pub(crate) fn encode_lopen(buf: &mut [u8], fid: u32, flags: u32) -> EncodeResult<usize> {
+    let mut enc = Encoder::new(buf);
+    enc.u32(fid)?.u32(flags)?;
+    Ok(enc.finish(MsgType::Tlopen, TAG))
+}
+
+/// Decodes an `Rlopen` reply.
+pub(crate) fn decode_lopen(buf: &[u8]) -> Result<()> {
+    Decoder::header(buf, MsgType::Rlopen)?;
+    Ok(())
+}
+
+/// Encodes a `Tlcreate` request, which both creates `name` under the directory `fid` and replaces
+/// `fid` with a handle to the new file (as a side effect of the protocol, not something this driver
+/// chooses).
+pub(crate) fn encode_lcreate(buf: &mut [u8], fid: u32, name: &str, flags: u32, mode: u32, gid: u32) -> EncodeResult<usize> {
+    let mut enc = Encoder::new(buf);
+    enc.u32(fid)?.string(name)?.u32(flags)?.u32(mode)?.u32(gid)?;
+    Ok(enc.finish(MsgType::Tlcreate, TAG))
+}
+
+/// Decodes an `Rlcreate` reply.
+pub(crate) fn decode_lcreate(buf: &[u8]) -> Result<()> {
+    Decoder::header(buf, MsgType::Rlcreate)?;
+    Ok(())
+}
+
+/// Encodes a `Tread` request for up to `count` bytes starting at `offset`.
+pub(crate) fn encode_read(buf: &mut [u8], fid: u32, offset: u64, count: u32) -> EncodeResult<usize> {
+    let mut enc = Encoder::new(buf);
+    enc.u32(fid)?.u64(offset)?.u32(count)?;
+    Ok(enc.finish(MsgType::Tread, TAG))
+}
+
+/// Decodes an `Rread` reply, returning a reference to the bytes that were actually read (borrowed
+/// from `buf`, which must outlive it).
+pub(crate) fn decode_read(buf: &[u8]) -> Result<&[u8]> {
+    let mut dec = Decoder::header(buf, MsgType::Rread)?;
+    let count = dec.u32()? as usize;
+    dec.bytes(count)
+}
+
+/// Encodes a `Twrite` request, writing `data` at `offset`.
+pub(crate) fn encode_write(buf: &mut [u8], fid: u32, offset: u64, data: &[u8]) -> EncodeResult<usize> {
+    let mut enc = Encoder::new(buf);
+    enc.u32(fid)?.u64(offset)?.u32(data.len().try_into().map_err(|_| Overflow)?)?.bytes(data)?;
+    Ok(enc.finish(MsgType::Twrite, TAG))
+}
+
+/// Decodes an `Rwrite` reply, returning the number of bytes the server actually wrote.
+pub(crate) fn decode_write(buf: &[u8]) -> Result<u32> {
+    let mut dec = Decoder::header(buf, MsgType::Rwrite)?;
+    dec.u32()
+}
+
+/// Encodes a `Tgetattr` request asking only for the file's size.
+pub(crate) fn encode_getattr(buf: &mut [u8], fid: u32) -> EncodeResult<usize> {
+    let mut enc = Encoder::new(buf);
+    enc.u32(fid)?.u64(GETATTR_SIZE)?;
+    Ok(enc.finish(MsgType::Tgetattr, TAG))
+}
+
+/// Decodes an `Rgetattr` reply, returning the file's size in bytes. This skips over every field that
+/// comes before `size` in the reply, since this driver never asks for anything else.
+pub(crate) fn decode_getattr(buf: &[u8]) -> Result<u64> {
+    let mut dec = Decoder::header(buf, MsgType::Rgetattr)?;
+    let _valid = dec.u64()?;
+    dec.bytes(13)?; // qid
+    let _mode = dec.u32()?;
+    let _uid = dec.u32()?;
+    let _gid = dec.u32()?;
+    let _nlink = dec.u64()?;
+    let _rdev = dec.u64()?;
+    dec.u64() // size
+}
+
+/// Encodes a `Tclunk` request, releasing `fid`.
+pub(crate) fn encode_clunk(buf: &mut [u8], fid: u32) -> EncodeResult<usize> {
+    let mut enc = Encoder::new(buf);
+    enc.u32(fid)?;
+    Ok(enc.finish(MsgType::Tclunk, TAG))
+}
+
+/// Decodes an `Rclunk` reply.
+pub(crate) fn decode_clunk(buf: &[u8]) -> Result<()> {
+    Decoder::header(buf, MsgType::Rclunk)?;
+    Ok(())
+}
@@ -0,0 +1,202 @@
+/* Copyright (C) 2023 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! This module gives every `Text` variant a stable, wording-independent numeric code and a
+//! coarse severity classification, so a host tool (or the kernel's own panic/abort paths) can
+//! make decisions about a diagnostic without string-matching the message it's displayed with.
+//!
+//! Codes are grouped by subsystem in blocks of 1000, the same way rustc groups its `Exxxx`
+//! error codes:
+//!
+//! * `0000..0999`: general/kernel-wide
+//! * `1000..1999`: ELF loading
+//! * `2000..2999`: the GIC interrupt controller
+//! * `3000..3999`: paging and virtual-address handling
+//! * `4000..4999`: I/O
+//! * `5000..5999`: the physical-memory allocator
+//! * `6000..6999`: device/bus setup
+//! * `7000..7999`: executable loading (above the file-format layer)
+//!
+//! A variant's code never changes once assigned, even if variants are added or removed elsewhere
+//! in the list, so a host-side decoder can hard-code a table without needing to match the
+//! kernel's exact build.
+
+use super::Text;
+
+/// How serious a `Text` diagnostic is, independent of the words used to describe it.
+///
+/// This has an explicit `repr(u8)` so `wire::encode` can write it as a single byte without a
+/// separate translation table.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The condition is purely informative; nothing went wrong.
+    Informational = 0,
+    /// The condition is an error, but the caller that observed it can recover (e.g. by returning
+    /// an `Err` to its own caller) without the kernel's state being compromised.
+    Recoverable = 1,
+    /// The condition means continuing would be unsound or the kernel's invariants have already
+    /// been violated. Whoever observes this should treat it as guaranteed to abort or panic.
+    Fatal = 2
+}
+
+impl<'a> Text<'a> {
+    /// Returns a stable numeric code for this diagnostic, grouped by subsystem. See the module
+    /// documentation for the group ranges. This code is meant to be decoded by a host tool that
+    /// doesn't want to depend on the exact wording of the kernel's messages.
+    pub const fn code(&self) -> u32 {
+        match self {
+            Text::KernelRoOverlapsRw(..) => 0,
+            Text::KernelSymbolMisaligned(..) => 1,
+            Text::PhoenixVersionHomepage(..) => 2,
+            Text::UnexpectedKernelError(..) => 3,
+            Text::KernelBacktrace(..) => 4,
+
+            Text::Elf32BitOn64Bit => 1000,
+            Text::Elf64BitOn32Bit => 1001,
+            Text::ElfArchExtFound => 1002,
+            Text::ElfBadSegAlign(..) => 1003,
+            // 1004 and 1010 used to belong to ElfBigOnLittle and ElfLittleOnBig, which no longer
+            // exist now that the loader handles both endiannesses. Those codes are retired, not
+            // reassigned.
+            Text::ElfEntryPointNotInSegment => 1005,
+            Text::ElfHeaderTooSmall(..) => 1006,
+            Text::ElfInterpretedInterp => 1007,
+            Text::ElfInvalidFile(..) => 1008,
+            Text::ElfInvalidSegmentFlags(..) => 1009,
+            Text::ElfMultipleHeaders(..) => 1034,
+            Text::ElfNotDlib => 1011,
+            Text::ElfNotExecutable => 1012,
+            Text::ElfPHEntriesTooSmall(..) => 1013,
+            Text::ElfSegmentMisaligned(..) => 1014,
+            Text::ElfSegmentsOverlap => 1015,
+            Text::ElfSHEntriesTooSmall(..) => 1016,
+            Text::ElfShLibFound => 1017,
+            Text::ElfUnsupportedVersion(..) => 1018,
+            Text::ElfUnsupportedAbi(..) => 1019,
+            Text::ElfUnsupportedArmAbi(..) => 1020,
+            Text::ElfUnsupportedArchitecture(..) => 1021,
+            Text::ElfUnsupportedEndianness(..) => 1022,
+            Text::ElfUnsupportedFileType(..) => 1023,
+            Text::ElfUnsupportedFlags(..) => 1024,
+            Text::ElfUnsupportedPtrSize(..) => 1025,
+            Text::ElfUnsupportedSegmentType(..) => 1026,
+            Text::ElfUnwindFound => 1027,
+            Text::ElfWrongMagicNumber(..) => 1028,
+            Text::ElfZeroSizedPH => 1029,
+            Text::ElfUnsupportedSectionType(..) => 1030,
+            Text::ElfBadSectionAlign(..) => 1031,
+            Text::ElfSectionMisaligned(..) => 1032,
+            Text::ElfUnsupportedRelocType(..) => 1033,
+            Text::ElfUnsupportedCompression(..) => 1035,
+
+            Text::GicCouldntReserveCpuIntBlock => 2000,
+            Text::GicCouldntReserveDistBlock => 2001,
+            Text::GicIrqOutOfBounds(..) => 2002,
+            Text::GicReadUnreadableCpuIntReg(..) => 2003,
+            Text::GicReadUnreadableDistReg(..) => 2004,
+            Text::GicWriteUnwritableCpuIntReg(..) => 2005,
+            Text::GicWriteUnwritableDistReg(..) => 2006,
+
+            Text::Aarch64UnrecognizedPhysAddrSize(..) => 3000,
+            Text::AddrTransLvlDoesntExist(..) => 3001,
+            Text::AddrUsesTooManyBits(..) => 3002,
+            Text::PageEntryInvalid(..) => 3003,
+            Text::PageSizeDifferent(..) => 3004,
+            Text::PageTableEntryInvalid(..) => 3005,
+            Text::PagesBaseMisaligned(..) => 3006,
+            Text::PagesPhysBaseMisaligned(..) => 3007,
+            Text::PagesSizeMisaligned(..) => 3008,
+            Text::PagesVirtBaseMisaligned(..) => 3009,
+            Text::TooFewAddressableBits(..) => 3010,
+            Text::TooManyAddressableBits(..) => 3011,
+
+            Text::HostedCouldntCloseFile(..) => 4000,
+            Text::IoErrAddrInUse => 4001,
+            Text::IoErrAddrNotAvailable => 4002,
+            Text::IoErrAlreadyExists => 4003,
+            Text::IoErrBrokenPipe => 4004,
+            Text::IoErrConnectionAborted => 4005,
+            Text::IoErrConnectionRefused => 4006,
+            Text::IoErrConnectionReset => 4007,
+            Text::IoErrInterrupted => 4008,
+            Text::IoErrInvalidData => 4009,
+            Text::IoErrInvalidInput => 4010,
+            Text::IoErrNotConnected => 4011,
+            Text::IoErrNotFound => 4012,
+            Text::IoErrOther => 4013,
+            Text::IoErrPermissionDenied => 4014,
+            Text::IoErrTimedOut => 4015,
+            Text::IoErrUnexpectedEof => 4016,
+            Text::IoErrWouldBlock => 4017,
+            Text::IoErrWriteZero => 4018,
+            Text::ReadPastBuffer => 4019,
+
+            Text::LoadSegmentAllocErr(..) => 5000,
+            Text::OutOfMemory(..) => 5001,
+            Text::TriedToFreeNothing(..) => 5002,
+            Text::TriedToShrinkNothing(..) => 5003,
+
+            Text::CouldntReserveDeviceResource(..) => 6000,
+            Text::GpioCouldntReserveRegs => 6001,
+            Text::MmioBusOutOfBounds(..) => 6002,
+            Text::Uart0CouldntReserveMmio => 6003,
+
+            Text::LoadSegmentOutOfBounds => 7000
+        }
+    }
+
+    /// Returns how serious this diagnostic is. Panic/abort paths can use this to decide whether
+    /// a condition is guaranteed fatal without having to re-inspect each variant themselves.
+    pub const fn severity(&self) -> Severity {
+        match self {
+            Text::PhoenixVersionHomepage(..) | Text::KernelBacktrace(..) => Severity::Informational,
+
+            Text::UnexpectedKernelError(..)
+                | Text::KernelRoOverlapsRw(..)
+                | Text::KernelSymbolMisaligned(..)
+                | Text::GicCouldntReserveCpuIntBlock
+                | Text::GicCouldntReserveDistBlock
+                | Text::GicReadUnreadableCpuIntReg(..)
+                | Text::GicReadUnreadableDistReg(..)
+                | Text::GicWriteUnwritableCpuIntReg(..)
+                | Text::GicWriteUnwritableDistReg(..)
+                | Text::GpioCouldntReserveRegs
+                | Text::Uart0CouldntReserveMmio
+                | Text::CouldntReserveDeviceResource(..)
+                | Text::MmioBusOutOfBounds(..)
+                | Text::OutOfMemory(..)
+                | Text::TriedToFreeNothing(..)
+                | Text::TriedToShrinkNothing(..)
+                | Text::Aarch64UnrecognizedPhysAddrSize(..)
+                | Text::AddrTransLvlDoesntExist(..)
+                | Text::AddrUsesTooManyBits(..)
+                | Text::TooFewAddressableBits(..)
+                | Text::TooManyAddressableBits(..)
+                | Text::PageEntryInvalid(..)
+                | Text::PageSizeDifferent(..)
+                | Text::PageTableEntryInvalid(..)
+                | Text::PagesBaseMisaligned(..)
+                | Text::PagesPhysBaseMisaligned(..)
+                | Text::PagesSizeMisaligned(..)
+                | Text::PagesVirtBaseMisaligned(..) => Severity::Fatal,
+
+            _ => Severity::Recoverable
+        }
+    }
+}
@@ -18,7 +18,7 @@
 
 use core::fmt;
 use core::panic::PanicInfo;
-use alloc::string::String;
+use alloc::{string::String, vec::Vec};
 
 #[derive(Debug)]
 pub enum Text<'a> {
@@ -29,17 +29,18 @@ pub enum Text<'a> {
     Elf32BitOn64Bit,
     Elf64BitOn32Bit,
     ElfArchExtFound,
+    ElfBadSectionAlign(&'a u64),
     ElfBadSegAlign(&'a u64),
-    ElfBigOnLittle,
     ElfEntryPointNotInSegment,
     ElfHeaderTooSmall(&'a usize, &'a u16),
     ElfInterpretedInterp,
     ElfInvalidFile(&'a String),
     ElfInvalidSegmentFlags(&'a u32),
-    ElfLittleOnBig,
+    ElfMultipleHeaders(&'a u32),
     ElfNotDlib,
     ElfNotExecutable,
     ElfPHEntriesTooSmall(&'a usize, &'a u16),
+    ElfSectionMisaligned(&'a u64, &'a u64),
     ElfSegmentMisaligned(&'a u64, &'a u64),
     ElfSegmentsOverlap,
     ElfSHEntriesTooSmall(&'a usize, &'a u16),
@@ -48,10 +49,13 @@ pub enum Text<'a> {
     ElfUnsupportedAbi(&'a u8),
     ElfUnsupportedArmAbi(&'a u32),
     ElfUnsupportedArchitecture(&'a u16),
+    ElfUnsupportedCompression(&'a u32),
     ElfUnsupportedEndianness(&'a u8),
     ElfUnsupportedFileType(&'a u16),
     ElfUnsupportedFlags(&'a u32),
     ElfUnsupportedPtrSize(&'a u8),
+    ElfUnsupportedRelocType(&'a u32),
+    ElfUnsupportedSectionType(&'a u32),
     ElfUnsupportedSegmentType(&'a u32),
     ElfUnwindFound,
     ElfWrongMagicNumber(&'a [u8; 4], &'a [u8; 4]),
@@ -83,6 +87,7 @@ pub enum Text<'a> {
     IoErrUnexpectedEof,
     IoErrWouldBlock,
     IoErrWriteZero,
+    KernelBacktrace(&'a Vec<String>),
     KernelRoOverlapsRw(&'a usize),
     KernelSymbolMisaligned(&'a &'static str),
     LoadSegmentAllocErr(&'a usize, &'a usize),
@@ -133,19 +138,21 @@ impl<'a> fmt::Display for Text<'a> {
             Text::Elf32BitOn64Bit                 => write!(f, "32-bit binary on a 64-bit system"),
             Text::Elf64BitOn32Bit                 => write!(f, "64-bit binary on a 32-bit system"),
             Text::ElfArchExtFound                 => write!(f, "section of reserved type PT_AARCH64_ARCHEXT found"),
+            Text::ElfBadSectionAlign(align)       => write!(f, "section alignment {:#x} is not a power of 2", align),
             Text::ElfBadSegAlign(align)           => write!(f, "segment alignment {:#x} is not a power of 2", align),
-            Text::ElfBigOnLittle                  => write!(f, "big-endian binary on a little-endian system"),
             Text::ElfEntryPointNotInSegment       => write!(f, "entry point not in a segment"),
             Text::ElfHeaderTooSmall(expected, actual)
                                                   => write!(f, "ELF header too small (expected {} bytes; found {})", expected, actual),
             Text::ElfInterpretedInterp            => write!(f, "interpreter must not be interpreted"),
             Text::ElfInvalidFile(desc)            => write!(f, "invalid ELF file: {}", desc),
             Text::ElfInvalidSegmentFlags(val)     => write!(f, "invalid segment flags {:#x}", val),
-            Text::ElfLittleOnBig                  => write!(f, "little-endian binary on a big-endian system"),
+            Text::ElfMultipleHeaders(val)          => write!(f, "segment type {:#x} appears more than once, but may appear at most once", val),
             Text::ElfNotDlib                      => write!(f, "not a dynamic library"),
             Text::ElfNotExecutable                => write!(f, "not an executable file"),
             Text::ElfPHEntriesTooSmall(expected, actual)
                                                   => write!(f, "program header entries too small (expected {} bytes; found {})", expected, actual),
+            Text::ElfSectionMisaligned(offset, addr)
+                                                  => write!(f, "section file offset {:#x} does not match image address {:#x}", offset, addr),
             Text::ElfSegmentMisaligned(offset, vaddr)
                                                   => write!(f, "segment file offset {:#x} does not match image address {:#x}", offset, vaddr),
             Text::ElfSegmentsOverlap              => write!(f, "segments overlap in memory"),
@@ -156,10 +163,13 @@ impl<'a> fmt::Display for Text<'a> {
             Text::ElfUnsupportedAbi(val)          => write!(f, "unsupported ABI {:#x}", val),
             Text::ElfUnsupportedArmAbi(val)       => write!(f, "unsupported ARM ABI {:#x}", val),
             Text::ElfUnsupportedArchitecture(val) => write!(f, "unsupported architecture {:#x}", val),
+            Text::ElfUnsupportedCompression(val)   => write!(f, "unsupported section compression type {:#x}", val),
             Text::ElfUnsupportedEndianness(val)   => write!(f, "unsupported endianness {:#x}", val),
             Text::ElfUnsupportedFileType(val)     => write!(f, "unsupported ELF file type {:#x}", val),
             Text::ElfUnsupportedFlags(val)        => write!(f, "unsupported ELF flags {:#x}", val),
             Text::ElfUnsupportedPtrSize(val)      => write!(f, "unsupported pointer size (ELF class) {:#x}", val),
+            Text::ElfUnsupportedRelocType(val)    => write!(f, "unsupported relocation type {:#x}", val),
+            Text::ElfUnsupportedSectionType(val)  => write!(f, "unsupported section type {:#x}", val),
             Text::ElfUnsupportedSegmentType(val)  => write!(f, "unsupported segment type {:#x}", val),
             Text::ElfUnwindFound                  => write!(f, "section of reserved type PT_AARCH64_UNWIND found"),
             Text::ElfWrongMagicNumber(expected, actual)
@@ -198,6 +208,12 @@ impl<'a> fmt::Display for Text<'a> {
             Text::IoErrUnexpectedEof              => write!(f, "unexpected end of file"),
             Text::IoErrWouldBlock                 => write!(f, "would block"),
             Text::IoErrWriteZero                  => write!(f, "write returned zero"),
+            Text::KernelBacktrace(frames)          => {
+                for (i, frame) in frames.iter().enumerate() {
+                    writeln!(f, "  {: >2}: {}", i, frame)?;
+                }
+                Ok(())
+            },
             Text::KernelRoOverlapsRw(bytes)       => write!(f, "kernel's read-only segments overlap the read-write segments by {:#x} bytes", bytes),
             Text::KernelSymbolMisaligned(symbol)  => write!(f, "kernel symbol {} not aligned to a page boundary", symbol),
             Text::LoadSegmentAllocErr(base, size) => write!(f, "unable to allocate a new segment of size {1:#x} at address {0:#x}", base, size),
@@ -75,9 +75,13 @@ extern crate alloc;
 
 use core::fmt;
 use core::panic::PanicInfo;
-use alloc::string::String;
+use alloc::{string::String, vec::Vec};
 
 #[macro_use] mod boilerplate;
+mod codes;
+pub mod wire;
+
+pub use codes::Severity;
 
 boilerplate! {
     pub enum Language {
@@ -95,17 +99,18 @@ boilerplate! {
         Elf32BitOn64Bit,
         Elf64BitOn32Bit,
         ElfArchExtFound,
+        ElfBadSectionAlign(align: u64),
         ElfBadSegAlign(align: u64),
-        ElfBigOnLittle,
         ElfEntryPointNotInSegment,
         ElfHeaderTooSmall(expected: usize, actual: u16),
         ElfInterpretedInterp,
         ElfInvalidFile(desc: String),
         ElfInvalidSegmentFlags(val: u32),
-        ElfLittleOnBig,
+        ElfMultipleHeaders(val: u32),
         ElfNotDlib,
         ElfNotExecutable,
         ElfPHEntriesTooSmall(expected: usize, actual: u16),
+        ElfSectionMisaligned(offset: u64, addr: u64),
         ElfSegmentMisaligned(offset: u64, vaddr: u64),
         ElfSegmentsOverlap,
         ElfSHEntriesTooSmall(expected: usize, actual: u16),
@@ -114,10 +119,13 @@ boilerplate! {
         ElfUnsupportedAbi(val: u8),
         ElfUnsupportedArmAbi(val: u32),
         ElfUnsupportedArchitecture(val: u16),
+        ElfUnsupportedCompression(val: u32),
         ElfUnsupportedEndianness(val: u8),
         ElfUnsupportedFileType(val: u16),
         ElfUnsupportedFlags(val: u32),
         ElfUnsupportedPtrSize(val: u8),
+        ElfUnsupportedRelocType(val: u32),
+        ElfUnsupportedSectionType(val: u32),
         ElfUnsupportedSegmentType(val: u32),
         ElfUnwindFound,
         ElfWrongMagicNumber(expected: [u8; 4], actual: [u8; 4]),
@@ -149,6 +157,9 @@ boilerplate! {
         IoErrUnexpectedEof,
         IoErrWouldBlock,
         IoErrWriteZero,
+        /// One line per stack frame, already formatted as `symbol+offset` (or a raw address if no
+        /// symbol covers it) by whoever captured the backtrace.
+        KernelBacktrace(frames: Vec<String>),
         KernelRoOverlapsRw(bytes: usize),
         KernelSymbolMisaligned(symbol: &'static str),
         LoadSegmentAllocErr(base: usize, size: usize),
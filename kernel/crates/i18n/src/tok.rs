@@ -26,17 +26,18 @@ pub enum Text<'a> {
     Elf32BitOn64Bit,
     Elf64BitOn32Bit,
     ElfArchExtFound,
+    ElfBadSectionAlign(&'a u64),
     ElfBadSegAlign(&'a u64),
-    ElfBigOnLittle,
     ElfEntryPointNotInSegment,
     ElfHeaderTooSmall(&'a usize, &'a u16),
     ElfInterpretedInterp,
     ElfInvalidFile(&'a String),
     ElfInvalidSegmentFlags(&'a u32),
-    ElfLittleOnBig,
+    ElfMultipleHeaders(&'a u32),
     ElfNotDlib,
     ElfNotExecutable,
     ElfPHEntriesTooSmall(&'a usize, &'a u16),
+    ElfSectionMisaligned(&'a u64, &'a u64),
     ElfSegmentMisaligned(&'a u64, &'a u64),
     ElfSegmentsOverlap,
     ElfSHEntriesTooSmall(&'a usize, &'a u16),
@@ -45,10 +46,13 @@ pub enum Text<'a> {
     ElfUnsupportedAbi(&'a u8),
     ElfUnsupportedArmAbi(&'a u32),
     ElfUnsupportedArchitecture(&'a u16),
+    ElfUnsupportedCompression(&'a u32),
     ElfUnsupportedEndianness(&'a u8),
     ElfUnsupportedFileType(&'a u16),
     ElfUnsupportedFlags(&'a u32),
     ElfUnsupportedPtrSize(&'a u8),
+    ElfUnsupportedRelocType(&'a u32),
+    ElfUnsupportedSectionType(&'a u32),
     ElfUnsupportedSegmentType(&'a u32),
     ElfUnwindFound,
     ElfWrongMagicNumber(&'a [u8; 4], &'a [u8; 4]),
@@ -165,20 +169,23 @@ impl<'a> fmt::Display for Text<'a> {
             Text::Elf32BitOn64Bit                 => write!(f, "lipu nanpa pi lili lili 32 li lon poki lawa pi lili lili 64"),
             Text::Elf64BitOn32Bit                 => write!(f, "lipu nanpa pi lili lili 64 li lon poki lawa pi lili lili 32"),
             Text::ElfArchExtFound                 => write!(f, "mi lukin e insa PT_AARCH64_ARCHEXT li ken ala kepeken e ona"),
+            Text::ElfBadSectionAlign(align)       => write!(f, "tomo ma pona {:#x} pi insa li mute mute ala pi 2", align),
             Text::ElfBadSegAlign(align)           => write!(f, "tomo ma pona {:#x} pi insa pali li mute mute ala pi 2", align),
-            Text::ElfBigOnLittle                  => write!(f, "lipu nanpa pi open suli li lon poki lawa pi open lili"),
             Text::ElfEntryPointNotInSegment       => write!(f, "ma open li lon ala insa pali"),
             Text::ElfHeaderTooSmall(expected, actual)
                                                   => write!(f, "open ELF li lili mute. (mi wile lili {} li lukin e lili {})", expected, actual),
             Text::ElfInterpretedInterp            => write!(f, "ijo li pali e ilo pi lipu pali"),
             Text::ElfInvalidFile(desc)            => write!(f, "lipu ELF li nasa: {}", desc),
             Text::ElfInvalidSegmentFlags(val)     => write!(f, "palisa lawa {:#x} pi insa pali li nasa", val),
-            Text::ElfLittleOnBig                  => write!(f, "lipu nanpa pi open lili li lon poki lawa pi open suli"),
+            Text::ElfMultipleHeaders(val)          => write!(f, "nasin tomo pi insa pali {:#x} li lon mute, taso ona li ken lon wan taso", val),
             Text::ElfNotDlib                      => write!(f, "ni li lipu pali kulupu ala"),
             Text::ElfNotExecutable                => write!(f, "ni li lipu pali ala"),
             Text::ElfPHEntriesTooSmall(expected, actual) => {
                 write!(f, "ijo lili pi open pi insa pali li lili mute. (mi wile lili {} li lukin e lili {})", expected, actual)
             },
+            Text::ElfSectionMisaligned(offset, addr) => {
+                write!(f, "ma pi insa lon lipu li {:#x}. ma pi ona lon tomo sona li {:#x}. tomo ma pona pi ona tu li ante", offset, addr)
+            },
             Text::ElfSegmentMisaligned(offset, vaddr) => {
                 write!(f, "ma pi insa pali lon lipu li {:#x}. ma pi ona lon tomo sona li {:#x}. tomo ma pona pi ona tu li ante", offset, vaddr)
             },
@@ -195,6 +202,9 @@ impl<'a> fmt::Display for Text<'a> {
             Text::ElfUnsupportedFileType(val)     => write!(f, "nasin tomo lipu ELF {:#x} li nasa", val),
             Text::ElfUnsupportedFlags(val)        => write!(f, "palisa lawa ELF {:#x} li nasa", val),
             Text::ElfUnsupportedPtrSize(val)      => write!(f, "suli pi nasin palisa (ijo ELF Class) {:#x} li nasa", val),
+            Text::ElfUnsupportedRelocType(val)    => write!(f, "nasin pi ante ma {:#x} li nasa", val),
+            Text::ElfUnsupportedSectionType(val)  => write!(f, "nasin tomo pi insa {:#x} li nasa", val),
+            Text::ElfUnsupportedCompression(val)   => write!(f, "nasin awen pi insa {:#x} li nasa", val),
             Text::ElfUnsupportedSegmentType(val)  => write!(f, "nasin tomo pi insa pali {:#x} li nasa", val),
             Text::ElfUnwindFound                  => write!(f, "mi lukin e insa PT_AARCH64_UNWIND li ken ala kepeken e ona"),
             Text::ElfWrongMagicNumber(expected, actual)
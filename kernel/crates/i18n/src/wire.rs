@@ -0,0 +1,278 @@
+/* Copyright (C) 2023 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! A compact, self-describing binary encoding for `Text` diagnostics, meant for a host tool that
+//! would rather decode a `code` and some typed fields than string-match formatted output. Each
+//! diagnostic becomes one length-prefixed frame:
+//!
+//! ```text
+//! [ length: u32 ][ code: u32 ][ severity: u8 ][ args... ]
+//! ```
+//!
+//! `length` counts every byte after itself, so a decoder can read four bytes, then read exactly
+//! that many more to have a whole frame in hand before it tries to interpret `code`. Every
+//! argument after that has a fixed layout of its own: integers are little-endian and exactly as
+//! wide as their Rust type, strings and the backtrace's list of frames are a `u32` length
+//! followed by that many bytes/elements, and an optional value is a `bool` byte followed by the
+//! value only when that byte is 1. None of this requires the
+//! reader to understand UTF-8 or track an allocator; it only has to know how many bytes to skip.
+//!
+//! This module doesn't decide *when* to use the binary channel instead of `Display`; see
+//! `hosted::arm::diag` for that.
+
+use alloc::{string::String, vec::Vec};
+use super::{Severity, Text};
+
+/// Implemented by every type that appears as a `Text` variant's argument, so `encode_args` can
+/// write each one down without a bespoke match arm per primitive type.
+pub trait Encode {
+    /// Appends this value's fixed-layout wire representation to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+macro_rules! impl_encode_le_bytes {
+    ($($t:ty),*) => {
+        $(impl Encode for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        })*
+    };
+}
+impl_encode_le_bytes!(u8, u16, u32, u64, usize, i64);
+
+impl Encode for [u8; 4] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+}
+
+impl Encode for *const u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as usize).encode(out);
+    }
+}
+
+impl<'s> Encode for &'s str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(x) => { true.encode(out); x.encode(out); },
+            None => false.encode(out)
+        }
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+}
+
+impl<'a> Text<'a> {
+    /// Encodes this diagnostic as a length-prefixed binary frame. See the module documentation
+    /// for the exact layout.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        self.code().encode(&mut body);
+        (self.severity() as u8).encode(&mut body);
+        self.encode_args(&mut body);
+
+        let mut frame = Vec::with_capacity(4 + body.len());
+        (body.len() as u32).encode(&mut frame);
+        frame.extend_from_slice(&body);
+        frame
+    }
+
+    /// Appends this variant's arguments (everything after the code and severity) to `out`, in
+    /// declaration order. Variants with no arguments write nothing.
+    fn encode_args(&self, out: &mut Vec<u8>) {
+        match self {
+            Text::Aarch64UnrecognizedPhysAddrSize(flags) => flags.encode(out),
+            Text::AddrTransLvlDoesntExist(level) => level.encode(out),
+            Text::AddrUsesTooManyBits(addr, expected_bits) => {
+                addr.encode(out);
+                expected_bits.encode(out);
+            },
+            Text::CouldntReserveDeviceResource(bus_type, base, size) => {
+                bus_type.encode(out);
+                base.encode(out);
+                size.encode(out);
+            },
+            Text::ElfBadSectionAlign(align) => align.encode(out),
+            Text::ElfBadSegAlign(align) => align.encode(out),
+            Text::ElfHeaderTooSmall(expected, actual) => {
+                expected.encode(out);
+                actual.encode(out);
+            },
+            Text::ElfInvalidFile(desc) => desc.encode(out),
+            Text::ElfInvalidSegmentFlags(val) => val.encode(out),
+            Text::ElfMultipleHeaders(val) => val.encode(out),
+            Text::ElfPHEntriesTooSmall(expected, actual) => {
+                expected.encode(out);
+                actual.encode(out);
+            },
+            Text::ElfSectionMisaligned(offset, addr) => {
+                offset.encode(out);
+                addr.encode(out);
+            },
+            Text::ElfSegmentMisaligned(offset, vaddr) => {
+                offset.encode(out);
+                vaddr.encode(out);
+            },
+            Text::ElfSHEntriesTooSmall(expected, actual) => {
+                expected.encode(out);
+                actual.encode(out);
+            },
+            Text::ElfUnsupportedVersion(val) => val.encode(out),
+            Text::ElfUnsupportedAbi(val) => val.encode(out),
+            Text::ElfUnsupportedArmAbi(val) => val.encode(out),
+            Text::ElfUnsupportedArchitecture(val) => val.encode(out),
+            Text::ElfUnsupportedCompression(val) => val.encode(out),
+            Text::ElfUnsupportedEndianness(val) => val.encode(out),
+            Text::ElfUnsupportedFileType(val) => val.encode(out),
+            Text::ElfUnsupportedFlags(val) => val.encode(out),
+            Text::ElfUnsupportedPtrSize(val) => val.encode(out),
+            Text::ElfUnsupportedRelocType(val) => val.encode(out),
+            Text::ElfUnsupportedSectionType(val) => val.encode(out),
+            Text::ElfUnsupportedSegmentType(val) => val.encode(out),
+            Text::ElfWrongMagicNumber(expected, actual) => {
+                expected.encode(out);
+                actual.encode(out);
+            },
+            Text::GicIrqOutOfBounds(irq, max_irq) => {
+                irq.encode(out);
+                max_irq.encode(out);
+            },
+            Text::GicReadUnreadableCpuIntReg(reg) => reg.encode(out),
+            Text::GicReadUnreadableDistReg(reg) => reg.encode(out),
+            Text::GicWriteUnwritableCpuIntReg(reg) => reg.encode(out),
+            Text::GicWriteUnwritableDistReg(reg) => reg.encode(out),
+            Text::HostedCouldntCloseFile(handle, errno) => {
+                handle.encode(out);
+                errno.encode(out);
+            },
+            Text::KernelBacktrace(frames) => frames.encode(out),
+            Text::KernelRoOverlapsRw(bytes) => bytes.encode(out),
+            Text::KernelSymbolMisaligned(symbol) => symbol.encode(out),
+            Text::LoadSegmentAllocErr(base, size) => {
+                base.encode(out);
+                size.encode(out);
+            },
+            Text::MmioBusOutOfBounds(base, size, parent_base, parent_size) => {
+                base.encode(out);
+                size.encode(out);
+                parent_base.encode(out);
+                parent_size.encode(out);
+            },
+            Text::OutOfMemory(size, align) => {
+                size.encode(out);
+                align.encode(out);
+            },
+            Text::PageEntryInvalid(entry) => entry.encode(out),
+            Text::PageSizeDifferent(expected, actual) => {
+                expected.encode(out);
+                actual.encode(out);
+            },
+            Text::PageTableEntryInvalid(entry) => entry.encode(out),
+            Text::PagesBaseMisaligned(base) => base.encode(out),
+            Text::PagesPhysBaseMisaligned(base) => base.encode(out),
+            Text::PagesSizeMisaligned(size) => size.encode(out),
+            Text::PagesVirtBaseMisaligned(base) => base.encode(out),
+            Text::PhoenixVersionHomepage(version, homepage) => {
+                version.encode(out);
+                homepage.encode(out);
+            },
+            Text::TooFewAddressableBits(expected, actual) => {
+                expected.encode(out);
+                actual.encode(out);
+            },
+            Text::TooManyAddressableBits(expected, actual) => {
+                expected.encode(out);
+                actual.encode(out);
+            },
+            Text::TriedToFreeNothing(base) => base.encode(out),
+            Text::TriedToShrinkNothing(base) => base.encode(out),
+            Text::UnexpectedKernelError(panic_info) => {
+                // `PanicInfo` has no stable binary layout of its own, so all the host gets is the
+                // same message `Display` would have shown, reusing `alloc`'s blanket `ToString`.
+                use alloc::string::ToString;
+                panic_info.to_string().encode(out);
+            },
+
+            // Every variant not listed above carries no arguments.
+            _ => {}
+        }
+    }
+}
+
+/// Renders a single `Text` diagnostic for some consumer. `Textual` wraps the existing `Display`
+/// impl; `Wire` produces the binary frame documented above. Code that doesn't care which channel
+/// is actually listening (see `hosted::arm::diag`) can stay generic over this trait instead of
+/// hard-coding one or the other.
+pub trait Render {
+    /// What this renderer produces.
+    type Output;
+
+    /// Renders `text`.
+    fn render(text: &Text) -> Self::Output;
+}
+
+/// Renders a `Text` the same way its `Display` impl always has: as a human-readable `String` in
+/// the current language.
+pub struct Textual;
+
+impl Render for Textual {
+    type Output = String;
+
+    fn render(text: &Text) -> String {
+        alloc::format!("{}", text)
+    }
+}
+
+/// Renders a `Text` as the binary frame described by this module.
+pub struct Wire;
+
+impl Render for Wire {
+    type Output = Vec<u8>;
+
+    fn render(text: &Text) -> Vec<u8> {
+        text.encode()
+    }
+}
@@ -42,7 +42,7 @@ use {
     libphoenix::{
         profiler, profiler_probe, profiler_setup,
         posix::errno::Errno,
-        syscall::TimeSelector,
+        syscall::{MemoryType, SharePermissions, TimeSelector},
     },
     collections::atomic::AtomicLinkedListSemaphore,
     devices::DEVICES,
@@ -55,7 +55,7 @@ use {
         virt::paging,
     },
     scheduler::{
-        process::SharedMemory,
+        process::{self, SharedMemory},
         Process, Thread, ThreadStatus,
     },
     shared::ffi_enum,
@@ -97,8 +97,8 @@ pub(super) fn handle_system_call(
         Ok(SystemCall::Memory_Free) => memory_free(thread, arg1),
         Ok(SystemCall::Memory_Alloc) => memory_alloc(thread, arg1, arg2, result1),
         Ok(SystemCall::Memory_AllocPhys) => memory_alloc_phys(thread, arg1, arg2, arg3, result),
-        Ok(SystemCall::Memory_AllocShared) => memory_alloc_shared(thread, arg1, result1),
-        Ok(SystemCall::Memory_AccessShared) => memory_access_shared(thread, arg1, arg2, result1),
+        Ok(SystemCall::Memory_AllocShared) => memory_alloc_shared(thread, arg1, arg2, arg3, result),
+        Ok(SystemCall::Memory_AccessShared) => memory_access_shared(thread, arg1, result),
         Ok(SystemCall::Memory_PageSize) => memory_page_size(result1),
 
         Ok(SystemCall::Time_NowUnix) => time_now_unix(thread, arg1, arg2, result1),
@@ -701,10 +701,13 @@ fn memory_alloc_phys(
 }
 
 // Allocates a block of memory containing `size` bytes with at least the given alignment. Returns
-// the virtual address of the block, or null on failure.
+// the virtual address of the block and an opaque handle to it, or (0, 0) on failure.
 //
-// Using this virtual address and the same `size`, a child process spawned after this system call
-// returns can gain access to the same block of memory by calling `memory_access_shared`.
+// The handle (not the address) is what a process should pass along to another process, e.g. as a
+// spawn argument or over a pipe, so that the other process can call `memory_access_shared` to gain
+// access to the same block. The kernel only recognizes handles it minted here itself, via
+// `process::register_share_handle`, but those are sequential integers that any process can
+// redeem, so a handle isn't secret from a process that counts through them.
 //
 // Freeing the memory is done in the usual way, by calling `memory_free`. The memory will remain
 // allocated until every process that has access to it has also freed it.
@@ -713,11 +716,28 @@ fn memory_alloc_phys(
 fn memory_alloc_shared(
     thread: Option<&mut Thread<File>>,
     size: usize,
-    mut userspace_addr: Volatile<&mut usize, WriteOnly>,
+    ty: usize,
+    perm: usize,
+    mut userspace_addr_and_handle: Volatile<&mut [usize; 2], WriteOnly>,
 ) -> Response {
     profiler_probe!(=> ENTRANCE);
     let thread = thread.expect("kernel thread attempted to allocate memory with a system call");
     let page_size = paging::page_size();
+    let permissions = SharePermissions::from(perm);
+
+    let Ok(ty) = MemoryType::try_from(ty) else {
+        userspace_addr_and_handle.write([0, 0]);
+        profiler_probe!(ENTRANCE);
+        return Response::eret()
+    };
+    // TODO: `memory::phys::RegionType` only distinguishes RAM from device memory, so
+    //       `WriteCombining` and `NonCacheable` can't be mapped precisely yet. That would require
+    //       adding more `MAIR_EL1` attribute indices and a richer region-type concept. For now,
+    //       fall back to the closest attributes we can actually express.
+    let region_type = match ty {
+        MemoryType::Cacheable | MemoryType::WriteCombining => memory::phys::RegionType::Ram,
+        MemoryType::NonCacheable | MemoryType::Device => memory::phys::RegionType::Mmio,
+    };
 
     // FIXME: Do this asynchronously. Memory allocation has unbounded time complexity, and we can't
     //        pre-empt the thread during a system call.
@@ -731,7 +751,7 @@ fn memory_alloc_shared(
 
     let root_page_table = thread.process.exec_image.page_table();
 
-    let virt_addr = match maybe_block {
+    let virt_addr_and_handle = match maybe_block {
         Some(block) => {
             if let Some(size) = NonZeroUsize::new(block.size()) {
                 // Scrub the pages.
@@ -742,46 +762,58 @@ fn memory_alloc_shared(
                 }
                 let block = block.assume_init();
 
+                // TODO: `RootPageTable::map` has no way to restrict a mapping to read-only or
+                //       non-executable, so `permissions` isn't enforced at the hardware level yet.
+                //       It's recorded in the handle table below so real enforcement can be added
+                //       here later without changing the handle-table API again. Userspace's
+                //       `SharedMemory` doesn't read it from there; it uses the permissions the
+                //       sender serialized alongside the handle.
                 match root_page_table.map(
                     block.base().as_addr_phys(),
                     None,
                     size,
-                    memory::phys::RegionType::Ram,
+                    region_type,
                 ) {
                     Ok(addr) => {
-                        match thread.process.shared_memory.insert_head(Box::new(Arc::new(SharedMemory::new(block, addr)))) {
+                        let mem = Arc::new(SharedMemory::new(block, addr));
+                        match thread.process.shared_memory.insert_head(Box::new(mem.clone())) {
                             Ok(()) => {},
                             Err(_shared_mem_record) => {
                                 // TODO
                                 todo!("prepare to retry without reallocating anything and return RetrySyscall");
                             },
                         };
-                        addr
+                        let handle = process::register_share_handle(mem, permissions);
+                        [addr, handle as usize]
                     },
-                    Err(()) => 0,
+                    Err(()) => [0, 0],
                 }
             } else {
-                0
+                [0, 0]
             }
         },
-        None => 0,
+        None => [0, 0],
     };
-    userspace_addr.write(virt_addr);
+    userspace_addr_and_handle.write(virt_addr_and_handle);
 
     profiler_probe!(ENTRANCE);
     Response::eret()
 }
 
-// Grants read-write access to a block of memory previously allocated via the `memory_alloc_shared`
-// system call. Returns the virtual address of the block, or null on failure.
+// Grants access to a block of memory previously allocated via the `memory_alloc_shared` system
+// call. The permissions recorded when the handle was minted are looked up but not applied: the
+// block is mapped the same way whatever they are. Returns the virtual address and size of the
+// block, or (0, 0) if `handle` isn't recognized.
 //
-// `addr` must be the value returned from `memory_alloc_shared`, and `size` must be the same size
-// that was provided to that system call. The address returned from `memory_access_shared` is not
-// guaranteed to be the same as the value of `addr`, since each process is in its own virtual
-// address space.
+// `handle` must be a handle returned from `memory_alloc_shared`, in this process or another one.
+// The address returned from `memory_access_shared` is not guaranteed to be the same as it was in
+// whatever process allocated the block, since each process is in its own virtual address space.
 //
-// The intent is for a parent process to call `memory_alloc_shared`, then spawn a child process,
-// which will then call `memory_access_shared` to open a communication channel with the parent.
+// The intent is for a parent process to call `memory_alloc_shared`, then hand the resulting handle
+// to a child process (e.g. as a spawn argument), which will then call `memory_access_shared` to
+// open a communication channel with the parent. Since the handle is meaningless outside this
+// table, a process that merely observes or guesses at addresses can't reach the block that way.
+// Handles themselves are sequential, though, and any process can redeem any of them.
 //
 // After gaining access to the memory, the process is responsible for eventually calling
 // `memory_free` on it, just as if it had allocated the memory itself. The memory will remain
@@ -790,41 +822,45 @@ fn memory_alloc_shared(
 // thread.
 fn memory_access_shared(
     thread: Option<&mut Thread<File>>,
-    addr: usize,
-    size: usize,
-    mut userspace_addr: Volatile<&mut usize, WriteOnly>,
+    handle: usize,
+    mut userspace_addr_and_len: Volatile<&mut [usize; 2], WriteOnly>,
 ) -> Response {
     profiler_probe!(=> ENTRANCE);
     let thread = thread.expect("kernel thread attempted to allocate memory with a system call");
 
-    let root_page_table = thread.process.exec_image.page_table();
-
-    userspace_addr.write(0); // In case the shared memory isn't found.
+    userspace_addr_and_len.write([0, 0]); // In case the handle isn't recognized.
 
-    for mem in thread.process.sharable_memory.iter() {
-        let Some(mem) = mem.upgrade() else { continue };
-
-        if mem.virt_addr != addr || mem.block.size() != size { continue }
+    let Some((mem, _permissions)) = process::redeem_share_handle(handle as u64) else {
+        profiler_probe!(ENTRANCE);
+        return Response::eret();
+    };
 
-        let Some(size) = NonZeroUsize::new(mem.block.size()) else { break };
-        let Ok(addr) = root_page_table.map(
-            mem.block.base().as_addr_phys(),
-            None,
-            size,
-            memory::phys::RegionType::Ram,
-        ) else { break };
+    let root_page_table = thread.process.exec_image.page_table();
+    let Some(size) = NonZeroUsize::new(mem.block.size()) else {
+        profiler_probe!(ENTRANCE);
+        return Response::eret();
+    };
+    // TODO: Enforce `_permissions` once `RootPageTable::map` can restrict a mapping's permissions.
+    // See the identical TODO in `memory_alloc_shared` above.
+    let Ok(addr) = root_page_table.map(
+        mem.block.base().as_addr_phys(),
+        None,
+        size,
+        memory::phys::RegionType::Ram,
+    ) else {
+        profiler_probe!(ENTRANCE);
+        return Response::eret();
+    };
 
-        match thread.process.shared_memory.insert_head(Box::new(mem.clone())) {
-            Ok(()) => {},
-            Err(_shared_mem_record) => {
-                // TODO
-                todo!("prepare to retry without reallocating anything and return RetrySyscall");
-            },
-        };
+    match thread.process.shared_memory.insert_head(Box::new(mem.clone())) {
+        Ok(()) => {},
+        Err(_shared_mem_record) => {
+            // TODO
+            todo!("prepare to retry without reallocating anything and return RetrySyscall");
+        },
+    };
 
-        userspace_addr.write(addr);
-        break
-    }
+    userspace_addr_and_len.write([addr, mem.block.size()]);
 
     profiler_probe!(ENTRANCE);
     Response::eret()
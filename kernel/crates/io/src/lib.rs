@@ -30,7 +30,9 @@ extern crate alloc;
 #[macro_use] extern crate shared;
 
 pub mod serial;
+mod read_buf;
 mod std;
+pub use read_buf::{BorrowedBuf, BorrowedCursor};
 pub use std::*;
 
 #[cfg(not(target_arch = "x86_64"))]
@@ -0,0 +1,180 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! This module gives readers a way to fill a caller's buffer without requiring every byte of it to
+//! be zeroed first, loosely mirroring `std::io`'s (currently unstable) `BorrowedBuf`/`BorrowedCursor`
+//! pair. A `BorrowedBuf` wraps a possibly-uninitialized `&mut [MaybeUninit<u8>]` and tracks two
+//! cursors into it: `filled`, the prefix that holds real data the buffer's owner has already seen,
+//! and `init`, the (always at least as long) prefix that's known to hold *some* valid bytes, even if
+//! they're leftovers from a previous read into the same buffer. `BorrowedBuf::unfilled` hands out a
+//! `BorrowedCursor` over the rest: an append-only view that a reader can use to add bytes without
+//! ever being able to see what, if anything, used to be there.
+
+use {
+    core::mem::MaybeUninit,
+    i18n::Text
+};
+
+/// A read buffer that doesn't require its backing memory to be zeroed before use. See the module
+/// documentation for how the `filled`/`init` cursors work.
+#[derive(Debug)]
+pub struct BorrowedBuf<'data> {
+    buf: &'data mut [MaybeUninit<u8>],
+    filled: usize,
+    init: usize
+}
+
+impl<'data> BorrowedBuf<'data> {
+    /// Wraps a buffer that may or may not already hold valid data. No bytes are assumed to be
+    /// filled or initialized.
+    pub fn new(buf: &'data mut [MaybeUninit<u8>]) -> Self {
+        BorrowedBuf { buf, filled: 0, init: 0 }
+    }
+
+    /// The total number of bytes this buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// The number of bytes that have been filled with real data so far.
+    pub fn len(&self) -> usize {
+        self.filled
+    }
+
+    /// Whether any bytes have been filled yet.
+    pub fn is_empty(&self) -> bool {
+        self.filled == 0
+    }
+
+    /// The bytes that have been filled so far.
+    pub fn filled(&self) -> &[u8] {
+        // SAFETY: every byte below `self.filled` was written by a `BorrowedCursor` before it
+        // advanced `self.filled` past it.
+        unsafe { &*(&self.buf[.. self.filled] as *const [MaybeUninit<u8>] as *const [u8]) }
+    }
+
+    /// Marks the buffer as holding no filled data, so it can be reused from the start. Bytes that
+    /// were already initialized stay initialized, so the next `unfilled()` cursor won't need to
+    /// zero them again before a reader can see them as a plain `&mut [u8]`.
+    pub fn clear(&mut self) -> &mut Self {
+        self.filled = 0;
+        self
+    }
+
+    /// Returns a cursor over the rest of the buffer, past whatever's already filled. The cursor
+    /// can only append data, never read whatever uninitialized (or stale) bytes used to be there.
+    pub fn unfilled<'cursor>(&'cursor mut self) -> BorrowedCursor<'cursor> {
+        let base = self.filled;
+        let tail_init = self.init - self.filled;
+        let (_, tail) = self.buf.split_at_mut(self.filled);
+        BorrowedCursor {
+            tail,
+            tail_init,
+            consumed: 0,
+            filled: &mut self.filled,
+            init: &mut self.init,
+            base
+        }
+    }
+}
+
+/// A write-only, append-only view over the unfilled tail of a `BorrowedBuf`. Writing through this
+/// cursor is the only way to extend the buffer's `filled` cursor, so a reader that's handed one can
+/// never observe bytes it didn't write itself.
+#[derive(Debug)]
+pub struct BorrowedCursor<'a> {
+    tail: &'a mut [MaybeUninit<u8>],
+    /// How many bytes at the front of `tail` are known to hold valid data already, whether from an
+    /// earlier `ensure_init`/`append` through this same cursor or left over from the buffer's
+    /// previous use.
+    tail_init: usize,
+    /// How many bytes of `tail` this cursor itself has filled.
+    consumed: usize,
+    filled: &'a mut usize,
+    init: &'a mut usize,
+    /// `self.filled`'s value when this cursor was created, i.e. `tail`'s offset into the buffer.
+    base: usize
+}
+
+impl<'a> BorrowedCursor<'a> {
+    /// How many more bytes this cursor can accept.
+    pub fn capacity(&self) -> usize {
+        self.tail.len() - self.consumed
+    }
+
+    /// Appends `buf` to the buffer.
+    ///
+    /// # Panics
+    /// Panics with `Text::ReadPastBuffer` if `buf` is longer than `capacity()`. A reader that
+    /// reports writing more bytes than the buffer can hold is corrupting memory, not just
+    /// returning less data than was asked for, so this can't be allowed to succeed quietly.
+    pub fn append(&mut self, buf: &[u8]) {
+        assert!(buf.len() <= self.capacity(), "{}", Text::ReadPastBuffer);
+        for (dst, &src) in self.tail[self.consumed ..].iter_mut().zip(buf) {
+            dst.write(src);
+        }
+        self.mark_filled(buf.len());
+    }
+
+    /// Zeroes however much of the remaining (unfilled) space, counted from the front, isn't
+    /// already known to be initialized, so that at least `n` bytes past what's been consumed can
+    /// be handed out as real `u8`s.
+    pub fn ensure_init(&mut self, n: usize) {
+        let target = usize::min(self.consumed + n, self.tail.len());
+        if target > self.tail_init {
+            for byte in &mut self.tail[self.tail_init .. target] {
+                byte.write(0);
+            }
+            self.tail_init = target;
+        }
+    }
+
+    /// Returns the part of the tail that's initialized but not yet filled, as a plain `&mut [u8]`,
+    /// for a reader that would rather write into a real slice than call `append` one buffer at a
+    /// time. Call `ensure_init` first if the reader needs more of the tail available than
+    /// whatever's already initialized.
+    pub fn init_mut(&mut self) -> &mut [u8] {
+        // SAFETY: every byte in `self.consumed .. self.tail_init` was initialized either by
+        // `ensure_init` or by a previous `append`/`advance` call on this cursor.
+        unsafe {
+            &mut *(&mut self.tail[self.consumed .. self.tail_init] as *mut [MaybeUninit<u8>] as *mut [u8])
+        }
+    }
+
+    /// Claims the first `n` bytes of the most recent `init_mut()` as real data.
+    ///
+    /// # Safety
+    /// The caller must actually have written `n` valid bytes into the front of the slice most
+    /// recently returned by `init_mut()`.
+    pub unsafe fn advance(&mut self, n: usize) {
+        assert!(self.consumed + n <= self.tail_init);
+        self.mark_filled(n);
+    }
+
+    fn mark_filled(&mut self, n: usize) {
+        self.consumed += n;
+        if self.consumed > self.tail_init {
+            self.tail_init = self.consumed;
+        }
+        *self.filled += n;
+        let absolute_init = self.base + self.tail_init;
+        if absolute_init > *self.init {
+            *self.init = absolute_init;
+        }
+    }
+}
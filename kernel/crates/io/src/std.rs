@@ -137,6 +137,47 @@ pub trait Read {
         Ok(())
     }
 
+    /// Reads bytes into the given cursor until it's either full or an error occurs, without
+    /// requiring the unfilled part of its buffer to be initialized first.
+    ///
+    /// # Returns
+    /// `Ok` once the reader decides to stop, which may be before the cursor is full (a reader
+    /// sitting on top of a stream that's reached EOF, for instance).
+    ///
+    /// `Err` if an error occurs. Whatever was appended to the cursor before the error is kept.
+    ///
+    /// The default implementation zeroes whatever of the cursor isn't already initialized and
+    /// calls `read`, so it's always correct but doesn't save any zeroing. A reader that can avoid
+    /// touching bytes it doesn't write (most of them can) should override this instead.
+    fn read_buf(&mut self, cursor: &mut crate::BorrowedCursor<'_>) -> Result<()> {
+        cursor.ensure_init(cursor.capacity());
+        let n = self.read(cursor.init_mut())?;
+        // SAFETY: `ensure_init` just initialized the entire remaining capacity, and `read`
+        // promises that the first `n` bytes of the buffer it was given now hold real data.
+        unsafe { cursor.advance(n); }
+        Ok(())
+    }
+
+    /// Reads bytes into the given cursor until it's completely full.
+    ///
+    /// # Returns
+    /// `Ok` after filling the rest of the cursor with no errors.
+    ///
+    /// `Err` if an error occurs, including `ErrorKind::UnexpectedEof` if the stream ends before
+    /// the cursor is full. Whatever was appended to the cursor before the error is kept.
+    fn read_buf_exact(&mut self, cursor: &mut crate::BorrowedCursor<'_>) -> Result<()> {
+        while cursor.capacity() > 0 {
+            let capacity_before = cursor.capacity();
+            match self.read_buf(cursor) {
+                Ok(()) if cursor.capacity() == capacity_before => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(())                                         => {},
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e)                                          => return Err(e)
+            }
+        }
+        Ok(())
+    }
+
     /// Returns an iterator over the bytes in this stream.
     fn bytes(self) -> Bytes<Self>
             where Self: Sized {
@@ -0,0 +1,292 @@
+/* Copyright (c) 2021 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! `#[derive(Serialize)]` and `#[derive(Deserialize)]`, as `serde_derive` provides for `serde`.
+//!
+//! A struct derive emits an impl that forwards to [`ipc::Serializer::object`]/
+//! [`ipc::Deserializer::object`], one field per name, in declaration order. An enum derive
+//! forwards to [`ipc::Serializer::variant`]/[`ipc::Deserializer::variant`] instead, one arm per
+//! variant, with the payload written through the `FieldSerializer`/`FieldDeserializer` each of
+//! those hands back: nothing for a unit variant, the one field for a newtype variant, or a nested
+//! object (numbered `"0"`, `"1"`, ... fields for a tuple variant, or named fields for a struct
+//! variant) otherwise. A field or variant can be given a wire name different from its Rust
+//! identifier with `#[serde(rename = "...")]`.
+
+use {
+    proc_macro::TokenStream,
+    quote::quote,
+    syn::{
+        parse_macro_input, Data, DeriveInput, Fields,
+        Attribute, Lit, Meta, NestedMeta
+    }
+};
+
+/// Reads the wire name an item should use: either the string given by a `#[serde(rename = "...")]`
+/// attribute, or `default` if there isn't one.
+fn wire_name(attrs: &[Attribute], default: String) -> String {
+    for attr in attrs {
+        if !attr.path.is_ident("serde") {
+            continue;
+        }
+        if let Ok(Meta::List(list)) = attr.parse_meta() {
+            for nested in list.nested.iter() {
+                if let NestedMeta::Meta(Meta::NameValue(name_value)) = nested {
+                    if name_value.path.is_ident("rename") {
+                        if let Lit::Str(s) = &name_value.lit {
+                            return s.value();
+                        }
+                    }
+                }
+            }
+        }
+    }
+    default
+}
+
+/// Derives `ipc::Serialize` for a struct or enum. See the module-level documentation.
+#[proc_macro_derive(Serialize, attributes(serde))]
+pub fn derive_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (field_idents, wire_names) = struct_field_names(&data.fields);
+            let indices: Vec<usize> = (0 .. field_idents.len()).collect();
+            quote! {
+                const FIELDS: &[&str] = &[ #(#wire_names),* ];
+                serializer.object(FIELDS.iter().copied(), |serializer, index| {
+                    match index {
+                        #( #indices => ::ipc::Serialize::serialize(&self.#field_idents, serializer), )*
+                        _ => unreachable!("object() gave a field index outside of FIELDS")
+                    }
+                })
+            }
+        },
+        Data::Enum(data) => {
+            let arms = data.variants.iter().enumerate().map(|(i, variant)| {
+                let variant_ident = &variant.ident;
+                let variant_wire_name = wire_name(&variant.attrs, variant_ident.to_string());
+                let variant_index = i as u32;
+                let (pattern, payload) = enum_variant_payload(&variant.fields);
+                quote! {
+                    #name::#variant_ident #pattern =>
+                        serializer.variant(#variant_index, #variant_wire_name, |field| { #payload })
+                }
+            });
+            quote! {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        },
+        Data::Union(_) => {
+            return syn::Error::new_spanned(&input, "Serialize cannot be derived for a union")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    let expanded = quote! {
+        impl ::ipc::Serialize for #name {
+            fn serialize<S: ::ipc::Serializer>(&self, serializer: &mut S) -> Result<(), ::ipc::SerializeError> {
+                #body
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// Derives `ipc::Deserialize` for a struct or enum. See the module-level documentation.
+#[proc_macro_derive(Deserialize, attributes(serde))]
+pub fn derive_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (field_idents, wire_names) = struct_field_names(&data.fields);
+            quote! {
+                #( let mut #field_idents = None; )*
+                deserializer.object(|deserializer, name| {
+                    match name {
+                        #( #wire_names => {
+                            #field_idents = Some(::ipc::Deserialize::deserialize(deserializer)?);
+                        }, )*
+                        _ => return Err(::ipc::DeserializeError::unknown_field(name))
+                    }
+                    Ok(())
+                })?;
+                Ok(#name {
+                    #( #field_idents: #field_idents.ok_or_else(|| ::ipc::DeserializeError::custom(
+                        concat!("missing field `", #wire_names, "`")
+                    ))? ),*
+                })
+            }
+        },
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let variant_ident = &variant.ident;
+                let variant_wire_name = wire_name(&variant.attrs, variant_ident.to_string());
+                let construct = enum_variant_construct(name, variant_ident, &variant.fields);
+                quote! { #variant_wire_name => Ok(#construct) }
+            });
+            quote! {
+                deserializer.variant(|_index, variant_name, field| {
+                    match variant_name {
+                        #(#arms,)*
+                        _ => Err(::ipc::DeserializeError::unknown_field(variant_name))
+                    }
+                })
+            }
+        },
+        Data::Union(_) => {
+            return syn::Error::new_spanned(&input, "Deserialize cannot be derived for a union")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    let expanded = quote! {
+        impl ::ipc::Deserialize for #name {
+            fn deserialize<D: ::ipc::Deserializer>(deserializer: &mut D) -> Result<Self, ::ipc::DeserializeError> {
+                #body
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// Returns every field's identifier (as both pattern and expression position) alongside its wire
+/// name, for a struct with named fields.
+fn struct_field_names(fields: &Fields) -> (Vec<&syn::Ident>, Vec<String>) {
+    let named = match fields {
+        Fields::Named(named) => named,
+        _ => panic!("Serialize/Deserialize can only be derived for a struct with named fields")
+    };
+    named.named.iter()
+        .map(|field| {
+            let ident = field.ident.as_ref().unwrap();
+            let wire_name = wire_name(&field.attrs, ident.to_string());
+            (ident, wire_name)
+        })
+        .unzip()
+}
+
+/// Builds the match pattern that binds a variant's fields and the expression that serializes them
+/// into the `field: FieldSerializer` a `variant` call hands to this arm.
+fn enum_variant_payload(fields: &Fields) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    match fields {
+        Fields::Unit => (quote!(), quote! { field.unit() }),
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            let pattern = quote!( (field_0) );
+            (pattern, quote! { field.newtype(field_0) })
+        },
+        Fields::Unnamed(unnamed) => {
+            let bindings: Vec<_> = (0 .. unnamed.unnamed.len())
+                .map(|i| quote::format_ident!("field_{}", i))
+                .collect();
+            let field_names: Vec<String> = (0 .. unnamed.unnamed.len()).map(|i| i.to_string()).collect();
+            let indices: Vec<usize> = (0 .. unnamed.unnamed.len()).collect();
+            let pattern = quote!( ( #(#bindings),* ) );
+            let payload = quote! {
+                const FIELDS: &[&str] = &[ #(#field_names),* ];
+                field.r#struct(FIELDS.iter().copied(), |serializer, index| {
+                    match index {
+                        #( #indices => ::ipc::Serialize::serialize(#bindings, serializer), )*
+                        _ => unreachable!("object() gave a field index outside of FIELDS")
+                    }
+                })
+            };
+            (pattern, payload)
+        },
+        Fields::Named(named) => {
+            let idents: Vec<_> = named.named.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+            let wire_names: Vec<String> = named.named.iter()
+                .map(|f| wire_name(&f.attrs, f.ident.as_ref().unwrap().to_string()))
+                .collect();
+            let indices: Vec<usize> = (0 .. idents.len()).collect();
+            let pattern = quote!( { #(#idents),* } );
+            let payload = quote! {
+                const FIELDS: &[&str] = &[ #(#wire_names),* ];
+                field.r#struct(FIELDS.iter().copied(), |serializer, index| {
+                    match index {
+                        #( #indices => ::ipc::Serialize::serialize(#idents, serializer), )*
+                        _ => unreachable!("object() gave a field index outside of FIELDS")
+                    }
+                })
+            };
+            (pattern, payload)
+        }
+    }
+}
+
+/// Builds the expression that deserializes a variant's payload out of the `field:
+/// FieldDeserializer` a `variant` call hands to this arm, and constructs that variant.
+fn enum_variant_construct(enum_name: &syn::Ident, variant_ident: &syn::Ident, fields: &Fields) -> proc_macro2::TokenStream {
+    match fields {
+        Fields::Unit => quote! {
+            { field.unit()?; #enum_name::#variant_ident }
+        },
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => quote! {
+            #enum_name::#variant_ident(field.newtype()?)
+        },
+        Fields::Unnamed(unnamed) => {
+            let bindings: Vec<_> = (0 .. unnamed.unnamed.len())
+                .map(|i| quote::format_ident!("field_{}", i))
+                .collect();
+            let field_names: Vec<String> = (0 .. unnamed.unnamed.len()).map(|i| i.to_string()).collect();
+            quote! {
+                {
+                    #( let mut #bindings = None; )*
+                    field.r#struct(|deserializer, name| {
+                        match name {
+                            #( #field_names => { #bindings = Some(::ipc::Deserialize::deserialize(deserializer)?); }, )*
+                            _ => return Err(::ipc::DeserializeError::unknown_field(name))
+                        }
+                        Ok(())
+                    })?;
+                    #enum_name::#variant_ident( #( #bindings.ok_or_else(|| ::ipc::DeserializeError::custom(
+                        concat!("missing field `", #field_names, "`")
+                    ))? ),* )
+                }
+            }
+        },
+        Fields::Named(named) => {
+            let idents: Vec<_> = named.named.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+            let wire_names: Vec<String> = named.named.iter()
+                .map(|f| wire_name(&f.attrs, f.ident.as_ref().unwrap().to_string()))
+                .collect();
+            quote! {
+                {
+                    #( let mut #idents = None; )*
+                    field.r#struct(|deserializer, name| {
+                        match name {
+                            #( #wire_names => { #idents = Some(::ipc::Deserialize::deserialize(deserializer)?); }, )*
+                            _ => return Err(::ipc::DeserializeError::unknown_field(name))
+                        }
+                        Ok(())
+                    })?;
+                    #enum_name::#variant_ident { #( #idents: #idents.ok_or_else(|| ::ipc::DeserializeError::custom(
+                        concat!("missing field `", #wire_names, "`")
+                    ))? ),* }
+                }
+            }
+        }
+    }
+}
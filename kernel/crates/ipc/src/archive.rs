@@ -0,0 +1,349 @@
+/* Copyright (c) 2021 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! A zero-copy alternative to [`Deserialize`](super::Deserialize), modeled on `rkyv`. A type that
+//! implements [`Archive`] can be read back out of a received byte buffer in place, as a
+//! `&Self::Archived` view that borrows straight from those bytes, instead of being copied out into
+//! a fresh `Vec`/`String`/etc. This matters for a large payload where the receiver only ever reads
+//! a few of its fields.
+//!
+//! [`Archive::write_archived`] lays a value out so that works: every fixed-size field sits at a
+//! fixed byte offset, and anything variable-length (a string's bytes, a list's elements) is
+//! reached through a [`RelPtr`] -- an offset counted from the pointer's own position, not from the
+//! start of the buffer -- so the same bytes stay valid no matter where the buffer as a whole ends
+//! up loaded in memory. [`Archive::validate`] is the only place that's allowed to trust those
+//! offsets before they've been checked: it walks the whole value and confirms that every `RelPtr`
+//! stays inside the buffer and lands on a correctly aligned offset, failing with
+//! [`DeserializeError`] otherwise. Only once that pass has succeeded is it sound to hand out the
+//! typed reference [`Archive::archived`] (and so [`AccessArchived::access`]) returns.
+//!
+//! Because an archived value's fields are read through ordinary references instead of a decoding
+//! pass, this format uses the host's native byte order rather than the little-endian one
+//! [`default::Bytes`](super::default::Bytes) uses for portability: an archive is already tied to
+//! the address space that validated it, so there's no portability left to buy by fixing an
+//! endianness.
+
+use {
+    core::{
+        convert::{TryFrom, TryInto},
+        marker::PhantomData,
+        mem, slice, str
+    },
+    alloc::{string::String, vec::Vec},
+    super::{DeserializeError, SerializeError}
+};
+
+/// Pads `out` with zero bytes until its length is a multiple of `align`, so that whatever gets
+/// written next starts at a correctly aligned offset.
+fn pad_to_align(out: &mut Vec<u8>, align: usize) {
+    let pad = (align - out.len() % align) % align;
+    out.resize(out.len() + pad, 0);
+}
+
+/// An offset, in bytes, from the position just past this pointer to the data it refers to. This
+/// is what lets an archived value stay valid no matter where the buffer holding it is loaded: every
+/// address inside it is relative to somewhere else inside the same buffer, never absolute.
+#[repr(transparent)]
+pub struct RelPtr(i32);
+
+impl RelPtr {
+    /// Returns the absolute address this pointer refers to, computed from its own address. Only
+    /// sound to follow once the `RelPtr` it's called on has passed `validate_target`.
+    fn as_ptr(&self) -> *const u8 {
+        let base = self as *const Self as *const u8;
+        unsafe { base.add(mem::size_of::<Self>()).wrapping_offset(self.0 as isize) }
+    }
+
+    /// Checks that a `RelPtr` sitting at `bytes[pos..]` is aligned, fits inside `bytes`, and
+    /// refers to a position that's also inside `bytes`; returns that position (not a pointer, so
+    /// the caller can go on to validate whatever's actually there).
+    fn validate_target(bytes: &[u8], pos: usize) -> Result<usize, DeserializeError> {
+        if pos % mem::align_of::<Self>() != 0 {
+            return Err(DeserializeError::custom("relative pointer isn't correctly aligned"));
+        }
+        let end = pos.checked_add(mem::size_of::<Self>()).ok_or(DeserializeError::UnexpectedEof)?;
+        if end > bytes.len() {
+            return Err(DeserializeError::UnexpectedEof);
+        }
+        let offset = i32::from_ne_bytes(bytes[pos .. end].try_into().map_err(|_| DeserializeError::UnexpectedEof)?);
+        let target = if offset >= 0 {
+            end.checked_add(offset as usize)
+        } else {
+            end.checked_sub(offset.unsigned_abs() as usize)
+        }.ok_or(DeserializeError::UnexpectedEof)?;
+        if target > bytes.len() {
+            return Err(DeserializeError::UnexpectedEof);
+        }
+        Ok(target)
+    }
+}
+
+/// A type that can be read back out of a byte buffer without copying: an "archived" view directly
+/// into the bytes a [`write_archived`](Self::write_archived) call wrote, as opposed to the
+/// always-allocating [`Deserialize`](super::Deserialize) path. See the module-level documentation.
+pub trait Archive {
+    /// The archived representation of `Self`: a fixed-size type that can be read directly out of
+    /// a byte buffer (any variable-length data it owns is reached through a [`RelPtr`] instead of
+    /// being inline).
+    type Archived: Sized;
+
+    /// Appends `self`'s archived representation to `out`, which may already hold unrelated data:
+    /// the value's fixed-size fields are written (after whatever padding `Self::Archived`'s
+    /// alignment requires) starting at `out`'s current length, and any variable-length data they
+    /// point to is appended after them.
+    fn write_archived(&self, out: &mut Vec<u8>) -> Result<(), SerializeError>;
+
+    /// Checks that a valid `Self::Archived` sits at `bytes[pos..]`: `pos` itself, and every
+    /// `RelPtr` reachable from it, must be correctly aligned and stay inside `bytes`. Returns the
+    /// number of bytes `Self::Archived`'s fixed-size part occupies (always
+    /// `mem::size_of::<Self::Archived>()`; any variable-length data it points to lies elsewhere in
+    /// `bytes` and was already bounds-checked by this same call).
+    fn validate(bytes: &[u8], pos: usize) -> Result<usize, DeserializeError>;
+
+    /// Views the `Self::Archived` already validated at `bytes[pos..]`.
+    ///
+    /// # Safety
+    /// `(bytes, pos)` must have already passed [`validate`](Self::validate); this reads through
+    /// whatever offsets are there as though they were trusted.
+    unsafe fn archived(bytes: &[u8], pos: usize) -> &Self::Archived;
+}
+
+/// Validates and returns a zero-copy view of a `T` archived at the start of `bytes`. Implemented
+/// for every `T: Archive`, so it's never implemented by hand; this is the normal way to use
+/// [`Archive`].
+pub trait AccessArchived: Archive {
+    /// Validates `bytes` and returns the `Self::Archived` view into it.
+    fn access(bytes: &[u8]) -> Result<&Self::Archived, DeserializeError> {
+        Self::validate(bytes, 0)?;
+        Ok(unsafe { Self::archived(bytes, 0) })
+    }
+}
+
+impl<T: Archive> AccessArchived for T {}
+
+macro_rules! impl_archive_numeric {
+    ($ty:ty) => {
+        impl Archive for $ty {
+            type Archived = $ty;
+
+            fn write_archived(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+                pad_to_align(out, mem::align_of::<$ty>());
+                out.extend_from_slice(&self.to_ne_bytes());
+                Ok(())
+            }
+
+            fn validate(bytes: &[u8], pos: usize) -> Result<usize, DeserializeError> {
+                if pos % mem::align_of::<$ty>() != 0 {
+                    return Err(DeserializeError::custom(concat!(stringify!($ty), " isn't correctly aligned")));
+                }
+                let size = mem::size_of::<$ty>();
+                if pos.checked_add(size).ok_or(DeserializeError::UnexpectedEof)? > bytes.len() {
+                    return Err(DeserializeError::UnexpectedEof);
+                }
+                Ok(size)
+            }
+
+            unsafe fn archived(bytes: &[u8], pos: usize) -> &Self::Archived {
+                &*(bytes.as_ptr().add(pos) as *const $ty)
+            }
+        }
+    };
+}
+
+impl_archive_numeric!(u8);
+impl_archive_numeric!(u16);
+impl_archive_numeric!(u32);
+impl_archive_numeric!(u64);
+impl_archive_numeric!(i8);
+impl_archive_numeric!(i16);
+impl_archive_numeric!(i32);
+impl_archive_numeric!(i64);
+impl_archive_numeric!(f32);
+impl_archive_numeric!(f64);
+
+impl Archive for bool {
+    type Archived = bool;
+
+    fn write_archived(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        out.push(*self as u8);
+        Ok(())
+    }
+
+    fn validate(bytes: &[u8], pos: usize) -> Result<usize, DeserializeError> {
+        // A `&bool` must always point at a byte that's exactly 0 or 1; anything else is UB to
+        // form a reference to, so this is checked instead of just bounds-checking a `u8` would be.
+        match bytes.get(pos) {
+            Some(0) | Some(1) => Ok(mem::size_of::<bool>()),
+            _ => Err(DeserializeError::TypeMismatch { expected: "a bool (0 or 1)", found: "some other byte" })
+        }
+    }
+
+    unsafe fn archived(bytes: &[u8], pos: usize) -> &Self::Archived {
+        &*(bytes.as_ptr().add(pos) as *const bool)
+    }
+}
+
+/// The archived form of a [`String`]: a length and a [`RelPtr`] to its UTF-8 bytes.
+#[repr(C)]
+pub struct ArchivedString {
+    len: u32,
+    ptr: RelPtr
+}
+
+impl ArchivedString {
+    /// Views the archived string's content as an ordinary `&str`.
+    pub fn as_str(&self) -> &str {
+        let bytes = unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len as usize) };
+        // SAFETY: `Archive::validate` confirmed these bytes are valid UTF-8 before this reference
+        // could have been formed.
+        unsafe { str::from_utf8_unchecked(bytes) }
+    }
+}
+
+impl Archive for String {
+    type Archived = ArchivedString;
+
+    fn write_archived(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        pad_to_align(out, mem::align_of::<ArchivedString>());
+        let len = u32::try_from(self.len()).map_err(|_| SerializeError::custom("string is too long to archive"))?;
+        out.extend_from_slice(&len.to_ne_bytes());
+        let relptr_pos = out.len();
+        out.extend_from_slice(&0i32.to_ne_bytes()); // Patched below, once the tail's position is known.
+        let tail_pos = out.len();
+        out.extend_from_slice(self.as_bytes());
+        patch_relptr(out, relptr_pos, tail_pos)?;
+        Ok(())
+    }
+
+    fn validate(bytes: &[u8], pos: usize) -> Result<usize, DeserializeError> {
+        let footprint = mem::size_of::<ArchivedString>();
+        if pos % mem::align_of::<ArchivedString>() != 0 {
+            return Err(DeserializeError::custom("archived string isn't correctly aligned"));
+        }
+        if pos.checked_add(footprint).ok_or(DeserializeError::UnexpectedEof)? > bytes.len() {
+            return Err(DeserializeError::UnexpectedEof);
+        }
+        let len = u32::from_ne_bytes(bytes[pos .. pos + 4].try_into().map_err(|_| DeserializeError::UnexpectedEof)?) as usize;
+        let target = RelPtr::validate_target(bytes, pos + 4)?;
+        let target_end = target.checked_add(len).ok_or(DeserializeError::UnexpectedEof)?;
+        if target_end > bytes.len() {
+            return Err(DeserializeError::UnexpectedEof);
+        }
+        str::from_utf8(&bytes[target .. target_end]).map_err(|_| DeserializeError::InvalidUtf8)?;
+        Ok(footprint)
+    }
+
+    unsafe fn archived(bytes: &[u8], pos: usize) -> &Self::Archived {
+        &*(bytes.as_ptr().add(pos) as *const ArchivedString)
+    }
+}
+
+/// Patches the `RelPtr` placeholder written at `out[relptr_pos..]` so that it resolves to
+/// `target_pos`, now that `target_pos` is known.
+fn patch_relptr(out: &mut [u8], relptr_pos: usize, target_pos: usize) -> Result<(), SerializeError> {
+    let offset = i32::try_from(target_pos as isize - (relptr_pos as isize + mem::size_of::<RelPtr>() as isize))
+        .map_err(|_| SerializeError::custom("relative pointer offset doesn't fit in 32 bits"))?;
+    out[relptr_pos .. relptr_pos + mem::size_of::<RelPtr>()].copy_from_slice(&offset.to_ne_bytes());
+    Ok(())
+}
+
+/// The archived form of a `Vec<T>`: a length and a [`RelPtr`] to a table of `len` more `RelPtr`s,
+/// one per element, each resolving to that element's `T::Archived`. Going through a table of
+/// pointers rather than laying elements out back-to-back lets this support element types whose own
+/// archived form points to variable-length data of its own (like `ArchivedString`), since such an
+/// element's tail can then be written whenever is convenient without disturbing its neighbors'
+/// fixed positions.
+#[repr(C)]
+pub struct ArchivedVec<T: Archive> {
+    len: u32,
+    ptr: RelPtr,
+    _element: PhantomData<T>
+}
+
+impl<T: Archive> ArchivedVec<T> {
+    /// The number of elements in the archived list.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Whether the archived list has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Views the element at `index`, or `None` if it's out of bounds.
+    pub fn get(&self, index: usize) -> Option<&T::Archived> {
+        if index >= self.len() {
+            return None;
+        }
+        let slot = unsafe { self.ptr.as_ptr().add(index * mem::size_of::<RelPtr>()) };
+        // SAFETY: `Archive::validate` confirmed every slot in the table, and the element each one
+        // points to, before this reference could have been formed.
+        let slot = unsafe { &*(slot as *const RelPtr) };
+        Some(unsafe { &*(slot.as_ptr() as *const T::Archived) })
+    }
+}
+
+impl<T: Archive> Archive for Vec<T> {
+    type Archived = ArchivedVec<T>;
+
+    fn write_archived(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        pad_to_align(out, mem::align_of::<ArchivedVec<T>>());
+        let len = u32::try_from(self.len()).map_err(|_| SerializeError::custom("list is too long to archive"))?;
+        out.extend_from_slice(&len.to_ne_bytes());
+        let relptr_pos = out.len();
+        out.extend_from_slice(&0i32.to_ne_bytes()); // Patched below, once the table's position is known.
+
+        pad_to_align(out, mem::align_of::<RelPtr>());
+        let table_pos = out.len();
+        out.resize(table_pos + self.len() * mem::size_of::<RelPtr>(), 0);
+        patch_relptr(out, relptr_pos, table_pos)?;
+
+        for (index, value) in self.iter().enumerate() {
+            pad_to_align(out, mem::align_of::<T::Archived>());
+            let elem_pos = out.len();
+            value.write_archived(out)?;
+            patch_relptr(out, table_pos + index * mem::size_of::<RelPtr>(), elem_pos)?;
+        }
+        Ok(())
+    }
+
+    fn validate(bytes: &[u8], pos: usize) -> Result<usize, DeserializeError> {
+        let footprint = mem::size_of::<ArchivedVec<T>>();
+        if pos % mem::align_of::<ArchivedVec<T>>() != 0 {
+            return Err(DeserializeError::custom("archived list isn't correctly aligned"));
+        }
+        if pos.checked_add(footprint).ok_or(DeserializeError::UnexpectedEof)? > bytes.len() {
+            return Err(DeserializeError::UnexpectedEof);
+        }
+        let len = u32::from_ne_bytes(bytes[pos .. pos + 4].try_into().map_err(|_| DeserializeError::UnexpectedEof)?) as usize;
+        let table_pos = RelPtr::validate_target(bytes, pos + 4)?;
+        for index in 0 .. len {
+            let slot_pos = table_pos.checked_add(
+                index.checked_mul(mem::size_of::<RelPtr>()).ok_or(DeserializeError::UnexpectedEof)?
+            ).ok_or(DeserializeError::UnexpectedEof)?;
+            let elem_pos = RelPtr::validate_target(bytes, slot_pos)?;
+            T::validate(bytes, elem_pos)?;
+        }
+        Ok(footprint)
+    }
+
+    unsafe fn archived(bytes: &[u8], pos: usize) -> &Self::Archived {
+        &*(bytes.as_ptr().add(pos) as *const ArchivedVec<T>)
+    }
+}
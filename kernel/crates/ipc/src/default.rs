@@ -0,0 +1,485 @@
+/* Copyright (c) 2021 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! The one wire format this crate ships with: a flat byte buffer plus a side-channel table of
+//! handles, both of which are what actually travel between processes over an IPC channel.
+
+use {
+    core::{
+        any::Any,
+        convert::TryInto,
+        fmt,
+        mem::MaybeUninit
+    },
+    alloc::{
+        rc::{Rc, Weak},
+        string::String,
+        sync::{Arc, Weak as ArcWeak},
+        vec::Vec
+    },
+    libdriver::Resource,
+    super::{
+        Deserialize, DeserializeError, Deserializer, FieldDeserializer,
+        Serialize, SerializeError, Serializer, FieldSerializer
+    }
+};
+
+/// Writes values into a flat byte buffer, recording any handles it encounters into a side table
+/// that must travel alongside the buffer.
+#[derive(Debug, Default)]
+pub struct Bytes {
+    buf: Vec<u8>,
+    handles: Vec<Resource>,
+    once_seen: Vec<usize>
+}
+
+impl Bytes {
+    /// Creates an empty serializer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serializes `value` and returns the finished buffer and handle table.
+    pub fn serialize<T: Serialize>(value: &T) -> Result<(Vec<u8>, Vec<Resource>), SerializeError> {
+        let mut serializer = Self::new();
+        value.serialize(&mut serializer)?;
+        Ok((serializer.buf, serializer.handles))
+    }
+}
+
+impl Serializer for Bytes {
+    fn serialize_bool(&mut self, v: bool) -> Result<(), SerializeError> {
+        self.buf.push(v as u8);
+        Ok(())
+    }
+    fn serialize_u8(&mut self, v: u8) -> Result<(), SerializeError> {
+        self.buf.push(v);
+        Ok(())
+    }
+    fn serialize_u16(&mut self, v: u16) -> Result<(), SerializeError> {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_u32(&mut self, v: u32) -> Result<(), SerializeError> {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_u64(&mut self, v: u64) -> Result<(), SerializeError> {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_i8(&mut self, v: i8) -> Result<(), SerializeError> {
+        self.buf.push(v as u8);
+        Ok(())
+    }
+    fn serialize_i16(&mut self, v: i16) -> Result<(), SerializeError> {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_i32(&mut self, v: i32) -> Result<(), SerializeError> {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_i64(&mut self, v: i64) -> Result<(), SerializeError> {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_f32(&mut self, v: f32) -> Result<(), SerializeError> {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_f64(&mut self, v: f64) -> Result<(), SerializeError> {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_str(&mut self, v: &str) -> Result<(), SerializeError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+    fn serialize_bytes(&mut self, v: &[u8]) -> Result<(), SerializeError> {
+        self.serialize_u32(v.len() as u32)?;
+        self.buf.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn list<F>(&mut self, len: usize, mut serialize_elem: F) -> Result<(), SerializeError>
+        where F: FnMut(&mut Self, usize) -> Result<(), SerializeError>
+    {
+        self.serialize_u32(len as u32)?;
+        for index in 0 .. len {
+            serialize_elem(self, index)?;
+        }
+        Ok(())
+    }
+
+    fn object<'a, I, F>(&mut self, field_names: I, mut serialize_field: F) -> Result<(), SerializeError>
+        where I: Iterator<Item = &'a str>, F: FnMut(&mut Self, usize) -> Result<(), SerializeError>
+    {
+        let field_names: Vec<&'a str> = field_names.collect();
+        self.serialize_u32(field_names.len() as u32)?;
+        for (index, name) in field_names.iter().copied().enumerate() {
+            self.serialize_str(name)?;
+            serialize_field(self, index)?;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, h: &Resource) -> Result<(), SerializeError> {
+        let index = self.handles.len();
+        self.handles.push(Resource { bus: h.bus, base: h.base, size: h.size });
+        self.serialize_u32(index as u32)
+    }
+
+    fn serialize_once<F>(&mut self, ptr: usize, serialize: F) -> Result<(), SerializeError>
+        where F: FnOnce(&mut Self) -> Result<(), SerializeError>
+    {
+        match self.once_seen.iter().position(|&seen| seen == ptr) {
+            Some(index) => {
+                self.serialize_bool(false)?; // This is a back-reference, not a new value.
+                self.serialize_u32(index as u32)
+            },
+            None => {
+                let index = self.once_seen.len();
+                self.once_seen.push(ptr);
+                self.serialize_bool(true)?; // This is the first time this value is serialized.
+                self.serialize_u32(index as u32)?;
+                serialize(self)
+            }
+        }
+    }
+
+    fn serialize_weak(&mut self, ptr: Option<usize>) -> Result<(), SerializeError> {
+        match ptr.and_then(|ptr| self.once_seen.iter().position(|&seen| seen == ptr)) {
+            Some(index) => {
+                self.serialize_bool(true)?;
+                self.serialize_u32(index as u32)
+            },
+            None => self.serialize_bool(false)
+        }
+    }
+
+    fn variant<F>(&mut self, variant_index: u32, variant_name: &str, serialize: F) -> Result<(), SerializeError>
+        where F: FnOnce(FieldSerializer<Self>) -> Result<(), SerializeError>
+    {
+        self.serialize_u32(variant_index)?;
+        self.serialize_str(variant_name)?;
+        serialize(FieldSerializer { serializer: self })
+    }
+
+    fn archived_blob(&mut self, bytes: &[u8]) -> Result<(), SerializeError> {
+        self.serialize_bytes(bytes)
+    }
+}
+
+/// An entry in [`BytesReader`]'s `Rc` back-reference table: either still being filled in (a
+/// `Weak` to a placeholder allocation, so a cyclic back-reference resolves to it instead of
+/// recursing forever) or fully built.
+enum RcSlot {
+    /// Registered before its value finished deserializing; see [`BytesReader::deserialize_once`].
+    Reserved(Weak<dyn Any>),
+    /// Finished deserializing and safe to hand out as a strong reference.
+    Ready(Rc<dyn Any>)
+}
+
+/// The `Arc` counterpart to [`RcSlot`].
+enum ArcSlot {
+    /// Registered before its value finished deserializing; see
+    /// [`BytesReader::deserialize_once_arc`].
+    Reserved(ArcWeak<dyn Any + Send + Sync>),
+    /// Finished deserializing and safe to hand out as a strong reference.
+    Ready(Arc<dyn Any + Send + Sync>)
+}
+
+/// Reads values back out of a byte buffer written by [`Bytes`], resolving any handles against a
+/// handle table supplied by whatever delivered the message.
+pub struct BytesReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    handles: Option<&'a [Resource]>,
+    once_seen: Vec<RcSlot>,
+    once_seen_arc: Vec<ArcSlot>
+}
+
+impl<'a> fmt::Debug for BytesReader<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BytesReader")
+            .field("buf", &self.buf)
+            .field("pos", &self.pos)
+            .field("handles", &self.handles)
+            .field("once_seen_len", &self.once_seen.len())
+            .field("once_seen_arc_len", &self.once_seen_arc.len())
+            .finish()
+    }
+}
+
+impl<'a> BytesReader<'a> {
+    /// Creates a deserializer over `buf`, resolving handles against `handles`. Pass `None` if the
+    /// message never left this address space and so was never given a handle table; any attempt
+    /// to deserialize a handle will then fail instead of fabricating one.
+    pub fn new(buf: &'a [u8], handles: Option<&'a [Resource]>) -> Self {
+        BytesReader { buf, pos: 0, handles, once_seen: Vec::new(), once_seen_arc: Vec::new() }
+    }
+
+    /// Deserializes a `T` from `buf`, resolving handles against `handles`.
+    pub fn deserialize<T: Deserialize>(buf: &'a [u8], handles: Option<&'a [Resource]>) -> Result<T, DeserializeError> {
+        T::deserialize(&mut Self::new(buf, handles))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DeserializeError> {
+        if self.pos + len > self.buf.len() {
+            return Err(DeserializeError::UnexpectedEof);
+        }
+        let slice = &self.buf[self.pos .. self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+}
+
+impl<'a> Deserializer for BytesReader<'a> {
+    fn deserialize_bool(&mut self) -> Result<bool, DeserializeError> {
+        Ok(self.deserialize_u8()? != 0)
+    }
+    fn deserialize_u8(&mut self) -> Result<u8, DeserializeError> {
+        Ok(self.take(1)?[0])
+    }
+    fn deserialize_u16(&mut self) -> Result<u16, DeserializeError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+    fn deserialize_u32(&mut self) -> Result<u32, DeserializeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn deserialize_u64(&mut self) -> Result<u64, DeserializeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn deserialize_i8(&mut self) -> Result<i8, DeserializeError> {
+        Ok(self.take(1)?[0] as i8)
+    }
+    fn deserialize_i16(&mut self) -> Result<i16, DeserializeError> {
+        Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+    fn deserialize_i32(&mut self) -> Result<i32, DeserializeError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn deserialize_i64(&mut self) -> Result<i64, DeserializeError> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn deserialize_f32(&mut self) -> Result<f32, DeserializeError> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn deserialize_f64(&mut self) -> Result<f64, DeserializeError> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn deserialize_str(&mut self) -> Result<String, DeserializeError> {
+        let bytes = self.deserialize_bytes()?;
+        String::from_utf8(bytes).map_err(|_| DeserializeError::InvalidUtf8)
+    }
+    fn deserialize_bytes(&mut self) -> Result<Vec<u8>, DeserializeError> {
+        let len = self.deserialize_u32()? as usize;
+        Ok(Vec::from(self.take(len)?))
+    }
+
+    fn list<T, F>(&mut self, mut deserialize_elem: F) -> Result<Vec<T>, DeserializeError>
+        where F: FnMut(&mut Self) -> Result<T, DeserializeError>
+    {
+        let len = self.deserialize_u32()? as usize;
+        let mut values = Vec::with_capacity(len);
+        for _ in 0 .. len {
+            values.push(deserialize_elem(self)?);
+        }
+        Ok(values)
+    }
+
+    fn object<F>(&mut self, mut deserialize_field: F) -> Result<(), DeserializeError>
+        where F: FnMut(&mut Self, &str) -> Result<(), DeserializeError>
+    {
+        let field_count = self.deserialize_u32()?;
+        for _ in 0 .. field_count {
+            let name = self.deserialize_str()?;
+            deserialize_field(self, name.as_str())?;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self) -> Result<(Resource, usize), DeserializeError> {
+        let index = self.deserialize_u32()? as usize;
+        let handles = self.handles.ok_or_else(|| DeserializeError::custom(
+            "tried to deserialize a handle, but this message was never given a handle table"
+        ))?;
+        let resource = handles.get(index).ok_or_else(|| DeserializeError::custom(
+            "handle index is out of range of the handle table"
+        ))?;
+        Ok((Resource { bus: resource.bus, base: resource.base, size: resource.size }, index))
+    }
+
+    fn deserialize_once<T, F>(&mut self, deserialize: F) -> Result<Rc<T>, DeserializeError>
+        where T: Any, F: FnOnce(&mut Self) -> Result<T, DeserializeError>
+    {
+        let is_new = self.deserialize_bool()?;
+        let index = self.deserialize_u32()? as usize;
+        if is_new {
+            if index != self.once_seen.len() {
+                return Err(DeserializeError::custom(
+                    "back-reference index introduced a new value out of order"
+                ));
+            }
+            let uninit: Rc<MaybeUninit<T>> = Rc::try_new_uninit()?;
+            // SAFETY: `placeholder` reinterprets `uninit`'s allocation as already holding a `T`,
+            // which it doesn't yet -- but nothing reads through it (a `Weak` only tracks an
+            // allocation and its refcounts; it doesn't touch the value inside) until after
+            // `deserialize` returns below and `uninit` has actually been filled in, at which
+            // point every `Weak`/`Rc` sharing this allocation observes the real value. This is
+            // the same invariant `Rc::new_cyclic` relies on, for the same reason: it lets a value
+            // reachable from inside `deserialize` (e.g. a child holding a `Weak` back to this
+            // not-yet-finished parent, read via `deserialize_weak`) resolve to this allocation.
+            let placeholder: Rc<T> = unsafe { Rc::from_raw(Rc::into_raw(Rc::clone(&uninit)) as *const T) };
+            let weak: Weak<dyn Any> = Rc::downgrade(&placeholder);
+            self.once_seen.push(RcSlot::Reserved(weak));
+            drop(placeholder);
+            let value = deserialize(self)?;
+            // SAFETY: `uninit`'s strong count is 1 here (the only clone, `placeholder`, was
+            // dropped above), so nothing else can read through this pointer while we write. We
+            // can't use `Rc::get_mut` for this, though: it refuses to hand out `&mut` whenever
+            // *any* `Weak` points at the allocation, even one as inert as the one we just stashed
+            // in `self.once_seen`, so it would panic here unconditionally. Writing through a raw
+            // pointer instead sidesteps that check, the same way `Rc::new_cyclic` does internally.
+            unsafe { (Rc::as_ptr(&uninit) as *mut MaybeUninit<T>).write(MaybeUninit::new(value)); }
+            // SAFETY: `value` was just written above.
+            let rc: Rc<T> = unsafe { uninit.assume_init() };
+            self.once_seen[index] = RcSlot::Ready(rc.clone());
+            Ok(rc)
+        } else {
+            match self.once_seen.get(index).ok_or_else(|| DeserializeError::custom(
+                "back-reference index was never registered by a previous value"
+            ))? {
+                RcSlot::Ready(rc) => rc.clone().downcast().map_err(|_| DeserializeError::TypeMismatch {
+                    expected: core::any::type_name::<T>(),
+                    found: "a different type behind the same back-reference index"
+                }),
+                // A strong reference can't resolve to a value that hasn't finished deserializing;
+                // only a `Weak` one (see `deserialize_weak`) can point into a cycle like this.
+                RcSlot::Reserved(_) => Err(DeserializeError::custom(
+                    "strong back-reference points into a value that hasn't finished deserializing yet"
+                ))
+            }
+        }
+    }
+
+    fn deserialize_once_arc<T, F>(&mut self, deserialize: F) -> Result<Arc<T>, DeserializeError>
+        where T: Any + Send + Sync, F: FnOnce(&mut Self) -> Result<T, DeserializeError>
+    {
+        let is_new = self.deserialize_bool()?;
+        let index = self.deserialize_u32()? as usize;
+        if is_new {
+            if index != self.once_seen_arc.len() {
+                return Err(DeserializeError::custom(
+                    "back-reference index introduced a new value out of order"
+                ));
+            }
+            let uninit: Arc<MaybeUninit<T>> = Arc::try_new_uninit()?;
+            // SAFETY: see the comment in `deserialize_once` above; the same reasoning applies.
+            let placeholder: Arc<T> = unsafe { Arc::from_raw(Arc::into_raw(Arc::clone(&uninit)) as *const T) };
+            let weak: ArcWeak<dyn Any + Send + Sync> = Arc::downgrade(&placeholder);
+            self.once_seen_arc.push(ArcSlot::Reserved(weak));
+            drop(placeholder);
+            let value = deserialize(self)?;
+            // SAFETY: see the comment in `deserialize_once` above; the same reasoning applies.
+            unsafe { (Arc::as_ptr(&uninit) as *mut MaybeUninit<T>).write(MaybeUninit::new(value)); }
+            // SAFETY: `value` was just written above.
+            let arc: Arc<T> = unsafe { uninit.assume_init() };
+            self.once_seen_arc[index] = ArcSlot::Ready(arc.clone());
+            Ok(arc)
+        } else {
+            match self.once_seen_arc.get(index).ok_or_else(|| DeserializeError::custom(
+                "back-reference index was never registered by a previous value"
+            ))? {
+                ArcSlot::Ready(arc) => arc.clone().downcast().map_err(|_| DeserializeError::TypeMismatch {
+                    expected: core::any::type_name::<T>(),
+                    found: "a different type behind the same back-reference index"
+                }),
+                ArcSlot::Reserved(_) => Err(DeserializeError::custom(
+                    "strong back-reference points into a value that hasn't finished deserializing yet"
+                ))
+            }
+        }
+    }
+
+    fn deserialize_weak<T: Any>(&mut self) -> Result<Weak<T>, DeserializeError> {
+        let has_target = self.deserialize_bool()?;
+        if !has_target {
+            return Ok(Weak::new());
+        }
+        let index = self.deserialize_u32()? as usize;
+        let Some(slot) = self.once_seen.get(index) else {
+            return Ok(Weak::new()); // Never registered: dangling, not an error.
+        };
+        match slot {
+            RcSlot::Ready(rc) => {
+                let rc = rc.clone().downcast::<T>().map_err(|_| DeserializeError::TypeMismatch {
+                    expected: core::any::type_name::<T>(),
+                    found: "a different type behind the same back-reference index"
+                })?;
+                Ok(Rc::downgrade(&rc))
+            },
+            RcSlot::Reserved(weak) => {
+                // SAFETY: a `Reserved` slot's `Weak<dyn Any>` was itself downgraded from a
+                // `Weak<T>` in `deserialize_once` above, so its concrete type really is `T`.
+                // Cloning it first and reinterpreting the clone's raw pointer transfers that
+                // clone's own weak-count tick into the new handle, instead of fabricating an
+                // extra one out of thin air the way reinterpreting `weak` itself would.
+                let ptr = Weak::into_raw(Weak::clone(weak)) as *const T;
+                Ok(unsafe { Weak::from_raw(ptr) })
+            }
+        }
+    }
+
+    fn deserialize_weak_arc<T: Any + Send + Sync>(&mut self) -> Result<ArcWeak<T>, DeserializeError> {
+        let has_target = self.deserialize_bool()?;
+        if !has_target {
+            return Ok(ArcWeak::new());
+        }
+        let index = self.deserialize_u32()? as usize;
+        let Some(slot) = self.once_seen_arc.get(index) else {
+            return Ok(ArcWeak::new()); // Never registered: dangling, not an error.
+        };
+        match slot {
+            ArcSlot::Ready(arc) => {
+                let arc = arc.clone().downcast::<T>().map_err(|_| DeserializeError::TypeMismatch {
+                    expected: core::any::type_name::<T>(),
+                    found: "a different type behind the same back-reference index"
+                })?;
+                Ok(Arc::downgrade(&arc))
+            },
+            ArcSlot::Reserved(weak) => {
+                // SAFETY: see the comment in `deserialize_weak` above; the same reasoning applies.
+                let ptr = ArcWeak::into_raw(ArcWeak::clone(weak)) as *const T;
+                Ok(unsafe { ArcWeak::from_raw(ptr) })
+            }
+        }
+    }
+
+    fn variant<F, R>(&mut self, visit: F) -> Result<R, DeserializeError>
+        where F: FnOnce(u32, &str, FieldDeserializer<Self>) -> Result<R, DeserializeError>
+    {
+        let index = self.deserialize_u32()?;
+        let name = self.deserialize_str()?;
+        visit(index, name.as_str(), FieldDeserializer { deserializer: self })
+    }
+
+    fn archived_blob(&mut self) -> Result<Vec<u8>, DeserializeError> {
+        self.deserialize_bytes()
+    }
+}
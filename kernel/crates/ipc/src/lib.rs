@@ -0,0 +1,608 @@
+/* Copyright (c) 2021 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! This crate defines a wire format-independent way to serialize and deserialize the messages
+//! that processes send each other over IPC, in the spirit of `serde`. A message type implements
+//! [`Serialize`]/[`Deserialize`] against the [`Serializer`]/[`Deserializer`] traits instead of
+//! against any one encoding, so the same message type can be sent over whichever backend a given
+//! channel actually uses. The [`default`] module provides the one backend this crate ships with.
+//!
+//! Unlike `serde`, this framework also has to carry kernel handles (e.g. the [`Resource`]s a
+//! driver reserves from a [`Bus`](../devices/bus/trait.Bus.html)) across the IPC boundary. A
+//! handle can't be inlined into the byte stream the way a number or a string can, since the
+//! receiving process has to be handed the underlying kernel object, not a copy of its bits.
+//! Following the model of crosvm's `msg_socket2`, a handle is instead recorded into an ordered
+//! "handle table" that travels alongside the serialized bytes, and only its index is written into
+//! the main stream; see [`Serializer::handle`] and [`Deserializer::handle`].
+
+#![no_std]
+#![feature(allocator_api)]
+
+#![deny(warnings, missing_docs)]
+
+extern crate alloc;
+
+/// The default wire format: a flat byte buffer plus a side-channel handle table.
+pub mod default;
+/// A zero-copy alternative to [`Deserialize`] for large payloads. See [`archive::Archive`].
+pub mod archive;
+
+use {
+    core::{
+        any::Any,
+        fmt
+    },
+    alloc::{
+        alloc::AllocError,
+        format,
+        rc::{Rc, Weak},
+        string::String,
+        sync::{Arc, Weak as ArcWeak},
+        vec::Vec
+    },
+    error::Error,
+    libdriver::Resource
+};
+
+/// The error returned when a value can't be serialized.
+#[derive(Debug)]
+pub enum SerializeError {
+    /// The allocator ran out of memory while writing the value.
+    Alloc(AllocError),
+    /// Any other failure, carrying a human-readable description. Use [`SerializeError::custom`] to
+    /// build one, the way `serde::ser::Error::custom` does.
+    Custom(String)
+}
+
+impl SerializeError {
+    /// Builds a [`SerializeError::Custom`] out of anything that can describe itself.
+    pub fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerializeError::Custom(format!("{}", msg))
+    }
+}
+
+impl Error for SerializeError {}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SerializeError::Alloc(_) => write!(f, "ran out of memory while serializing a value"),
+            SerializeError::Custom(msg) => write!(f, "{}", msg)
+        }
+    }
+}
+
+impl From<AllocError> for SerializeError {
+    fn from(err: AllocError) -> Self {
+        SerializeError::Alloc(err)
+    }
+}
+
+/// The error returned when a value can't be deserialized.
+#[derive(Debug)]
+pub enum DeserializeError {
+    /// The buffer ended before a value that was expected to be there could be fully read.
+    UnexpectedEof,
+    /// A value didn't have the shape its type expected, e.g. a field whose declared and actual
+    /// wire-format types disagree.
+    TypeMismatch {
+        /// What the reader was trying to read.
+        expected: &'static str,
+        /// What it found instead.
+        found: &'static str
+    },
+    /// An object had a field, or an enum a variant, that the reader didn't recognize.
+    UnknownField(String),
+    /// A byte sequence that was supposed to be UTF-8 wasn't.
+    InvalidUtf8,
+    /// The allocator ran out of memory while building the deserialized value.
+    Alloc(AllocError),
+    /// Any other failure, carrying a human-readable description. Use [`DeserializeError::custom`]
+    /// to build one, the way `serde::de::Error::custom` does.
+    Custom(String)
+}
+
+impl DeserializeError {
+    /// Builds a [`DeserializeError::Custom`] out of anything that can describe itself.
+    pub fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeserializeError::Custom(format!("{}", msg))
+    }
+
+    /// Builds a [`DeserializeError::UnknownField`] naming the field or variant that wasn't
+    /// recognized.
+    pub fn unknown_field(name: &str) -> Self {
+        DeserializeError::UnknownField(String::from(name))
+    }
+}
+
+impl Error for DeserializeError {}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeserializeError::UnexpectedEof => write!(f, "buffer ended before a value could be fully read"),
+            DeserializeError::TypeMismatch { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            },
+            DeserializeError::UnknownField(name) => write!(f, "unrecognized field or variant `{}`", name),
+            DeserializeError::InvalidUtf8 => write!(f, "a string wasn't valid UTF-8"),
+            DeserializeError::Alloc(_) => write!(f, "ran out of memory while deserializing a value"),
+            DeserializeError::Custom(msg) => write!(f, "{}", msg)
+        }
+    }
+}
+
+impl From<AllocError> for DeserializeError {
+    fn from(err: AllocError) -> Self {
+        DeserializeError::Alloc(err)
+    }
+}
+
+/// A type that can write Rust values into some IPC wire format.
+///
+/// A `Serializer` implementation owns the actual encoding (see [`default::Bytes`]);
+/// [`Serialize`] impls are written against this trait so they don't need to know which encoding
+/// is in use.
+pub trait Serializer {
+    /// Serializes a `bool`.
+    fn serialize_bool(&mut self, v: bool) -> Result<(), SerializeError>;
+    /// Serializes a `u8`.
+    fn serialize_u8(&mut self, v: u8) -> Result<(), SerializeError>;
+    /// Serializes a `u16`.
+    fn serialize_u16(&mut self, v: u16) -> Result<(), SerializeError>;
+    /// Serializes a `u32`.
+    fn serialize_u32(&mut self, v: u32) -> Result<(), SerializeError>;
+    /// Serializes a `u64`.
+    fn serialize_u64(&mut self, v: u64) -> Result<(), SerializeError>;
+    /// Serializes an `i8`.
+    fn serialize_i8(&mut self, v: i8) -> Result<(), SerializeError>;
+    /// Serializes an `i16`.
+    fn serialize_i16(&mut self, v: i16) -> Result<(), SerializeError>;
+    /// Serializes an `i32`.
+    fn serialize_i32(&mut self, v: i32) -> Result<(), SerializeError>;
+    /// Serializes an `i64`.
+    fn serialize_i64(&mut self, v: i64) -> Result<(), SerializeError>;
+    /// Serializes an `f32`.
+    fn serialize_f32(&mut self, v: f32) -> Result<(), SerializeError>;
+    /// Serializes an `f64`.
+    fn serialize_f64(&mut self, v: f64) -> Result<(), SerializeError>;
+    /// Serializes a UTF-8 string.
+    fn serialize_str(&mut self, v: &str) -> Result<(), SerializeError>;
+    /// Serializes an arbitrary byte buffer.
+    fn serialize_bytes(&mut self, v: &[u8]) -> Result<(), SerializeError>;
+
+    /// Serializes a list of `len` values, calling `serialize_elem` once per element (with that
+    /// element's index) to write each one in turn.
+    fn list<F>(&mut self, len: usize, serialize_elem: F) -> Result<(), SerializeError>
+        where F: FnMut(&mut Self, usize) -> Result<(), SerializeError>;
+
+    /// Serializes an object with named fields. `field_names` enumerates every field, in
+    /// declaration order; `serialize_field` is called once per name, with that name's index into
+    /// `field_names`, and is responsible for serializing the corresponding field's value.
+    fn object<'a, I, F>(&mut self, field_names: I, serialize_field: F) -> Result<(), SerializeError>
+        where I: Iterator<Item = &'a str>, F: FnMut(&mut Self, usize) -> Result<(), SerializeError>;
+
+    /// Records `h` into this serializer's handle table and writes the resulting index into the
+    /// main byte stream in place of the handle itself. See the crate-level documentation for why
+    /// handles can't simply be inlined like every other value.
+    fn handle(&mut self, h: &Resource) -> Result<(), SerializeError>;
+
+    /// Serializes a value that may be reachable through more than one `Rc`/`Arc` pointer, writing
+    /// only a back-reference index on every call after the first one made with this same `ptr`
+    /// (which must uniquely identify the value, e.g. via `Rc::as_ptr`). `serialize` is called to
+    /// write the value itself only the first time `ptr` is seen.
+    fn serialize_once<F>(&mut self, ptr: usize, serialize: F) -> Result<(), SerializeError>
+        where F: FnOnce(&mut Self) -> Result<(), SerializeError>;
+
+    /// Serializes a `Weak` reference, as a back-reference to the index `ptr` was given by an
+    /// earlier [`serialize_once`](Serializer::serialize_once) call in the same message, or `None`
+    /// if the `Weak` had already expired or its value wasn't serialized earlier in this message (a
+    /// `serialize_once` call that comes later doesn't count). There's nothing to write eagerly here: unlike
+    /// `serialize_once`, a `Weak` never owns a value to serialize in the first place.
+    fn serialize_weak(&mut self, ptr: Option<usize>) -> Result<(), SerializeError>;
+
+    /// Serializes an enum variant. `variant_index` is the variant's position in its enum's
+    /// declaration (for formats that prefer a compact numeric tag) and `variant_name` is its wire
+    /// name (for formats that prefer a self-describing one); an implementation is free to write
+    /// either, both, or something more compact still (e.g. a fixed-width tag with no name at all)
+    /// as long as [`Deserializer::variant`] can read whatever it chooses. `serialize` writes the
+    /// variant's payload through the [`FieldSerializer`] it's given, distinguishing a unit variant
+    /// (no payload) from a newtype variant (one payload value) from a struct variant (a nested
+    /// object), so that an implementation can special-case each instead of always paying for the
+    /// most general encoding.
+    fn variant<F>(&mut self, variant_index: u32, variant_name: &str, serialize: F) -> Result<(), SerializeError>
+        where F: FnOnce(FieldSerializer<Self>) -> Result<(), SerializeError>;
+
+    /// Embeds a pre-built [`archive`] (see [`archive::Archive::write_archived`]) as an opaque
+    /// length-prefixed blob, for a payload large enough that the receiver would rather validate it
+    /// in place than pay to deserialize it into owned values.
+    fn archived_blob(&mut self, bytes: &[u8]) -> Result<(), SerializeError>;
+}
+
+/// The payload-writing half of [`Serializer::variant`]. Exactly one of [`unit`](Self::unit),
+/// [`newtype`](Self::newtype), or [`r#struct`](Self::struct) should be called, depending on which
+/// kind of payload the variant being serialized actually has.
+pub struct FieldSerializer<'a, S: Serializer + ?Sized> {
+    serializer: &'a mut S
+}
+
+impl<'a, S: Serializer + ?Sized> FieldSerializer<'a, S> {
+    /// Serializes a unit variant, which has no payload to write.
+    pub fn unit(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+
+    /// Serializes a newtype variant's single payload value.
+    pub fn newtype<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self.serializer)
+    }
+
+    /// Serializes a struct variant's payload as a nested object. See
+    /// [`Serializer::object`] for what `field_names` and `serialize_field` mean.
+    pub fn r#struct<'b, I, F>(self, field_names: I, serialize_field: F) -> Result<(), SerializeError>
+        where I: Iterator<Item = &'b str>, F: FnMut(&mut S, usize) -> Result<(), SerializeError>
+    {
+        self.serializer.object(field_names, serialize_field)
+    }
+}
+
+/// A type that can read Rust values back out of some IPC wire format.
+///
+/// See [`Serializer`] for why `Deserialize` impls are written against this trait instead of
+/// against a concrete encoding.
+pub trait Deserializer {
+    /// Deserializes a `bool`.
+    fn deserialize_bool(&mut self) -> Result<bool, DeserializeError>;
+    /// Deserializes a `u8`.
+    fn deserialize_u8(&mut self) -> Result<u8, DeserializeError>;
+    /// Deserializes a `u16`.
+    fn deserialize_u16(&mut self) -> Result<u16, DeserializeError>;
+    /// Deserializes a `u32`.
+    fn deserialize_u32(&mut self) -> Result<u32, DeserializeError>;
+    /// Deserializes a `u64`.
+    fn deserialize_u64(&mut self) -> Result<u64, DeserializeError>;
+    /// Deserializes an `i8`.
+    fn deserialize_i8(&mut self) -> Result<i8, DeserializeError>;
+    /// Deserializes an `i16`.
+    fn deserialize_i16(&mut self) -> Result<i16, DeserializeError>;
+    /// Deserializes an `i32`.
+    fn deserialize_i32(&mut self) -> Result<i32, DeserializeError>;
+    /// Deserializes an `i64`.
+    fn deserialize_i64(&mut self) -> Result<i64, DeserializeError>;
+    /// Deserializes an `f32`.
+    fn deserialize_f32(&mut self) -> Result<f32, DeserializeError>;
+    /// Deserializes an `f64`.
+    fn deserialize_f64(&mut self) -> Result<f64, DeserializeError>;
+    /// Deserializes a UTF-8 string.
+    fn deserialize_str(&mut self) -> Result<String, DeserializeError>;
+    /// Deserializes an arbitrary byte buffer.
+    fn deserialize_bytes(&mut self) -> Result<Vec<u8>, DeserializeError>;
+
+    /// Deserializes a list of values, calling `deserialize_elem` once per element until the list
+    /// is exhausted.
+    fn list<T, F>(&mut self, deserialize_elem: F) -> Result<Vec<T>, DeserializeError>
+        where F: FnMut(&mut Self) -> Result<T, DeserializeError>;
+
+    /// Deserializes an object with named fields, calling `deserialize_field` once per field
+    /// present in the stream with that field's name. It's up to the caller to match the name
+    /// against the fields it knows about and to notice afterward whether any required field never
+    /// showed up.
+    fn object<F>(&mut self, deserialize_field: F) -> Result<(), DeserializeError>
+        where F: FnMut(&mut Self, &str) -> Result<(), DeserializeError>;
+
+    /// Reads a handle index out of the main stream and resolves it against the handle table this
+    /// deserializer was given, returning the resolved `Resource` along with the index it was
+    /// found at. A deserializer that was never given a handle table (e.g. because the message
+    /// never left this address space) returns `DeserializeError` here rather than fabricate a
+    /// `Resource`.
+    fn handle(&mut self) -> Result<(Resource, usize), DeserializeError>;
+
+    /// Deserializes a value that may have been shared behind more than one `Rc` pointer on the
+    /// sending side. `deserialize` is called only the first time a given back-reference index is
+    /// encountered; every later reference to that index reuses the same `Rc`.
+    ///
+    /// The back-reference index is registered *before* `deserialize` runs, against a placeholder
+    /// allocation that isn't filled in until `deserialize` returns, so a cyclic object graph (e.g.
+    /// a value that holds a `Weak` back-reference to itself, read via
+    /// [`deserialize_weak`](Deserializer::deserialize_weak)) resolves to that in-progress
+    /// allocation instead of recursing forever. A *strong* back-reference into a value that hasn't
+    /// finished deserializing yet (as opposed to a `Weak` one) still fails with
+    /// [`DeserializeError`], since there's no value to hand back a real `Rc` to.
+    fn deserialize_once<T, F>(&mut self, deserialize: F) -> Result<Rc<T>, DeserializeError>
+        where T: Any, F: FnOnce(&mut Self) -> Result<T, DeserializeError>;
+
+    /// The `Arc` counterpart to [`deserialize_once`](Deserializer::deserialize_once), kept as a
+    /// separate method (and a separate back-reference table) because an `Arc`'s table has to hold
+    /// `Send + Sync` values, which an `Rc`'s never does.
+    fn deserialize_once_arc<T, F>(&mut self, deserialize: F) -> Result<Arc<T>, DeserializeError>
+        where T: Any + Send + Sync, F: FnOnce(&mut Self) -> Result<T, DeserializeError>;
+
+    /// Deserializes a `Weak` reference written by [`Serializer::serialize_weak`]. An index
+    /// registered by a [`deserialize_once`](Deserializer::deserialize_once) call, including one
+    /// still in progress (a cyclic back-reference to a value currently being built), resolves to
+    /// that value's allocation. If the index was never registered, or if there was no index at
+    /// all because the sender's `Weak` had already expired, this yields an ordinary dangling
+    /// `Weak` (as if from `Weak::new()`) rather than an error --
+    /// a dangling `Weak` is a perfectly ordinary value, not a broken one.
+    fn deserialize_weak<T: Any>(&mut self) -> Result<Weak<T>, DeserializeError>;
+
+    /// The `Arc` counterpart to [`deserialize_weak`](Deserializer::deserialize_weak), resolving
+    /// against the same table [`deserialize_once_arc`](Deserializer::deserialize_once_arc) fills.
+    fn deserialize_weak_arc<T: Any + Send + Sync>(&mut self) -> Result<ArcWeak<T>, DeserializeError>;
+
+    /// Deserializes an enum variant. `visit` is handed the variant's index and wire name (however
+    /// many of those the serializer actually wrote, the other coming back as a placeholder the
+    /// format can't recover) along with the [`FieldDeserializer`] that reads its payload, and
+    /// returns whatever value that variant should deserialize to.
+    fn variant<F, R>(&mut self, visit: F) -> Result<R, DeserializeError>
+        where F: FnOnce(u32, &str, FieldDeserializer<Self>) -> Result<R, DeserializeError>;
+
+    /// Reads back an opaque blob embedded by [`Serializer::archived_blob`], still in its archived
+    /// form; pass it to [`archive::AccessArchived::access`] to validate and view it.
+    fn archived_blob(&mut self) -> Result<Vec<u8>, DeserializeError>;
+}
+
+/// The payload-reading half of [`Deserializer::variant`]. Call whichever of
+/// [`unit`](Self::unit), [`newtype`](Self::newtype), or [`r#struct`](Self::struct) matches the
+/// variant that was actually read.
+pub struct FieldDeserializer<'a, D: Deserializer + ?Sized> {
+    deserializer: &'a mut D
+}
+
+impl<'a, D: Deserializer + ?Sized> FieldDeserializer<'a, D> {
+    /// Deserializes a unit variant, which has no payload to read.
+    pub fn unit(self) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    /// Deserializes a newtype variant's single payload value.
+    pub fn newtype<T: Deserialize>(self) -> Result<T, DeserializeError> {
+        T::deserialize(self.deserializer)
+    }
+
+    /// Deserializes a struct variant's payload out of a nested object. See
+    /// [`Deserializer::object`] for what `deserialize_field` means.
+    pub fn r#struct<F>(self, deserialize_field: F) -> Result<(), DeserializeError>
+        where F: FnMut(&mut D, &str) -> Result<(), DeserializeError>
+    {
+        self.deserializer.object(deserialize_field)
+    }
+}
+
+/// A type that can be written into an IPC message.
+pub trait Serialize {
+    /// Serializes `self` using the given serializer.
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError>;
+}
+
+/// A type that can be read back out of an IPC message.
+pub trait Deserialize: Sized {
+    /// Deserializes a value of this type using the given deserializer.
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError>;
+}
+
+// These share their names with the traits above on purpose: a derive macro and a trait live in
+// separate namespaces, so `#[derive(Serialize)]` and `dyn Serialize` don't conflict, exactly as
+// `serde_derive`'s `Serialize`/`Deserialize` don't conflict with `serde`'s.
+pub use ipc_derive::{Deserialize, Serialize};
+
+/// Implements `Serialize`/`Deserialize` for a struct by forwarding to a `Serializer`/
+/// `Deserializer`'s `object` method, field by field, in the order given.
+///
+/// This is the hand-written equivalent of `#[derive(Serialize, Deserialize)]`; write new message
+/// types with the derive instead where possible, and reach for this macro only where the derive
+/// doesn't apply.
+#[macro_export]
+macro_rules! serialize_object {
+    ($ty:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::Serialize for $ty {
+            fn serialize<S: $crate::Serializer>(&self, serializer: &mut S) -> Result<(), $crate::SerializeError> {
+                const FIELDS: &[&str] = &[$(stringify!($field)),*];
+                serializer.object(FIELDS.iter().copied(), |serializer, index| {
+                    #[allow(unused_mut, unused_assignments, unused_variables)]
+                    let mut i = 0usize;
+                    $(
+                        if index == i {
+                            return $crate::Serialize::serialize(&self.$field, serializer);
+                        }
+                        i += 1;
+                    )*
+                    unreachable!("object() gave a field index outside of FIELDS")
+                })
+            }
+        }
+
+        impl $crate::Deserialize for $ty {
+            fn deserialize<D: $crate::Deserializer>(deserializer: &mut D) -> Result<Self, $crate::DeserializeError> {
+                $(let mut $field = None;)*
+                deserializer.object(|deserializer, name| {
+                    match name {
+                        $(stringify!($field) => { $field = Some($crate::Deserialize::deserialize(deserializer)?); },)*
+                        _ => return Err($crate::DeserializeError::unknown_field(name))
+                    }
+                    Ok(())
+                })?;
+                Ok($ty {
+                    $($field: $field.ok_or_else(|| $crate::DeserializeError::custom(
+                        concat!("missing field `", stringify!($field), "`")
+                    ))?),*
+                })
+            }
+        }
+    };
+}
+
+macro_rules! impl_serde_primitive {
+    ($ty:ty, $serialize_method:ident, $deserialize_method:ident) => {
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+                serializer.$serialize_method(*self)
+            }
+        }
+
+        impl Deserialize for $ty {
+            fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+                deserializer.$deserialize_method()
+            }
+        }
+    };
+}
+
+impl_serde_primitive!(bool, serialize_bool, deserialize_bool);
+impl_serde_primitive!(u8, serialize_u8, deserialize_u8);
+impl_serde_primitive!(u16, serialize_u16, deserialize_u16);
+impl_serde_primitive!(u32, serialize_u32, deserialize_u32);
+impl_serde_primitive!(u64, serialize_u64, deserialize_u64);
+impl_serde_primitive!(i8, serialize_i8, deserialize_i8);
+impl_serde_primitive!(i16, serialize_i16, deserialize_i16);
+impl_serde_primitive!(i32, serialize_i32, deserialize_i32);
+impl_serde_primitive!(i64, serialize_i64, deserialize_i64);
+impl_serde_primitive!(f32, serialize_f32, deserialize_f32);
+impl_serde_primitive!(f64, serialize_f64, deserialize_f64);
+
+impl Serialize for str {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl Serialize for String {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl Deserialize for String {
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+        deserializer.deserialize_str()
+    }
+}
+
+impl<T: Serialize> Serialize for Vec<T> {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        serializer.list(self.len(), |serializer, index| self[index].serialize(serializer))
+    }
+}
+
+impl<T: Deserialize> Deserialize for Vec<T> {
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+        deserializer.list(|deserializer| T::deserialize(deserializer))
+    }
+}
+
+impl<T: Serialize> Serialize for Option<T> {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        match self {
+            None => serializer.variant(0, "None", |field| field.unit()),
+            Some(value) => serializer.variant(1, "Some", |field| field.newtype(value))
+        }
+    }
+}
+
+impl<T: Deserialize> Deserialize for Option<T> {
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+        deserializer.variant(|_index, name, field| {
+            match name {
+                "None" => { field.unit()?; Ok(None) },
+                "Some" => Ok(Some(field.newtype()?)),
+                _ => Err(DeserializeError::unknown_field(name))
+            }
+        })
+    }
+}
+
+impl<T: Serialize, E: Serialize> Serialize for Result<T, E> {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        match self {
+            Ok(value) => serializer.variant(0, "Ok", |field| field.newtype(value)),
+            Err(err) => serializer.variant(1, "Err", |field| field.newtype(err))
+        }
+    }
+}
+
+impl<T: Deserialize, E: Deserialize> Deserialize for Result<T, E> {
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+        deserializer.variant(|_index, name, field| {
+            match name {
+                "Ok" => Ok(Ok(field.newtype()?)),
+                "Err" => Ok(Err(field.newtype()?)),
+                _ => Err(DeserializeError::unknown_field(name))
+            }
+        })
+    }
+}
+
+impl Serialize for Resource {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        serializer.handle(self)
+    }
+}
+
+impl Deserialize for Resource {
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+        deserializer.handle().map(|(resource, _index)| resource)
+    }
+}
+
+impl<T: Serialize> Serialize for Rc<T> {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        serializer.serialize_once(Rc::as_ptr(self) as usize, |serializer| (**self).serialize(serializer))
+    }
+}
+
+impl<T: Deserialize + Any> Deserialize for Rc<T> {
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+        deserializer.deserialize_once(|deserializer| T::deserialize(deserializer))
+    }
+}
+
+impl<T: Serialize> Serialize for Arc<T> {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        serializer.serialize_once(Arc::as_ptr(self) as usize, |serializer| (**self).serialize(serializer))
+    }
+}
+
+impl<T: Deserialize + Any + Send + Sync> Deserialize for Arc<T> {
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+        deserializer.deserialize_once_arc(|deserializer| T::deserialize(deserializer))
+    }
+}
+
+impl<T> Serialize for Weak<T> {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        serializer.serialize_weak(self.upgrade().map(|rc| Rc::as_ptr(&rc) as usize))
+    }
+}
+
+impl<T: Any> Deserialize for Weak<T> {
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+        deserializer.deserialize_weak()
+    }
+}
+
+impl<T> Serialize for ArcWeak<T> {
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), SerializeError> {
+        serializer.serialize_weak(self.upgrade().map(|arc| Arc::as_ptr(&arc) as usize))
+    }
+}
+
+impl<T: Any + Send + Sync> Deserialize for ArcWeak<T> {
+    fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Self, DeserializeError> {
+        deserializer.deserialize_weak_arc()
+    }
+}
@@ -20,16 +20,15 @@
 //! with a GIC.
 
 use {
-    core::{
-        fmt::Debug,
-        mem,
-        sync::atomic::{AtomicU8, AtomicUsize, Ordering}
-    },
+    alloc::boxed::Box,
+    core::sync::atomic::{AtomicU8, Ordering},
 
+    collections::atomic::AtomicLinkedList,
     i18n::Text,
     io::printlndebug,
+    locks::Semaphore,
 
-    crate::{IsrFn, IsrResult}
+    crate::{IsrFn, IsrKind, IsrResult}
 };
 
 extern "Rust" {
@@ -38,10 +37,15 @@ extern "Rust" {
 
 /// A smart pointer to an interrupt service routine. When it is dropped, the ISR
 /// is unregistered.
+///
+/// `addr` identifies which of the (possibly several) ISRs registered for `irq` is this pointer's
+/// own: it's the address of the `RegisteredIsr` inside the `Box` that was handed to the list, which stays
+/// stable for as long as that entry remains in the list.
 #[derive(Debug, PartialEq, Eq)]
 #[must_use]
 pub struct IsrPtr {
-    irq: usize
+    irq: usize,
+    addr: usize
 }
 
 /// Describes the priority of an IRQ as compared to other IRQs. Higher-priority IRQs can pre-empt
@@ -77,74 +81,120 @@ pub enum IrqTrigger {
     Edge
 }
 
-struct IsrPtrNode {
-    // TODO: Turn this into a linked list to allow multiple ISRs for the same IRQ number.
-    isr: AtomicOptionIsrFnPtr
+// One ISR's registration: always a top half, plus the bottom half to queue if the top half
+// returns `IsrResult::WakeThread`.
+struct RegisteredIsr {
+    top_half: IsrFn,
+    bottom_half: Option<IsrFn>
 }
 
-// Provides atomic access to the equivalent of an `Option<IsrFn>`.
-struct AtomicOptionIsrFnPtr {
-    ptr: AtomicUsize // The function pointer as an integer
+impl From<IsrKind> for RegisteredIsr {
+    fn from(kind: IsrKind) -> Self {
+        match kind {
+            IsrKind::TopHalf(top_half) => RegisteredIsr { top_half, bottom_half: None },
+            IsrKind::Threaded { top_half, bottom_half } =>
+                RegisteredIsr { top_half, bottom_half: Some(bottom_half) }
+        }
+    }
 }
 
-/*impl IsrPtr {
-    fn deref(&self) -> IsrFn {
-        ISR_PTR_NODES.nodes[self.irq].isr.load(Ordering::Acquire).unwrap()
-    }
-}*/
+struct IsrPtrNode {
+    // A lock-free singly linked list of every ISR currently registered for this IRQ, so that more
+    // than one driver can share the same line.
+    isrs: Semaphore<AtomicLinkedList<RegisteredIsr>>
+}
 
 impl Drop for IsrPtr {
     fn drop(&mut self) {
-        // FIXME: Only unregister the ISR if this is the last `IsrPtr` referencing it.
-        // FIXME: Only disable the interrupt if this is the last ISR registered for it.
-
-        // Disable the interrupt in the GIC if it's not already disabled.
-        super::GIC.dist_regs.disable_irq(self.irq as usize);
+        // Remove just this handler...
+        ISR_PTR_NODES.remove_node(self.irq, self.addr);
 
-        // Remove the handler.
-        ISR_PTR_NODES.remove_node(self.irq);
+        // ...and disable the interrupt in the GIC only if no other handler is left for it.
+        if ISR_PTR_NODES.nodes[self.irq].isrs.is_empty() {
+            super::GIC.dist_regs.disable_irq(self.irq);
+        }
     }
 }
 
 impl IsrPtrNode {
     pub const fn new() -> IsrPtrNode {
         IsrPtrNode {
-            isr: AtomicOptionIsrFnPtr::new()
+            isrs: AtomicLinkedList::new()
         }
     }
 }
 
 // The data structure used to store all of the IsrPtrNodes.
 struct IsrPtrNodeHeap {
-    // TODO: We may need more information here to allow multiple ISRs for each IRQ.
     nodes: [IsrPtrNode; 1019]
 }
 
 impl IsrPtrNodeHeap {
-    // Inserts a node for the given IRQ/ISR pair and returns an error if the heap is full.
-    fn insert_node(&self, irq: usize, isr: IsrFn) -> Result<IsrPtr, ()> {
-        match self.nodes[irq].isr.compare_exchange(None, Some(isr), Ordering::AcqRel, Ordering::Acquire) {
-            Ok(_) => Ok(IsrPtr { irq }),
-            Err(existing) if existing == Some(isr) => Ok(IsrPtr { irq }),
-            Err(_) => Err(())
+    // Appends a new ISR to the given IRQ's list and returns a handle that identifies this
+    // particular registration.
+    fn insert_node(&self, irq: usize, isr: IsrKind) -> Result<IsrPtr, ()> {
+        let mut isr = Box::new(RegisteredIsr::from(isr));
+        loop {
+            let addr = &*isr as *const RegisteredIsr as usize;
+            match self.nodes[irq].isrs.insert_head(isr) {
+                Ok(()) => return Ok(IsrPtr { irq, addr }),
+                Err(returned) => isr = returned // Another visitor raced us for the head. Retry.
+            }
         }
     }
 
-    // Removes the node with the given IRQ.
-    fn remove_node(&self, irq: usize) {
-        self.nodes[irq].isr.store(None, Ordering::Release);
+    // Removes the entry identified by `addr` from the given IRQ's list, if it's still there.
+    fn remove_node(&self, irq: usize, addr: usize) {
+        loop {
+            let Ok(guard) = self.nodes[irq].isrs.try_access() else { continue };
+            let list: &AtomicLinkedList<RegisteredIsr> = &guard;
+
+            let mut pre_element = None;
+            let mut target = None;
+            for elem in list.iter() {
+                if &*elem as *const RegisteredIsr as usize == addr {
+                    target = Some(elem);
+                    break;
+                }
+                pre_element = Some(elem);
+            }
+
+            let Some(target) = target else { return }; // Already gone.
+
+            let removed = match pre_element {
+                Some(pre_element) => list.remove_after(&pre_element, target).is_ok(),
+                None => list.remove_head(target).is_ok()
+            };
+            if removed {
+                return;
+            }
+            // Someone else changed the list around us. Retry from the head.
+        }
     }
 
-    // Runs the ISRs registered with the given IRQ until the correct one is found.
+    // Runs every ISR registered with the given IRQ, in turn, until one of them handles it.
     fn handle_irq(&self, irq: usize) -> IsrResult {
-        // TODO: Make this able to handle multiple ISRs.
-        if let Some(isr) = self.nodes[irq].isr.load(Ordering::Acquire) {
-            // We have an ISR. Run it and see if it's correct.
-            match isr() {
+        let Ok(guard) = self.nodes[irq].isrs.try_access_weak() else {
+            // Every visitor slot is taken (e.g. by a concurrent registration). Treat this the same
+            // as not finding the right ISR; the interrupt controller will simply re-assert it.
+            return IsrResult::WrongIsr;
+        };
+
+        for isr in guard.iter() {
+            match (isr.top_half)() {
                 IsrResult::Serviced => return IsrResult::Serviced,
                 IsrResult::PreemptThread => return IsrResult::PreemptThread,
-                IsrResult::WrongIsr => {}
-            };
+                IsrResult::WakeThread => {
+                    // The bottom half does the real work, outside interrupt context. As far as
+                    // our caller (which only cares about finishing the top half and sending the
+                    // EOI) is concerned, this IRQ has been serviced.
+                    if let Some(bottom_half) = isr.bottom_half {
+                        queue_bottom_half(bottom_half);
+                    }
+                    return IsrResult::Serviced;
+                },
+                IsrResult::WrongIsr => {} // Try the next one.
+            }
         }
 
         // None of the ISRs were correct.
@@ -152,70 +202,55 @@ impl IsrPtrNodeHeap {
     }
 }
 
-impl AtomicOptionIsrFnPtr {
-    const NULL: usize = 0;
-
-    pub const fn new() -> Self {
-        AtomicOptionIsrFnPtr {
-            ptr: AtomicUsize::new(Self::NULL)
-        }
-    }
-
-    pub fn load(&self, order: Ordering) -> Option<IsrFn> {
-        Self::from_raw(self.ptr.load(order))
-    }
-
-    pub fn store(&self, val: Option<IsrFn>, order: Ordering) {
-        self.ptr.store(Self::to_raw(val), order)
-    }
-
-    pub fn compare_exchange(&self, current: Option<IsrFn>, new: Option<IsrFn>, success: Ordering, failure: Ordering)
-            -> Result<Option<IsrFn>, Option<IsrFn>> {
-        self.ptr.compare_exchange(Self::to_raw(current), Self::to_raw(new), success, failure)
-            .map(|raw| Self::from_raw(raw)).map_err(|raw| Self::from_raw(raw))
-    }
-
-    fn to_raw(val: Option<IsrFn>) -> usize {
-        match val {
-            None => Self::NULL,
-            Some(v) => unsafe { mem::transmute(v) }
-        }
-    }
+static ISR_PTR_NODES: IsrPtrNodeHeap = IsrPtrNodeHeap {
+    nodes: [const { IsrPtrNode::new() }; 1019]
+};
 
-    fn from_raw(raw: usize) -> Option<IsrFn> {
-        if raw == Self::NULL {
-            None
-        } else {
-            unsafe { Some(mem::transmute(raw)) }
-        }
-    }
+// One deferred bottom half, waiting to be run in thread context.
+struct BottomHalfWork {
+    handler: IsrFn
 }
 
-impl From<Option<IsrFn>> for AtomicOptionIsrFnPtr {
-    fn from(maybe_isr: Option<IsrFn>) -> Self {
-        Self { ptr: AtomicUsize::new(Self::to_raw(maybe_isr)) }
+// The queue of threaded ISRs' bottom halves that are waiting to run outside interrupt context.
+//
+// TODO: This should be one queue per CPU, so that a bottom half always runs close to the top half
+// that queued it. Phoenix doesn't have real per-CPU storage yet (see the hard-coded `cpu_index`
+// in `scheduler::run`), so for now there's just the one, shared, queue.
+static BOTTOM_HALVES: Semaphore<AtomicLinkedList<BottomHalfWork>> = AtomicLinkedList::new();
+
+// Queues a bottom half to run later, in thread context.
+fn queue_bottom_half(handler: IsrFn) {
+    let mut work = Box::new(BottomHalfWork { handler });
+    loop {
+        match BOTTOM_HALVES.insert_head(work) {
+            Ok(()) => return,
+            Err(returned) => work = returned // Another visitor raced us for the head. Retry.
+        }
     }
 }
 
-impl Debug for AtomicOptionIsrFnPtr {
-    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        if let Some(ptr) = self.load(Ordering::Acquire) {
-            write!(f, "Some({:p})", ptr as *const ())
-        } else {
-            write!(f, "None")
+/// Removes and returns one queued bottom half, if any are waiting.
+///
+/// The scheduler calls this on its own time, outside interrupt context, to run the heavy or
+/// blocking work that a threaded ISR's top half deferred with [`IsrResult::WakeThread`].
+pub fn take_bottom_half() -> Option<IsrFn> {
+    loop {
+        let Ok(guard) = BOTTOM_HALVES.try_access() else { continue };
+        let list: &AtomicLinkedList<BottomHalfWork> = &guard;
+
+        let Some(work) = list.head() else { return None };
+        match list.remove_head(work) {
+            Ok(work) => return Some(work.handler),
+            Err(_) => {} // Someone else changed the list around us. Retry from the head.
         }
     }
 }
 
-static ISR_PTR_NODES: IsrPtrNodeHeap = IsrPtrNodeHeap {
-    nodes: [const { IsrPtrNode::new() }; 1019]
-};
-
 /// Registers the given ISR to handle the given IRQ.
 ///
 /// # Returns
 /// A unique handle that will be unregistered when it's dropped.
-pub fn register_irq(irq: u64, isr: IsrFn, priority: Priority, trigger: IrqTrigger) -> Result<IsrPtr, ()> {
+pub fn register_irq(irq: u64, isr: IsrKind, priority: Priority, trigger: IrqTrigger) -> Result<IsrPtr, ()> {
     let max_irq = super::max_irq();
     if irq > max_irq {
         panic!("{}", Text::GicIrqOutOfBounds(irq, max_irq));
@@ -269,7 +304,10 @@ pub fn aarch64_handle_irq() -> u8 {
         IsrResult::WrongIsr => {
             printlndebug!("Could not handle IRQ {}: wrong ISR", intid);
             0
-        }
+        },
+        // `IsrPtrNodeHeap::handle_irq` already turns this into `Serviced` once it has queued the
+        // bottom half, so an ISR's own top half is the only place this should ever be seen.
+        IsrResult::WakeThread => unreachable!("a top half's IsrResult::WakeThread escaped handle_irq")
     };
 
     send_eoi(icc_iar);
@@ -31,6 +31,8 @@
 // TODO: Can we unit-test this module at all?
 #![cfg(not(feature = "unit-test"))]
 
+extern crate alloc;
+
 #[cfg(any(target_arch = "arm", target_arch = "armv5te", target_arch = "armv7", target_arch = "aarch64"))]
 mod arm;
 #[cfg(any(target_arch = "arm", target_arch = "armv5te", target_arch = "armv7", target_arch = "aarch64"))]
@@ -38,14 +40,36 @@ use self::arm as self_impl;
 
 pub use self::self_impl::interrupt_controller::irq::{
     register_irq,
+    take_bottom_half,
     IsrPtr,
     Priority,
     IrqTrigger
 };
 
-/// Any function that can be used as an ISR.
+/// Any function that can be used as an ISR, whether a top half or a bottom half.
 pub type IsrFn = fn() -> IsrResult;
 
+/// How an ISR should be invoked when its IRQ fires.
+#[derive(Debug, Clone, Copy)]
+pub enum IsrKind {
+    /// The ISR runs entirely in interrupt context. This is the simplest mode, and the only one
+    /// that existed before threaded IRQs were added; use it for handlers that are fast and never
+    /// need to block.
+    TopHalf(IsrFn),
+
+    /// `top_half` runs in interrupt context and should do only as much as it takes to acknowledge
+    /// the device and decide whether real work is needed. If it returns
+    /// [`IsrResult::WakeThread`], `bottom_half` is queued to run later in thread context (see
+    /// [`take_bottom_half`]), where it's free to do the heavier or blocking work that driver
+    /// authors shouldn't put in an ISR.
+    Threaded {
+        /// Runs in interrupt context, same as [`TopHalf`](Self::TopHalf).
+        top_half: IsrFn,
+        /// Runs later, in thread context, whenever `top_half` returns [`IsrResult::WakeThread`].
+        bottom_half: IsrFn
+    }
+}
+
 /// The required return value of an ISR. It exists in order to allow multiple devices to share the
 /// same IRQ if necessary, having only to deal with slower response times from the CPU instead of
 /// being completely unable to function.
@@ -57,5 +81,9 @@ pub enum IsrResult {
     /// The IRQ wasn't serviced because this was the wrong ISR.
     WrongIsr,
     /// The IRQ was successfully serviced, and the current thread should be pre-empted.
-    PreemptThread
+    PreemptThread,
+    /// The top half is done, but the real work needs to happen in thread context. Only meaningful
+    /// as the return value of an [`IsrKind::Threaded`] ISR's `top_half`; its `bottom_half` is
+    /// queued to run later, and the IRQ is considered serviced for this invocation.
+    WakeThread
 }
@@ -109,6 +109,17 @@ fn trampoline_end() -> usize   { unsafe { &__trampoline_end as *const _ as usize
 fn trampoline_virt() -> usize { unsafe { &__trampoline_virt as *const _ as usize } }
 fn trampoline_stacks_virt() -> usize { unsafe { &__trampoline_stacks_virt as *const _ as usize } }
 
+/// Returns true if `addr` falls within memory that's always mapped as part of the kernel image
+/// (its code, its read-write data, or the trampoline). This is a coarse check meant for
+/// validating a pointer (e.g. a frame pointer found while walking a backtrace) before
+/// dereferencing it, not a precise "is this exact byte mapped" query.
+pub fn addr_in_kernel_image(addr: usize) -> bool {
+    (addr >= readonly_start() && addr < readonly_end())
+        || (addr >= rw_shareable_start() && addr < rw_shareable_end())
+        || (addr >= rw_nonshareable_start() && addr < rw_nonshareable_end())
+        || (addr >= trampoline_start() && addr < trampoline_end())
+}
+
 const TRAMPOLINE_STACK_SIZE: usize = 1024;
 
 /// Converts the given pointer into one that points into the trampoline code (the code that is
@@ -46,6 +46,7 @@ use {
     },
     collections::{AtomicLinkedList, AtomicLinkedListSemaphore},
     io::printlndebug,
+    irqs,
     locks::Semaphore,
     shared::{/*count_cpus, cpu_index,*/ wait_for_event},
     fs::File,
@@ -116,6 +117,15 @@ pub fn run(mut thread_queue: ThreadQueue<File>) -> ! {
             };
         }
 
+        // Run any bottom halves that a threaded IRQ's top half deferred to thread context. This is
+        // normal thread-context code, not interrupt context, so it's free to block.
+        // TODO: Once Phoenix has a notion of kernel-only worker threads, run these there instead
+        // of borrowing the scheduler's own time; for now, this is the closest thing to "thread
+        // context" available.
+        while let Some(bottom_half) = irqs::take_bottom_half() {
+            bottom_half();
+        }
+
         // The rest of this loop should have a time complexity of O(1) in order to get back to the
         // running threads as soon as possible.
 
@@ -21,10 +21,13 @@
 //! The main type in this module is `Process`, and everything else attaches to it.
 
 use {
-    alloc::sync::Arc,
-    core::convert::TryInto,
-    libphoenix::process::ProcessId,
-    collections::atomic::AtomicLinkedList,
+    alloc::{boxed::Box, sync::Arc},
+    core::{
+        convert::TryInto,
+        sync::atomic::{AtomicU64, Ordering},
+    },
+    libphoenix::{process::ProcessId, syscall::SharePermissions},
+    collections::atomic::{AtomicLinkedList, AtomicLinkedListSemaphore},
     exec::ExecImage,
     io::{Read, Seek},
     locks::Semaphore,
@@ -42,14 +45,14 @@ pub struct Process<T: Read+Seek> {
     pub exec_image: ExecImage<T>,
 
     /// A record of all the memory this process might be sharing with another.
-    pub shared_memory: Semaphore<AtomicLinkedList<SharedMemory>>,
+    pub shared_memory: Semaphore<AtomicLinkedList<Arc<SharedMemory>>>,
 }
 
 impl<T: Read+Seek> Process<T> {
     /// Creates a new process.
     ///
     /// The new process won't have any threads. Call `Thread::new` to make one.
-    pub fn new(exec_image: ExecImage<T>, shared_memory: Semaphore<AtomicLinkedList<SharedMemory>>) -> Self {
+    pub fn new(exec_image: ExecImage<T>, shared_memory: Semaphore<AtomicLinkedList<Arc<SharedMemory>>>) -> Self {
         Self { exec_image, shared_memory }
     }
 
@@ -78,3 +81,43 @@ impl SharedMemory {
         Self { block, virt_addr }
     }
 }
+
+// A table that maps opaque handles to the shared memory blocks they were minted for. Handles are
+// taken from a single kernel-wide counter (1, 2, 3, ...), and any process that presents a minted
+// handle can redeem it: a handle isn't tied to a recipient, and nearby values are other handles.
+struct ShareRecord {
+    handle:      u64,
+    mem:         Arc<SharedMemory>,
+    permissions: SharePermissions,
+}
+
+static NEXT_SHARE_HANDLE: AtomicU64 = AtomicU64::new(1);
+
+// FIXME: Entries are never removed from this list, even after every process that redeemed one has
+// freed the underlying memory. See the identical FIXME on `shared_memory` in `memory_free` (in the
+// `int` crate's syscall handler).
+static SHARE_HANDLES: Semaphore<AtomicLinkedList<ShareRecord>> = AtomicLinkedList::new();
+
+/// Mints a new opaque handle for `mem` and registers it, together with the permissions that were
+/// granted over it, so that a later call to `redeem_share_handle`, potentially from another
+/// process that was given the handle, can look it up again.
+pub fn register_share_handle(mem: Arc<SharedMemory>, permissions: SharePermissions) -> u64 {
+    let handle = NEXT_SHARE_HANDLE.fetch_add(1, Ordering::Relaxed);
+    let mut record = Box::new(ShareRecord { handle, mem, permissions });
+    loop {
+        match SHARE_HANDLES.insert_head(record) {
+            Ok(()) => break,
+            Err(x) => record = x // We moved this into `insert_head`, so we need to move it back.
+        };
+    }
+    handle
+}
+
+/// Looks up the shared memory block and granted permissions that were registered under `handle`.
+///
+/// # Returns
+/// `Some` if `handle` was previously returned by `register_share_handle`, else `None`.
+pub fn redeem_share_handle(handle: u64) -> Option<(Arc<SharedMemory>, SharePermissions)> {
+    let records = SHARE_HANDLES.try_access_weak().ok()?;
+    records.iter().find(|record| record.handle == handle).map(|record| (record.mem.clone(), record.permissions))
+}
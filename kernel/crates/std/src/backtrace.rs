@@ -0,0 +1,127 @@
+/* Copyright (c) 2023 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! This module captures a best-effort backtrace by walking the AArch64 frame-pointer chain, so a
+//! panic can show where in the kernel it happened instead of just the panic message.
+//!
+//! The walk relies on the kernel always being built with frame pointers (the default for this
+//! target): `x29` holds the current frame pointer, `[x29]` holds the caller's frame pointer, and
+//! `[x29 + 8]` holds the return address into the caller. We stop as soon as any of those stop
+//! looking plausible, since a corrupted stack must never turn a backtrace into a second fault.
+
+#![cfg(target_arch = "aarch64")]
+
+use {
+    core::arch::asm,
+    alloc::{format, string::String, vec::Vec},
+
+    locks::Mutex,
+    memory::virt::paging
+};
+
+/// The most stack frames `capture` will walk before giving up. This bounds the cost of capturing
+/// a backtrace and guarantees termination even if the frame-pointer chain is corrupted in a way
+/// that would otherwise loop forever (e.g. a frame pointing back at itself).
+const MAX_DEPTH: usize = 64;
+
+/// The kernel's symbol table, if anything has registered one. Each entry pairs a symbol's start
+/// address with its name; entries should be sorted by address so `resolve` can binary-search.
+/// TODO: Have the kernel parse its own ELF symbol table at boot and call `set_symbols` with the
+/// result, once that parsing capability lands. Until then, every frame is shown as a raw address.
+static SYMBOLS: Mutex<Option<&'static [(usize, &'static str)]>> = Mutex::new(None);
+
+/// Registers the kernel's symbol table so future backtraces can resolve addresses to names.
+/// `symbols` must be sorted by ascending address.
+pub fn set_symbols(symbols: &'static [(usize, &'static str)]) {
+    if let Ok(mut symbols_guard) = SYMBOLS.try_lock() {
+        *symbols_guard = Some(symbols);
+    }
+}
+
+/// Walks the current AArch64 frame-pointer chain and returns one formatted line per frame, each
+/// either `symbol+offset` (if a registered symbol covers the return address) or a raw address.
+///
+/// This never faults: every frame pointer is validated (non-null, 16-byte aligned, inside the
+/// kernel's known mapped image, and strictly further up the stack than the last one) before it's
+/// dereferenced, and the walk always stops within `MAX_DEPTH` frames.
+pub fn capture() -> Vec<String> {
+    let mut frames = Vec::new();
+    let mut fp: usize;
+    unsafe {
+        asm!("mov {}, x29", out(reg) fp, options(nomem, nostack, preserves_flags));
+    }
+
+    for _ in 0 .. MAX_DEPTH {
+        if !fp_is_plausible(fp) {
+            break;
+        }
+
+        // SAFETY: `fp_is_plausible` just confirmed `fp` and `fp + 8` are aligned and inside the
+        // kernel's mapped image, so both 8-byte reads below are sound.
+        let (caller_fp, return_addr) = unsafe {
+            (*(fp as *const usize), *((fp + 8) as *const usize))
+        };
+        if return_addr == 0 {
+            break;
+        }
+        frames.push(format_frame(return_addr));
+
+        if caller_fp <= fp {
+            // The chain should always move toward higher addresses as we walk up the stack;
+            // anything else means we've reached the bottom or the chain is corrupted.
+            break;
+        }
+        fp = caller_fp;
+    }
+
+    frames
+}
+
+fn fp_is_plausible(fp: usize) -> bool {
+    fp != 0
+        && fp % 16 == 0
+        && paging::addr_in_kernel_image(fp)
+        && paging::addr_in_kernel_image(fp + 8)
+}
+
+fn format_frame(addr: usize) -> String {
+    if let Ok(symbols_guard) = SYMBOLS.try_lock() {
+        if let Some(symbols) = *symbols_guard {
+            if let Some((name, offset)) = resolve(symbols, addr) {
+                return if offset == 0 {
+                    format!("{}", name)
+                } else {
+                    format!("{}+{:#x}", name, offset)
+                };
+            }
+        }
+    }
+    format!("{:#x}", addr)
+}
+
+/// Finds the symbol with the greatest start address not exceeding `addr`, on the assumption that
+/// `addr` falls somewhere inside it, and returns its name along with `addr`'s offset into it.
+fn resolve(symbols: &'static [(usize, &'static str)], addr: usize) -> Option<(&'static str, usize)> {
+    let index = match symbols.binary_search_by_key(&addr, |&(start, _)| start) {
+        Ok(index) => index,
+        Err(0) => return None,
+        Err(index) => index - 1
+    };
+    let (start, name) = symbols[index];
+    Some((name, addr - start))
+}
@@ -33,5 +33,7 @@ extern crate alloc;
 #[cfg_attr(any(target_arch = "arm", target_arch = "armv5te", target_arch = "armv7", target_arch = "aarch64"), macro_use)]
     extern crate io as io_impl;
 
+#[cfg(target_arch = "aarch64")]
+pub mod backtrace;
 pub mod fmt;
 pub mod panic;
@@ -31,7 +31,8 @@ use {
 #[cold]
 fn panic_handler(panic_info: &PanicInfo) -> ! {
     println!("{}", Text::UnexpectedKernelError(panic_info));
-    // TODO: Can we manage to get any kind of backtrace here? Or maybe a core dump?
+    #[cfg(target_arch = "aarch64")]
+    println!("{}", Text::KernelBacktrace(crate::backtrace::capture()));
     unsafe { hang() }
 }
 
@@ -28,12 +28,16 @@
 
 use {
     core::{
+        cell::UnsafeCell,
         marker::PhantomData,
         mem,
         sync::atomic::{AtomicUsize, AtomicPtr, Ordering}
     }
 };
 
+#[cfg(target_arch = "aarch64")]
+use core::arch::asm;
+
 /// A pointer paired with a pointer-sized tag. The pointer and tag are only ever updated at the same
 /// time, using an atomic compare-and-swap operation.
 #[derive(Debug)]
@@ -142,6 +146,197 @@ impl<T> TaggedPtr<T> {
     }
 }
 
+/// The raw double-width payload behind a `TaggedPtr128`: a pointer and an independent 64-bit tag,
+/// occupying adjacent registers so they can be loaded, stored, and compared-and-swapped as a
+/// single 128-bit unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, align(16))]
+struct Pair {
+    ptr: usize,
+    tag: usize
+}
+
+/// A pointer paired with a full, alignment-independent 64-bit generation counter.
+///
+/// `TaggedPtr<T>` packs its tag into the high bits freed up by `T`'s alignment, so low-alignment
+/// types are left with only a few tag bits — not enough to close the ABA window in a busy
+/// lock-free structure. This type instead stores the pointer and tag side by side and updates them
+/// together with a double-width (128-bit) atomic operation, so the tag is a genuine 64-bit counter
+/// no matter what `T` is.
+///
+/// On AArch64, this is implemented with the `CASP`/`LDAXP`/`STLXP` instructions instead of
+/// `core::sync::atomic`, since Rust has no portable 128-bit atomic type.
+#[derive(Debug)]
+pub struct TaggedPtr128<T> {
+    internal: UnsafeCell<Pair>,
+    _phantom: PhantomData<AtomicPtr<T>>
+}
+
+// SAFETY: `internal` is only ever accessed through the atomic double-width operations below, which
+// give it the same thread-safety guarantees as `AtomicUsize`.
+unsafe impl<T> Sync for TaggedPtr128<T> {}
+unsafe impl<T> Send for TaggedPtr128<T> {}
+
+impl<T> TaggedPtr128<T> {
+    /// Creates a new `TaggedPtr128` with the given pointer and tag. Unlike `TaggedPtr::new`, `tag`
+    /// can be any value at all; it isn't coupled to `T`'s alignment.
+    pub fn new(ptr: *mut T, tag: usize) -> Self {
+        Self {
+            internal: UnsafeCell::new(Pair { ptr: ptr as usize, tag }),
+            _phantom: PhantomData
+        }
+    }
+
+    /// Loads the current pointer and tag.
+    pub fn load(&self, ordering: Ordering) -> (*mut T, usize) {
+        let pair = unsafe { load_pair(self.internal.get(), ordering) };
+        (pair.ptr as *mut T, pair.tag)
+    }
+
+    /// Stores a new pointer and tag, unconditionally.
+    pub fn store(&self, (ptr, tag): (*mut T, usize), ordering: Ordering) {
+        let new = Pair { ptr: ptr as usize, tag };
+        unsafe { store_pair(self.internal.get(), new, ordering); }
+    }
+
+    /// Performs a double-width compare-and-swap: if the current pointer and tag match
+    /// `(old_ptr, old_tag)`, replaces them with `(new_ptr, new_tag)` in one atomic step. Since the
+    /// tag isn't limited by `T`'s alignment here, a caller can give every successful swap a fresh,
+    /// monotonically increasing tag and get real ABA protection regardless of `T`.
+    pub fn compare_exchange(
+            &self,
+            (old_ptr, old_tag): (*mut T, usize),
+            (new_ptr, new_tag): (*mut T, usize),
+            success: Ordering,
+            failure: Ordering
+    ) -> Result<(*mut T, usize), (*mut T, usize)> {
+        let old = Pair { ptr: old_ptr as usize, tag: old_tag };
+        let new = Pair { ptr: new_ptr as usize, tag: new_tag };
+
+        match unsafe { compare_exchange_pair(self.internal.get(), old, new, success, failure) } {
+            Ok(found) => Ok((found.ptr as *mut T, found.tag)),
+            Err(found) => Err((found.ptr as *mut T, found.tag))
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn load_pair(addr: *mut Pair, _ordering: Ordering) -> Pair {
+    let (ptr, tag): (usize, usize);
+    // Always uses the acquire-ordered form; it's never weaker than what a caller might have asked
+    // for, and AArch64 has no unordered double-width load worth using here instead.
+    asm!(
+        "ldaxp {ptr}, {tag}, [{addr}]",
+        "clrex",
+        ptr = out(reg) ptr,
+        tag = out(reg) tag,
+        addr = in(reg) addr,
+        options(nostack)
+    );
+    Pair { ptr, tag }
+}
+
+/// Like `load_pair`, but leaves the exclusive-access monitor open (no trailing `clrex`) so a
+/// `STLXP` to the same address right afterward can succeed. A caller that ends up not storing
+/// must explicitly `clrex` before giving up the exclusive sequence.
+#[cfg(target_arch = "aarch64")]
+unsafe fn load_pair_exclusive(addr: *mut Pair) -> Pair {
+    let (ptr, tag): (usize, usize);
+    asm!(
+        "ldaxp {ptr}, {tag}, [{addr}]",
+        ptr = out(reg) ptr,
+        tag = out(reg) tag,
+        addr = in(reg) addr,
+        options(nostack)
+    );
+    Pair { ptr, tag }
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn store_pair(addr: *mut Pair, new: Pair, _ordering: Ordering) {
+    loop {
+        let _ = load_pair_exclusive(addr);
+        let success: u32;
+        asm!(
+            "stlxp {success:w}, {ptr}, {tag}, [{addr}]",
+            success = out(reg) success,
+            ptr = in(reg) new.ptr,
+            tag = in(reg) new.tag,
+            addr = in(reg) addr,
+            options(nostack)
+        );
+        if success == 0 {
+            break;
+        }
+    }
+}
+
+/// Prefers `CASP` (FEAT_LSE) when the kernel is built to assume it's available: one instruction
+/// instead of a load-linked/store-conditional retry loop.
+#[cfg(all(target_arch = "aarch64", target_feature = "lse"))]
+unsafe fn compare_exchange_pair(
+        addr: *mut Pair,
+        old: Pair,
+        new: Pair,
+        _success: Ordering,
+        _failure: Ordering
+) -> Result<Pair, Pair> {
+    // Always uses the acquire-and-release-ordered form (`CASPAL`), for the same reason
+    // `load_pair` always uses `LDAXP` and `store_pair` always uses `STLXP`: it's never weaker
+    // than what a caller might have asked for, so there's no need to thread `_success`/`_failure`
+    // through to pick a weaker variant.
+    let (out_ptr, out_tag): (usize, usize);
+    asm!(
+        "caspal x0, x1, x2, x3, [{addr}]",
+        addr = in(reg) addr,
+        inout("x0") old.ptr => out_ptr,
+        inout("x1") old.tag => out_tag,
+        in("x2") new.ptr,
+        in("x3") new.tag,
+        options(nostack)
+    );
+    if out_ptr == old.ptr && out_tag == old.tag {
+        Ok(Pair { ptr: out_ptr, tag: out_tag })
+    } else {
+        Err(Pair { ptr: out_ptr, tag: out_tag })
+    }
+}
+
+/// Falls back to an `LDAXP`/`STLXP` retry loop where `CASP` can't be assumed to exist.
+#[cfg(all(target_arch = "aarch64", not(target_feature = "lse")))]
+unsafe fn compare_exchange_pair(
+        addr: *mut Pair,
+        old: Pair,
+        new: Pair,
+        _success: Ordering,
+        _failure: Ordering
+) -> Result<Pair, Pair> {
+    loop {
+        let current = load_pair_exclusive(addr);
+        if current != old {
+            // We're abandoning the exclusive sequence without storing, so clear the monitor
+            // ourselves instead of leaving it open for whatever runs next.
+            asm!("clrex", options(nostack));
+            return Err(current);
+        }
+
+        let success: u32;
+        asm!(
+            "stlxp {success:w}, {ptr}, {tag}, [{addr}]",
+            success = out(reg) success,
+            ptr = in(reg) new.ptr,
+            tag = in(reg) new.tag,
+            addr = in(reg) addr,
+            options(nostack)
+        );
+        if success == 0 {
+            return Ok(old);
+        }
+        // The store-exclusive failed because something else touched this address between our
+        // load and store; reload and try again.
+    }
+}
+
 #[cfg(test)]
 mod tests {
     // TODO: Add some tests to make sure tagged pointers remain consistent between reads and writes.
@@ -87,7 +87,7 @@ impl Timer {
     /// Initializes the Generic Timer as a one-shot timer that can interrupt this CPU.
     pub fn new() -> Result<Self, ()> {
         // Register the IRQ handler if that hasn't been done already.
-        let isr = irqs::register_irq(IRQ, on_timer_irq, irqs::Priority::Medium, irqs::IrqTrigger::Edge)?;
+        let isr = irqs::register_irq(IRQ, irqs::IsrKind::TopHalf(on_timer_irq), irqs::Priority::Medium, irqs::IrqTrigger::Edge)?;
         mem::forget(isr);
 
         Self::init_cntkctl();
@@ -119,7 +119,7 @@ pub fn init_clock_per_cpu() -> Result<(), ()> {
     assert_eq!(get_primecell_id(), 0xb105f00d);
 
     // Register the IRQ handler if that hasn't been done already.
-    let isr = irqs::register_irq(IRQ, on_clock_irq, irqs::Priority::Medium, irqs::IrqTrigger::Level)?;
+    let isr = irqs::register_irq(IRQ, irqs::IsrKind::TopHalf(on_clock_irq), irqs::Priority::Medium, irqs::IrqTrigger::Level)?;
     mem::forget(isr);
 
     // Enable the RTC.
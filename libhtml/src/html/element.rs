@@ -21,7 +21,10 @@
 use alloc::vec::Vec;
 
 use {
-    alloc::rc::{Rc, Weak},
+    alloc::{
+        boxed::Box,
+        rc::{Rc, Weak}
+    },
     core::{
         cell::RefCell,
         fmt
@@ -42,7 +45,7 @@ pub struct Element<A: alloc::alloc::Allocator+Copy> {
     pub identifier:                Identifier<A>,
     pub attributes:                Vec<Attribute<A>, A>,
     pub custom_element_state:      CustomElementState,
-    pub custom_element_definition: Option<CustomElementDefinition>,
+    pub custom_element_definition: Option<Rc<CustomElementDefinition<A>>>,
     pub is:                        Option<InternedString<A>>,
     pub children:                  Vec<Rc<RefCell<Node<A>>>, A>,
     pub parent:                    Weak<RefCell<Node<A>>>,
@@ -61,27 +64,76 @@ impl<A: alloc::alloc::Allocator+Copy> Element<A> {
             allocator:                   A
     ) -> Rc<RefCell<Node<A>>> {
         let definition = look_up_custom_element_definition(document, namespace, &local_name, &is);
-        match definition {
-            Some(definition) => todo!(),
-            None => {
-                // TODO: let interface = element_interface_for(local_name, namespace);
-                Rc::new(RefCell::new(Node::Element(Self {
-                    allocator,
-                    identifier: Identifier {
-                        namespace_prefix: prefix,
-                        namespace,
-                        local_name
-                    },
-                    attributes: Vec::new_in(allocator),
-                    custom_element_state: CustomElementState::Undefined,
-                    custom_element_definition: None,
-                    is: None,
-                    // TODO: interface,
-                    // TODO: node_document: Rc::downgrade(document),
-                    children: Vec::new_in(allocator),
-                    parent:   Weak::new(),
-                    self_closing_acknowledged: false
-                })))
+
+        // TODO: let interface = element_interface_for(local_name, namespace);
+        let element = Rc::new(RefCell::new(Node::Element(Self {
+            allocator,
+            identifier: Identifier {
+                namespace_prefix: prefix,
+                namespace,
+                local_name
+            },
+            attributes: Vec::new_in(allocator),
+            custom_element_state: if definition.is_some() { CustomElementState::Undefined } else { CustomElementState::Uncustomized },
+            custom_element_definition: None,
+            is,
+            // TODO: interface,
+            // TODO: node_document: Rc::downgrade(document),
+            children: Vec::new_in(allocator),
+            parent:   Weak::new(),
+            self_closing_acknowledged: false
+        })));
+
+        // https://dom.spec.whatwg.org/#concept-create-element, steps 6-7
+        if let Some(definition) = definition {
+            if synchronous_custom_elements {
+                Self::upgrade(&element, &definition);
+            }
+            // TODO: Otherwise, "enqueue a custom element upgrade reaction given result and
+            // definition." There's no agent-wide custom element reactions stack to enqueue onto yet
+            // (see the similar TODOs in `html::insert_foreign_element`), so an asynchronous upgrade
+            // simply never happens until that machinery exists.
+        }
+
+        element
+    }
+
+    // https://html.spec.whatwg.org/multipage/custom-elements.html#concept-try-upgrade
+    fn upgrade(element: &Rc<RefCell<Node<A>>>, definition: &Rc<CustomElementDefinition<A>>) {
+        match definition.callbacks.construct(element) {
+            Ok(()) => {
+                // Snapshot the observed attributes before mutating or borrowing again, since calling
+                // back into `definition.callbacks` while a borrow of `element` is still outstanding
+                // would panic.
+                let observed_attrs: Vec<(InternedString<A>, String<A>)> = {
+                    let node = element.borrow();
+                    let this = node.as_elem();
+                    this.attributes.iter()
+                        .filter(|attr| definition.observed_attributes.iter().any(|observed| *observed == attr.name))
+                        .map(|attr| (attr.name.clone(), attr.value.clone()))
+                        .collect()
+                };
+
+                {
+                    let mut node = element.borrow_mut();
+                    let this = node.as_elem_mut();
+                    this.custom_element_state = CustomElementState::Custom;
+                    this.custom_element_definition = Some(Rc::clone(definition));
+                }
+
+                // "For each attribute in element's attribute list, in order, enqueue a custom element
+                // callback reaction with ... callback name attributeChangedCallback." Invoked
+                // synchronously here, since this crate doesn't yet have the reactions-stack
+                // machinery to truly queue them.
+                for (name, value) in &observed_attrs {
+                    definition.callbacks.attribute_changed_callback(element, name.as_str(), None, Some(value.as_str()));
+                }
+
+                // TODO: "If element is connected, enqueue a custom element callback reaction with
+                // ... callback name connectedCallback." This crate doesn't track connectedness yet.
+            },
+            Err(()) => {
+                element.borrow_mut().as_elem_mut().custom_element_state = CustomElementState::Failed;
             }
         }
     }
@@ -93,9 +145,29 @@ impl<A: alloc::alloc::Allocator+Copy> Element<A> {
 
     // https://dom.spec.whatwg.org/#concept-element-attributes-append
     pub fn append_attribute(elem: &Rc<RefCell<Node<A>>>, attribute: Attribute<A>) {
-        // TODO: "Handle attribute changes for attribute with element, null, and attributeâ€™s value."
         // TODO: attribute.element = Rc::downgrade(elem);
+
+        // "Handle attribute changes for attribute with element, null, and attribute's value."
+        // https://dom.spec.whatwg.org/#handle-attribute-changes
+        // The reaction is computed before the attribute is pushed (and outside any borrow that's
+        // still live when the callback below runs), so the callback sees the old value as "none".
+        let reaction = {
+            let node = elem.borrow();
+            let this = node.as_elem();
+            this.custom_element_definition.as_ref().and_then(|definition| {
+                definition.observed_attributes.iter().any(|observed| *observed == attribute.name)
+                    .then(|| (Rc::clone(definition), attribute.name.clone(), attribute.value.clone()))
+            })
+        };
+
         elem.borrow_mut().as_elem_mut().attributes.push(attribute);
+
+        // "If element is custom, then enqueue a custom element callback reaction with element,
+        // callback name "attributeChangedCallback" ..." Invoked synchronously, for the same reason
+        // as in `Element::upgrade`.
+        if let Some((definition, name, value)) = reaction {
+            definition.callbacks.attribute_changed_callback(elem, name.as_str(), None, Some(value.as_str()));
+        }
     }
 
     // https://html.spec.whatwg.org/multipage/forms.html#category-reset
@@ -180,19 +252,11 @@ pub(super) fn look_up_custom_element_definition<A: alloc::alloc::Allocator+Copy>
         namespace:  &'static str,
         local_name: &InternedString<A>, // FIXME: InternedString should implement Copy.
         is:         &Option<InternedString<A>> // FIXME: InternedString should implement Copy.
-) -> Option<CustomElementDefinition> {
+) -> Option<Rc<CustomElementDefinition<A>>> {
     if namespace != namespace::HTML { return None; }
     if document.browsing_context.is_none() { return None; }
 
-    /* TODO
-    let registry = &document.relevant_global_object.custom_element_registry;
-    if let Some(definition) = registry.get_definition(local_name, local_name) {
-        return Some(definition);
-    }
-    if let Some(definition) = registry.get_definition(is, local_name) {
-        return Some(definition);
-    } */
-    None
+    document.custom_element_registry.lookup(local_name, is)
 }
 
 #[derive(Debug)]
@@ -288,6 +352,153 @@ pub enum CustomElementState {
     Custom
 }
 
+/// The registry of custom element definitions belonging to a document. Corresponds to the
+/// `CustomElementRegistry` interface, except that it's owned directly by the document rather than
+/// by a "relevant global object" (this crate doesn't model one) and its callbacks are native Rust
+/// trait objects rather than JavaScript constructors/functions.
 #[derive(Debug)]
-#[cfg_attr(test, derive(PartialEq))]
-pub struct CustomElementDefinition;
+pub struct CustomElementRegistry<A: alloc::alloc::Allocator+Copy> {
+    definitions: Vec<Rc<CustomElementDefinition<A>>, A>
+}
+
+impl<A: alloc::alloc::Allocator+Copy> CustomElementRegistry<A> {
+    pub(crate) fn new(allocator: A) -> Self {
+        Self { definitions: Vec::new_in(allocator) }
+    }
+
+    /// Defines a new custom element named `name`, whose lifecycle is driven by `callbacks`.
+    ///
+    /// `extends` names the built-in local name this definition customizes (making it a
+    /// "customized built-in element", looked up via an `is` attribute); pass `None` to define an
+    /// ordinary autonomous custom element, whose local name is `name` itself.
+    ///
+    /// https://html.spec.whatwg.org/multipage/custom-elements.html#dom-customelementregistry-define
+    pub fn define(
+            &mut self,
+            name:                InternedString<A>,
+            extends:             Option<InternedString<A>>,
+            observed_attributes: Vec<InternedString<A>, A>,
+            callbacks:           Box<dyn CustomElementCallbacks<A>>
+    ) -> Result<(), CustomElementDefinitionError> {
+        if !is_valid_custom_element_name(name.as_str()) {
+            return Err(CustomElementDefinitionError::InvalidName);
+        }
+        if self.definitions.iter().any(|def| def.name == name) {
+            return Err(CustomElementDefinitionError::AlreadyDefined);
+        }
+
+        let local_name = extends.unwrap_or_else(|| name.clone());
+        self.definitions.push(Rc::new(CustomElementDefinition { name, local_name, observed_attributes, callbacks }));
+        Ok(())
+    }
+
+    // https://html.spec.whatwg.org/multipage/custom-elements.html#look-up-a-custom-element-definition
+    pub(super) fn lookup(&self, local_name: &InternedString<A>, is: &Option<InternedString<A>>) -> Option<Rc<CustomElementDefinition<A>>> {
+        if let Some(def) = self.definitions.iter().find(|def| def.name == *local_name && def.local_name == *local_name) {
+            return Some(Rc::clone(def));
+        }
+        if let Some(is) = is {
+            if let Some(def) = self.definitions.iter().find(|def| def.name == *is && def.local_name == *local_name) {
+                return Some(Rc::clone(def));
+            }
+        }
+        None
+    }
+}
+
+/// The reason a call to [`CustomElementRegistry::define`] was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomElementDefinitionError {
+    /// The given name isn't a valid custom element name: it must contain a hyphen, start with a
+    /// lowercase ASCII letter, and not be one of the names HTML already reserves (like
+    /// `annotation-xml`).
+    InvalidName,
+    /// This registry already has a definition with the given name.
+    AlreadyDefined
+}
+
+// https://html.spec.whatwg.org/multipage/custom-elements.html#valid-custom-element-name
+const RESERVED_CUSTOM_ELEMENT_NAMES: &[&str] = &[
+    "annotation-xml", "color-profile", "font-face", "font-face-src", "font-face-uri",
+    "font-face-format", "font-face-name", "missing-glyph"
+];
+
+// This is a simplified approximation of the PCENChar production, which also allows several
+// specific Unicode ranges that aren't worth enumerating here; those characters are accepted too
+// (rather than rejected), since the more important job of this check is to reject the common
+// mistakes: no hyphen, uppercase letters, or one of the reserved names above.
+fn is_valid_custom_element_name(name: &str) -> bool {
+    if RESERVED_CUSTOM_ELEMENT_NAMES.contains(&name) || !name.contains('-') {
+        return false;
+    }
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_lowercase() => {},
+        _ => return false
+    }
+    chars.all(|c| !c.is_ascii() || c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '.' | '_' | '\u{b7}'))
+}
+
+/// The lifecycle hooks a custom element's definition provides: the constructor and callbacks that,
+/// in the specification, a JavaScript custom element class would supply. Since this crate doesn't
+/// integrate with a script engine, native Rust types implement this trait directly instead.
+///
+/// Every method has a default no-op implementation, so an implementor only needs to override the
+/// hooks it actually cares about.
+pub trait CustomElementCallbacks<A: alloc::alloc::Allocator+Copy> {
+    /// Constructs the element's backing state, corresponding to invoking the custom element
+    /// constructor during the "upgrade an element" algorithm. Returning `Err(())` fails the
+    /// upgrade, moving the element to [`CustomElementState::Failed`].
+    fn construct(&self, element: &Rc<RefCell<Node<A>>>) -> Result<(), ()> {
+        let _ = element;
+        Ok(())
+    }
+
+    /// Runs once, immediately after a successful upgrade, for every observed attribute the element
+    /// already has; and again every time an observed attribute is appended through
+    /// `Element::append_attribute`. Removing or changing an attribute doesn't invoke it.
+    fn attribute_changed_callback(
+            &self,
+            element:   &Rc<RefCell<Node<A>>>,
+            name:      &str,
+            old_value: Option<&str>,
+            new_value: Option<&str>
+    ) {
+        let _ = (element, name, old_value, new_value);
+    }
+
+    /// Runs when the element becomes connected to a document.
+    fn connected_callback(&self, element: &Rc<RefCell<Node<A>>>) {
+        let _ = element;
+    }
+
+    /// Runs when the element becomes disconnected from a document.
+    fn disconnected_callback(&self, element: &Rc<RefCell<Node<A>>>) {
+        let _ = element;
+    }
+
+    /// Runs when the element is moved to a new document.
+    fn adopted_callback(&self, element: &Rc<RefCell<Node<A>>>) {
+        let _ = element;
+    }
+}
+
+/// A custom element's definition: its name(s), the attributes it cares about, and the callbacks
+/// that drive its lifecycle.
+pub struct CustomElementDefinition<A: alloc::alloc::Allocator+Copy> {
+    pub name: InternedString<A>,
+    pub local_name: InternedString<A>,
+    pub observed_attributes: Vec<InternedString<A>, A>,
+    pub callbacks: Box<dyn CustomElementCallbacks<A>>
+}
+
+// `callbacks` can't auto-derive `Debug`, so this impl prints every other field and omits it.
+impl<A: alloc::alloc::Allocator+Copy> fmt::Debug for CustomElementDefinition<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomElementDefinition")
+            .field("name", &self.name)
+            .field("local_name", &self.local_name)
+            .field("observed_attributes", &self.observed_attributes)
+            .finish_non_exhaustive()
+    }
+}
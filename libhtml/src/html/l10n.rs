@@ -0,0 +1,420 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! A localization subsystem modeled on [Fluent]'s l10n-registry: elements that carry a
+//! `data-l10n-id` attribute (and optionally a `data-l10n-args` attribute, a flat JSON object of
+//! string arguments) have their text content and any `.attribute`-overriding parts of their
+//! matching message applied in place. [`Localization`] is given an ordered locale fallback chain
+//! and an [`L10nRegistry`] of [`ResourceSource`]s; for a given id, it tries each locale in the
+//! chain in turn, and within a locale, each source's resources in registration order, stopping at
+//! the first one that defines the message. A missing translation in the preferred locale thus
+//! transparently falls back to the next locale, exactly as in Fluent.
+//!
+//! [Fluent]: https://projectfluent.org/
+
+use alloc::{
+    borrow::ToOwned,
+    boxed::Box,
+    collections::btree_map::BTreeMap,
+    string::String,
+    vec::Vec
+};
+use super::{
+    element::{Attribute, Element},
+    node::Node
+};
+
+/// The attribute that names the message to resolve for an element.
+const L10N_ID_ATTR: &str = "data-l10n-id";
+/// The attribute (a flat JSON object of string values) that supplies a message's arguments.
+const L10N_ARGS_ATTR: &str = "data-l10n-args";
+
+/// One piece of a parsed [`Pattern`]: either literal text or a reference to a named argument.
+#[derive(Debug, Clone)]
+enum PatternPart {
+    Text(String),
+    Placeable(String)
+}
+
+/// An unresolved Fluent pattern -- a message's value, or one of its `.attribute` overrides --
+/// parsed once out of FTL source and re-resolved against a fresh argument set on every lookup.
+#[derive(Debug, Clone, Default)]
+struct Pattern(Vec<PatternPart>);
+
+impl Pattern {
+    // Splits `value` into literal text and `{$name}` placeables. This driver only understands the
+    // simple variable-reference placeable, not Fluent's function calls or selectors.
+    fn parse(value: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut rest = value;
+        while let Some(start) = rest.find("{$") {
+            if start > 0 {
+                parts.push(PatternPart::Text(rest[.. start].to_owned()));
+            }
+            rest = &rest[start + 2 ..];
+            match rest.find('}') {
+                Some(end) => {
+                    parts.push(PatternPart::Placeable(rest[.. end].trim().to_owned()));
+                    rest = &rest[end + 1 ..];
+                },
+                None => {
+                    // An unterminated placeable. Rather than silently dropping the rest of the
+                    // line, keep it as literal text so a malformed resource is merely ugly instead
+                    // of lossy.
+                    parts.push(PatternPart::Text(rest.to_owned()));
+                    rest = "";
+                    break;
+                }
+            }
+        }
+        if !rest.is_empty() {
+            parts.push(PatternPart::Text(rest.to_owned()));
+        }
+        Self(parts)
+    }
+
+    // Folds a continuation line (FTL's way of writing a multiline value) into this pattern,
+    // joined with a space. Real Fluent preserves the line break; this driver doesn't need
+    // multiline values to come back out verbatim, just to parse without being truncated.
+    fn push_continuation(&mut self, line: &str) {
+        if self.0.is_empty() {
+            *self = Self::parse(line);
+            return;
+        }
+        self.0.push(PatternPart::Text(" ".to_owned()));
+        self.0.extend(Self::parse(line).0);
+    }
+
+    fn resolve(&self, args: &Args) -> String {
+        let mut out = String::new();
+        for part in &self.0 {
+            match part {
+                PatternPart::Text(text) => out.push_str(text),
+                // A placeable with no matching argument resolves to nothing rather than failing
+                // the whole message; `Localization::format` still succeeds, just without that
+                // piece of text, since dropping a well-formed translation over one missing
+                // argument would be worse than showing it with a gap.
+                PatternPart::Placeable(name) => if let Some(value) = args.get(name) {
+                    out.push_str(value);
+                }
+            }
+        }
+        out
+    }
+}
+
+// One message parsed out of an FTL resource.
+#[derive(Debug, Clone, Default)]
+struct FtlMessage {
+    value: Option<Pattern>,
+    attributes: Vec<(String, Pattern)>
+}
+
+/// The arguments a message's placeables are resolved against, i.e. a parsed `data-l10n-args`
+/// attribute. Fluent calls this a message's "arguments"; this driver only supports string-valued
+/// ones, since that covers every argument an HTML attribute can realistically carry.
+pub type Args = BTreeMap<String, String>;
+
+/// A parsed FTL resource: every message it defines, keyed by identifier.
+///
+/// Only a subset of FTL syntax is understood: `identifier = value` messages, optionally followed
+/// by indented `.attribute = value` lines (for the attribute overrides Fluent uses to localize
+/// things like `.label` or `.placeholder`), indented continuation lines for values that span more
+/// than one line, blank lines, and `#`-prefixed comments. Terms, selectors, and function calls
+/// aren't supported.
+#[derive(Debug, Clone, Default)]
+pub struct FtlResource {
+    messages: BTreeMap<String, FtlMessage>
+}
+
+impl FtlResource {
+    /// Parses an FTL resource out of its source text. Lines this driver doesn't understand (for
+    /// instance, a `-term = ...` definition) are silently skipped rather than treated as an error,
+    /// since a resource written for a fuller Fluent implementation should still yield whatever
+    /// messages this driver can make sense of.
+    pub fn parse(source: &str) -> Self {
+        let mut messages = BTreeMap::new();
+        let mut current: Option<(String, FtlMessage)> = None;
+
+        for line in source.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            // A `.attribute = value` line overrides one of the current message's attributes. It's
+            // always indented in well-formed FTL, so this has to be checked before the generic
+            // continuation-line case below, or every attribute line would be swallowed as a
+            // continuation of the value instead.
+            if let Some(rest) = trimmed.strip_prefix('.') {
+                if let (Some((name, value)), Some((_, message))) = (rest.split_once('='), current.as_mut()) {
+                    message.attributes.push((name.trim().to_owned(), Pattern::parse(value.trim())));
+                }
+                continue;
+            }
+
+            if line.starts_with(|c: char| c.is_whitespace()) {
+                // Any other indented line continues whichever pattern was opened most recently:
+                // the last attribute if one has been seen for this message, otherwise the
+                // message's value.
+                if let Some((_, message)) = current.as_mut() {
+                    match message.attributes.last_mut() {
+                        Some((_, pattern)) => pattern.push_continuation(trimmed),
+                        None => match message.value.as_mut() {
+                            Some(pattern) => pattern.push_continuation(trimmed),
+                            None => {}
+                        }
+                    }
+                }
+                continue;
+            }
+
+            if let Some((id, value)) = trimmed.split_once('=') {
+                if let Some((id, message)) = current.take() {
+                    messages.insert(id, message);
+                }
+                // A `-term = ...` definition isn't a message. With `current` now `None`, its own
+                // attribute and continuation lines are dropped too.
+                if id.trim().starts_with('-') {
+                    continue;
+                }
+                current = Some((
+                    id.trim().to_owned(),
+                    FtlMessage { value: Some(Pattern::parse(value.trim())), attributes: Vec::new() }
+                ));
+            }
+            // Anything else (a malformed line with no `=`) isn't a message this driver can
+            // resolve, so it's left out of `messages` entirely.
+        }
+        if let Some((id, message)) = current.take() {
+            messages.insert(id, message);
+        }
+
+        Self { messages }
+    }
+}
+
+/// One source of FTL resources that a [`Localization`] can draw from. Corresponds to a single
+/// entry in Fluent's l10n-registry, which might be backed by resources bundled with the program,
+/// ones loaded from disk, or ones fetched over a network -- this driver only needs the bundled
+/// case, but the trait leaves room for the others.
+pub trait ResourceSource {
+    /// Returns every FTL resource this source provides for `locale`, in the order they should be
+    /// checked for a message. An empty slice means this source has nothing at all for that
+    /// locale.
+    fn resources_for_locale(&self, locale: &str) -> &[FtlResource];
+}
+
+/// A [`ResourceSource`] backed by a fixed list of `(locale, resources)` pairs, for the common case
+/// where every resource is already parsed and held in memory rather than fetched on demand.
+#[derive(Debug, Default)]
+pub struct StaticSource {
+    locales: Vec<(String, Vec<FtlResource>)>
+}
+
+impl StaticSource {
+    /// Creates a source with no resources registered for any locale.
+    pub fn new() -> Self {
+        Self { locales: Vec::new() }
+    }
+
+    /// Registers `resources` as what this source provides for `locale`, replacing anything
+    /// already registered for it.
+    pub fn insert(&mut self, locale: impl Into<String>, resources: Vec<FtlResource>) -> &mut Self {
+        let locale = locale.into();
+        self.locales.retain(|(l, _)| *l != locale);
+        self.locales.push((locale, resources));
+        self
+    }
+}
+
+impl ResourceSource for StaticSource {
+    fn resources_for_locale(&self, locale: &str) -> &[FtlResource] {
+        self.locales.iter()
+            .find(|(l, _)| l == locale)
+            .map_or(&[][..], |(_, resources)| resources.as_slice())
+    }
+}
+
+/// The set of [`ResourceSource`]s a [`Localization`] draws from, checked in registration order for
+/// each locale in the fallback chain. Modeled on Fluent's l10n-registry.
+#[derive(Default)]
+pub struct L10nRegistry {
+    sources: Vec<Box<dyn ResourceSource>>
+}
+
+impl L10nRegistry {
+    /// Creates a registry with no sources registered.
+    pub fn new() -> Self {
+        Self { sources: Vec::new() }
+    }
+
+    /// Adds `source` to the end of the list of sources this registry checks.
+    pub fn register(&mut self, source: impl ResourceSource + 'static) -> &mut Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    // Finds the first message named `id` when trying, in order, every source's resources for each
+    // locale in `locales`. A locale is fully exhausted (every source, every resource) before the
+    // next locale is tried, so a source registered later never pre-empts an earlier source's
+    // translation in the same locale.
+    fn resolve<'a>(&'a self, locales: &[String], id: &str) -> Option<&'a FtlMessage> {
+        for locale in locales {
+            for source in &self.sources {
+                for resource in source.resources_for_locale(locale) {
+                    if let Some(message) = resource.messages.get(id) {
+                        return Some(message);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// A message resolved against a specific argument set: the text meant for the element's content
+/// (absent if the message defines only attribute overrides), and any `.attribute` overrides meant
+/// for the element's attributes.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedMessage {
+    /// The message's main value, to be written into the element's child text nodes. `None` if the
+    /// message defines only attribute overrides.
+    pub value: Option<String>,
+    /// The message's `.attribute` overrides, each a `(name, value)` pair to be written into (or
+    /// added to) the element's attributes.
+    pub attributes: Vec<(String, String)>
+}
+
+/// Resolves `data-l10n-id` strings against a locale fallback chain and an [`L10nRegistry`], then
+/// applies the results to a DOM subtree.
+pub struct Localization {
+    locales: Vec<String>,
+    registry: L10nRegistry
+}
+
+impl Localization {
+    /// Creates a localization that tries `locales` in order (most preferred first) against
+    /// `registry`'s sources.
+    pub fn new(locales: Vec<String>, registry: L10nRegistry) -> Self {
+        Self { locales, registry }
+    }
+
+    /// Resolves `id` against this localization's fallback chain, substituting `args` into any
+    /// placeables.
+    ///
+    /// # Returns
+    /// `None` if no resource in the fallback chain defines `id`, in which case the caller should
+    /// leave whatever used the id untouched rather than applying a partial or placeholder result.
+    pub fn format(&self, id: &str, args: &Args) -> Option<ResolvedMessage> {
+        let message = self.registry.resolve(&self.locales, id)?;
+        Some(ResolvedMessage {
+            value: message.value.as_ref().map(|pattern| pattern.resolve(args)),
+            attributes: message.attributes.iter()
+                .map(|(name, pattern)| (name.clone(), pattern.resolve(args)))
+                .collect()
+        })
+    }
+}
+
+// Parses a `data-l10n-args` attribute's value as a flat JSON object of strings, e.g.
+// `{"name": "World"}`. This is deliberately not a general JSON parser: it doesn't handle escaped
+// characters, nested objects, or non-string values, since a translation argument is never any of
+// those in practice.
+fn parse_args(json: &str) -> Args {
+    let mut args = Args::new();
+    let body = json.trim().trim_start_matches('{').trim_end_matches('}');
+    for pair in body.split(',') {
+        if let Some((key, value)) = pair.split_once(':') {
+            let key = key.trim().trim_matches('"');
+            if !key.is_empty() {
+                args.insert(key.to_owned(), value.trim().trim_matches('"').to_owned());
+            }
+        }
+    }
+    args
+}
+
+// Walks `root` and every descendant, applying `localization` to each element that carries a
+// `data-l10n-id` this localization can resolve. An element with an unresolvable id (or none at
+// all) is left completely untouched, but its children are still visited -- a missing translation
+// on an ancestor shouldn't hide translations that do exist further down the tree.
+pub(super) fn translate_subtree<A: alloc::alloc::Allocator+Copy>(
+        root: &alloc::rc::Rc<core::cell::RefCell<Node<A>>>,
+        localization: &Localization,
+        allocator: A
+) {
+    // Resolved with the borrow dropped immediately afterward (rather than held across the call to
+    // `apply_resolved`), since that function needs to borrow `root` mutably to apply the result.
+    let resolved = match &*root.borrow() {
+        Node::Element(elem) => resolve_element(elem, localization),
+        _ => None
+    };
+    if let Some(resolved) = resolved {
+        apply_resolved(root, &resolved, allocator);
+    }
+
+    // Collected into a fresh `Vec` (rather than held across the loop) so the borrow backing it is
+    // released before `translate_subtree` recurses and possibly borrows `root`'s descendants
+    // mutably in `apply_resolved`.
+    let children: Vec<_> = match &*root.borrow() {
+        Node::Element(elem) => elem.children.iter().cloned().collect(),
+        _ => return
+    };
+    for child in &children {
+        translate_subtree(child, localization, allocator);
+    }
+}
+
+fn resolve_element<A: alloc::alloc::Allocator+Copy>(elem: &Element<A>, localization: &Localization) -> Option<ResolvedMessage> {
+    let id = elem.attributes.iter().find(|attr| attr.name == L10N_ID_ATTR)?;
+    let args = match elem.attributes.iter().find(|attr| attr.name == L10N_ARGS_ATTR) {
+        Some(attr) => parse_args(attr.value.as_str()),
+        None => Args::new()
+    };
+    localization.format(id.value.as_str(), &args)
+}
+
+// Applies an already-fully-resolved message to `elem_node`. Since `resolved` was built in full by
+// `Localization::format` before this is ever called, there's no point partway through this
+// function where the element could be left in a half-translated state.
+fn apply_resolved<A: alloc::alloc::Allocator+Copy>(
+        elem_node: &alloc::rc::Rc<core::cell::RefCell<Node<A>>>,
+        resolved: &ResolvedMessage,
+        allocator: A
+) {
+    let mut node = elem_node.borrow_mut();
+    let elem = node.as_elem_mut();
+
+    if let Some(value) = &resolved.value {
+        elem.children.clear();
+        elem.children.push(alloc::rc::Rc::new(core::cell::RefCell::new(
+            Node::Text(crate::shim::String::from_in(value, allocator))
+        )));
+    }
+
+    for (name, value) in &resolved.attributes {
+        match elem.attributes.iter_mut().find(|attr| attr.name == name.as_str()) {
+            Some(attr) => attr.value = crate::shim::String::from_in(value, allocator),
+            None => elem.attributes.push(Attribute {
+                name: crate::interned_string::InternedString::from_in(name, allocator),
+                value: crate::shim::String::from_in(value, allocator)
+            })
+        }
+    }
+}
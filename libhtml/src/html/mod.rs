@@ -25,6 +25,7 @@ pub(super) mod decoder;
 pub        mod dom;
 pub        mod element;
 pub        mod encoding;
+pub(super) mod l10n;
 pub(super) mod node;
            mod prescan;
 pub(super) mod tokenizer;
@@ -222,6 +223,16 @@ impl<A: alloc::alloc::Allocator+Copy> Parser<A> {
         }
     }
 
+    /// Applies `localization` to every element of `document`'s DOM tree that declares a
+    /// `data-l10n-id` this localization can resolve, writing the resolved text and attributes
+    /// directly into the tree. Elements with no id, or with an id that no bundle in
+    /// `localization`'s fallback chain defines, are left untouched.
+    pub fn localize(&self, document: &DocumentInternal<A>, localization: &l10n::Localization) {
+        for child in document.dom.children.iter() {
+            l10n::translate_subtree(child, localization, self.allocator);
+        }
+    }
+
     // https://html.spec.whatwg.org/multipage/parsing.html#tree-construction-dispatcher
     fn parse_token(
             &mut self,
@@ -118,10 +118,15 @@ struct DocumentInternal<A: alloc::alloc::Allocator+Copy> {
     enc_confidence: CharEncodingConfidence,
 
     // https://html.spec.whatwg.org/multipage/parsing.html#head-element-pointer
-    head_element: Option<Rc<RefCell<Node<A>>>>
+    head_element: Option<Rc<RefCell<Node<A>>>>,
+
+    // https://html.spec.whatwg.org/multipage/custom-elements.html#custom-element-registry
+    custom_element_registry: html::element::CustomElementRegistry<A>
 }
 
 pub use html::encoding::CharEncoding;
+pub use html::element::{CustomElementCallbacks, CustomElementDefinitionError, CustomElementRegistry};
+pub use html::l10n::{Args, FtlResource, L10nRegistry, Localization, ResolvedMessage, ResourceSource, StaticSource};
 
 
 impl ByteDocument {
@@ -185,6 +190,18 @@ impl<A: alloc::alloc::Allocator+Copy> ByteDocument<A> {
         self.parser.flush_byte_stream(&mut self.tokenizer, &mut self.internal, true);
         self
     }
+
+    /// Applies `localization` to this document's DOM tree, writing the resolved text and
+    /// attributes of every element with a matching `data-l10n-id` directly into the tree.
+    pub fn localize(&mut self, localization: &Localization) {
+        self.parser.localize(&self.internal.internal, localization);
+    }
+
+    /// Returns this document's custom element registry, through which custom elements can be
+    /// defined.
+    pub fn custom_elements(&mut self) -> &mut CustomElementRegistry<A> {
+        &mut self.internal.internal.custom_element_registry
+    }
 }
 
 impl CharDocument {
@@ -226,6 +243,18 @@ impl<A: alloc::alloc::Allocator+Copy> CharDocument<A> {
         self.parser.flush_char_stream(&mut self.tokenizer, &mut self.internal, true);
         self
     }
+
+    /// Applies `localization` to this document's DOM tree, writing the resolved text and
+    /// attributes of every element with a matching `data-l10n-id` directly into the tree.
+    pub fn localize(&mut self, localization: &Localization) {
+        self.parser.localize(&self.internal.internal, localization);
+    }
+
+    /// Returns this document's custom element registry, through which custom elements can be
+    /// defined.
+    pub fn custom_elements(&mut self) -> &mut CustomElementRegistry<A> {
+        &mut self.internal.internal.custom_element_registry
+    }
 }
 
 impl<A: alloc::alloc::Allocator+Copy> DocumentInternal<A> {
@@ -238,7 +267,8 @@ impl<A: alloc::alloc::Allocator+Copy> DocumentInternal<A> {
             browsing_context: None,
             encoding,
             enc_confidence,
-            head_element: None
+            head_element: None,
+            custom_element_registry: html::element::CustomElementRegistry::new(allocator)
         }
     }
 }
@@ -34,9 +34,10 @@ use {
         marker::Unsize,
         mem::{self, MaybeUninit},
         ops::{CoerceUnsized, Deref, DerefMut},
-        ptr,
+        ptr::{self, NonNull},
+        sync::atomic::{AtomicUsize, Ordering},
     },
-    crate::syscall,
+    crate::{lock::RwLock, syscall},
 };
 
 #[cfg(feature = "global-allocator")]
@@ -57,6 +58,102 @@ extern "C" {
 // space allocated ...."
 const ALIGNMENT_FOR_ANYTHING: usize = 16;
 
+/// The sub-page pool's size classes, in bytes. An allocation whose `AllocPrefix` plus requested
+/// size fits in the largest of these (and whose alignment is no stricter than
+/// [`ALIGNMENT_FOR_ANYTHING`]) is served out of a pool instead of costing its own
+/// `memory_alloc`/`memory_free` syscall round trip. Anything bigger goes straight to the kernel,
+/// a page at a time, exactly as before this pool existed.
+const CLASS_SIZES: [usize; 8] = [16, 32, 64, 128, 256, 512, 1024, 2048];
+
+/// One size class's free-list/page-list bookkeeping, protected by [`POOLS`].
+#[derive(Clone, Copy)]
+struct PoolClass {
+    /// The most recently allocated page backing this class, or `None` if this class has never
+    /// been used. Pages are singly head-linked into a doubly linked list via [`PageHeader`] so a
+    /// page can be unlinked in O(1) once it's fully reclaimed.
+    pages: Option<NonNull<PageHeader>>,
+    /// The head of this class's free-chunk list, threaded through the chunks themselves via
+    /// [`FreeChunk`].
+    free_list: Option<NonNull<FreeChunk>>,
+}
+
+impl PoolClass {
+    const fn new() -> Self {
+        Self { pages: None, free_list: None }
+    }
+}
+
+// `NonNull` opts out of `Send`/`Sync` by default, but every access to a `PoolClass`'s pointers
+// (here and in the `PageHeader`/`FreeChunk` lists they lead to) only ever happens while holding
+// `POOLS`'s lock, so it's no less safe to move or share one across threads than it is to move or
+// share the `RwLock` itself.
+unsafe impl Send for PoolClass {}
+unsafe impl Sync for PoolClass {}
+
+/// Sits at the very start of a kernel page that's been carved into chunks for one size class.
+/// A page is always requested with `align == memory_page_size()`, so a chunk's page can be found
+/// just by rounding its address down to the page size.
+struct PageHeader {
+    prev: Option<NonNull<PageHeader>>,
+    next: Option<NonNull<PageHeader>>,
+    /// How many chunks this page was carved into.
+    chunk_count: usize,
+    /// How many of those chunks are currently on the free list. Once this equals `chunk_count`,
+    /// the whole page is free and can be handed back to the kernel.
+    free_count: usize,
+}
+
+/// Overlaid on a chunk's memory for as long as it's free. A chunk must be at least
+/// `size_of::<FreeChunk>()` bytes, which is guaranteed by [`CLASS_SIZES`]'s smallest entry.
+struct FreeChunk {
+    next: Option<NonNull<FreeChunk>>,
+}
+
+/// The pools backing [`Allocator::try_alloc`]/[`GlobalAlloc::dealloc`], one per entry in
+/// [`CLASS_SIZES`].
+static POOLS: RwLock<[PoolClass; CLASS_SIZES.len()]> = RwLock::new([PoolClass::new(); CLASS_SIZES.len()]);
+
+/// Rounds `value` up to the nearest multiple of `align`, which must be a power of two.
+fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+/// The number of bytes an `AllocPrefix` takes up immediately before a pointer allocated with the
+/// given alignment.
+fn prefix_size_for(align: usize) -> usize {
+    align_up(mem::size_of::<AllocPrefix>(), align)
+}
+
+/// The size of a page, as reported by the kernel. This never changes at run time, so it's cached
+/// after the first syscall.
+fn page_size() -> usize {
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+    match PAGE_SIZE.load(Ordering::Relaxed) {
+        0 => {
+            let size = syscall::memory_page_size();
+            PAGE_SIZE.store(size, Ordering::Relaxed);
+            size
+        },
+        size => size,
+    }
+}
+
+/// Finds the page that a pool chunk belongs to by rounding its address down to the page size.
+fn page_of(ptr: NonNull<u8>) -> NonNull<PageHeader> {
+    let page_addr = (ptr.as_ptr() as usize) & !(page_size() - 1);
+    unsafe { NonNull::new_unchecked(page_addr as *mut PageHeader) }
+}
+
+/// Returns the pool size class that should serve an allocation with this layout, or `None` if it
+/// should bypass the pools and go straight to the kernel.
+fn pool_class_for(layout: Layout) -> Option<usize> {
+    if layout.align() > ALIGNMENT_FOR_ANYTHING {
+        return None;
+    }
+    let needed = prefix_size_for(layout.align()) + layout.size();
+    CLASS_SIZES.iter().position(|&size| size >= needed)
+}
+
 // https://pubs.opengroup.org/onlinepubs/9699919799/functions/malloc.html
 #[cfg(feature = "global-allocator")]
 #[no_mangle]
@@ -78,9 +175,9 @@ unsafe extern "C" fn malloc(size: usize) -> *mut c_void {
 unsafe extern "C" fn free(ptr: *mut c_void) {
     if ptr.is_null() { return; }
 
-    let prefix_size = (mem::size_of::<AllocPrefix>() + (ALIGNMENT_FOR_ANYTHING - 1)) & !(ALIGNMENT_FOR_ANYTHING);
+    let prefix_size = prefix_size_for(ALIGNMENT_FOR_ANYTHING);
     let prefix = ptr.cast::<u8>().sub(prefix_size).cast::<AllocPrefix>();
-    let Ok(layout) = Layout::from_size_align((*prefix).size, ALIGNMENT_FOR_ANYTHING) else { return };
+    let Ok(layout) = Layout::from_size_align((*prefix).size(), ALIGNMENT_FOR_ANYTHING) else { return };
     Allocator.dealloc(ptr.cast::<u8>(), layout);
 }
 
@@ -90,9 +187,9 @@ unsafe extern "C" fn free(ptr: *mut c_void) {
 unsafe extern "C" fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
     if ptr.is_null() { return malloc(size); }
 
-    let prefix_size = (mem::size_of::<AllocPrefix>() + (ALIGNMENT_FOR_ANYTHING - 1)) & !(ALIGNMENT_FOR_ANYTHING);
+    let prefix_size = prefix_size_for(ALIGNMENT_FOR_ANYTHING);
     let prefix = ptr.cast::<u8>().sub(prefix_size).cast::<AllocPrefix>();
-    let Ok(layout) = Layout::from_size_align((*prefix).size, ALIGNMENT_FOR_ANYTHING) else {
+    let Ok(layout) = Layout::from_size_align((*prefix).size(), ALIGNMENT_FOR_ANYTHING) else {
         errno = Errno::ENOMEM.into();
         return ptr::null_mut();
     };
@@ -181,34 +278,195 @@ impl Allocator {
             })
         }
     }
+
+    /// Allocates a block of memory with the given layout, returning `Err(AllocError)` instead of
+    /// dereferencing a null pointer if the kernel can't satisfy the request.
+    ///
+    /// Small, ordinarily aligned requests are served out of a pool of kernel pages kept around
+    /// for exactly this purpose, to avoid paying for a [`memory_alloc`](crate::syscall::memory_alloc)
+    /// syscall on every allocation. Anything that doesn't fit a pool goes straight to the kernel,
+    /// just like [`GlobalAlloc::alloc`] always used to.
+    pub fn try_alloc(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        match pool_class_for(layout) {
+            Some(class) => self.try_alloc_pooled(class, layout.align()),
+            None => self.try_alloc_direct(layout),
+        }
+    }
+
+    /// Allocates directly from the kernel, bypassing the pools. Used for anything too big (or too
+    /// strictly aligned) for a size class to hold.
+    fn try_alloc_direct(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        let prefix_size = prefix_size_for(layout.align());
+        let ptr = syscall::memory_alloc(prefix_size + layout.size(), layout.align());
+        if ptr.is_null() {
+            return Err(AllocError);
+        }
+        unsafe { (*ptr.cast::<MaybeUninit<AllocPrefix>>()).write(AllocPrefix::Direct { size: layout.size() }); }
+        let ptr = unsafe { ptr.cast::<u8>().add(prefix_size) };
+        NonNull::new(ptr).ok_or(AllocError)
+    }
+
+    /// Pops a free chunk off the given size class, growing the pool with a fresh kernel page
+    /// first if it's empty.
+    fn try_alloc_pooled(&self, class: usize, align: usize) -> Result<NonNull<u8>, AllocError> {
+        let mut pools = POOLS.write();
+        if pools[class].free_list.is_none() {
+            self.grow_pool(&mut pools[class], class)?;
+        }
+
+        let chunk = pools[class].free_list.expect("grow_pool should have added at least one free chunk");
+        pools[class].free_list = unsafe { chunk.as_ref().next };
+        unsafe { page_of(chunk.cast()).as_mut().free_count -= 1; }
+        drop(pools);
+
+        let prefix_size = prefix_size_for(align);
+        unsafe {
+            chunk.cast::<MaybeUninit<AllocPrefix>>().as_ptr().write(MaybeUninit::new(AllocPrefix::Pooled { class }));
+            Ok(NonNull::new_unchecked(chunk.cast::<u8>().as_ptr().add(prefix_size)))
+        }
+    }
+
+    /// Carves a fresh, page-aligned kernel page into chunks for `class` and links it into `pool`.
+    fn grow_pool(&self, pool: &mut PoolClass, class: usize) -> Result<(), AllocError> {
+        let chunk_size = CLASS_SIZES[class];
+        let page_size = page_size();
+
+        // Requesting an allocation aligned to the page size guarantees it starts on a page
+        // boundary, which is how `page_of` later finds a chunk's `PageHeader`.
+        let page = syscall::memory_alloc(page_size, page_size);
+        if page.is_null() {
+            return Err(AllocError);
+        }
+        let page = page.cast::<PageHeader>();
+
+        let reserved = align_up(mem::size_of::<PageHeader>(), chunk_size);
+        let chunk_count = (page_size - reserved) / chunk_size;
+
+        unsafe {
+            page.write(PageHeader {
+                prev: None,
+                next: pool.pages,
+                chunk_count,
+                free_count: chunk_count,
+            });
+            if let Some(mut next) = pool.pages {
+                next.as_mut().prev = NonNull::new(page);
+            }
+            pool.pages = NonNull::new(page);
+
+            let base = page.cast::<u8>().add(reserved);
+            for i in 0 .. chunk_count {
+                let chunk = base.add(i * chunk_size).cast::<FreeChunk>();
+                chunk.write(FreeChunk { next: pool.free_list });
+                pool.free_list = NonNull::new(chunk);
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes a pooled chunk back onto its class's free list, reclaiming the whole page back to
+    /// the kernel if that was the page's last chunk in use.
+    fn dealloc_pooled(&self, class: usize, ptr: *mut u8, prefix_size: usize) {
+        let chunk = unsafe { NonNull::new_unchecked(ptr.sub(prefix_size).cast::<FreeChunk>()) };
+        let page = page_of(chunk.cast());
+
+        let mut pools = POOLS.write();
+        unsafe { chunk.as_ptr().write(FreeChunk { next: pools[class].free_list }); }
+        pools[class].free_list = Some(chunk);
+
+        let (free_count, chunk_count) = unsafe {
+            let mut page_mut = page;
+            page_mut.as_mut().free_count += 1;
+            (page_mut.as_ref().free_count, page_mut.as_ref().chunk_count)
+        };
+        if free_count == chunk_count {
+            self.reclaim_page(&mut pools[class], page);
+        }
+    }
+
+    /// Unlinks a fully-free page from its class's page list, scrubs its chunks out of the free
+    /// list (which mixes chunks from every page in the class), and returns it to the kernel.
+    fn reclaim_page(&self, pool: &mut PoolClass, page: NonNull<PageHeader>) {
+        unsafe {
+            let header = page.as_ref();
+            match header.prev {
+                Some(mut prev) => prev.as_mut().next = header.next,
+                None => pool.pages = header.next,
+            }
+            if let Some(mut next) = header.next {
+                next.as_mut().prev = header.prev;
+            }
+        }
+
+        let mut kept = None;
+        let mut cursor = pool.free_list;
+        while let Some(chunk) = cursor {
+            cursor = unsafe { chunk.as_ref().next };
+            if page_of(chunk.cast()) != page {
+                unsafe { (*chunk.as_ptr()).next = kept; }
+                kept = Some(chunk);
+            }
+        }
+        pool.free_list = kept;
+
+        syscall::memory_free(page.cast::<MaybeUninit<u8>>().as_ptr());
+    }
 }
 
 unsafe impl GlobalAlloc for Allocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        // FIXME: This is extremely wasteful, as the kernel can't give us anything smaller than
-        // a page, and it can also take a while. Instead, allocate a buffer from the kernel and use
-        // that for multiple allocations until it's full.
-        let prefix_size = (mem::size_of::<AllocPrefix>() + (layout.align() - 1)) & !(layout.align() - 1);
-        let ptr = syscall::memory_alloc(prefix_size + layout.size(), layout.align());
-        (*ptr.cast::<MaybeUninit<AllocPrefix>>()).write(AllocPrefix { size: layout.size() }); // Record the size for future calls to libc's `free` and `realloc`.
-        ptr.cast::<u8>().add(prefix_size)
+        match self.try_alloc(layout) {
+            Ok(ptr) => ptr.as_ptr(),
+            Err(AllocError) => ptr::null_mut(),
+        }
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        let prefix_size = (mem::size_of::<AllocPrefix>() + (layout.align() - 1)) & !(layout.align() - 1);
-        let ptr = ptr.cast::<MaybeUninit<u8>>().sub(prefix_size);
-        ptr.cast::<AllocPrefix>().drop_in_place();
-        syscall::memory_free(ptr);
+        let prefix_size = prefix_size_for(layout.align());
+        let prefix_ptr = ptr.sub(prefix_size).cast::<AllocPrefix>();
+        match ptr::read(prefix_ptr) {
+            AllocPrefix::Direct { .. } => {
+                prefix_ptr.drop_in_place();
+                syscall::memory_free(prefix_ptr.cast::<MaybeUninit<u8>>());
+            },
+            AllocPrefix::Pooled { class } => self.dealloc_pooled(class, ptr, prefix_size),
+        }
     }
 
     // TODO: Write a more efficient implementation of `GlobalAlloc::realloc`.
 }
 
+/// Metadata stored just before every allocation's user-visible data, recovered by
+/// [`GlobalAlloc::dealloc`] (and by the `free`/`realloc` C shims above) to know how to free the
+/// block.
 #[derive(Debug)]
-struct AllocPrefix {
-    size: usize,
+enum AllocPrefix {
+    /// Allocated directly from the kernel with `memory_alloc`; freed the same way.
+    Direct {
+        size: usize,
+    },
+    /// Carved out of a pool page; freed by pushing the chunk back onto its class's free list.
+    Pooled {
+        class: usize,
+    },
 }
 
+impl AllocPrefix {
+    /// The size that should be passed to `Layout::from_size_align` to describe this allocation,
+    /// for callers (like the `free`/`realloc` shims) that only know the pointer, not the original
+    /// layout.
+    ///
+    /// For a pooled allocation, this is the size class's full usable capacity rather than the
+    /// exact size requested: nothing needs to know the original size once a chunk is in a pool,
+    /// and `realloc`'s default implementation only uses this to bound how much to copy, so
+    /// copying a few extra harmless bytes from the same chunk is fine.
+    fn size(&self) -> usize {
+        match *self {
+            Self::Direct { size } => size,
+            Self::Pooled { class } => CLASS_SIZES[class] - prefix_size_for(ALIGNMENT_FOR_ANYTHING),
+        }
+    }
+}
 
 /// A smart pointer that remembers the physical address of its referent in addition to its virtual
 /// address. This is intended for use in drivers, which sometimes need access to physical memory
@@ -222,10 +480,26 @@ pub struct PhysBox<T: ?Sized> {
 impl<T> PhysBox<T> {
     /// Allocates a box and places the given value inside it. Analogous to `Box::new`.
     pub fn new(value: T) -> Self {
-        let mut phys_box = Allocator.malloc_phys::<T>(mem::size_of::<usize>() * 8)
-            .expect("failed to allocate a PhysBox");
+        Self::try_new(value).expect("failed to allocate a PhysBox")
+    }
+
+    /// Fallible counterpart to [`new`](Self::new): returns `Err(AllocError)` instead of panicking
+    /// if the allocation fails.
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        let mut phys_box = Self::try_new_uninit()?;
         phys_box.write(value);
-        PhysBox::assume_init(phys_box)
+        Ok(PhysBox::assume_init(phys_box))
+    }
+
+    /// Allocates a box big enough to hold a `T`, without initializing it. Analogous to
+    /// `Box::try_new_uninit`.
+    pub fn try_new_uninit() -> Result<PhysBox<MaybeUninit<T>>, AllocError> {
+        Allocator.malloc_phys::<T>(mem::size_of::<usize>() * 8)
+    }
+
+    /// Allocates a box big enough to hold `len` `T`s, without initializing any of them.
+    pub fn try_new_slice(len: usize) -> Result<PhysBox<[MaybeUninit<T>]>, AllocError> {
+        Allocator.malloc_phys_array::<T>(len, mem::size_of::<usize>() * 8)
     }
 }
 
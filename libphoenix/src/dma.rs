@@ -0,0 +1,201 @@
+/* Copyright (c) 2024 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! A DMA-coherent buffer type for drivers that hand memory to devices reading and writing
+//! physical memory directly.
+//!
+//! [`PhysBox`] already exposes a buffer's physical address, but it does nothing about cache
+//! coherency: if the CPU's view of a cache line hasn't been written back, a device reading the
+//! same physical memory can see stale data, and if the CPU doesn't invalidate its cache after a
+//! device writes to memory, it can keep reading back its own stale copy. [`DmaBox`] wraps
+//! `PhysBox` and adds [`map_for_device`](DmaBox::map_for_device) and
+//! [`map_for_cpu`](DmaBox::map_for_cpu), which perform the cache maintenance needed at each
+//! hand-off point so that `addr_phys()` is actually coherent when it matters.
+
+use {
+    alloc::alloc::AllocError,
+    core::{
+        arch::asm,
+        marker::Unsize,
+        mem::{self, MaybeUninit},
+        ops::{CoerceUnsized, Deref, DerefMut},
+    },
+    crate::allocator::PhysBox,
+};
+
+/// A buffer suitable for DMA: like [`PhysBox`], it remembers its own physical address, but it
+/// also knows how to clean and invalidate the CPU cache over its contents so that the CPU and a
+/// device can safely hand it back and forth.
+///
+/// # Example
+/// ```no_run
+/// let mut buf = DmaBox::new([0u8; 512]);
+/// buf.fill(0x42);
+/// buf.map_for_device(); // Write the CPU's changes back to memory before the device reads them.
+/// give_buffer_to_device(buf.addr_phys(), buf.len());
+/// wait_for_device();
+/// buf.map_for_cpu(); // Throw away any stale cached copy before the CPU reads what the device wrote.
+/// ```
+#[derive(Debug)]
+pub struct DmaBox<T: ?Sized>(PhysBox<T>);
+
+impl<T> DmaBox<T> {
+    /// Allocates a DMA-coherent box and places the given value inside it. Analogous to
+    /// `PhysBox::new`.
+    pub fn new(value: T) -> Self {
+        Self::try_new(value).expect("failed to allocate a DmaBox")
+    }
+
+    /// Fallible counterpart to [`new`](Self::new): returns `Err(AllocError)` instead of panicking
+    /// if the allocation fails.
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        Ok(Self(PhysBox::try_new(value)?))
+    }
+
+    /// Allocates a box big enough to hold a `T`, without initializing it. Analogous to
+    /// `PhysBox::try_new_uninit`.
+    pub fn try_new_uninit() -> Result<DmaBox<MaybeUninit<T>>, AllocError> {
+        Ok(DmaBox(PhysBox::try_new_uninit()?))
+    }
+}
+
+impl<T> DmaBox<[T]> {
+    /// Allocates a box big enough to hold `len` `T`s, without initializing any of them. Analogous
+    /// to `PhysBox::try_new_slice`.
+    pub fn try_new_slice(len: usize) -> Result<DmaBox<[MaybeUninit<T>]>, AllocError> {
+        Ok(DmaBox(PhysBox::try_new_slice(len)?))
+    }
+
+    /// Returns the physical address of the first element and the number of elements in this
+    /// buffer, for building a scatter-gather list or a descriptor ring entry out of it.
+    pub fn phys_range(&self) -> (usize, usize) {
+        (self.addr_phys(), self.0.len())
+    }
+}
+
+impl<T> DmaBox<MaybeUninit<T>> {
+    /// Unwraps the `MaybeUninit` in the same manner as `MaybeUninit::assume_init`.
+    pub fn assume_init(boxed: Self) -> DmaBox<T> {
+        DmaBox(PhysBox::assume_init(boxed.0))
+    }
+}
+
+impl<T> DmaBox<[MaybeUninit<T>]> {
+    /// Unwraps all the `MaybeUninit` values in the slice in the same manner as
+    /// `MaybeUninit::assume_init`.
+    pub fn slice_assume_init(boxed: Self) -> DmaBox<[T]> {
+        DmaBox(PhysBox::slice_assume_init(boxed.0))
+    }
+}
+
+impl<T: ?Sized> DmaBox<T> {
+    /// Returns the physical address of the buffer that this box contains.
+    pub fn addr_phys(&self) -> usize {
+        self.0.addr_phys()
+    }
+
+    /// Cleans the CPU cache over this buffer, writing back any of the CPU's changes that haven't
+    /// reached memory yet.
+    ///
+    /// Call this right before handing the buffer's physical address to a device, so that the
+    /// device reads the CPU's latest writes instead of whatever was already in memory.
+    pub fn map_for_device(&mut self) {
+        cache_op(&*self.0 as *const T as *const u8, mem::size_of_val(&*self.0), CacheOp::Clean);
+    }
+
+    /// Invalidates the CPU cache over this buffer, discarding any cached copy of its contents.
+    ///
+    /// Call this right after a device is done writing to the buffer's physical address, so that
+    /// the CPU's next read goes to memory instead of returning a stale cached value.
+    pub fn map_for_cpu(&mut self) {
+        cache_op(&*self.0 as *const T as *const u8, mem::size_of_val(&*self.0), CacheOp::Invalidate);
+    }
+}
+
+impl<T: ?Sized+Unsize<U>, U: ?Sized> CoerceUnsized<DmaBox<U>> for DmaBox<T> {}
+
+impl<T: ?Sized> Deref for DmaBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> DerefMut for DmaBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// The two cache-maintenance operations a DMA hand-off needs.
+#[derive(Clone, Copy)]
+enum CacheOp {
+    /// Writes back (but keeps) every dirty cache line in the range, via `DC CVAC`.
+    Clean,
+    /// Discards every cache line in the range, via `DC IVAC`, without writing it back.
+    Invalidate,
+}
+
+/// Performs a cache-maintenance operation over a virtual address range, one cache line at a time,
+/// followed by a barrier to make sure it's visible before this function returns.
+///
+/// # Note
+/// `DC IVAC` is normally restricted to EL1, since blindly discarding a dirty cache line can lose
+/// data that belongs to another, unrelated mapping of the same physical page. This assumes the
+/// kernel has configured the hardware (e.g. `SCTLR_EL1.UCI`) to allow userspace cache maintenance
+/// on memory it owns exclusively, as DMA buffers always are. If that ever stops being true, this
+/// will need to go through a system call instead.
+fn cache_op(ptr: *const u8, len: usize, op: CacheOp) {
+    if len == 0 {
+        return;
+    }
+
+    let line_size = dcache_line_size();
+    let start = (ptr as usize) & !(line_size - 1);
+    let end = (ptr as usize) + len;
+
+    let mut addr = start;
+    while addr < end {
+        match op {
+            CacheOp::Clean => unsafe {
+                asm!("dc cvac, {0}", in(reg) addr, options(nostack, preserves_flags));
+            },
+            CacheOp::Invalidate => unsafe {
+                asm!("dc ivac, {0}", in(reg) addr, options(nostack, preserves_flags));
+            },
+        }
+        addr += line_size;
+    }
+
+    // Make sure every other observer (including a device with its own view of memory) sees the
+    // maintenance above before this function returns.
+    unsafe {
+        asm!("dsb ish", options(nostack, preserves_flags));
+    }
+}
+
+/// Reads the size of a data cache line, in bytes, from `CTR_EL0`.
+fn dcache_line_size() -> usize {
+    let ctr_el0: u64;
+    unsafe {
+        asm!("mrs {0}, ctr_el0", out(reg) ctr_el0, options(nomem, nostack, preserves_flags));
+    }
+    // Bits [19:16] (DminLine) hold log2 of the line size in words (4 bytes each).
+    4usize << ((ctr_el0 >> 16) & 0xf)
+}
@@ -28,9 +28,11 @@ use {
 };
 
 pub mod pipe;
+pub mod ring;
 pub mod sharing;
 
 pub use pipe::*;
+pub use ring::*;
 pub use sharing::*;
 
 #[cfg(not(feature = "no-start"))]
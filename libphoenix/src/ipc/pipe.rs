@@ -145,7 +145,8 @@ impl Pipe {
         assert!(buffer.len() >= MIN_PIPE_SIZE);
         assert!(MIN_PIPE_SIZE >= mem::size_of::<PipeBuffer>());
 
-        unsafe { PipeBuffer::initialize(buffer.as_raw_slice().as_mut_ptr().cast::<PipeBuffer>()); }
+        let bytes = buffer.as_raw_slice().expect("a freshly allocated pipe buffer is always writable");
+        unsafe { PipeBuffer::initialize(bytes.as_mut_ptr().cast::<PipeBuffer>()); }
 
         Ok(Self { buffer })
     }
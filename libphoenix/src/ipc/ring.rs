@@ -0,0 +1,198 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! A lock-free single-producer/single-consumer byte queue built on top of [`SharedMemory`].
+//!
+//! Unlike [`Pipe`](super::pipe::Pipe), which supports multiple readers and writers through a
+//! lock, [`SharedRing`] assumes exactly one producer and one consumer and never blocks or takes a
+//! lock; every operation is a handful of atomic loads/stores on indices that live inside the
+//! shared block itself, since the producer and consumer are in different address spaces and can't
+//! share any state that isn't part of the mapping they both hold.
+
+use {
+    alloc::alloc::AllocError,
+    core::{
+        cell::UnsafeCell,
+        mem::{self, MaybeUninit},
+        ptr::{addr_of, addr_of_mut},
+        sync::atomic::{AtomicU8, AtomicU32, Ordering},
+    },
+    crate::{
+        ipc::sharing::SharedMemory,
+        serde::{Serialize, Deserialize, Serializer, Deserializer, SerializeError, DeserializeError},
+    },
+};
+
+// A conservative upper bound on AArch64 data cache line sizes. `head` and `tail` are each padded
+// out to this size so that the producer's and consumer's writes never land in the same cache line
+// and force the two CPUs to keep invalidating it from each other.
+const CACHE_LINE_SIZE: usize = 64;
+
+/// A single-producer/single-consumer byte queue, laid out entirely inside a [`SharedMemory`]
+/// block so that it can be used to communicate between two processes.
+///
+/// The producer must be the only caller of [`push_slice`](Self::push_slice), and the consumer must
+/// be the only caller of [`pop_slice`](Self::pop_slice), but those two methods may be called
+/// concurrently with each other without any external synchronization.
+#[derive(Debug)]
+pub struct SharedRing {
+    buffer: SharedMemory,
+}
+
+impl SharedRing {
+    /// Wraps `shared` as a new, empty ring buffer.
+    ///
+    /// `shared`'s length must equal the header's size plus a power-of-two number of data bytes
+    /// (the ring's capacity). A block received from [`SharedMemory::try_new_with_type`] and sized
+    /// accordingly works.
+    ///
+    /// # Returns
+    /// `Ok`, or `Err(AllocError)` if `shared` isn't a valid power-of-two layout.
+    pub fn try_new(shared: SharedMemory) -> Result<Self, AllocError> {
+        if !Self::is_valid_layout(&shared) {
+            return Err(AllocError);
+        }
+        let ring = Self { buffer: shared };
+        unsafe { RingHeader::initialize(ring.header() as *const RingHeader as *mut RingHeader); }
+        Ok(ring)
+    }
+
+    /// Pushes as many bytes of `buf` as there is room for onto the ring.
+    ///
+    /// This never blocks. If the ring doesn't have room for the whole buffer, only the bytes that
+    /// fit are pushed.
+    ///
+    /// # Returns
+    /// The number of bytes actually pushed, which may be 0 if the ring is full.
+    pub fn push_slice(&self, buf: &[u8]) -> usize {
+        let header = self.header();
+        let mask = self.capacity() as u32 - 1;
+
+        let tail = header.tail.load(Ordering::Relaxed);
+        let head = header.head.load(Ordering::Acquire);
+        let used = tail.wrapping_sub(head) as usize;
+        let free = self.capacity() - 1 - used;
+
+        let len = usize::min(buf.len(), free);
+        let data = self.data();
+        for (i, &byte) in buf[ .. len].iter().enumerate() {
+            let idx = (tail.wrapping_add(i as u32) & mask) as usize;
+            unsafe { data.add(idx).write_volatile(byte); }
+        }
+
+        header.tail.store(tail.wrapping_add(len as u32), Ordering::Release);
+        len
+    }
+
+    /// Pops as many bytes as are available into `buf`, up to its length.
+    ///
+    /// This never blocks. If the ring has fewer bytes available than `buf` can hold, only those
+    /// bytes are popped.
+    ///
+    /// # Returns
+    /// The number of bytes actually popped, which may be 0 if the ring is empty.
+    pub fn pop_slice(&self, buf: &mut [u8]) -> usize {
+        let header = self.header();
+        let mask = self.capacity() as u32 - 1;
+
+        let head = header.head.load(Ordering::Relaxed);
+        let tail = header.tail.load(Ordering::Acquire);
+        let used = tail.wrapping_sub(head) as usize;
+
+        let len = usize::min(buf.len(), used);
+        let data = self.data();
+        for (i, byte) in buf[ .. len].iter_mut().enumerate() {
+            let idx = (head.wrapping_add(i as u32) & mask) as usize;
+            *byte = unsafe { data.add(idx).read_volatile() };
+        }
+
+        header.head.store(head.wrapping_add(len as u32), Ordering::Release);
+        len
+    }
+
+    /// The size of the ring's data area, in bytes. One slot is always kept empty to distinguish a
+    /// full ring from an empty one, so at most `capacity() - 1` bytes can be queued at once.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len() - RingHeader::data_offset()
+    }
+
+    fn is_valid_layout(shared: &SharedMemory) -> bool {
+        let header_size = RingHeader::data_offset();
+        shared.len() > header_size && (shared.len() - header_size).is_power_of_two()
+    }
+
+    fn header(&self) -> &RingHeader {
+        unsafe { &*(&*self.buffer as *const [AtomicU8] as *const RingHeader) }
+    }
+
+    fn data(&self) -> *mut u8 {
+        let header = self.header() as *const RingHeader as *mut u8;
+        unsafe { header.add(RingHeader::data_offset()) }
+    }
+}
+
+impl Serialize for SharedRing {
+    fn serialize<S: Serializer + ?Sized>(&self, s: &mut S) -> Result<(), SerializeError> {
+        self.buffer.serialize(s)
+    }
+}
+
+impl Deserialize for SharedRing {
+    // Unlike `try_new`, this doesn't reinitialize the header: the other end of the ring may
+    // already have pushed data onto it by the time this side deserializes it.
+    fn deserialize<D: Deserializer + ?Sized>(d: &mut D) -> Result<(Self, usize), DeserializeError> {
+        let (buffer, serialized_len) = d.deserialize::<SharedMemory>()?;
+        if !Self::is_valid_layout(&buffer) { return Err(DeserializeError); }
+        Ok((Self { buffer }, serialized_len))
+    }
+}
+
+#[repr(C)]
+struct RingHeader {
+    tail: AtomicU32,
+    _tail_padding: [u8; CACHE_LINE_SIZE - mem::size_of::<AtomicU32>()],
+    head: AtomicU32,
+    _head_padding: [u8; CACHE_LINE_SIZE - mem::size_of::<AtomicU32>()],
+    bytes: UnsafeCell<[u8; 0]>,
+}
+
+impl RingHeader {
+    // Returns the offset of `bytes` within `Self`, i.e. the number of bytes the header itself
+    // takes up at the front of the shared block.
+    fn data_offset() -> usize {
+        let dummy = MaybeUninit::<Self>::uninit();
+        let dummy = dummy.as_ptr();
+        let bytes_ptr = unsafe { addr_of!((*dummy).bytes) };
+        unsafe { (*bytes_ptr).get().cast::<u8>().sub_ptr(dummy.cast::<u8>()) }
+    }
+
+    // Initializes a ring header in place. The data bytes are left uninitialized; the kernel
+    // already scrubs freshly allocated shared memory to zero, and neither `push_slice` nor
+    // `pop_slice` ever reads a byte that hasn't first been written by `push_slice`.
+    unsafe fn initialize(header: *mut Self) {
+        addr_of_mut!((*header).tail).write(AtomicU32::new(0));
+        addr_of_mut!((*header).head).write(AtomicU32::new(0));
+    }
+
+    // This function just ensures that the type is FFI-safe, since two processes (potentially
+    // built with different compiler versions) interact with the same in-memory layout. See
+    // `PipeBuffer::_ffi_safe` for the same trick.
+    extern "C" fn _ffi_safe(self) -> ! {
+        unimplemented!("not meant to be called")
+    }
+}
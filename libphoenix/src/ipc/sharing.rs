@@ -23,16 +23,17 @@
 //! IPC, like pipes, are insufficient.
 
 use {
-    alloc::alloc::AllocError,
+    alloc::{alloc::AllocError, vec::Vec},
     core::{
-        convert::TryFrom,
+        error,
+        fmt,
         ops::Deref,
         ptr,
-        sync::atomic::AtomicU8,
+        sync::atomic::{AtomicU8, Ordering},
     },
     crate::{
         serde::{Serialize, Deserialize, Serializer, Deserializer, SerializeError, DeserializeError, serialize_object},
-        syscall,
+        syscall::{self, MemoryType, ShareHandle, SharePermissions},
     },
 };
 
@@ -40,35 +41,134 @@ use {
 #[derive(Debug)]
 pub struct SharedMemory {
     bytes: *mut [AtomicU8],
+    handle: ShareHandle,
+    permissions: SharePermissions,
 }
 
 impl SharedMemory {
-    /// Allocates a new block of shared memory.
+    /// Allocates a new block of plain cacheable shared memory with read-write permissions.
     ///
-    /// See the documentation on [`memory_alloc_shared`] for more details.
+    /// See [`try_new_with_type`](Self::try_new_with_type) to request a different memory type, e.g.
+    /// for a buffer shared with a DMA-capable device, or
+    /// [`try_new_with_type_and_permissions`](Self::try_new_with_type_and_permissions) to share the
+    /// block read-only.
     ///
     /// # Returns
     /// `Ok`, or `Err(AllocError)` if the block couldn't be allocated for any reason.
     pub fn try_new(len: usize) -> Result<Self, AllocError> {
-        let ptr = syscall::memory_alloc_shared(len);
-        if ptr.is_null() {
+        Self::try_new_with_type(len, MemoryType::Cacheable)
+    }
+
+    /// Allocates a new block of shared memory with the given memory type and read-write
+    /// permissions.
+    ///
+    /// See the documentation on [`memory_alloc_shared`] for more details.
+    ///
+    /// # Returns
+    /// `Ok`, or `Err(AllocError)` if the block couldn't be allocated for any reason.
+    pub fn try_new_with_type(len: usize, ty: MemoryType) -> Result<Self, AllocError> {
+        Self::try_new_with_type_and_permissions(len, ty, SharePermissions::READ_WRITE)
+    }
+
+    /// Allocates a new block of shared memory with the given memory type and permissions.
+    ///
+    /// The permissions are fixed at allocation time and travel with the block when it's
+    /// serialized, so a receiving `SharedMemory` has the same ones. Pass
+    /// [`SharePermissions::READ_ONLY`] to make [`as_raw_slice`](Self::as_raw_slice) refuse on
+    /// every side. The kernel records them with the handle but doesn't restrict the mapping.
+    ///
+    /// See the documentation on [`memory_alloc_shared`] for more details.
+    ///
+    /// # Returns
+    /// `Ok`, or `Err(AllocError)` if the block couldn't be allocated for any reason.
+    pub fn try_new_with_type_and_permissions(len: usize, ty: MemoryType, permissions: SharePermissions) -> Result<Self, AllocError> {
+        let alloc = syscall::memory_alloc_shared(len, ty, permissions);
+        if alloc.is_null() {
             return Err(AllocError);
         }
 
-        let bytes = ptr::slice_from_raw_parts_mut(ptr.cast::<AtomicU8>(), len);
+        let bytes = ptr::slice_from_raw_parts_mut(alloc.virt.cast::<AtomicU8>(), len);
         for i in 0 .. len {
             unsafe { bytes.get_unchecked_mut(i).write(AtomicU8::new(0)); }
         }
 
-        Ok(Self { bytes })
+        Ok(Self { bytes, handle: alloc.handle, permissions })
     }
 
     /// Returns the shared memory as a raw byte slice.
-    pub fn as_raw_slice(&mut self) -> *mut [AtomicU8] {
-        self.bytes
+    ///
+    /// # Returns
+    /// `Err(NotWritable)` if this block was opened with [`SharePermissions::READ_ONLY`]
+    /// (e.g. because it arrived through [`Deserialize`] from a sender that shared it read-only).
+    pub fn as_raw_slice(&mut self) -> Result<*mut [AtomicU8], NotWritable> {
+        if !self.permissions.write {
+            return Err(NotWritable);
+        }
+        Ok(self.bytes)
+    }
+
+    /// Serializes this block by value, copying its current contents into the message instead of
+    /// sharing the mapping itself.
+    ///
+    /// Unlike [`Serialize`], which emits only a handle that's only redeemable by a process the
+    /// kernel already knows about, this produces a self-contained snapshot that can be forwarded
+    /// anywhere the message itself can go, e.g. to a process that was never given the handle, or
+    /// (eventually) across a machine boundary. The receiver gets its own independent copy of the
+    /// data rather than a view of the same memory; use [`deserialize_bytes`](Self::deserialize_bytes)
+    /// to read it back.
+    pub fn serialize_bytes<S: Serializer + ?Sized>(&self, s: &mut S) -> Result<(), SerializeError> {
+        let bytes = self.iter().map(|byte| byte.load(Ordering::Relaxed)).collect::<Vec<u8>>();
+        serialize_object!(s, {
+            "bytes" => |s| s.list(bytes.iter().copied()),
+        })
+    }
+
+    /// Deserializes a block previously serialized with [`serialize_bytes`](Self::serialize_bytes).
+    ///
+    /// Allocates a fresh, read-write shared memory block sized to match the snapshot and copies
+    /// its bytes in.
+    pub fn deserialize_bytes<D: Deserializer + ?Sized>(d: &mut D) -> Result<(Self, usize), DeserializeError> {
+        let mut bytes = None;
+
+        let ((), serialized_len) = d.object(|field_name, mut deserializer| {
+            let field_len;
+            match field_name {
+                "bytes" => {
+                    if bytes.is_some() { return Err(DeserializeError); }
+                    let (val, val_len) = deserializer.vec::<u8>()?;
+                    bytes = Some(val);
+                    field_len = val_len;
+                },
+                _ => return Err(DeserializeError),
+            };
+            Ok(field_len)
+        })?;
+
+        let Some(bytes) = bytes else { return Err(DeserializeError) };
+
+        let mut mem = Self::try_new(bytes.len()).map_err(|AllocError| DeserializeError)?;
+        let dest = mem.as_raw_slice().expect("a freshly allocated block is always writable");
+        for (i, byte) in bytes.into_iter().enumerate() {
+            unsafe { (*dest.get_unchecked_mut(i)).store(byte, Ordering::Relaxed); }
+        }
+
+        Ok((mem, serialized_len))
+    }
+}
+
+/// Returned by [`SharedMemory::as_raw_slice`] when the block was shared with
+/// [`SharePermissions::READ_ONLY`] and so can't be written to.
+#[derive(Debug)]
+pub struct NotWritable;
+
+impl fmt::Display for NotWritable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the shared memory block is read-only")
     }
 }
 
+impl error::Error for NotWritable {}
+
 impl Deref for SharedMemory {
     type Target = [AtomicU8];
 
@@ -84,37 +184,36 @@ impl Drop for SharedMemory {
 }
 
 impl Serialize for SharedMemory {
+    // Unlike the scheme this replaced, this serializes only the opaque handle (and the
+    // permissions the sender granted), never the block's address or length. A handle the kernel
+    // never minted just fails to redeem in `deserialize` below. Minted handles are sequential,
+    // though, so a tampered handle can name another block the kernel shared with someone else.
     fn serialize<S: Serializer + ?Sized>(&self, s: &mut S) -> Result<(), SerializeError> {
-        let addr = u64::try_from(self.bytes.cast::<AtomicU8>().addr())
-            .map_err(|_| SerializeError)?;
-        let len = u64::try_from(self.bytes.len())
-            .map_err(|_| SerializeError)?;
-
         serialize_object!(s, {
-            "addr" => |s| s.u64(addr),
-            "len"  => |s| s.u64(len),
+            "handle" => |s| s.u64(self.handle.0),
+            "perm" => |s| s.u64(usize::from(self.permissions) as u64),
         })
     }
 }
 
 impl Deserialize for SharedMemory {
     fn deserialize<D: Deserializer + ?Sized>(d: &mut D) -> Result<(Self, usize), DeserializeError> {
-        let mut addr = None;
-        let mut len = None;
+        let mut handle = None;
+        let mut permissions = None;
 
         let ((), serialized_len) = d.object(|field_name, mut deserializer| {
             let field_len;
             match field_name {
-                "addr" => {
-                    if addr.is_some() { return Err(DeserializeError); }
+                "handle" => {
+                    if handle.is_some() { return Err(DeserializeError); }
                     let (val, val_len) = deserializer.u64()?;
-                    addr = Some(val);
+                    handle = Some(val);
                     field_len = val_len;
                 },
-                "len" => {
-                    if len.is_some() { return Err(DeserializeError); }
+                "perm" => {
+                    if permissions.is_some() { return Err(DeserializeError); }
                     let (val, val_len) = deserializer.u64()?;
-                    len = Some(val);
+                    permissions = Some(SharePermissions::from(val as usize));
                     field_len = val_len;
                 },
                 _ => return Err(DeserializeError),
@@ -122,16 +221,19 @@ impl Deserialize for SharedMemory {
             Ok(field_len)
         })?;
 
-        let Some(addr) = addr else { return Err(DeserializeError) };
-        let Some(len) = len else { return Err(DeserializeError) };
+        let Some(handle) = handle else { return Err(DeserializeError) };
+        let Some(permissions) = permissions else { return Err(DeserializeError) };
+        let handle = ShareHandle(handle);
 
-        let addr = usize::try_from(addr).map_err(|_| DeserializeError)?;
-        let len = usize::try_from(len).map_err(|_| DeserializeError)?;
-
-        let ptr = syscall::memory_access_shared(addr, len);
-
-        let len = if ptr.is_null() { 0 } else { len };
+        let access = syscall::memory_access_shared(handle);
+        if access.is_null() {
+            // A handle the kernel never minted. There's no block to back a `SharedMemory` with,
+            // so fail instead of fabricating one from a null pointer (which would also leave
+            // `Drop` calling `memory_free` on an address the kernel never handed out).
+            return Err(DeserializeError);
+        }
+        let bytes = ptr::slice_from_raw_parts_mut(access.virt.cast::<AtomicU8>(), access.len);
 
-        Ok((Self { bytes: ptr::slice_from_raw_parts_mut(ptr.cast::<AtomicU8>(), len) }, serialized_len))
+        Ok((Self { bytes, handle, permissions }, serialized_len))
     }
 }
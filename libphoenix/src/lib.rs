@@ -43,6 +43,10 @@ extern crate alloc;
 #[cfg(target_arch = "aarch64")]
 #[cfg(not(feature = "kernelspace"))]
 pub mod allocator;
+// FIXME: This is only here to allow compiling on an x86-64 host.
+#[cfg(target_arch = "aarch64")]
+#[cfg(not(feature = "kernelspace"))]
+pub mod dma;
 #[cfg(not(feature = "kernelspace"))]
 pub mod ipc;
 #[cfg(not(feature = "kernelspace"))]
@@ -75,6 +75,12 @@ impl<T> RwLock<T> {
     }
 }
 
+// `UnsafeCell` opts a type out of `Sync` by default; `RwLock` puts it back by construction, since
+// every access to `value` is already guarded by `lock`. The bounds match `std::sync::RwLock`:
+// `read` hands out genuinely concurrent `&T` to multiple threads, so `T` must be `Sync`, and
+// `into_inner` moves `T` out to whatever thread calls it, so `T` must be `Send`.
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
 impl<T: ?Sized> RwLock<T> {
     const MAX_READERS:      usize = usize::max_value() - 1;
     const WRITER_SIGNATURE: usize = usize::max_value();
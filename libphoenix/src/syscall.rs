@@ -345,33 +345,40 @@ pub extern "C" fn memory_alloc_phys(size: usize, align: usize, max_bits: usize)
 }
 
 #[cfg(not(feature = "kernelspace"))]
-/// Allocates a new block of shared virtual memory with the given size.
+/// Allocates a new block of shared virtual memory with the given size, memory type, and
+/// permissions.
 ///
 /// This is a low-level primitive for inter-process communication and should probably not be used
 /// directly. Instead, use one of the abstractions in the [`ipc` module].
 ///
-/// The memory will not be shared with any existing processes, but any child process created after
-/// the memory is allocated can call [`memory_access_shared`] to get read-write access to it.
+/// The memory will not be shared with any existing processes. Instead, this returns an opaque
+/// [`ShareHandle`] that can be passed to another process (through whatever channel that process
+/// already trusts) so it can call [`memory_access_shared`] to get access to the same block.
+/// The kernel records `perm` with the handle but doesn't restrict any process's mapping to it.
 ///
 /// Freeing the memory is done via [`memory_free`]. The memory will not actually be freed until
 /// every process that has gained access has also called `memory_free`.
 ///
 /// # Returns
-/// A pointer to the allocated block, or null if the allocation failed.
+/// The address of and handle to the allocated block, or a null address if the allocation failed.
 ///
 /// [`ipc` module]: super::ipc
 #[no_mangle]
-pub extern "C" fn memory_alloc_shared(size: usize) -> *mut MaybeUninit<u8> {
-    let addr: *mut MaybeUninit<u8>;
+pub extern "C" fn memory_alloc_shared(size: usize, ty: MemoryType, perm: SharePermissions) -> SharedAlloc {
+    let virt: *mut MaybeUninit<u8>;
+    let handle: u64;
     unsafe {
         asm!(
             "svc 0x0303",
             in("x2") size,
-            lateout("x0") addr,
+            in("x3") ty as usize,
+            in("x4") usize::from(perm),
+            lateout("x0") virt,
+            lateout("x1") handle,
             options(nomem, nostack, preserves_flags),
         );
     }
-    addr
+    SharedAlloc { virt, handle: ShareHandle(handle) }
 }
 
 #[cfg(not(feature = "kernelspace"))]
@@ -380,33 +387,35 @@ pub extern "C" fn memory_alloc_shared(size: usize) -> *mut MaybeUninit<u8> {
 /// This is a low-level primitive for inter-process communication and should probably not be used
 /// directly. Instead, use one of the abstractions in the [`ipc` module].
 ///
-/// `orig_addr` and `size` must be the address and size of a block of shared memory as returned by
-/// [`memory_alloc_shared`]. Note that `orig_addr` is the address of the block in the _original_
-/// process's address space, hence the name. The pointer returned by this system call is not
-/// guaranteed to have the same address.
+/// `handle` must be a handle to a block of shared memory, as returned by [`memory_alloc_shared`]
+/// (possibly in another process; the handle means the same thing everywhere it's redeemed). The
+/// pointer returned by this system call is not guaranteed to have the same address as it had in
+/// the process that allocated the block, since each process has its own address space.
 ///
 /// Any process that gains access to shared memory is responsible for eventually calling
 /// [`memory_free`]. The memory will not actually be freed until every process that has gained
 /// access has also called `memory_free`.
 ///
 /// # Returns
-/// A pointer to the block of shared memory, or null if the block can't be accessed (e.g. if it has
-/// already been freed).
+/// The address of and size of the block of shared memory, or a null address if `handle` isn't
+/// recognized, i.e. the kernel never minted it. A handle stays recognized even after its block has
+/// been freed everywhere, since the kernel never drops its record of a handle.
 ///
 /// [`ipc` module]: super::ipc
 #[no_mangle]
-pub extern "C" fn memory_access_shared(orig_addr: usize, size: usize) -> *mut MaybeUninit<u8> {
-    let addr: *mut MaybeUninit<u8>;
+pub extern "C" fn memory_access_shared(handle: ShareHandle) -> SharedAccess {
+    let virt: *mut MaybeUninit<u8>;
+    let len: usize;
     unsafe {
         asm!(
             "svc 0x0304",
-            in("x2") orig_addr,
-            in("x3") size,
-            lateout("x0") addr,
+            in("x2") handle.0 as usize,
+            lateout("x0") virt,
+            lateout("x1") len,
             options(nomem, nostack, preserves_flags),
         );
     }
-    addr
+    SharedAccess { virt, len }
 }
 
 #[cfg(not(feature = "kernelspace"))]
@@ -546,6 +555,137 @@ impl VirtPhysAddr {
     }
 }
 
+#[cfg(not(feature = "kernelspace"))]
+/// Used for packaging the address of a newly allocated shared memory block together with the
+/// handle that another process can redeem to gain access to the same block.
+#[derive(Debug)]
+#[repr(C)]
+pub struct SharedAlloc {
+    /// A pointer to the value using a virtual address.
+    pub virt: *mut MaybeUninit<u8>,
+    /// An opaque handle to the block, meant to be given to another process.
+    pub handle: ShareHandle,
+}
+
+#[cfg(not(feature = "kernelspace"))]
+impl SharedAlloc {
+    /// Returns `true` if the allocation failed.
+    pub fn is_null(&self) -> bool {
+        self.virt.is_null()
+    }
+}
+
+#[cfg(not(feature = "kernelspace"))]
+/// Used for packaging the address and size of a shared memory block gained through
+/// [`memory_access_shared`]. Unlike the caller of [`memory_alloc_shared`], the caller here didn't
+/// choose the block's size itself, so the kernel reports it.
+#[derive(Debug)]
+#[repr(C)]
+pub struct SharedAccess {
+    /// A pointer to the value using a virtual address.
+    pub virt: *mut MaybeUninit<u8>,
+    /// The size of the block, in bytes.
+    pub len: usize,
+}
+
+#[cfg(not(feature = "kernelspace"))]
+impl SharedAccess {
+    /// Returns `true` if the handle wasn't recognized.
+    pub fn is_null(&self) -> bool {
+        self.virt.is_null()
+    }
+}
+
+/// An opaque capability that grants whoever holds it access to a block of shared memory through
+/// [`memory_access_shared`].
+///
+/// The kernel mints it in [`memory_alloc_shared`] and only ever recognizes handles it minted
+/// itself. Handles are sequential integers, and any process can redeem any of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct ShareHandle(pub u64);
+
+/// The permissions a process is granted over a block of memory shared through
+/// [`memory_alloc_shared`] and [`memory_access_shared`].
+///
+/// These are requested by whoever allocates the block and recorded with its handle. The kernel
+/// doesn't restrict any process's mapping to them; `SharedMemory` carries them alongside the
+/// handle and refuses raw mutable access to a read-only block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharePermissions {
+    /// Whether the block may be read.
+    pub read: bool,
+    /// Whether the block may be written.
+    pub write: bool,
+    /// Whether the block may be executed as code.
+    pub execute: bool,
+}
+
+impl SharePermissions {
+    /// Read and write access, but not execute. This is the right choice for ordinary data shared
+    /// between processes.
+    pub const READ_WRITE: Self = Self { read: true, write: true, execute: false };
+    /// Read-only access.
+    pub const READ_ONLY: Self = Self { read: true, write: false, execute: false };
+}
+
+impl From<usize> for SharePermissions {
+    fn from(val: usize) -> Self {
+        Self {
+            read:    val & 0b001 != 0,
+            write:   val & 0b010 != 0,
+            execute: val & 0b100 != 0,
+        }
+    }
+}
+
+impl From<SharePermissions> for usize {
+    fn from(perm: SharePermissions) -> Self {
+        (perm.read as usize) | (perm.write as usize) << 1 | (perm.execute as usize) << 2
+    }
+}
+
+/// Used for specifying the memory type (cacheability and ordering behavior) of a block allocated
+/// by [`memory_alloc_shared`].
+///
+/// These correspond to the memory types the AArch64 MMU can assign to a region of virtual memory
+/// (via `MAIR_EL1` and the page tables' attribute indices): Normal memory can be cacheable or
+/// non-cacheable, and Device memory is always non-cacheable and additionally forbids speculative
+/// or reordered accesses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+pub enum MemoryType {
+    /// Normal, cacheable memory. This is the right choice for ordinary data shared between
+    /// processes, and it's what [`SharedMemory::try_new`](crate::ipc::sharing::SharedMemory::try_new)
+    /// uses by default.
+    Cacheable      = 0,
+    /// Normal memory that's cacheable for reads but has its writes buffered and combined before
+    /// they reach memory. Suitable for something like a framebuffer, where the order individual
+    /// writes reach memory doesn't matter.
+    WriteCombining = 1,
+    /// Normal memory with caching disabled. Every access goes all the way to memory, but unlike
+    /// [`Device`](Self::Device), accesses may still be reordered or combined by the CPU.
+    NonCacheable   = 2,
+    /// Device memory. Accesses are neither cached, reordered, nor combined, which is the right
+    /// (and often only correct) choice for memory that a DMA-capable device reads from or writes
+    /// to concurrently, such as a descriptor ring.
+    Device         = 3,
+}
+
+impl TryFrom<usize> for MemoryType {
+    type Error = ();
+
+    fn try_from(val: usize) -> Result<Self, Self::Error> {
+        match val {
+            x if x == Self::Cacheable as usize      => Ok(Self::Cacheable),
+            x if x == Self::WriteCombining as usize => Ok(Self::WriteCombining),
+            x if x == Self::NonCacheable as usize    => Ok(Self::NonCacheable),
+            x if x == Self::Device as usize          => Ok(Self::Device),
+            _                                        => Err(())
+        }
+    }
+}
+
 /// Used for specifying whether the `time_*` syscalls will use the current time or a time already
 /// saved from an earlier call.
 #[derive(Debug)]
@@ -0,0 +1,116 @@
+/* Copyright (c) 2026 Jeremy Davis (jeremydavis519@gmail.com)
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of this software
+ * and associated documentation files (the "Software"), to deal in the Software without restriction,
+ * including without limitation the rights to use, copy, modify, merge, publish, distribute,
+ * sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all copies or
+ * substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT
+ * NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
+ * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM,
+ * DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! A minimal, `no_std` wrapper around the Phoenix kernel's AArch64 `svc` ABI.
+//!
+//! Unlike [`libphoenix`](../libphoenix/index.html), this crate doesn't install a language-item
+//! `start` shim, a global allocator, or any other userspace runtime machinery. It exists for bare
+//! test binaries (and anything else that defines its own `_start`) that just need a safe way to
+//! reach the handful of syscalls they actually use, without writing `asm!` by hand. Every function
+//! here encodes the calling convention for its syscall -- which number goes in the `svc`
+//! immediate, which arguments go in which registers, which registers the kernel clobbers --
+//! exactly once, so callers never have to get it right (or wrong) themselves.
+
+#![no_std]
+#![deny(warnings, missing_docs)]
+
+use core::{
+    arch::asm,
+    fmt,
+    num::NonZeroU64,
+    time::Duration
+};
+
+/// A handle identifying a thread spawned with [`spawn_thread`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadId(NonZeroU64);
+
+/// Spawns a new thread running `entry`, with the given scheduling `priority` and a stack of at
+/// least `max_stack` bytes.
+///
+/// # Returns
+/// A handle identifying the new thread, or `None` if the kernel couldn't spawn it (e.g. because it
+/// couldn't allocate the new stack).
+pub fn spawn_thread(entry: fn() -> !, priority: u8, max_stack: usize) -> Option<ThreadId> {
+    let handle: u64;
+    unsafe {
+        asm!(
+            "svc 0x0002", // spawn thread
+            in("x2") entry as usize,
+            in("x3") priority as usize,
+            in("x4") max_stack,
+            lateout("x0") handle,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+    NonZeroU64::new(handle).map(ThreadId)
+}
+
+/// Halts the current thread for at least `duration`.
+///
+/// The kernel may (and likely will) keep the thread halted for slightly longer than requested, but
+/// it won't wake the thread up early.
+pub fn sleep(duration: Duration) {
+    let milliseconds = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+    unsafe {
+        asm!(
+            "svc 0x0001", // sleep
+            in("x2") milliseconds,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+}
+
+/// Terminates the current thread.
+pub fn exit() -> ! {
+    unsafe {
+        asm!(
+            "svc 0x0000", // terminate thread
+            in("x2") 0,
+            options(nomem, nostack, preserves_flags, noreturn),
+        );
+    }
+}
+
+/// A handle for writing characters to the kernel's debug console.
+///
+/// Implements [`fmt::Write`], so it can be used with the [`write!`] and [`writeln!`] macros.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Console;
+
+impl Console {
+    /// Writes a single character to the console.
+    pub fn put_char(&mut self, c: char) {
+        unsafe {
+            asm!(
+                "svc 0xff00", // putc
+                in("x2") u64::from(u32::from(c)),
+                options(nomem, nostack, preserves_flags),
+            );
+        }
+    }
+}
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.put_char(c);
+        }
+        Ok(())
+    }
+}